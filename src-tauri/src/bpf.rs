@@ -0,0 +1,573 @@
+//! Translation of display filters into capture (BPF) filters.
+//!
+//! Only part of the display filter language has a BPF equivalent: hosts and
+//! networks, ports and port ranges, IP protocols, VLAN IDs and a few
+//! link-layer protocols.
+//! [`translate`] pushes down the top-level conjuncts that map onto BPF and
+//! reports the rest, which still has to be applied as a display filter once
+//! packets have been captured.
+//!
+//! Some terms are only approximated: `protocol:dhcp` is narrowed to
+//! `port 67 or port 68` at capture time, which keeps every DHCP packet but may
+//! also keep other traffic on those ports. Such terms are pushed down *and* kept in the residual
+//! display filter.
+//!
+//! IP protocols are matched with `ip6 protochain`, which follows IPv6
+//! extension headers like the display filter does; `ip6 proto` only checks
+//! the first Next Header. Port primitives cannot be fixed the same way: they
+//! never match non-first IPv4 fragments or IPv6 packets with extension
+//! headers, so the translation notes that such packets are not captured.
+
+use crate::filter::{self, CmpOp, Expr, Field, Value};
+use serde::Serialize;
+use std::net::IpAddr;
+
+/// Ports the packet list derives application protocols from.
+///
+/// Mirrors the port-only dissectors in the registry: a packet is only labelled
+/// with one of these protocols when one of its ports is listed, so the BPF
+/// port filter is a superset of the display filter. Protocols also recognized
+/// by content on other ports (DNS, HTTP, TLS, SSH, SMB) are left out.
+const APP_PROTOCOL_PORTS: &[(&str, &[u16])] = &[
+    ("dhcp", &[67, 68]),
+    ("dhcpv6", &[546, 547]),
+    ("tftp", &[69]),
+    ("ntp", &[123]),
+    ("netbios", &[137, 138]),
+    ("snmp", &[161, 162]),
+    ("ldap", &[389]),
+    ("https", &[443, 8443]),
+    ("quic", &[443]),
+    ("syslog", &[514]),
+    ("ipp", &[631]),
+    ("ssdp", &[1900]),
+    ("mdns", &[5353]),
+    ("sip", &[7070]),
+    ("elasticsearch", &[9200]),
+    ("mongodb", &[27017]),
+];
+
+/// Result of translating a display filter into a capture filter.
+#[derive(Debug, Clone, Serialize)]
+pub struct BpfTranslation {
+    /// Capture filter covering the translatable part (None if nothing maps to BPF)
+    pub filter: Option<String>,
+    /// Display filter still to apply after capture (None if the BPF is exact)
+    pub residual: Option<String>,
+    /// One explanation per term that is approximated or left to the display filter
+    pub notes: Vec<String>,
+}
+
+/// A term compiled to BPF.
+struct Bpf {
+    text: String,
+    /// False when the BPF also accepts packets the display filter rejects
+    exact: bool,
+    /// True when the BPF rejects fragments and extension header chains the
+    /// display filter can still match after reassembly
+    misses_fragments: bool,
+}
+
+impl Bpf {
+    fn exact(text: impl Into<String>) -> Self {
+        Bpf {
+            text: text.into(),
+            exact: true,
+            misses_fragments: false,
+        }
+    }
+
+    fn superset(text: impl Into<String>) -> Self {
+        Bpf {
+            text: text.into(),
+            exact: false,
+            misses_fragments: false,
+        }
+    }
+
+    fn missing_fragments(self) -> Self {
+        Bpf {
+            misses_fragments: true,
+            ..self
+        }
+    }
+}
+
+/// Translates a parsed display filter into a capture filter plus residual.
+pub fn translate(expr: Expr) -> BpfTranslation {
+    let mut conjuncts = Vec::new();
+    filter::flatten_and(expr, &mut conjuncts);
+
+    let mut pushed = Vec::new();
+    // BPF `vlan` shifts the offsets of every later term, so the VLAN test goes first
+    let vlan = conjuncts
+        .iter()
+        .enumerate()
+        .find_map(|(i, conjunct)| vlan_id(conjunct).map(|id| (i, id)));
+    if let Some((i, id)) = vlan {
+        conjuncts.remove(i);
+        pushed.push(format!("vlan {}", id));
+    }
+    let mut residual: Option<Expr> = None;
+    let mut notes = Vec::new();
+    let mut misses_fragments = false;
+
+    for conjunct in conjuncts {
+        let translated = to_bpf(&conjunct);
+        if let Ok(bpf) = &translated {
+            misses_fragments |= bpf.misses_fragments;
+        }
+        match translated {
+            Ok(bpf) if bpf.exact => {
+                pushed.push(bpf.text);
+                continue;
+            }
+            Ok(bpf) => {
+                notes.push(format!(
+                    "'{}' is narrowed to '{}' during capture and re-checked afterwards",
+                    conjunct, bpf.text
+                ));
+                pushed.push(bpf.text);
+            }
+            Err(reason) => {
+                notes.push(format!(
+                    "'{}' is applied after capture: {}",
+                    conjunct, reason
+                ));
+            }
+        }
+        residual = Some(match residual {
+            Some(prev) => Expr::And(Box::new(prev), Box::new(conjunct)),
+            None => conjunct,
+        });
+    }
+
+    if misses_fragments {
+        notes.push(
+            "Port terms do not match non-first IPv4 fragments or IPv6 packets with extension \
+             headers, so those packets are not captured or reassembled"
+                .to_string(),
+        );
+    }
+
+    BpfTranslation {
+        filter: match pushed.len() {
+            0 => None,
+            1 => pushed.pop(),
+            _ => Some(
+                pushed
+                    .iter()
+                    .map(|text| wrap(text))
+                    .collect::<Vec<_>>()
+                    .join(" and "),
+            ),
+        },
+        residual: residual.map(|expr| expr.to_string()),
+        notes,
+    }
+}
+
+fn to_bpf(expr: &Expr) -> Result<Bpf, String> {
+    match expr {
+        Expr::And(lhs, rhs) => match (to_bpf(lhs), to_bpf(rhs)) {
+            (Ok(l), Ok(r)) => Ok(Bpf {
+                text: format!("{} and {}", wrap(&l.text), wrap(&r.text)),
+                exact: l.exact && r.exact,
+                misses_fragments: l.misses_fragments || r.misses_fragments,
+            }),
+            // Dropping one side of a conjunction still keeps every match
+            (Ok(side), Err(_)) | (Err(_), Ok(side)) => Ok(Bpf {
+                exact: false,
+                ..side
+            }),
+            (Err(reason), Err(_)) => Err(reason),
+        },
+        Expr::Or(lhs, rhs) => {
+            let (l, r) = (to_bpf(lhs)?, to_bpf(rhs)?);
+            Ok(Bpf {
+                text: format!("{} or {}", wrap(&l.text), wrap(&r.text)),
+                exact: l.exact && r.exact,
+                misses_fragments: l.misses_fragments || r.misses_fragments,
+            })
+        }
+        Expr::Not(inner) => {
+            let inner = to_bpf(inner)?;
+            if !inner.exact {
+                return Err(format!(
+                    "negating the approximation '{}' would drop matching packets",
+                    inner.text
+                ));
+            }
+            Ok(Bpf::exact(format!("not {}", wrap(&inner.text))))
+        }
+        Expr::Protocol(name) => protocol_bpf(name),
+        Expr::Search(_) => Err("free-text search needs the dissected packet".to_string()),
+        Expr::Exists(field) => exists_bpf(field),
+        Expr::Compare { field, op, value } => compare_bpf(field, *op, value),
+    }
+}
+
+/// Parenthesizes a compound BPF expression.
+///
+/// BPF gives `and` and `or` the same precedence, so any operand containing
+/// either at its top level needs parentheses when combined.
+fn wrap(text: &str) -> String {
+    let mut depth = 0;
+    let mut compound = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ' ' if depth == 0 => {
+                let rest = &text[i..];
+                compound |= rest.starts_with(" and ") || rest.starts_with(" or ");
+            }
+            _ => {}
+        }
+    }
+    if compound {
+        format!("({})", text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn protocol_bpf(name: &str) -> Result<Bpf, String> {
+    let text = match name {
+        "tcp" => ip_proto_bpf(filter::IP_PROTO_TCP),
+        "udp" => ip_proto_bpf(filter::IP_PROTO_UDP),
+        "icmpv6" => format!("ip6 protochain {}", filter::IP_PROTO_ICMPV6),
+        "icmp" | "arp" => name.to_string(),
+        "ip" => "ip or ip6".to_string(),
+        "ipv4" => "ip".to_string(),
+        "ipv6" => "ip6".to_string(),
+        _ => return app_protocol_bpf(name),
+    };
+    Ok(Bpf::exact(text))
+}
+
+/// Matches IP protocol `proto`, after any IPv6 extension headers.
+fn ip_proto_bpf(proto: impl std::fmt::Display) -> String {
+    format!("ip proto {} or ip6 protochain {}", proto, proto)
+}
+
+/// Narrows an application protocol to the ports it is detected on.
+fn app_protocol_bpf(name: &str) -> Result<Bpf, String> {
+    let (_, ports) = APP_PROTOCOL_PORTS
+        .iter()
+        .find(|(protocol, _)| *protocol == name)
+        .ok_or_else(|| format!("'{}' is detected from packet contents", name))?;
+    let text = ports
+        .iter()
+        .map(|port| format!("port {}", port))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    Ok(Bpf::superset(text).missing_fragments())
+}
+
+/// BPF qualifier of the port primitives for a port field.
+fn transport_qualifier(field: &Field) -> &'static str {
+    match field.transport() {
+        Some(filter::IP_PROTO_TCP) => "tcp",
+        Some(_) => "udp",
+        None => "tcp or udp",
+    }
+}
+
+/// BPF selecting the packets that carry a port field.
+fn transport_bpf(field: &Field) -> String {
+    match field.transport() {
+        Some(proto) => ip_proto_bpf(proto),
+        None => format!(
+            "{} or {}",
+            ip_proto_bpf(filter::IP_PROTO_TCP),
+            ip_proto_bpf(filter::IP_PROTO_UDP)
+        ),
+    }
+}
+
+fn exists_bpf(field: &Field) -> Result<Bpf, String> {
+    match field {
+        Field::IpSrc | Field::IpDst | Field::IpAddr | Field::IpProto => Ok(Bpf::exact("ip or ip6")),
+        Field::SrcPort(_) | Field::DstPort(_) | Field::Port(_) => {
+            Ok(Bpf::exact(transport_bpf(field)))
+        }
+        Field::FrameLen => Ok(Bpf::exact("len > 0")),
+        _ => Err(format!("'{}' is only known after dissection", field.name())),
+    }
+}
+
+fn compare_bpf(field: &Field, op: CmpOp, value: &Value) -> Result<Bpf, String> {
+    match field {
+        Field::IpSrc | Field::IpDst | Field::IpAddr => address_bpf(field, op, value),
+        Field::SrcPort(_) | Field::DstPort(_) | Field::Port(_) => port_bpf(field, op, value),
+        Field::IpProto => match (op, value) {
+            (CmpOp::Eq, Value::Number(n)) => Ok(Bpf::exact(ip_proto_bpf(n))),
+            (CmpOp::Ne, Value::Number(n)) => Ok(Bpf::exact(format!(
+                "(ip or ip6) and not ({})",
+                ip_proto_bpf(n)
+            ))),
+            _ => Err("only '==' and '!=' on ip.proto have a BPF equivalent".to_string()),
+        },
+        // BPF `len` is the on-wire length, which is never below the captured
+        // length, so only lower bounds carry over
+        Field::FrameLen => match (op, value) {
+            (CmpOp::Gt | CmpOp::Ge, Value::Number(n)) => {
+                Ok(Bpf::superset(format!("len {} {}", op, n)))
+            }
+            _ => Err(
+                "frame.len is the captured length, BPF only sees the on-wire length".to_string(),
+            ),
+        },
+        Field::Protocol => match (op, value) {
+            (CmpOp::Eq, Value::Text(name)) => {
+                let name = name.to_ascii_lowercase();
+                // The protocol column shows the application protocol when one is detected
+                let bpf = protocol_bpf(&name)?;
+                Ok(Bpf {
+                    exact: false,
+                    ..bpf
+                })
+            }
+            _ => Err("only exact protocol names have a BPF equivalent".to_string()),
+        },
+        Field::Info => Err("the info column is only known after dissection".to_string()),
+        // BPF `vlan` shifts the offsets of every later term, so it cannot be combined freely
+        Field::VlanId => Err(
+            "only one top-level 'vlan.id == N' is translated, as 'vlan N' comes first".to_string(),
+        ),
+        Field::Tag => Err("tags are only assigned after dissection".to_string()),
+        Field::Payload => Err("payload matching has no BPF equivalent".to_string()),
+        Field::Dissected(def) => Err(format!("'{}' is only known after dissection", def.name)),
+    }
+}
+
+/// The ID of a `vlan.id == N` term with a valid 12-bit VLAN ID.
+fn vlan_id(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Compare {
+            field: Field::VlanId,
+            op: CmpOp::Eq,
+            value: Value::Number(id),
+        } if (0..=4095).contains(id) => Some(*id),
+        _ => None,
+    }
+}
+
+fn address_bpf(field: &Field, op: CmpOp, value: &Value) -> Result<Bpf, String> {
+    let direction = match field {
+        Field::IpSrc => "src ",
+        Field::IpDst => "dst ",
+        _ => "",
+    };
+    // Qualify with the IP version: a bare `host` would also match ARP
+    let primitive = match value {
+        Value::Ip(ip) => format!("{} {}host {}", ip_qualifier(ip), direction, ip),
+        Value::Cidr(ip, prefix) => {
+            let network = network_address(*ip, *prefix);
+            format!(
+                "{} {}net {}/{}",
+                ip_qualifier(&network),
+                direction,
+                network,
+                prefix
+            )
+        }
+        _ => {
+            return Err(format!(
+                "partial or MAC matches on '{}' have no BPF equivalent",
+                field.name()
+            ))
+        }
+    };
+
+    match op {
+        CmpOp::Eq => Ok(Bpf::exact(primitive)),
+        CmpOp::Ne => Ok(Bpf::exact(format!("(ip or ip6) and not {}", primitive))),
+        _ => Err(format!(
+            "'{}' on '{}' has no BPF equivalent",
+            op,
+            field.name()
+        )),
+    }
+}
+
+fn ip_qualifier(ip: &IpAddr) -> &'static str {
+    if ip.is_ipv4() {
+        "ip"
+    } else {
+        "ip6"
+    }
+}
+
+/// Clears the host bits, which libpcap rejects in `net` primitives.
+fn network_address(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    }
+}
+
+fn port_bpf(field: &Field, op: CmpOp, value: &Value) -> Result<Bpf, String> {
+    let direction = match field {
+        Field::SrcPort(_) => "src ",
+        Field::DstPort(_) => "dst ",
+        _ => "",
+    };
+    // Unqualified `port` also covers SCTP, which never carries a port field here
+    let qualifier = match field.transport() {
+        Some(_) => format!("{} ", transport_qualifier(field)),
+        None => String::new(),
+    };
+    let primitive = match value {
+        Value::Number(port) => format!("{}{}port {}", qualifier, direction, port),
+        Value::Range(lo, hi) => format!("{}{}portrange {}-{}", qualifier, direction, lo, hi),
+        _ => return Err(format!("'{}' only compares against numbers", field.name())),
+    };
+
+    match op {
+        CmpOp::Eq => Ok(Bpf::exact(primitive).missing_fragments()),
+        CmpOp::Ne => Ok(Bpf::exact(format!(
+            "{} and not {}",
+            wrap(&transport_bpf(field)),
+            primitive
+        ))),
+        _ => Err(format!(
+            "'{}' on '{}' has no BPF equivalent, use a port range",
+            op,
+            field.name()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translated(input: &str) -> BpfTranslation {
+        translate(filter::parse(input).expect("filter should parse"))
+    }
+
+    #[test]
+    fn test_exact_terms_leave_no_residual() {
+        let t = translated("ip:10.0.0.5 and tcp and not (port 22 or port 53)");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some(
+                "ip host 10.0.0.5 and (ip proto 6 or ip6 protochain 6) \
+                 and not (port 22 or port 53)"
+            )
+        );
+        assert!(t.residual.is_none());
+        assert!(t.notes.is_empty());
+
+        let t = translated("ip.src == 10.1.2.3/8 and udp.dstport == 1000-2000");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("ip src net 10.0.0.0/8 and udp dst portrange 1000-2000")
+        );
+
+        let t = translated("ip.dst != 2001:db8::1");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("(ip or ip6) and not ip6 dst host 2001:db8::1")
+        );
+    }
+
+    #[test]
+    fn test_ip_protocols_follow_ipv6_extension_headers() {
+        let t = translated("udp or icmpv6");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("(ip proto 17 or ip6 protochain 17) or ip6 protochain 58")
+        );
+        assert!(t.notes.is_empty());
+
+        let t = translated("ip.proto == 47");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("ip proto 47 or ip6 protochain 47")
+        );
+        let t = translated("ip.proto != 47");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("(ip or ip6) and not (ip proto 47 or ip6 protochain 47)")
+        );
+    }
+
+    #[test]
+    fn test_port_terms_note_missed_fragments() {
+        let t = translated("udp.port == 53 and host 10.0.0.5");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("udp port 53 and ip host 10.0.0.5")
+        );
+        assert!(t.residual.is_none());
+        assert_eq!(t.notes.len(), 1);
+        assert!(t.notes[0].contains("non-first IPv4 fragments"));
+
+        // Negated ports keep the fragments, which carry no port
+        let t = translated("tcp.port != 22");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("(ip proto 6 or ip6 protochain 6) and not tcp port 22")
+        );
+        assert!(t.notes.is_empty());
+    }
+
+    #[test]
+    fn test_vlan_goes_first() {
+        let t = translated("host 10.0.0.5 and vlan.id == 100");
+        assert_eq!(t.filter.as_deref(), Some("vlan 100 and ip host 10.0.0.5"));
+        assert!(t.residual.is_none());
+
+        // Under `or` the offset shift would apply to the other branch too
+        let t = translated("vlan.id == 100 or tcp");
+        assert!(t.filter.is_none());
+        assert_eq!(t.residual.as_deref(), Some("vlan.id == 100 or tcp"));
+
+        let t = translated("vlan.id == 100 and vlan.id == 200");
+        assert_eq!(t.filter.as_deref(), Some("vlan 100"));
+        assert_eq!(t.residual.as_deref(), Some("vlan.id == 200"));
+    }
+
+    #[test]
+    fn test_approximated_terms_stay_in_residual() {
+        let t = translated("protocol:dhcp and host 10.0.0.5");
+        assert_eq!(
+            t.filter.as_deref(),
+            Some("(port 67 or port 68) and ip host 10.0.0.5")
+        );
+        assert_eq!(t.residual.as_deref(), Some("protocol == \"dhcp\""));
+        // The narrowing, and the fragments its port terms miss
+        assert_eq!(t.notes.len(), 2);
+
+        // An approximation cannot be negated
+        let t = translated("not dhcp");
+        assert!(t.filter.is_none());
+        assert_eq!(t.residual.as_deref(), Some("not dhcp"));
+    }
+
+    #[test]
+    fn test_untranslatable_terms_are_explained() {
+        let t = translated("tcp and info contains \"syn\" and http.method == GET");
+        assert_eq!(t.filter.as_deref(), Some("ip proto 6 or ip6 protochain 6"));
+        assert_eq!(
+            t.residual.as_deref(),
+            Some("info contains \"syn\" and http.method == \"GET\"")
+        );
+        assert_eq!(t.notes.len(), 2);
+        assert!(t.notes[1].contains("http.method"));
+
+        // Residuals round-trip through the parser
+        let residual = t.residual.unwrap();
+        let reparsed = filter::parse(&residual).unwrap().to_string();
+        assert_eq!(reparsed, residual);
+    }
+}
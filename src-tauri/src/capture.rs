@@ -1,5 +1,6 @@
 use crate::dissector;
 use crate::model::PacketSummary;
+use crate::rules::{self, RuleEngine};
 use crate::state::FlowTable;
 use rusqlite::Connection;
 use std::sync::{mpsc as std_mpsc, Arc, Mutex};
@@ -12,6 +13,50 @@ const MAX_PACKET_COUNT: u64 = 5_000_000;
 const MEMORY_WARNING_THRESHOLD: u64 = 7 * 1024 * 1024 * 1024;
 const MEMORY_CRITICAL_THRESHOLD: u64 = 7_500 * 1024 * 1024;
 
+/// Bytes captured per packet on live interfaces.
+pub const CAPTURE_SNAPLEN: i32 = 1600;
+
+/// Snaplen libpcap uses for dead handles opened through the `pcap` crate.
+const DEAD_HANDLE_SNAPLEN: u32 = 65535;
+
+/// BPF opcode for `ret #k`.
+const BPF_RET_K: u16 = 0x06;
+
+/// A summarized packet awaiting insertion, with the endpoints of the frame it
+/// was analysed as and its raw bytes.
+type DbBatchEntry = (PacketSummary, Option<dissector::Endpoints>, Vec<u8>);
+
+/// Compiles a BPF expression against a dead handle, without opening a device.
+///
+/// Returns the compiled instructions as `code jt jf k` lines, the same layout
+/// as `tcpdump -ddd`. Accepting `ret` instructions are reported with `snaplen`,
+/// which is what a live handle with that snaplen would generate.
+pub fn compile_bpf(filter: &str, link_type: i32, snaplen: i32) -> Result<Vec<String>, String> {
+    let dead = pcap::Capture::dead(pcap::Linktype(link_type))
+        .map_err(|e| format!("Failed to open dead capture handle: {}", e))?;
+    let program = dead.compile(filter, true).map_err(|e| e.to_string())?;
+
+    Ok(program
+        .get_instructions()
+        .iter()
+        .map(|insn| with_snaplen(&insn.to_string(), snaplen))
+        .collect())
+}
+
+/// Rewrites an accepting `ret` instruction from the dead-handle snaplen to `snaplen`.
+fn with_snaplen(insn: &str, snaplen: i32) -> String {
+    let parts: Vec<u32> = insn
+        .split_whitespace()
+        .filter_map(|p| p.parse().ok())
+        .collect();
+    match parts.as_slice() {
+        [code, jt, jf, k] if *code == BPF_RET_K as u32 && *k == DEAD_HANDLE_SNAPLEN => {
+            format!("{} {} {} {}", code, jt, jf, snaplen)
+        }
+        _ => insn.to_string(),
+    }
+}
+
 pub async fn run_capture(
     app_handle: tauri::AppHandle,
     interface_name: String,
@@ -19,6 +64,7 @@ pub async fn run_capture(
     mut stop_rx: tokio_mpsc::Receiver<()>,
     db_conn: Arc<Mutex<Connection>>,
     flow_table: Arc<Mutex<FlowTable>>,
+    rules: Arc<Mutex<RuleEngine>>,
 ) -> Result<(), String> {
     log::info!(
         "Starting packet capture on interface: {} with filter: {:?}",
@@ -38,7 +84,7 @@ pub async fn run_capture(
             }
         })?
         .promisc(true)
-        .snaplen(1600)
+        .snaplen(CAPTURE_SNAPLEN)
         .timeout(500)
         .open()
         .map_err(|e| {
@@ -91,7 +137,7 @@ pub async fn run_capture(
     });
 
     let mut batch: Vec<PacketSummary> = Vec::new();
-    let mut db_batch: Vec<(PacketSummary, Vec<u8>)> = Vec::new();
+    let mut db_batch: Vec<DbBatchEntry> = Vec::new();
     let mut last_emit = Instant::now();
     let mut total_packets_captured: u64 = 0;
     let mut memory_warning_logged = false;
@@ -101,7 +147,7 @@ pub async fn run_capture(
     const DB_BATCH_SIZE: usize = 500;
     const BATCH_TIMEOUT_MS: u64 = 100;
 
-    let insert_packets = |db: &mut Connection, packets: &Vec<(PacketSummary, Vec<u8>)>| {
+    let insert_packets = |db: &mut Connection, packets: &Vec<DbBatchEntry>| {
         if packets.is_empty() {
             return;
         }
@@ -109,9 +155,9 @@ pub async fn run_capture(
             Ok(tx) => {
                 let mut success = true;
                 {
-                    match tx.prepare_cached("INSERT INTO packets (id, timestamp_ns, source_addr, dest_addr, protocol, length, info, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)") {
+                    match tx.prepare_cached("INSERT INTO packets (id, timestamp_ns, source_addr, dest_addr, protocol, length, info, vlan_id, ip_proto, src_port, dst_port, tags, color, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)") {
                         Ok(mut stmt) => {
-                            for (summary, data) in packets {
+                            for (summary, endpoints, data) in packets {
                                 let id_i64 = summary.id as i64;
                                 if let Err(e) = stmt.execute(rusqlite::params![
                                     id_i64,
@@ -121,6 +167,12 @@ pub async fn run_capture(
                                     summary.protocol,
                                     summary.length,
                                     summary.info,
+                                    summary.vlan_id,
+                                    endpoints.map(|e| e.0),
+                                    endpoints.and_then(|e| e.1),
+                                    endpoints.and_then(|e| e.2),
+                                    rules::encode_tags(&summary.tags),
+                                    summary.color,
                                     data
                                 ]) {
                                     log::error!("Failed to insert packet {}: {}", id_i64, e);
@@ -173,14 +225,91 @@ pub async fn run_capture(
                                 break;
                             }
 
-                            if let Some(summary) = dissector::parse_summary(&packet_data, packet_id, timestamp_ns) {
-                                if let Some(key) = dissector::get_flow_key(&packet_data) {
+                            // A fragment that completes its datagram is analysed as the reassembled datagram
+                            let reassembly = dissector::ip_fragment(&packet_data).and_then(|fragment| {
+                                flow_table.lock().ok()?.fragments.record(
+                                    packet_id,
+                                    timestamp_ns,
+                                    packet_data.len() as u32,
+                                    fragment,
+                                )
+                            });
+                            let reassembled = reassembly.as_ref().map(dissector::reassembled_frame);
+                            let frame = reassembled.as_deref().unwrap_or(&packet_data);
+
+                            // Later packets of a flow keep the application protocol learned from its first ones
+                            let flow_key = dissector::get_flow_key(frame);
+                            let learned = flow_key.as_ref().and_then(|key| {
+                                flow_table.lock().ok()?.app_protocol(key).map(str::to_string)
+                            });
+
+                            // Seq/Ack numbers are shown relative to each direction's ISN
+                            let sequence_bases = dissector::tcp_segment(frame)
+                                .and_then(|segment| Some(flow_table.lock().ok()?.sequences.record(&segment)))
+                                .unwrap_or_default();
+
+                            if let Some(mut summary) = dissector::parse_flow_summary(frame, packet_id, timestamp_ns, learned.as_deref(), sequence_bases) {
+                                summary.length = packet_data.len() as u32;
+                                if let Ok(mut rules) = rules.lock() {
+                                    rules.apply(&mut summary, frame);
+                                }
+
+                                if let Some(key) = flow_key {
                                     if let Ok(mut flows) = flow_table.lock() {
-                                        flows.update(packet_id, timestamp_ns, summary.length, key);
+                                        match &reassembly {
+                                            Some(reassembly) => {
+                                                for fragment in &reassembly.fragments {
+                                                    flows.update(fragment.packet_id, fragment.timestamp_ns, fragment.length, key.clone());
+                                                }
+                                            }
+                                            None => flows.update(packet_id, timestamp_ns, summary.length, key.clone()),
+                                        }
+                                        if flows.needs_app_probe(&key) {
+                                            if let Some(detected) = dissector::detect_application(frame) {
+                                                flows.record_app_probe(&key, detected);
+                                            }
+                                        }
+                                        if let Some(fingerprints) = dissector::flow_tls_fingerprints(frame, &mut flows.quic) {
+                                            flows.record_fingerprints(&key, &fingerprints);
+                                        }
+                                        let ssh = dissector::ssh_fingerprints(frame, flows.app_protocol(&key));
+                                        if let Some(fingerprints) = ssh {
+                                            flows.record_ssh_fingerprints(&key, &fingerprints);
+                                        }
+                                        flows.record_tunnels(&key, &dissector::tunnel_ids(frame));
                                     }
                                 }
 
-                                db_batch.push((summary.clone(), packet_data));
+                                if let Some(event) = dissector::dhcp_lease_event(frame) {
+                                    if let Ok(mut flows) = flow_table.lock() {
+                                        flows.leases.record(packet_id, timestamp_ns, event);
+                                    }
+                                }
+
+                                let commands = dissector::smb_commands(frame);
+                                if !commands.is_empty() {
+                                    if let Ok(mut flows) = flow_table.lock() {
+                                        for command in commands {
+                                            flows.smb.record(packet_id, timestamp_ns, command);
+                                        }
+                                    }
+                                }
+
+                                if let Some(echo) = dissector::icmp_echo(frame) {
+                                    if let Ok(mut flows) = flow_table.lock() {
+                                        if let Some(matched) = flows.echoes.record(packet_id, timestamp_ns, echo) {
+                                            summary.info.push_str(&format!(
+                                                " (request in {}, rtt {:.3} ms)",
+                                                matched.request_id,
+                                                matched.rtt_ms()
+                                            ));
+                                        }
+                                    }
+                                }
+
+                                // Ports of a reassembled datagram come from its first fragment
+                                let endpoints = dissector::get_transport_endpoints(frame);
+                                db_batch.push((summary.clone(), endpoints, packet_data));
                                 batch.push(summary);
                             }
                         }
@@ -255,3 +384,16 @@ pub async fn run_capture(
     let _ = cap_handle.join();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_snaplen_rewrites_only_accepting_returns() {
+        assert_eq!(with_snaplen("6 0 0 65535", 1600), "6 0 0 1600");
+        // Reject stays as-is, as does a load that happens to use the same constant
+        assert_eq!(with_snaplen("6 0 0 0", 1600), "6 0 0 0");
+        assert_eq!(with_snaplen("40 0 0 65535", 1600), "40 0 0 65535");
+    }
+}
@@ -0,0 +1,163 @@
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Number of read-only connections kept open for UI queries.
+pub const READER_POOL_SIZE: usize = 4;
+
+/// How long a reader waits on a locked database (e.g. during a WAL checkpoint).
+const READER_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SQLite access split into a single writer and a pool of read-only connections.
+///
+/// The capture and import pipelines own the writer. UI commands check out a
+/// reader, so with the database in WAL mode they never wait on a capture
+/// transaction and only see committed batches.
+pub struct DbPool {
+    writer: Arc<Mutex<Connection>>,
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+}
+
+impl DbPool {
+    /// Wraps an initialized writer connection and opens `reader_count` read-only
+    /// connections to the same database file.
+    ///
+    /// The writer must already have created the schema and switched the database
+    /// to WAL mode, otherwise readers would block behind writes.
+    pub fn new(writer: Connection, path: &Path, reader_count: usize) -> rusqlite::Result<Self> {
+        let mut readers = Vec::with_capacity(reader_count.max(1));
+        for _ in 0..reader_count.max(1) {
+            readers.push(Mutex::new(open_reader(path)?));
+        }
+
+        Ok(DbPool {
+            writer: Arc::new(Mutex::new(writer)),
+            readers,
+            next_reader: AtomicUsize::new(0),
+        })
+    }
+
+    /// Shared handle to the writer connection, for long-running tasks such as capture.
+    pub fn writer_handle(&self) -> Arc<Mutex<Connection>> {
+        Arc::clone(&self.writer)
+    }
+
+    /// Locks the writer connection.
+    pub fn write(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        self.writer
+            .lock()
+            .map_err(|e| format!("Failed to lock db: {}", e))
+    }
+
+    /// Checks out a read-only connection.
+    ///
+    /// Any idle reader is preferred; if all are busy the caller waits on the next
+    /// one in round-robin order.
+    pub fn read(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        let count = self.readers.len();
+
+        for i in 0..count {
+            if let Ok(conn) = self.readers[(start + i) % count].try_lock() {
+                return Ok(conn);
+            }
+        }
+
+        self.readers[start % count]
+            .lock()
+            .map_err(|e| format!("Failed to lock db reader: {}", e))
+    }
+}
+
+fn open_reader(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(READER_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("auracap-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn open_writer(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "
+            PRAGMA journal_mode = WAL;
+            CREATE TABLE packets (id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+        ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_reader_not_blocked_by_open_write_transaction() {
+        let path = temp_db_path("pool-concurrency");
+        let pool = DbPool::new(open_writer(&path), &path, 2).unwrap();
+
+        let mut writer = pool.write().unwrap();
+        let tx = writer.transaction().unwrap();
+        tx.execute("INSERT INTO packets (id, data) VALUES (1, x'00')", [])
+            .unwrap();
+
+        // The uncommitted insert is invisible, and the read does not wait on it
+        let count: i64 = pool
+            .read()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM packets", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        tx.commit().unwrap();
+        drop(writer);
+
+        let count: i64 = pool
+            .read()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM packets", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_readers_reject_writes() {
+        let path = temp_db_path("pool-readonly");
+        let pool = DbPool::new(open_writer(&path), &path, 1).unwrap();
+
+        let reader = pool.read().unwrap();
+        let result = reader.execute("INSERT INTO packets (id, data) VALUES (1, x'00')", []);
+        assert!(result.is_err(), "Reader connections must be read-only");
+
+        drop(reader);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_busy_reader_falls_through_to_idle_one() {
+        let path = temp_db_path("pool-checkout");
+        let pool = DbPool::new(open_writer(&path), &path, 2).unwrap();
+
+        let first = pool.read().unwrap();
+        // With one reader checked out, the next checkout must not deadlock
+        let second = pool.read().unwrap();
+        drop(first);
+        drop(second);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
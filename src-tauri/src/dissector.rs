@@ -1,16 +1,41 @@
+mod arp;
+mod checksum;
+mod dhcp;
+mod dhcpv6;
+mod dns;
+mod encap;
+mod fingerprint;
+mod http;
+mod icmp;
+mod ipv6ext;
+mod ntlmssp;
+mod quic;
+pub mod registry;
+mod smb;
+mod ssh;
+mod tcp;
+mod tls;
+mod tunnel;
+mod x509;
+
 use crate::model::{
     Artifact, ForensicIntelligence, ForensicNarrative, PacketDetail, PacketField, PacketSummary,
-    ProtocolLayer,
+    ProtocolLayer, SshFingerprints, TlsFingerprints,
+};
+use crate::state::{
+    FlowKey, Fragment, FragmentKey, FragmentTable, IcmpEcho, LeaseEvent, QuicCryptoTable,
+    Reassembly, SequenceBases, SmbCommand, SmbOp, TcpSegment,
 };
-use crate::state::FlowKey;
 use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
-use pnet::packet::ip::IpNextHeaderProtocols;
-use pnet::packet::ipv4::Ipv4Packet;
-use pnet::packet::ipv6::Ipv6Packet;
-use pnet::packet::tcp::TcpPacket;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::{self, Ipv4Flags, Ipv4Packet, MutableIpv4Packet};
+use pnet::packet::ipv6::{Ipv6Packet, MutableIpv6Packet};
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
 use pnet::packet::udp::UdpPacket;
 use pnet::packet::Packet;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::net::IpAddr;
 
 // Protocol name constants to avoid repeated string allocations
@@ -23,8 +48,380 @@ const PROTO_IPV6: &str = "IPv6";
 const PROTO_ARP: &str = "ARP";
 const PROTO_HTTP: &str = "HTTP";
 const PROTO_HTTPS: &str = "HTTPS";
+const PROTO_TLS: &str = "TLS";
+const PROTO_QUIC: &str = "QUIC";
 const PROTO_UNKNOWN: &str = "Unknown";
 
+/// Value type of a registered dissector field, used to interpret its display value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    /// Leading integer of the value, decimal or `0x` hex (e.g. "60 bytes", "0x0012")
+    Number,
+    /// Free text
+    Text,
+    /// IP or MAC address
+    Address,
+}
+
+/// A filterable field published by the dissector.
+///
+/// Maps a stable filter name such as `http.method` to the protocol layer and
+/// field label produced by [`dissect_packet`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldDef {
+    pub name: &'static str,
+    pub layer: &'static str,
+    pub field: &'static str,
+    pub field_type: FieldType,
+    pub description: &'static str,
+}
+
+const fn field_def(
+    name: &'static str,
+    layer: &'static str,
+    field: &'static str,
+    field_type: FieldType,
+    description: &'static str,
+) -> FieldDef {
+    FieldDef {
+        name,
+        layer,
+        field,
+        field_type,
+        description,
+    }
+}
+
+const LAYER_ETHERNET: &str = "Ethernet";
+const LAYER_IPV4: &str = "Internet Protocol Version 4";
+const LAYER_IPV6: &str = "Internet Protocol Version 6";
+const LAYER_IPV4_FRAGMENTS: &str = "IPv4 Fragments";
+const LAYER_IPV6_HOP_BY_HOP: &str = "IPv6 Hop-by-Hop Option";
+const LAYER_IPV6_ROUTING: &str = "Routing Header for IPv6";
+const LAYER_IPV6_FRAGMENT: &str = "Fragment Header for IPv6";
+const LAYER_IPV6_DESTINATION: &str = "Destination Options for IPv6";
+const LAYER_IPV6_FRAGMENTS: &str = "IPv6 Fragments";
+const LAYER_AH: &str = "Authentication Header";
+const LAYER_ESP: &str = "Encapsulating Security Payload";
+const LAYER_VLAN: &str = "802.1Q Virtual LAN";
+const LAYER_MPLS: &str = "MultiProtocol Label Switching Header";
+const LAYER_GRE: &str = "Generic Routing Encapsulation";
+const LAYER_ERSPAN: &str = "Encapsulated Remote Switch Packet ANalysis";
+const LAYER_VXLAN: &str = "Virtual eXtensible Local Area Network";
+const LAYER_GENEVE: &str = "Generic Network Virtualization Encapsulation";
+const LAYER_ARP: &str = "Address Resolution Protocol";
+const LAYER_TCP: &str = "Transmission Control Protocol";
+const LAYER_UDP: &str = "User Datagram Protocol";
+const LAYER_ICMP: &str = "Internet Control Message Protocol";
+const LAYER_ICMPV6: &str = "Internet Control Message Protocol v6";
+const LAYER_DNS: &str = "Domain Name System";
+const LAYER_DHCP: &str = "Dynamic Host Configuration Protocol";
+const LAYER_DHCPV6: &str = "DHCPv6";
+const LAYER_HTTP: &str = "Hypertext Transfer Protocol";
+const LAYER_TLS: &str = "Transport Layer Security";
+const LAYER_SSH: &str = "SSH Protocol";
+const LAYER_NBSS: &str = "NetBIOS Session Service";
+const LAYER_SMB: &str = "SMB (Server Message Block Protocol)";
+const LAYER_SMB2: &str = "SMB2 (Server Message Block Protocol version 2)";
+const LAYER_NTLMSSP: &str = "NTLM Secure Service Provider";
+const LAYER_QUIC: &str = "QUIC";
+const LAYER_DATA: &str = "Application Data";
+
+/// Registry of every field the dissector can produce, keyed by filter name.
+#[rustfmt::skip]
+pub const FIELD_REGISTRY: &[FieldDef] = &[
+    field_def("eth.dst", LAYER_ETHERNET, "Destination", FieldType::Address, "Destination MAC address"),
+    field_def("eth.src", LAYER_ETHERNET, "Source", FieldType::Address, "Source MAC address"),
+    field_def("eth.type", LAYER_ETHERNET, "Type", FieldType::Number, "EtherType"),
+    field_def("vlan.priority", LAYER_VLAN, "Priority", FieldType::Number, "VLAN priority code point"),
+    field_def("vlan.dei", LAYER_VLAN, "DEI", FieldType::Number, "VLAN drop eligible indicator"),
+    field_def("vlan.etype", LAYER_VLAN, "Type", FieldType::Number, "EtherType following a VLAN tag"),
+    field_def("mpls.label", LAYER_MPLS, "Label", FieldType::Number, "MPLS label"),
+    field_def("mpls.exp", LAYER_MPLS, "Traffic Class", FieldType::Number, "MPLS traffic class"),
+    field_def("mpls.bottom", LAYER_MPLS, "Bottom of Stack", FieldType::Number, "MPLS bottom of stack flag"),
+    field_def("mpls.ttl", LAYER_MPLS, "TTL", FieldType::Number, "MPLS time to live"),
+    field_def("gre.proto", LAYER_GRE, "Protocol Type", FieldType::Number, "EtherType carried by GRE"),
+    field_def("gre.key", LAYER_GRE, "Key", FieldType::Number, "GRE key"),
+    field_def("gre.sequence_number", LAYER_GRE, "Sequence Number", FieldType::Number, "GRE sequence number"),
+    field_def("erspan.version", LAYER_ERSPAN, "Version", FieldType::Number, "ERSPAN header version (1 Type II, 2 Type III)"),
+    field_def("erspan.vlan", LAYER_ERSPAN, "VLAN", FieldType::Number, "VLAN of the mirrored frame"),
+    field_def("erspan.spanid", LAYER_ERSPAN, "Session ID", FieldType::Number, "ERSPAN session ID"),
+    field_def("vxlan.vni", LAYER_VXLAN, "VNI", FieldType::Number, "VXLAN network identifier"),
+    field_def("geneve.vni", LAYER_GENEVE, "VNI", FieldType::Number, "Geneve virtual network identifier"),
+    field_def("geneve.proto_type", LAYER_GENEVE, "Protocol Type", FieldType::Number, "EtherType carried by Geneve"),
+    field_def("ip.version", LAYER_IPV4, "Version", FieldType::Number, "IPv4 version"),
+    field_def("ip.hdr_len", LAYER_IPV4, "Header Length", FieldType::Number, "IPv4 header length in bytes"),
+    field_def("ip.len", LAYER_IPV4, "Total Length", FieldType::Number, "IPv4 total length in bytes"),
+    field_def("ip.id", LAYER_IPV4, "Identification", FieldType::Number, "IPv4 identification"),
+    field_def("ip.flags", LAYER_IPV4, "Flags", FieldType::Number, "IPv4 flags (0x2 Don't Fragment, 0x1 More Fragments)"),
+    field_def("ip.frag_offset", LAYER_IPV4, "Fragment Offset", FieldType::Number, "IPv4 fragment offset in bytes"),
+    field_def("ip.fragment", LAYER_IPV4_FRAGMENTS, "Frame", FieldType::Text, "Fragment of a reassembled IPv4 datagram"),
+    field_def("ip.fragment.count", LAYER_IPV4_FRAGMENTS, "Fragment Count", FieldType::Number, "Number of fragments reassembled"),
+    field_def("ip.reassembled.length", LAYER_IPV4_FRAGMENTS, "Reassembled Length", FieldType::Number, "Length of the reassembled IPv4 payload"),
+    field_def("ip.ttl", LAYER_IPV4, "TTL", FieldType::Number, "IPv4 time to live"),
+    field_def("ip.checksum", LAYER_IPV4, "Header Checksum", FieldType::Text, "IPv4 header checksum and its verification status"),
+    field_def("icmp.type", LAYER_ICMP, "Type", FieldType::Number, "ICMP type"),
+    field_def("icmp.code", LAYER_ICMP, "Code", FieldType::Number, "ICMP code"),
+    field_def("icmp.ident", LAYER_ICMP, "Identifier", FieldType::Number, "ICMP echo identifier"),
+    field_def("icmp.seq", LAYER_ICMP, "Sequence Number", FieldType::Number, "ICMP echo sequence number"),
+    field_def("icmp.mtu", LAYER_ICMP, "Next-Hop MTU", FieldType::Number, "MTU of the next hop (fragmentation needed)"),
+    field_def("icmp.redir_gw", LAYER_ICMP, "Gateway Address", FieldType::Address, "ICMP redirect gateway"),
+    field_def("icmp.orig.src", LAYER_ICMP, "Original Source", FieldType::Address, "Source of the datagram quoted by an ICMP error"),
+    field_def("icmp.orig.dst", LAYER_ICMP, "Original Destination", FieldType::Address, "Destination of the datagram quoted by an ICMP error"),
+    field_def("icmp.orig.proto", LAYER_ICMP, "Original Protocol", FieldType::Number, "Protocol of the datagram quoted by an ICMP error"),
+    field_def("icmp.orig.dstport", LAYER_ICMP, "Original Destination Port", FieldType::Number, "Destination port of the datagram quoted by an ICMP error"),
+    field_def("icmpv6.type", LAYER_ICMPV6, "Type", FieldType::Number, "ICMPv6 type"),
+    field_def("icmpv6.code", LAYER_ICMPV6, "Code", FieldType::Number, "ICMPv6 code"),
+    field_def("icmpv6.echo.identifier", LAYER_ICMPV6, "Identifier", FieldType::Number, "ICMPv6 echo identifier"),
+    field_def("icmpv6.echo.sequence_number", LAYER_ICMPV6, "Sequence Number", FieldType::Number, "ICMPv6 echo sequence number"),
+    field_def("icmpv6.mtu", LAYER_ICMPV6, "MTU", FieldType::Number, "Packet Too Big or NDP MTU option"),
+    field_def("icmpv6.orig.src", LAYER_ICMPV6, "Original Source", FieldType::Address, "Source of the datagram quoted by an ICMPv6 error"),
+    field_def("icmpv6.orig.dst", LAYER_ICMPV6, "Original Destination", FieldType::Address, "Destination of the datagram quoted by an ICMPv6 error"),
+    field_def("icmpv6.nd.target", LAYER_ICMPV6, "Target Address", FieldType::Address, "Neighbor Discovery target address"),
+    field_def("icmpv6.nd.ra.router_lifetime", LAYER_ICMPV6, "Router Lifetime", FieldType::Number, "Router Advertisement lifetime in seconds"),
+    field_def("icmpv6.opt.src_linkaddr", LAYER_ICMPV6, "Source Link-layer Address", FieldType::Address, "NDP source link-layer address option"),
+    field_def("icmpv6.opt.target_linkaddr", LAYER_ICMPV6, "Target Link-layer Address", FieldType::Address, "NDP target link-layer address option"),
+    field_def("icmpv6.opt.prefix", LAYER_ICMPV6, "Prefix", FieldType::Text, "NDP prefix information (prefix/length)"),
+    field_def("icmpv6.opt.rdnss", LAYER_ICMPV6, "Recursive DNS Server", FieldType::Address, "NDP recursive DNS server option"),
+    field_def("arp.opcode", LAYER_ARP, "Opcode", FieldType::Number, "ARP opcode (1 request, 2 reply)"),
+    field_def("arp.src.hw_mac", LAYER_ARP, "Sender MAC Address", FieldType::Address, "ARP sender MAC address"),
+    field_def("arp.src.proto_ipv4", LAYER_ARP, "Sender IP Address", FieldType::Address, "ARP sender IP address"),
+    field_def("arp.dst.hw_mac", LAYER_ARP, "Target MAC Address", FieldType::Address, "ARP target MAC address"),
+    field_def("arp.dst.proto_ipv4", LAYER_ARP, "Target IP Address", FieldType::Address, "ARP target IP address"),
+    field_def("arp.isgratuitous", LAYER_ARP, "Gratuitous", FieldType::Number, "Gratuitous ARP (sender IP = target IP)"),
+    field_def("arp.isprobe", LAYER_ARP, "Probe", FieldType::Number, "ARP probe (sender IP 0.0.0.0)"),
+    field_def("ipv6.plen", LAYER_IPV6, "Payload Length", FieldType::Number, "IPv6 payload length in bytes"),
+    field_def("ipv6.hlim", LAYER_IPV6, "Hop Limit", FieldType::Number, "IPv6 hop limit"),
+    field_def("ipv6.src", LAYER_IPV6, "Source", FieldType::Address, "IPv6 source address"),
+    field_def("ipv6.dst", LAYER_IPV6, "Destination", FieldType::Address, "IPv6 destination address"),
+    field_def("ipv6.nxt", LAYER_IPV6, "Next Header", FieldType::Text, "Header following the IPv6 header"),
+    field_def("ipv6.hopopts.opt", LAYER_IPV6_HOP_BY_HOP, "Option", FieldType::Text, "Hop-by-Hop option"),
+    field_def("ipv6.dstopts.opt", LAYER_IPV6_DESTINATION, "Option", FieldType::Text, "Destination option"),
+    field_def("ipv6.routing.type", LAYER_IPV6_ROUTING, "Routing Type", FieldType::Text, "IPv6 routing header type"),
+    field_def("ipv6.routing.segleft", LAYER_IPV6_ROUTING, "Segments Left", FieldType::Number, "Route segments still to visit"),
+    field_def("ipv6.routing.addr", LAYER_IPV6_ROUTING, "Address", FieldType::Address, "Address in a routing header"),
+    field_def("ipv6.fragment.offset", LAYER_IPV6_FRAGMENT, "Fragment Offset", FieldType::Number, "IPv6 fragment offset in bytes"),
+    field_def("ipv6.fragment.more", LAYER_IPV6_FRAGMENT, "More Fragments", FieldType::Text, "IPv6 more fragments flag"),
+    field_def("ipv6.fragment.id", LAYER_IPV6_FRAGMENT, "Identification", FieldType::Number, "IPv6 fragment identification"),
+    field_def("ipv6.fragment", LAYER_IPV6_FRAGMENTS, "Frame", FieldType::Text, "Fragment of a reassembled IPv6 datagram"),
+    field_def("ipv6.fragment.count", LAYER_IPV6_FRAGMENTS, "Fragment Count", FieldType::Number, "Number of fragments reassembled"),
+    field_def("ipv6.reassembled.length", LAYER_IPV6_FRAGMENTS, "Reassembled Length", FieldType::Number, "Length of the reassembled IPv6 payload"),
+    field_def("ah.spi", LAYER_AH, "SPI", FieldType::Number, "IPsec AH security parameters index"),
+    field_def("ah.sequence", LAYER_AH, "Sequence Number", FieldType::Number, "IPsec AH sequence number"),
+    field_def("esp.spi", LAYER_ESP, "SPI", FieldType::Number, "IPsec ESP security parameters index"),
+    field_def("esp.sequence", LAYER_ESP, "Sequence Number", FieldType::Number, "IPsec ESP sequence number"),
+    field_def("tcp.seq", LAYER_TCP, "Sequence Number", FieldType::Number, "TCP sequence number"),
+    field_def("tcp.ack", LAYER_TCP, "Acknowledgment Number", FieldType::Number, "TCP acknowledgment number"),
+    field_def("tcp.hdr_len", LAYER_TCP, "Header Length", FieldType::Number, "TCP header length in bytes"),
+    field_def("tcp.flags", LAYER_TCP, "Flags", FieldType::Number, "TCP flags byte"),
+    field_def("tcp.flags.cwr", LAYER_TCP, "CWR", FieldType::Number, "TCP Congestion Window Reduced flag"),
+    field_def("tcp.flags.ece", LAYER_TCP, "ECE", FieldType::Number, "TCP ECN-Echo flag"),
+    field_def("tcp.flags.urg", LAYER_TCP, "URG", FieldType::Number, "TCP Urgent flag"),
+    field_def("tcp.flags.ack", LAYER_TCP, "ACK", FieldType::Number, "TCP Acknowledgment flag"),
+    field_def("tcp.flags.push", LAYER_TCP, "PSH", FieldType::Number, "TCP Push flag"),
+    field_def("tcp.flags.reset", LAYER_TCP, "RST", FieldType::Number, "TCP Reset flag"),
+    field_def("tcp.flags.syn", LAYER_TCP, "SYN", FieldType::Number, "TCP Syn flag"),
+    field_def("tcp.flags.fin", LAYER_TCP, "FIN", FieldType::Number, "TCP Fin flag"),
+    field_def("tcp.window", LAYER_TCP, "Window Size", FieldType::Number, "TCP window size"),
+    field_def("tcp.checksum", LAYER_TCP, "Checksum", FieldType::Text, "TCP checksum and its verification status"),
+    field_def("tcp.options.mss_val", LAYER_TCP, "Maximum Segment Size", FieldType::Number, "TCP MSS option"),
+    field_def("tcp.options.wscale.shift", LAYER_TCP, "Window Scale", FieldType::Number, "TCP window scale shift count"),
+    field_def("tcp.options.sack_perm", LAYER_TCP, "SACK Permitted", FieldType::Text, "TCP SACK permitted option"),
+    field_def("tcp.options.sack", LAYER_TCP, "SACK Block", FieldType::Text, "TCP SACK block (left edge-right edge)"),
+    field_def("tcp.options.timestamp.tsval", LAYER_TCP, "Timestamp Value", FieldType::Number, "TCP timestamp value"),
+    field_def("tcp.options.timestamp.tsecr", LAYER_TCP, "Timestamp Echo Reply", FieldType::Number, "TCP timestamp echo reply"),
+    field_def("tcp.options.tfo.cookie", LAYER_TCP, "TFO Cookie", FieldType::Text, "TCP Fast Open cookie, or a cookie request"),
+    field_def("tcp.options.mptcp.subtype", LAYER_TCP, "MPTCP Subtype", FieldType::Text, "Multipath TCP option subtype"),
+    field_def("udp.length", LAYER_UDP, "Length", FieldType::Number, "UDP length in bytes"),
+    field_def("udp.checksum", LAYER_UDP, "Checksum", FieldType::Text, "UDP checksum and its verification status"),
+    field_def("dns.len", LAYER_DNS, "Payload Length", FieldType::Number, "DNS payload length in bytes"),
+    field_def("dns.id", LAYER_DNS, "Transaction ID", FieldType::Number, "DNS transaction ID"),
+    field_def("dns.flags", LAYER_DNS, "Flags", FieldType::Number, "DNS header flags"),
+    field_def("dns.flags.response", LAYER_DNS, "Response", FieldType::Number, "1 for responses, 0 for queries"),
+    field_def("dns.flags.opcode", LAYER_DNS, "Opcode", FieldType::Number, "DNS opcode"),
+    field_def("dns.flags.truncated", LAYER_DNS, "Truncated", FieldType::Number, "DNS truncation (TC) flag"),
+    field_def("dns.flags.rcode", LAYER_DNS, "Response Code", FieldType::Number, "DNS response code, including EDNS extended bits"),
+    field_def("dns.count.queries", LAYER_DNS, "Questions", FieldType::Number, "DNS question count"),
+    field_def("dns.count.answers", LAYER_DNS, "Answer RRs", FieldType::Number, "DNS answer record count"),
+    field_def("dns.qry.name", LAYER_DNS, "Query Name", FieldType::Text, "DNS queried name"),
+    field_def("dns.qry.type", LAYER_DNS, "Query Type", FieldType::Number, "DNS queried record type"),
+    field_def("dns.answer", LAYER_DNS, "Answer", FieldType::Text, "DNS answer record"),
+    field_def("dns.authority", LAYER_DNS, "Authority", FieldType::Text, "DNS authority record"),
+    field_def("dns.additional", LAYER_DNS, "Additional", FieldType::Text, "DNS additional record"),
+    field_def("dns.edns.udp_size", LAYER_DNS, "EDNS UDP Payload Size", FieldType::Number, "EDNS0 advertised UDP payload size"),
+    field_def("http.method", LAYER_HTTP, "Method", FieldType::Text, "HTTP request method"),
+    field_def("http.request.uri", LAYER_HTTP, "Request URI", FieldType::Text, "HTTP request target"),
+    field_def("http.version", LAYER_HTTP, "Version", FieldType::Text, "HTTP version"),
+    field_def("http.response.code", LAYER_HTTP, "Status Code", FieldType::Number, "HTTP response status code"),
+    field_def("http.response.phrase", LAYER_HTTP, "Reason Phrase", FieldType::Text, "HTTP response reason phrase"),
+    field_def("http.host", LAYER_HTTP, "Host", FieldType::Text, "HTTP Host header"),
+    field_def("http.user_agent", LAYER_HTTP, "User-Agent", FieldType::Text, "HTTP User-Agent header"),
+    field_def("http.referer", LAYER_HTTP, "Referer", FieldType::Text, "HTTP Referer header"),
+    field_def("http.cookie", LAYER_HTTP, "Cookie", FieldType::Text, "HTTP Cookie header"),
+    field_def("http.set_cookie", LAYER_HTTP, "Set-Cookie", FieldType::Text, "HTTP Set-Cookie header"),
+    field_def("http.authorization", LAYER_HTTP, "Authorization", FieldType::Text, "HTTP Authorization header"),
+    field_def("http.location", LAYER_HTTP, "Location", FieldType::Text, "HTTP Location header"),
+    field_def("http.server", LAYER_HTTP, "Server", FieldType::Text, "HTTP Server header"),
+    field_def("http.content_type", LAYER_HTTP, "Content-Type", FieldType::Text, "HTTP Content-Type header"),
+    field_def("http.content_length", LAYER_HTTP, "Content-Length", FieldType::Number, "HTTP Content-Length header"),
+    field_def("http.transfer_encoding", LAYER_HTTP, "Transfer-Encoding", FieldType::Text, "HTTP Transfer-Encoding header"),
+    field_def("http.len", LAYER_HTTP, "Data", FieldType::Number, "HTTP payload length in bytes"),
+    field_def("dhcp.type", LAYER_DHCP, "Message Type", FieldType::Number, "BOOTP op (1 request, 2 reply)"),
+    field_def("dhcp.id", LAYER_DHCP, "Transaction ID", FieldType::Number, "DHCP transaction ID"),
+    field_def("dhcp.ip.client", LAYER_DHCP, "Client IP Address", FieldType::Address, "DHCP client IP address (ciaddr)"),
+    field_def("dhcp.ip.your", LAYER_DHCP, "Your IP Address", FieldType::Address, "DHCP assigned IP address (yiaddr)"),
+    field_def("dhcp.ip.relay", LAYER_DHCP, "Relay Agent IP Address", FieldType::Address, "DHCP relay agent IP address (giaddr)"),
+    field_def("dhcp.hw.mac_addr", LAYER_DHCP, "Client Hardware Address", FieldType::Address, "DHCP client MAC address"),
+    field_def("dhcp.option.dhcp", LAYER_DHCP, "DHCP Message Type", FieldType::Number, "DHCP message type (1 Discover ... 8 Inform)"),
+    field_def("dhcp.option.hostname", LAYER_DHCP, "Host Name", FieldType::Text, "DHCP host name option"),
+    field_def("dhcp.option.requested_ip_address", LAYER_DHCP, "Requested IP Address", FieldType::Address, "DHCP requested IP address"),
+    field_def("dhcp.option.ip_address_lease_time", LAYER_DHCP, "IP Address Lease Time", FieldType::Number, "DHCP lease time in seconds"),
+    field_def("dhcp.option.dhcp_server_id", LAYER_DHCP, "DHCP Server Identifier", FieldType::Address, "DHCP server identifier"),
+    field_def("dhcp.option.vendor_class_id", LAYER_DHCP, "Vendor Class Identifier", FieldType::Text, "DHCP vendor class identifier"),
+    field_def("dhcp.option.request_list_item", LAYER_DHCP, "Parameter Request List Item", FieldType::Number, "DHCP parameter request list entry"),
+    field_def("dhcp.option.client_id", LAYER_DHCP, "Client Identifier", FieldType::Text, "DHCP client identifier"),
+    field_def("dhcp.fqdn.name", LAYER_DHCP, "Client FQDN", FieldType::Text, "DHCP client FQDN"),
+    field_def("dhcpv6.msgtype", LAYER_DHCPV6, "Message Type", FieldType::Number, "DHCPv6 message type"),
+    field_def("dhcpv6.xid", LAYER_DHCPV6, "Transaction ID", FieldType::Number, "DHCPv6 transaction ID"),
+    field_def("dhcpv6.duid", LAYER_DHCPV6, "Client DUID", FieldType::Text, "DHCPv6 client DUID"),
+    field_def("dhcpv6.iaaddr.ip", LAYER_DHCPV6, "IA Address", FieldType::Address, "DHCPv6 assigned address"),
+    field_def("dhcpv6.iaaddr.valid_lifetime", LAYER_DHCPV6, "Valid Lifetime", FieldType::Number, "DHCPv6 address valid lifetime in seconds"),
+    field_def("dhcpv6.client_domain", LAYER_DHCPV6, "Client FQDN", FieldType::Text, "DHCPv6 client FQDN"),
+    field_def("dhcpv6.vendorclass", LAYER_DHCPV6, "Vendor Class", FieldType::Text, "DHCPv6 vendor class"),
+    field_def("dhcpv6.requested_option_code", LAYER_DHCPV6, "Requested Option Code", FieldType::Number, "DHCPv6 option request entry"),
+    field_def("tls.record.content_type", LAYER_TLS, "Record Type", FieldType::Number, "TLS record content type"),
+    field_def("tls.record.version", LAYER_TLS, "Record Version", FieldType::Number, "TLS record layer version"),
+    field_def("tls.handshake.type", LAYER_TLS, "Handshake Type", FieldType::Number, "TLS handshake message type"),
+    field_def("tls.handshake.version", LAYER_TLS, "Version", FieldType::Number, "TLS ClientHello/ServerHello version"),
+    field_def("tls.handshake.ciphersuite", LAYER_TLS, "Cipher Suite", FieldType::Number, "TLS offered or selected cipher suite"),
+    field_def("tls.handshake.extension.type", LAYER_TLS, "Extension", FieldType::Number, "TLS hello extension type"),
+    field_def("tls.handshake.extensions_server_name", LAYER_TLS, "Server Name", FieldType::Text, "TLS Server Name Indication"),
+    field_def("tls.handshake.extensions_alpn_str", LAYER_TLS, "ALPN Protocol", FieldType::Text, "TLS ALPN protocol"),
+    field_def("tls.handshake.extensions.supported_version", LAYER_TLS, "Supported Version", FieldType::Number, "TLS supported or selected version"),
+    field_def("tls.handshake.extensions_supported_group", LAYER_TLS, "Supported Group", FieldType::Number, "TLS supported group"),
+    field_def("tls.handshake.extensions_key_share_group", LAYER_TLS, "Key Share Group", FieldType::Number, "TLS key share group"),
+    field_def("tls.handshake.ja3", LAYER_TLS, "JA3", FieldType::Text, "JA3 client fingerprint (MD5)"),
+    field_def("tls.handshake.ja3_full", LAYER_TLS, "JA3 Fullstring", FieldType::Text, "JA3 client fingerprint input"),
+    field_def("tls.handshake.ja3s", LAYER_TLS, "JA3S", FieldType::Text, "JA3S server fingerprint (MD5)"),
+    field_def("tls.handshake.ja3s_full", LAYER_TLS, "JA3S Fullstring", FieldType::Text, "JA3S server fingerprint input"),
+    field_def("tls.handshake.ja4", LAYER_TLS, "JA4", FieldType::Text, "JA4 client fingerprint"),
+    field_def("tls.handshake.ja4s", LAYER_TLS, "JA4S", FieldType::Text, "JA4S server fingerprint"),
+    field_def("quic.header_form", LAYER_QUIC, "Header Form", FieldType::Text, "QUIC long or short header"),
+    field_def("quic.long.packet_type", LAYER_QUIC, "Packet Type", FieldType::Text, "QUIC packet type (Initial, Handshake, ...)"),
+    field_def("quic.version", LAYER_QUIC, "Version", FieldType::Number, "QUIC version"),
+    field_def("quic.dcid", LAYER_QUIC, "Destination Connection ID", FieldType::Text, "QUIC destination connection ID"),
+    field_def("quic.scid", LAYER_QUIC, "Source Connection ID", FieldType::Text, "QUIC source connection ID"),
+    field_def("quic.packet_number", LAYER_QUIC, "Packet Number", FieldType::Number, "QUIC packet number (decrypted Initials)"),
+    field_def("quic.frame", LAYER_QUIC, "Frame", FieldType::Text, "QUIC frame in a decrypted Initial"),
+    field_def("tls.alert.description", LAYER_TLS, "Alert Description", FieldType::Number, "TLS alert description"),
+    field_def("tls.x509.subject", LAYER_TLS, "Subject", FieldType::Text, "Certificate subject"),
+    field_def("tls.x509.issuer", LAYER_TLS, "Issuer", FieldType::Text, "Certificate issuer"),
+    field_def("tls.x509.not_after", LAYER_TLS, "Not After", FieldType::Text, "Certificate expiry"),
+    field_def("tls.x509.san", LAYER_TLS, "Subject Alt Name", FieldType::Text, "Certificate subject alternative name"),
+    field_def("ssh.protocol", LAYER_SSH, "Protocol", FieldType::Text, "SSH identification string"),
+    field_def("ssh.software_version", LAYER_SSH, "Software Version", FieldType::Text, "SSH software version from the identification string"),
+    field_def("ssh.packet_length", LAYER_SSH, "Packet Length", FieldType::Number, "SSH binary packet length"),
+    field_def("ssh.padding_length", LAYER_SSH, "Padding Length", FieldType::Number, "SSH binary packet padding length"),
+    field_def("ssh.message_code", LAYER_SSH, "Message Code", FieldType::Number, "SSH message code of a cleartext packet"),
+    field_def("ssh.kex_algorithms", LAYER_SSH, "Key Exchange Algorithms", FieldType::Text, "SSH KEXINIT key exchange algorithms"),
+    field_def("ssh.server_host_key_algorithms", LAYER_SSH, "Server Host Key Algorithms", FieldType::Text, "SSH KEXINIT server host key algorithms"),
+    field_def("ssh.encryption_algorithms_client_to_server", LAYER_SSH, "Encryption Algorithms (client to server)", FieldType::Text, "SSH KEXINIT client to server ciphers"),
+    field_def("ssh.encryption_algorithms_server_to_client", LAYER_SSH, "Encryption Algorithms (server to client)", FieldType::Text, "SSH KEXINIT server to client ciphers"),
+    field_def("ssh.mac_algorithms_client_to_server", LAYER_SSH, "MAC Algorithms (client to server)", FieldType::Text, "SSH KEXINIT client to server MACs"),
+    field_def("ssh.mac_algorithms_server_to_client", LAYER_SSH, "MAC Algorithms (server to client)", FieldType::Text, "SSH KEXINIT server to client MACs"),
+    field_def("ssh.kex.hassh", LAYER_SSH, "HASSH", FieldType::Text, "HASSH client fingerprint (MD5)"),
+    field_def("ssh.kex.hassh_algorithms", LAYER_SSH, "HASSH Algorithms", FieldType::Text, "HASSH client fingerprint input"),
+    field_def("ssh.kex.hasshserver", LAYER_SSH, "HASSHServer", FieldType::Text, "HASSHServer server fingerprint (MD5)"),
+    field_def("ssh.kex.hasshserver_algorithms", LAYER_SSH, "HASSHServer Algorithms", FieldType::Text, "HASSHServer server fingerprint input"),
+    field_def("ssh.encrypted_packet", LAYER_SSH, "Encrypted Packet", FieldType::Number, "Length of encrypted SSH data"),
+    field_def("nbss.length", LAYER_NBSS, "Length", FieldType::Number, "NetBIOS session message length"),
+    field_def("smb.cmd", LAYER_SMB, "SMB Command", FieldType::Number, "SMB1 command"),
+    field_def("smb2.cmd", LAYER_SMB2, "Command", FieldType::Number, "SMB2 command"),
+    field_def("smb2.nt_status", LAYER_SMB2, "NT Status", FieldType::Number, "SMB2 NT status code"),
+    field_def("smb2.flags.response", LAYER_SMB2, "Response", FieldType::Number, "1 for SMB2 responses, 0 for requests"),
+    field_def("smb2.msg_id", LAYER_SMB2, "Message ID", FieldType::Number, "SMB2 message ID"),
+    field_def("smb2.tid", LAYER_SMB2, "Tree Id", FieldType::Number, "SMB2 tree ID"),
+    field_def("smb2.sesid", LAYER_SMB2, "Session Id", FieldType::Number, "SMB2 session ID"),
+    field_def("smb2.dialect", LAYER_SMB2, "Dialect", FieldType::Number, "SMB2 dialect offered or selected"),
+    field_def("smb2.tree", LAYER_SMB2, "Tree", FieldType::Text, "SMB2 share path of a tree connect"),
+    field_def("smb2.filename", LAYER_SMB2, "Filename", FieldType::Text, "SMB2 file name of a create request"),
+    field_def("smb2.fid", LAYER_SMB2, "File Id", FieldType::Text, "SMB2 file ID"),
+    field_def("smb2.file_offset", LAYER_SMB2, "File Offset", FieldType::Number, "SMB2 read or write offset"),
+    field_def("smb2.read_length", LAYER_SMB2, "Read Length", FieldType::Number, "SMB2 bytes requested by a read"),
+    field_def("smb2.write_length", LAYER_SMB2, "Write Length", FieldType::Number, "SMB2 bytes written"),
+    field_def("smb2.data_length", LAYER_SMB2, "Data Length", FieldType::Number, "SMB2 bytes returned by a read"),
+    field_def("smb2.end_of_file", LAYER_SMB2, "End Of File", FieldType::Number, "SMB2 file size of an opened file"),
+    field_def("ntlmssp.auth.domain", LAYER_NTLMSSP, "Domain", FieldType::Text, "NTLMSSP authenticating domain"),
+    field_def("ntlmssp.auth.username", LAYER_NTLMSSP, "User", FieldType::Text, "NTLMSSP authenticating user"),
+    field_def("ntlmssp.auth.hostname", LAYER_NTLMSSP, "Host", FieldType::Text, "NTLMSSP client workstation"),
+    field_def("data.len", LAYER_DATA, "Payload Length", FieldType::Number, "Application payload length in bytes"),
+];
+
+/// Looks up a registered dissector field by filter name.
+pub fn lookup_field(name: &str) -> Option<&'static FieldDef> {
+    FIELD_REGISTRY
+        .iter()
+        .find(|def| def.name.eq_ignore_ascii_case(name))
+}
+
+/// Collects the display values of a registered field from a dissected packet.
+///
+/// A field can occur more than once, e.g. when a protocol layer is repeated.
+pub fn field_values<'a>(detail: &'a PacketDetail, def: &FieldDef) -> Vec<&'a str> {
+    detail
+        .layers
+        .iter()
+        .filter(|layer| layer.name == def.layer)
+        .flat_map(|layer| layer.fields.iter())
+        .filter(|field| field.name == def.field)
+        .map(|field| field.value.as_str())
+        .collect()
+}
+
+/// A detail view field without an expert note.
+pub(crate) fn field(name: &str, value: String, range: (usize, usize)) -> PacketField {
+    PacketField {
+        name: name.to_string(),
+        value,
+        range,
+        expert: None,
+    }
+}
+
+/// Lowercase hex digits, or "(empty)" for no bytes.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "(empty)".to_string();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads a big-endian u16 at `pos`, if in bounds.
+pub(crate) fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+/// Reads a big-endian u32 at `pos`, if in bounds.
+pub(crate) fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Reads a little-endian u16 at `pos`, if in bounds.
+pub(crate) fn le_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+/// Reads a little-endian u32 at `pos`, if in bounds.
+pub(crate) fn le_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Reads a little-endian u64 at `pos`, if in bounds.
+pub(crate) fn le_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
 /// Local OUI database for standalone manufacturer identification
 fn get_manufacturer(mac: &str) -> Option<String> {
     let prefix = mac.replace(':', "").to_uppercase();
@@ -96,10 +493,14 @@ fn detect_artifacts(data: &[u8]) -> Vec<Artifact> {
 
 /// Extracts a flow key from a raw packet if it's an IP packet with a transport layer.
 pub fn get_flow_key(raw_data: &[u8]) -> Option<FlowKey> {
-    let ethernet = EthernetPacket::new(raw_data)?;
-    match ethernet.get_ethertype() {
+    let frame = tunnel::decapsulate(raw_data)?;
+    match frame.ethertype {
         EtherTypes::Ipv4 => {
-            let ipv4 = Ipv4Packet::new(ethernet.payload())?;
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            // Fragments join their flow once the datagram is reassembled
+            if is_fragment(&ipv4) {
+                return None;
+            }
             let src_ip = IpAddr::V4(ipv4.get_source());
             let dst_ip = IpAddr::V4(ipv4.get_destination());
             let protocol = ipv4.get_next_level_protocol().0;
@@ -118,18 +519,22 @@ pub fn get_flow_key(raw_data: &[u8]) -> Option<FlowKey> {
             Some(FlowKey::new(src_ip, dst_ip, protocol, src_port, dst_port))
         }
         EtherTypes::Ipv6 => {
-            let ipv6 = Ipv6Packet::new(ethernet.payload())?;
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
+            if chain.is_fragment() {
+                return None;
+            }
             let src_ip = IpAddr::V6(ipv6.get_source());
             let dst_ip = IpAddr::V6(ipv6.get_destination());
-            let protocol = ipv6.get_next_header().0;
+            let protocol = next_header.0;
 
-            let (src_port, dst_port) = match ipv6.get_next_header() {
+            let (src_port, dst_port) = match next_header {
                 IpNextHeaderProtocols::Tcp => {
-                    let tcp = TcpPacket::new(ipv6.payload())?;
+                    let tcp = TcpPacket::new(payload)?;
                     (tcp.get_source(), tcp.get_destination())
                 }
                 IpNextHeaderProtocols::Udp => {
-                    let udp = UdpPacket::new(ipv6.payload())?;
+                    let udp = UdpPacket::new(payload)?;
                     (udp.get_source(), udp.get_destination())
                 }
                 _ => (0, 0),
@@ -140,219 +545,214 @@ pub fn get_flow_key(raw_data: &[u8]) -> Option<FlowKey> {
     }
 }
 
-// Application layer protocol detection based on ports
-fn detect_app_protocol(src_port: u16, dst_port: u16, payload: &[u8]) -> Option<(String, String)> {
-    let port = if dst_port < 1024 { dst_port } else { src_port };
+/// IP protocol number and transport ports (in wire order) of a packet.
+pub type Endpoints = (u8, Option<u16>, Option<u16>);
 
-    match port {
-        53 => Some(("DNS".to_string(), "DNS Query".to_string())),
-        67 | 68 => Some((
-            "DHCP".to_string(),
-            if payload.get(0) == Some(&1) {
-                "DHCP Discover"
-            } else {
-                "DHCP"
-            }
-            .to_string(),
-        )),
-        69 => Some(("TFTP".to_string(), "TFTP".to_string())),
-        123 => Some(("NTP".to_string(), "NTP Request".to_string())),
-        137 | 138 => Some((
-            "NetBIOS".to_string(),
-            if port == 137 {
-                "NetBIOS Name Query"
-            } else {
-                "NetBIOS Datagram"
-            }
-            .to_string(),
-        )),
-        161 | 162 => Some((
-            "SNMP".to_string(),
-            if port == 161 { "SNMP Get" } else { "SNMP Trap" }.to_string(),
-        )),
-        389 => Some(("LDAP".to_string(), "LDAP Query".to_string())),
-        443 => Some(("HTTPS".to_string(), "TLS/SSL".to_string())),
-        445 => Some(("SMB".to_string(), "SMB".to_string())),
-        514 => Some(("Syslog".to_string(), "Syslog".to_string())),
-        631 => Some(("IPP".to_string(), "Printer (IPP)".to_string())),
-        1900 => Some(("SSDP".to_string(), "UPnP Discovery".to_string())),
-        5353 => Some(("mDNS".to_string(), "Multicast DNS".to_string())),
-        7070 => Some(("SIP".to_string(), "SIP Invite".to_string())),
-        8080 => Some(("HTTP".to_string(), "HTTP Proxy".to_string())),
-        8443 => Some(("HTTPS".to_string(), "TLS Alt".to_string())),
-        9200 => Some(("Elasticsearch".to_string(), "ES Query".to_string())),
-        27017 => Some(("MongoDB".to_string(), "MongoDB Query".to_string())),
-        _ => {
-            if payload.len() >= 4 {
-                if payload.starts_with(b"GET ")
-                    || payload.starts_with(b"POST ")
-                    || payload.starts_with(b"PUT ")
-                    || payload.starts_with(b"DELETE ")
-                    || payload.starts_with(b"HEAD ")
-                    || payload.starts_with(b"HTTP/")
-                {
-                    Some(("HTTP".to_string(), "HTTP".to_string()))
-                } else if payload.starts_with(b"{\"") || payload.starts_with(b"[{\"") {
-                    Some(("JSON".to_string(), "JSON Data".to_string()))
-                } else {
-                    None
-                }
+/// Extracts the IP protocol number and transport ports (in wire order) from a raw packet.
+///
+/// Ports are `None` for IP packets without a TCP/UDP header.
+pub fn get_transport_endpoints(raw_data: &[u8]) -> Option<Endpoints> {
+    let frame = tunnel::decapsulate(raw_data)?;
+    let (protocol, ports) = match frame.ethertype {
+        EtherTypes::Ipv4 => {
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            let protocol = ipv4.get_next_level_protocol();
+            // Only the first fragment carries the transport header
+            let ports = if ipv4.get_fragment_offset() == 0 {
+                transport_ports(protocol, ipv4.payload())
             } else {
                 None
-            }
+            };
+            (protocol, ports)
         }
+        EtherTypes::Ipv6 => {
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            let (protocol, payload, chain) = ipv6_upper_layer(&ipv6);
+            let ports = match chain.fragment() {
+                Some(fragment) if fragment.offset != 0 => None,
+                _ => transport_ports(protocol, payload),
+            };
+            (protocol, ports)
+        }
+        _ => return None,
+    };
+    Some((protocol.0, ports.map(|p| p.0), ports.map(|p| p.1)))
+}
+
+fn transport_ports(protocol: IpNextHeaderProtocol, payload: &[u8]) -> Option<(u16, u16)> {
+    match protocol {
+        IpNextHeaderProtocols::Tcp => {
+            TcpPacket::new(payload).map(|tcp| (tcp.get_source(), tcp.get_destination()))
+        }
+        IpNextHeaderProtocols::Udp => {
+            UdpPacket::new(payload).map(|udp| (udp.get_source(), udp.get_destination()))
+        }
+        _ => None,
     }
 }
 
+/// Protocol and info columns from the application dissectors, if one accepts the payload.
+fn application_summary(
+    transport: registry::Transport,
+    (src_port, dst_port): (u16, u16),
+    payload: &[u8],
+    transport_info: &str,
+    learned: Option<&str>,
+) -> Option<(String, String)> {
+    let ctx = registry::Context {
+        transport,
+        src_port,
+        dst_port,
+        payload,
+        offset: 0,
+        transport_info,
+        parent: None,
+        learned,
+    };
+    registry::read().summarize(&ctx)
+}
+
+/// Protocol and info columns for the transport layer of an IP packet and
+/// whatever it carries. None for other IP protocols.
+fn transport_summary(
+    protocol: IpNextHeaderProtocol,
+    segment: &[u8],
+    src: &str,
+    dst: &str,
+    learned: Option<&str>,
+    bases: SequenceBases,
+) -> Option<(String, String)> {
+    let unparsed = |proto: &str| (proto.to_string(), format!("{} → {}", src, dst));
+    Some(match protocol {
+        IpNextHeaderProtocols::Tcp => match TcpPacket::new(segment) {
+            Some(tcp) => {
+                let info = tcp::summarize(&tcp, bases);
+                let ports = (tcp.get_source(), tcp.get_destination());
+                application_summary(
+                    registry::Transport::Tcp,
+                    ports,
+                    tcp.payload(),
+                    &info,
+                    learned,
+                )
+                .unwrap_or_else(|| (PROTO_TCP.to_string(), info))
+            }
+            None => unparsed(PROTO_TCP),
+        },
+        IpNextHeaderProtocols::Udp => match UdpPacket::new(segment) {
+            Some(udp) => {
+                let ports = (udp.get_source(), udp.get_destination());
+                let info = format!("{}:{} → {}:{}", src, ports.0, dst, ports.1);
+                application_summary(
+                    registry::Transport::Udp,
+                    ports,
+                    udp.payload(),
+                    &info,
+                    learned,
+                )
+                .unwrap_or_else(|| (PROTO_UDP.to_string(), info))
+            }
+            None => unparsed(PROTO_UDP),
+        },
+        IpNextHeaderProtocols::Icmp => (
+            PROTO_ICMP.to_string(),
+            icmp::summarize(icmp::Version::V4, segment),
+        ),
+        IpNextHeaderProtocols::Icmpv6 => (
+            PROTO_ICMPV6.to_string(),
+            icmp::summarize(icmp::Version::V6, segment),
+        ),
+        _ => return None,
+    })
+}
+
 // Lightweight parser for the packet list view
 pub fn parse_summary(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<PacketSummary> {
-    let ethernet = EthernetPacket::new(raw_data)?;
+    parse_flow_summary(raw_data, id, timestamp_ns, None, SequenceBases::default())
+}
 
-    let (source_addr, dest_addr, protocol, info) = match ethernet.get_ethertype() {
+/// Summarizes a packet like [`parse_summary`], trying the application
+/// dissector `learned` for its flow first and showing TCP Seq/Ack numbers
+/// relative to `bases`.
+pub fn parse_flow_summary(
+    raw_data: &[u8],
+    id: u64,
+    timestamp_ns: i64,
+    learned: Option<&str>,
+    bases: SequenceBases,
+) -> Option<PacketSummary> {
+    let frame = tunnel::decapsulate(raw_data)?;
+    let ethernet = EthernetPacket::new(&raw_data[frame.ethernet_offset..])?;
+
+    let (source_addr, dest_addr, protocol, info) = match frame.ethertype {
         EtherTypes::Ipv4 => {
-            let ipv4 = Ipv4Packet::new(ethernet.payload())?;
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
             let src = ipv4.get_source().to_string();
             let dst = ipv4.get_destination().to_string();
 
             match ipv4.get_next_level_protocol() {
-                IpNextHeaderProtocols::Tcp => {
-                    if let Some(tcp) = TcpPacket::new(ipv4.payload()) {
-                        let dst_port = tcp.get_destination();
-                        let src_port = tcp.get_source();
-                        let (proto, info_str) = if dst_port == 80 || src_port == 80 {
-                            (PROTO_HTTP.to_string(), "HTTP".to_string())
-                        } else if dst_port == 443 || src_port == 443 {
-                            (PROTO_HTTPS.to_string(), "TLS/SSL".to_string())
-                        } else {
-                            (
-                                PROTO_TCP.to_string(),
-                                format!("{}:{} → {}:{}", src, src_port, dst, dst_port),
-                            )
-                        };
-                        (src.clone(), dst.clone(), proto, info_str)
-                    } else {
-                        (
-                            src.clone(),
-                            dst.clone(),
-                            PROTO_TCP.to_string(),
-                            format!("{} → {}", src, dst),
-                        )
-                    }
-                }
-                IpNextHeaderProtocols::Udp => {
-                    if let Some(udp) = UdpPacket::new(ipv4.payload()) {
-                        let dst_port = udp.get_destination();
-                        let src_port = udp.get_source();
-                        let payload = udp.payload();
-
-                        let (proto, info_str) = if let Some((app_proto, app_info)) =
-                            detect_app_protocol(src_port, dst_port, payload)
-                        {
-                            (app_proto, app_info)
-                        } else {
-                            (
-                                PROTO_UDP.to_string(),
-                                format!("{}:{} → {}:{}", src, src_port, dst, dst_port),
-                            )
-                        };
-                        (src.clone(), dst.clone(), proto, info_str)
-                    } else {
-                        (
-                            src.clone(),
-                            dst.clone(),
-                            PROTO_UDP.to_string(),
-                            format!("{} → {}", src, dst),
-                        )
-                    }
-                }
-                IpNextHeaderProtocols::Icmp => (
-                    src.clone(),
-                    dst.clone(),
-                    PROTO_ICMP.to_string(),
-                    format!("{} → {} [ICMP]", src, dst),
-                ),
-                _ => (
-                    src.clone(),
-                    dst.clone(),
+                protocol if is_fragment(&ipv4) => (
+                    src,
+                    dst,
                     PROTO_IPV4.to_string(),
-                    format!("{} → {}", src, dst),
+                    format!(
+                        "Fragmented IP protocol (proto={} {}, off={}, ID={:04x})",
+                        protocol,
+                        protocol.0,
+                        ipv4.get_fragment_offset() as usize * 8,
+                        ipv4.get_identification()
+                    ),
                 ),
+                protocol => {
+                    let (proto, info) =
+                        transport_summary(protocol, ipv4.payload(), &src, &dst, learned, bases)
+                            .unwrap_or_else(|| {
+                                (PROTO_IPV4.to_string(), format!("{} → {}", src, dst))
+                            });
+                    (src, dst, proto, info)
+                }
             }
         }
         EtherTypes::Ipv6 => {
-            let ipv6 = Ipv6Packet::new(ethernet.payload())?;
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
             let src = ipv6.get_source().to_string();
             let dst = ipv6.get_destination().to_string();
+            let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
 
-            match ipv6.get_next_header() {
-                IpNextHeaderProtocols::Tcp => {
-                    if let Some(tcp) = TcpPacket::new(ipv6.payload()) {
-                        let dst_port = tcp.get_destination();
-                        let src_port = tcp.get_source();
-                        let (proto, info_str) = if dst_port == 80 || src_port == 80 {
-                            (PROTO_HTTP.to_string(), "HTTP".to_string())
-                        } else if dst_port == 443 || src_port == 443 {
-                            (PROTO_HTTPS.to_string(), "TLS/SSL".to_string())
-                        } else {
-                            (
-                                PROTO_TCP.to_string(),
-                                format!("{}:{} → {}:{}", src, src_port, dst, dst_port),
-                            )
-                        };
-                        (src.clone(), dst.clone(), proto, info_str)
-                    } else {
-                        (
-                            src.clone(),
-                            dst.clone(),
-                            PROTO_TCP.to_string(),
-                            format!("{} → {}", src, dst),
-                        )
-                    }
+            match next_header {
+                protocol if chain.is_fragment() => {
+                    let fragment = chain.fragment().unwrap_or(ipv6ext::FragmentHeader {
+                        offset: 0,
+                        more_fragments: false,
+                        identification: 0,
+                    });
+                    (
+                        src,
+                        dst,
+                        PROTO_IPV6.to_string(),
+                        format!(
+                            "IPv6 fragment (nxt={} {}, off={}, ID=0x{:08x})",
+                            protocol, protocol.0, fragment.offset, fragment.identification
+                        ),
+                    )
                 }
-                IpNextHeaderProtocols::Udp => {
-                    if let Some(udp) = UdpPacket::new(ipv6.payload()) {
-                        let dst_port = udp.get_destination();
-                        let src_port = udp.get_source();
-                        let payload = udp.payload();
-
-                        let (proto, info_str) = if let Some((app_proto, app_info)) =
-                            detect_app_protocol(src_port, dst_port, payload)
-                        {
-                            (app_proto, app_info)
-                        } else {
-                            (
-                                PROTO_UDP.to_string(),
-                                format!("{}:{} → {}:{}", src, src_port, dst, dst_port),
-                            )
-                        };
-                        (src.clone(), dst.clone(), proto, info_str)
-                    } else {
-                        (
-                            src.clone(),
-                            dst.clone(),
-                            PROTO_UDP.to_string(),
-                            format!("{} → {}", src, dst),
-                        )
-                    }
+                protocol => {
+                    let (proto, info) =
+                        transport_summary(protocol, payload, &src, &dst, learned, bases)
+                            .unwrap_or_else(|| {
+                                (PROTO_IPV6.to_string(), format!("{} → {}", src, dst))
+                            });
+                    (src, dst, proto, info)
                 }
-                IpNextHeaderProtocols::Icmpv6 => (
-                    src.clone(),
-                    dst.clone(),
-                    PROTO_ICMPV6.to_string(),
-                    format!("{} → {} [ICMPv6]", src, dst),
-                ),
-                _ => (
-                    src.clone(),
-                    dst.clone(),
-                    PROTO_IPV6.to_string(),
-                    format!("{} → {}", src, dst),
-                ),
             }
         }
         EtherTypes::Arp => {
             let src = ethernet.get_source().to_string();
             let dst = ethernet.get_destination().to_string();
-            (src, dst, PROTO_ARP.to_string(), "ARP Request".to_string())
+            let info = arp::parse(frame.payload)
+                .map(|packet| packet.summary())
+                .unwrap_or_else(|_| PROTO_ARP.to_string());
+            (src, dst, PROTO_ARP.to_string(), info)
         }
         _ => {
             let src = ethernet.get_source().to_string();
@@ -374,15 +774,492 @@ pub fn parse_summary(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<Pack
         protocol,
         length: raw_data.len() as u32,
         info,
+        vlan_id: frame.vlan_id(),
+        tags: Vec::new(),
+        color: None,
+    })
+}
+
+/// JA3/JA3S/JA4/JA4S of a complete ClientHello or ServerHello in a TCP
+/// packet, or in a decrypted QUIC client Initial.
+pub fn tls_fingerprints(raw_data: &[u8]) -> Option<TlsFingerprints> {
+    fingerprints_with(raw_data, None)
+}
+
+/// Fingerprints a packet like [`tls_fingerprints`], buffering the CRYPTO data
+/// of QUIC client Initials in `initials` until the ClientHello is complete.
+pub fn flow_tls_fingerprints(
+    raw_data: &[u8],
+    initials: &mut QuicCryptoTable,
+) -> Option<TlsFingerprints> {
+    fingerprints_with(raw_data, Some(initials))
+}
+
+fn fingerprints_with(
+    raw_data: &[u8],
+    initials: Option<&mut QuicCryptoTable>,
+) -> Option<TlsFingerprints> {
+    let (ip_proto, src_port, dst_port) = get_transport_endpoints(raw_data)?;
+    let payload = get_transport_payload(raw_data)?;
+    let mut fingerprints = TlsFingerprints::default();
+    if ip_proto == IpNextHeaderProtocols::Tcp.0 {
+        for record in tls::parse_records(&payload) {
+            if record.content_type == tls::CONTENT_HANDSHAKE {
+                hello_fingerprints(
+                    record.fragment,
+                    fingerprint::Transport::Tcp,
+                    &mut fingerprints,
+                );
+            }
+        }
+    } else if ip_proto == IpNextHeaderProtocols::Udp.0
+        && (src_port == Some(quic::QUIC_PORT) || dst_port == Some(quic::QUIC_PORT))
+    {
+        let (dcid, chunks) = quic::client_crypto(&payload)?;
+        let transport = fingerprint::Transport::Quic;
+        match initials {
+            Some(table) => {
+                let key = (get_flow_key(raw_data)?, dcid);
+                let buffered = table.record(key.clone(), chunks);
+                let (stream, _) =
+                    quic::assemble_crypto(buffered.iter().map(|(o, d)| (*o, d.as_slice())));
+                hello_fingerprints(&stream, transport, &mut fingerprints);
+                if !fingerprints.is_empty() {
+                    table.finish(&key);
+                }
+            }
+            None => {
+                let (stream, _) =
+                    quic::assemble_crypto(chunks.iter().map(|(o, d)| (*o, d.as_slice())));
+                hello_fingerprints(&stream, transport, &mut fingerprints);
+            }
+        }
+    }
+    (!fingerprints.is_empty()).then_some(fingerprints)
+}
+
+fn hello_fingerprints(
+    handshake_data: &[u8],
+    transport: fingerprint::Transport,
+    fingerprints: &mut TlsFingerprints,
+) {
+    for message in tls::parse_handshakes(handshake_data).unwrap_or_default() {
+        if !message.complete {
+            continue;
+        }
+        match &message.body {
+            tls::HandshakeBody::ClientHello(hello) => {
+                fingerprints.ja3 = Some(fingerprint::ja3(hello));
+                fingerprints.ja4 = Some(fingerprint::ja4(hello, transport));
+            }
+            tls::HandshakeBody::ServerHello(hello) => {
+                fingerprints.ja3s = Some(fingerprint::ja3s(hello));
+                fingerprints.ja4s = Some(fingerprint::ja4s(hello, transport));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// HASSH or HASSHServer of a complete KEXINIT, the identification string and
+/// whether a NEWKEYS ends the cleartext phase, in a TCP packet on port 22, in
+/// a flow `learned` to be SSH, or opening with an identification string.
+pub fn ssh_fingerprints(raw_data: &[u8], learned: Option<&str>) -> Option<SshFingerprints> {
+    let (ip_proto, src_port, dst_port) = get_transport_endpoints(raw_data)?;
+    if ip_proto != IpNextHeaderProtocols::Tcp.0 {
+        return None;
+    }
+    let payload = get_transport_payload(raw_data)?;
+    let ctx = registry::Context {
+        transport: registry::Transport::Tcp,
+        src_port: src_port?,
+        dst_port: dst_port?,
+        payload: &payload,
+        offset: 0,
+        transport_info: "",
+        parent: None,
+        learned,
+    };
+    let fingerprints = ssh::fingerprints(&ctx);
+    (!fingerprints.is_empty()).then_some(fingerprints)
+}
+
+/// The SMB2 requests and responses in a TCP packet, for the capture's SMB
+/// session table.
+pub fn smb_commands(raw_data: &[u8]) -> Vec<SmbCommand> {
+    let is_tcp = get_transport_endpoints(raw_data)
+        .is_some_and(|(ip_proto, _, _)| ip_proto == IpNextHeaderProtocols::Tcp.0);
+    let (true, Some(key), Some(payload)) = (
+        is_tcp,
+        get_flow_key(raw_data),
+        get_transport_payload(raw_data),
+    ) else {
+        return Vec::new();
+    };
+    smb::smb2_messages(&payload)
+        .into_iter()
+        .map(|message| {
+            let op = match message.body {
+                smb::Body::SessionSetupRequest { auth: Some(auth) } => SmbOp::SessionSetup {
+                    user: auth.user.value,
+                    domain: auth.domain.value,
+                    host: auth.host.value,
+                },
+                smb::Body::TreeConnectRequest { path } => SmbOp::TreeConnect { path },
+                smb::Body::CreateRequest { name } => SmbOp::Create { name },
+                smb::Body::CreateResponse { file_id, .. } => SmbOp::Opened { file_id },
+                smb::Body::ReadRequest {
+                    file_id,
+                    offset,
+                    length,
+                } => SmbOp::Read {
+                    file_id,
+                    offset,
+                    length: u64::from(length),
+                },
+                smb::Body::ReadResponse { data } => SmbOp::ReadData {
+                    length: u64::from(data.length),
+                },
+                smb::Body::WriteRequest {
+                    file_id,
+                    offset,
+                    data,
+                } => SmbOp::Write {
+                    file_id,
+                    offset,
+                    length: u64::from(data.length),
+                },
+                smb::Body::CloseRequest { file_id } => SmbOp::Close { file_id },
+                _ => SmbOp::Other,
+            };
+            SmbCommand {
+                key: key.clone(),
+                message_id: message.message_id,
+                session_id: message.session_id,
+                tree_id: message.tree_id,
+                is_response: message.response,
+                status: message.status,
+                op,
+            }
+        })
+        .collect()
+}
+
+/// MAC → IP → hostname information in a DHCP or DHCPv6 packet, for the
+/// capture's lease table.
+pub fn dhcp_lease_event(raw_data: &[u8]) -> Option<LeaseEvent> {
+    let (ip_proto, src_port, dst_port) = get_transport_endpoints(raw_data)?;
+    if ip_proto != IpNextHeaderProtocols::Udp.0 {
+        return None;
+    }
+    let is_port = |port| src_port == Some(port) || dst_port == Some(port);
+    if is_port(dhcp::SERVER_PORT) || is_port(dhcp::CLIENT_PORT) {
+        let payload = get_transport_payload(raw_data)?;
+        return dhcp::lease_event(&payload);
+    }
+    if is_port(dhcpv6::SERVER_PORT) || is_port(dhcpv6::CLIENT_PORT) {
+        let payload = get_transport_payload(raw_data)?;
+        let frame = tunnel::decapsulate(raw_data)?;
+        let ethernet = EthernetPacket::new(&raw_data[frame.ethernet_offset..])?;
+        let client_mac = if dhcpv6::is_from_client(&payload) {
+            ethernet.get_source()
+        } else {
+            ethernet.get_destination()
+        };
+        return dhcpv6::lease_event(&payload, &client_mac.to_string());
+    }
+    None
+}
+
+/// VXLAN/Geneve VNIs, GRE keys and ERSPAN sessions of the tunnels carrying
+/// a packet, kept as flow metadata since the flow key is the inner 5-tuple.
+pub fn tunnel_ids(raw_data: &[u8]) -> Vec<String> {
+    tunnel::decapsulate(raw_data)
+        .map(|frame| frame.tunnel_ids())
+        .unwrap_or_default()
+}
+
+fn is_fragment(ipv4: &Ipv4Packet) -> bool {
+    ipv4.get_flags() & Ipv4Flags::MoreFragments != 0 || ipv4.get_fragment_offset() != 0
+}
+
+/// The upper-layer protocol and payload of an IPv6 packet, past its extension headers.
+fn ipv6_upper_layer<'p>(ipv6: &'p Ipv6Packet) -> (IpNextHeaderProtocol, &'p [u8], ipv6ext::Chain) {
+    let payload = ipv6.payload();
+    let chain = ipv6ext::walk(ipv6.get_next_header().0, payload);
+    let upper = &payload[chain.payload_offset.min(payload.len())..];
+    (IpNextHeaderProtocol(chain.protocol), upper, chain)
+}
+
+fn extension_layer_name(kind: ipv6ext::Kind) -> &'static str {
+    match kind {
+        ipv6ext::Kind::HopByHop => LAYER_IPV6_HOP_BY_HOP,
+        ipv6ext::Kind::Routing { .. } => LAYER_IPV6_ROUTING,
+        ipv6ext::Kind::Fragment(_) => LAYER_IPV6_FRAGMENT,
+        ipv6ext::Kind::DestinationOptions => LAYER_IPV6_DESTINATION,
+        ipv6ext::Kind::Authentication => LAYER_AH,
+        ipv6ext::Kind::EncapsulatingSecurityPayload => LAYER_ESP,
+    }
+}
+
+/// The IPv4 or IPv6 fragment in a raw packet, for the capture's reassembly table.
+pub fn ip_fragment(raw_data: &[u8]) -> Option<Fragment> {
+    let frame = tunnel::decapsulate(raw_data)?;
+    match frame.ethertype {
+        EtherTypes::Ipv4 => {
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            let header_len = (ipv4.get_header_length() as usize) * 4;
+            if ipv4.get_version() != 4
+                || !is_fragment(&ipv4)
+                || header_len < 20
+                || frame.payload.len() < header_len
+            {
+                return None;
+            }
+            Some(Fragment {
+                key: FragmentKey {
+                    src: IpAddr::V4(ipv4.get_source()),
+                    dst: IpAddr::V4(ipv4.get_destination()),
+                    protocol: ipv4.get_next_level_protocol().0,
+                    id: u32::from(ipv4.get_identification()),
+                },
+                offset: ipv4.get_fragment_offset() as usize * 8,
+                more_fragments: ipv4.get_flags() & Ipv4Flags::MoreFragments != 0,
+                payload: ipv4.payload().to_vec(),
+                headers: raw_data[..frame.offset + header_len].to_vec(),
+                ip_offset: frame.offset,
+                version: 4,
+            })
+        }
+        EtherTypes::Ipv6 => {
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            if ipv6.get_version() != 6 {
+                return None;
+            }
+            let payload = ipv6.payload();
+            let chain = ipv6ext::walk(ipv6.get_next_header().0, payload);
+            let index = chain
+                .headers
+                .iter()
+                .position(|h| matches!(h.kind, ipv6ext::Kind::Fragment(f) if f.is_fragment()))?;
+            let header = chain.headers[index];
+            let ipv6ext::Kind::Fragment(fragment) = header.kind else {
+                return None;
+            };
+            // The unfragmentable part, with the Fragment header unlinked from the chain
+            let payload_offset = frame.offset + 40;
+            let mut headers = raw_data[..payload_offset + header.offset].to_vec();
+            let link = match index {
+                0 => frame.offset + 6,
+                _ => payload_offset + chain.headers[index - 1].offset,
+            };
+            headers[link] = header.next_header;
+            Some(Fragment {
+                key: FragmentKey {
+                    src: IpAddr::V6(ipv6.get_source()),
+                    dst: IpAddr::V6(ipv6.get_destination()),
+                    protocol: header.next_header,
+                    id: fragment.identification,
+                },
+                offset: fragment.offset,
+                more_fragments: fragment.more_fragments,
+                payload: payload[header.offset + header.len..].to_vec(),
+                headers,
+                ip_offset: frame.offset,
+                version: 6,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Rebuilds a frame carrying a reassembled datagram, with the first
+/// fragment's headers and the IP length (and IPv4 flags and checksum) fixed up.
+pub fn reassembled_frame(reassembly: &Reassembly) -> Vec<u8> {
+    let mut frame = reassembly.headers.clone();
+    frame.extend_from_slice(&reassembly.payload);
+    let header_len = reassembly
+        .headers
+        .len()
+        .saturating_sub(reassembly.ip_offset);
+    let Some(packet) = frame.get_mut(reassembly.ip_offset..) else {
+        return frame;
+    };
+    if reassembly.version == 6 {
+        if let Some(mut ipv6) = MutableIpv6Packet::new(packet) {
+            let payload_len = header_len.saturating_sub(40) + reassembly.payload.len();
+            ipv6.set_payload_length(payload_len as u16);
+        }
+    } else if let Some(mut ipv4) = MutableIpv4Packet::new(packet) {
+        ipv4.set_total_length((header_len + reassembly.payload.len()) as u16);
+        ipv4.set_flags(ipv4.get_flags() & !Ipv4Flags::MoreFragments);
+        ipv4.set_fragment_offset(0);
+        ipv4.set_checksum(0);
+        let checksum = ipv4::checksum(&ipv4.to_immutable());
+        ipv4.set_checksum(checksum);
+    }
+    frame
+}
+
+/// Replays the fragment frames of one datagram, as (packet ID, timestamp,
+/// frame) in capture order, returning the datagram they complete.
+pub fn reassemble_frames<'a>(
+    frames: impl IntoIterator<Item = (u64, i64, &'a [u8])>,
+) -> Option<Reassembly> {
+    let mut table = FragmentTable::default();
+    let mut reassembly = None;
+    for (id, timestamp_ns, data) in frames {
+        if let Some(fragment) = ip_fragment(data) {
+            reassembly = table.record(id, timestamp_ns, data.len() as u32, fragment);
+        }
+    }
+    reassembly
+}
+
+/// Dissects a reassembled datagram, listing its fragments after the IP layer
+/// and its unfragmentable extension headers.
+pub fn dissect_reassembled(
+    reassembly: &Reassembly,
+    id: u64,
+    timestamp_ns: i64,
+    options: &DissectOptions,
+) -> Option<PacketDetail> {
+    let frame = reassembled_frame(reassembly);
+    let mut detail = dissect_packet_with(&frame, id, timestamp_ns, options)?;
+    let mut fields: Vec<PacketField> = reassembly
+        .fragments
+        .iter()
+        .map(|fragment| PacketField {
+            name: "Frame".to_string(),
+            value: format!(
+                "#{}, payload {}-{} ({} bytes)",
+                fragment.packet_id,
+                fragment.offset,
+                fragment.offset + fragment.payload_len.max(1) - 1,
+                fragment.payload_len
+            ),
+            range: (0, 0),
+            expert: None,
+        })
+        .collect();
+    fields.push(PacketField {
+        name: "Fragment Count".to_string(),
+        value: reassembly.fragments.len().to_string(),
+        range: (0, 0),
+        expert: None,
+    });
+    fields.push(PacketField {
+        name: "Reassembled Length".to_string(),
+        value: format!("{} bytes", reassembly.payload.len()),
+        range: (0, 0),
+        expert: None,
+    });
+    let (ip_layer, name) = match reassembly.version {
+        6 => (LAYER_IPV6, LAYER_IPV6_FRAGMENTS),
+        _ => (LAYER_IPV4, LAYER_IPV4_FRAGMENTS),
+    };
+    let mut position = detail
+        .layers
+        .iter()
+        .rposition(|layer| layer.name == ip_layer)
+        .map_or(detail.layers.len(), |i| i + 1);
+    while detail.layers.get(position).is_some_and(|layer| {
+        [
+            LAYER_IPV6_HOP_BY_HOP,
+            LAYER_IPV6_ROUTING,
+            LAYER_IPV6_DESTINATION,
+        ]
+        .contains(&layer.name.as_str())
+    }) {
+        position += 1;
+    }
+    detail.layers.insert(
+        position,
+        ProtocolLayer {
+            name: name.to_string(),
+            fields,
+        },
+    );
+    Some(detail)
+}
+
+/// Identifies an ICMP or ICMPv6 echo request or reply for RTT matching.
+pub fn icmp_echo(raw_data: &[u8]) -> Option<IcmpEcho> {
+    let frame = tunnel::decapsulate(raw_data)?;
+    let echo = match frame.ethertype {
+        EtherTypes::Ipv4 => {
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            if ipv4.get_next_level_protocol() != IpNextHeaderProtocols::Icmp || is_fragment(&ipv4) {
+                return None;
+            }
+            icmp::echo(icmp::Version::V4, ipv4.payload())?
+        }
+        EtherTypes::Ipv6 => {
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
+            if next_header != IpNextHeaderProtocols::Icmpv6 || chain.is_fragment() {
+                return None;
+            }
+            icmp::echo(icmp::Version::V6, payload)?
+        }
+        _ => return None,
+    };
+    Some(IcmpEcho {
+        key: get_flow_key(raw_data)?,
+        is_request: echo.is_request,
+        identifier: echo.identifier,
+        sequence: echo.sequence,
+    })
+}
+
+/// The direction and sequence number of a TCP segment, for relative numbering.
+pub fn tcp_segment(raw_data: &[u8]) -> Option<TcpSegment> {
+    let frame = tunnel::decapsulate(raw_data)?;
+    let read = |src: IpAddr, segment: &[u8]| {
+        let tcp = TcpPacket::new(segment)?;
+        Some((
+            src,
+            tcp.get_source(),
+            tcp.get_sequence(),
+            tcp.get_flags() & TcpFlags::SYN != 0,
+        ))
+    };
+    let (src, src_port, sequence, syn) = match frame.ethertype {
+        EtherTypes::Ipv4 => {
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            if ipv4.get_next_level_protocol() != IpNextHeaderProtocols::Tcp || is_fragment(&ipv4) {
+                return None;
+            }
+            read(IpAddr::V4(ipv4.get_source()), ipv4.payload())?
+        }
+        EtherTypes::Ipv6 => {
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
+            if next_header != IpNextHeaderProtocols::Tcp || chain.is_fragment() {
+                return None;
+            }
+            read(IpAddr::V6(ipv6.get_source()), payload)?
+        }
+        _ => return None,
+    };
+    let key = get_flow_key(raw_data)?;
+    Some(TcpSegment {
+        forward: (key.src_ip, key.src_port) == (src, src_port),
+        key,
+        sequence,
+        syn,
     })
 }
 
 /// Extracts the transport layer payload from a raw packet.
 pub fn get_transport_payload(raw_data: &[u8]) -> Option<Vec<u8>> {
-    let ethernet = EthernetPacket::new(raw_data)?;
-    match ethernet.get_ethertype() {
+    let frame = tunnel::decapsulate(raw_data)?;
+    match frame.ethertype {
         EtherTypes::Ipv4 => {
-            let ipv4 = Ipv4Packet::new(ethernet.payload())?;
+            let ipv4 = Ipv4Packet::new(frame.payload)?;
+            if is_fragment(&ipv4) {
+                return None;
+            }
             match ipv4.get_next_level_protocol() {
                 IpNextHeaderProtocols::Tcp => {
                     let tcp = TcpPacket::new(ipv4.payload())?;
@@ -396,14 +1273,18 @@ pub fn get_transport_payload(raw_data: &[u8]) -> Option<Vec<u8>> {
             }
         }
         EtherTypes::Ipv6 => {
-            let ipv6 = Ipv6Packet::new(ethernet.payload())?;
-            match ipv6.get_next_header() {
+            let ipv6 = Ipv6Packet::new(frame.payload)?;
+            let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
+            if chain.is_fragment() {
+                return None;
+            }
+            match next_header {
                 IpNextHeaderProtocols::Tcp => {
-                    let tcp = TcpPacket::new(ipv6.payload())?;
+                    let tcp = TcpPacket::new(payload)?;
                     Some(tcp.payload().to_vec())
                 }
                 IpNextHeaderProtocols::Udp => {
-                    let udp = UdpPacket::new(ipv6.payload())?;
+                    let udp = UdpPacket::new(payload)?;
                     Some(udp.payload().to_vec())
                 }
                 _ => None,
@@ -452,14 +1333,13 @@ fn generate_narrative(summary: &PacketSummary, layers: &[ProtocolLayer]) -> Fore
                 details.push("Standard IPv4 routing used for this exchange.".to_string());
             }
             "Transmission Control Protocol" => {
-                let flags = layer
-                    .fields
-                    .iter()
-                    .find(|f| f.name == "Flags")
-                    .map(|f| f.value.as_str())
-                    .unwrap_or("");
-                if flags.contains("0x02") {
-                    // SYN
+                let flag = |name: &str| {
+                    layer
+                        .fields
+                        .iter()
+                        .any(|f| f.name == name && f.value == "1")
+                };
+                if flag("SYN") && !flag("ACK") {
                     narrative_summary = format!(
                         "Connection attempt initiated by {} to {}.",
                         summary.source_addr, summary.dest_addr
@@ -467,8 +1347,7 @@ fn generate_narrative(summary: &PacketSummary, layers: &[ProtocolLayer]) -> Fore
                     details.push(
                         "The source host is requesting to open a new TCP session.".to_string(),
                     );
-                } else if flags.contains("0x12") {
-                    // SYN-ACK
+                } else if flag("SYN") && flag("ACK") {
                     narrative_summary = format!(
                         "Connection request acknowledged by {} to {}.",
                         summary.source_addr, summary.dest_addr
@@ -486,238 +1365,410 @@ fn generate_narrative(summary: &PacketSummary, layers: &[ProtocolLayer]) -> Fore
     }
 }
 
-// Full packet dissection for detail view
-pub fn dissect_packet(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<PacketDetail> {
-    let mut layers = Vec::new();
-    let mut expert_summary = Vec::new();
-
-    // Parse Ethernet layer (L2)
-    let ethernet = EthernetPacket::new(raw_data)?;
+fn ethernet_fields(ethernet: &EthernetPacket, offset: usize) -> Vec<PacketField> {
     let src_mac = ethernet.get_source().to_string();
     let manufacturer = get_manufacturer(&src_mac);
-
-    let ethernet_fields = vec![
+    vec![
         PacketField {
             name: "Destination".to_string(),
             value: ethernet.get_destination().to_string(),
-            range: (0, 6),
+            range: (offset, offset + 6),
             expert: None,
         },
         PacketField {
             name: "Source".to_string(),
-            value: src_mac.clone(),
-            range: (6, 12),
-            expert: manufacturer
-                .clone()
-                .map(|m| format!("Hardware detected as {}", m)),
+            value: src_mac,
+            range: (offset + 6, offset + 12),
+            expert: manufacturer.map(|m| format!("Hardware detected as {}", m)),
         },
         PacketField {
             name: "Type".to_string(),
             value: format!("0x{:04x}", ethernet.get_ethertype().0),
-            range: (12, 14),
+            range: (offset + 12, offset + 14),
+            expert: None,
+        },
+    ]
+}
+
+fn ipv4_fields(ipv4: &Ipv4Packet, offset: usize) -> Vec<PacketField> {
+    let header_len = (ipv4.get_header_length() as usize) * 4;
+    let ttl = ipv4.get_ttl();
+    vec![
+        PacketField {
+            name: "Version".to_string(),
+            value: "4".to_string(),
+            range: (offset, offset),
+            expert: None,
+        },
+        PacketField {
+            name: "Header Length".to_string(),
+            value: format!("{} bytes", header_len),
+            range: (offset, offset),
+            expert: None,
+        },
+        PacketField {
+            name: "Total Length".to_string(),
+            value: format!("{} bytes", ipv4.get_total_length()),
+            range: (offset + 2, offset + 4),
+            expert: None,
+        },
+        PacketField {
+            name: "Identification".to_string(),
+            value: format!("0x{:04x}", ipv4.get_identification()),
+            range: (offset + 4, offset + 6),
+            expert: None,
+        },
+        PacketField {
+            name: "Flags".to_string(),
+            value: format!("0x{:x}", ipv4.get_flags()),
+            range: (offset + 6, offset + 7),
+            expert: None,
+        },
+        PacketField {
+            name: "Fragment Offset".to_string(),
+            value: format!("{} bytes", ipv4.get_fragment_offset() as usize * 8),
+            range: (offset + 6, offset + 8),
+            expert: None,
+        },
+        PacketField {
+            name: "TTL".to_string(),
+            value: ttl.to_string(),
+            range: (offset + 8, offset + 9),
+            expert: if ttl < 10 {
+                Some("Very Low TTL".to_string())
+            } else {
+                None
+            },
+        },
+        PacketField {
+            name: "Protocol".to_string(),
+            value: format!(
+                "{} ({})",
+                ipv4.get_next_level_protocol().0,
+                ipv4.get_next_level_protocol()
+            ),
+            range: (offset + 9, offset + 10),
+            expert: None,
+        },
+        PacketField {
+            name: "Header Checksum".to_string(),
+            value: format!("0x{:04x}", ipv4.get_checksum()),
+            range: (offset + 10, offset + 12),
+            expert: None,
+        },
+        PacketField {
+            name: "Source".to_string(),
+            value: ipv4.get_source().to_string(),
+            range: (offset + 12, offset + 16),
+            expert: None,
+        },
+        PacketField {
+            name: "Destination".to_string(),
+            value: ipv4.get_destination().to_string(),
+            range: (offset + 16, offset + 20),
+            expert: None,
+        },
+    ]
+}
+
+fn ipv6_fields(ipv6: &Ipv6Packet, offset: usize) -> Vec<PacketField> {
+    let payload_length = ipv6.get_payload_length();
+    let hop_limit = ipv6.get_hop_limit();
+    vec![
+        PacketField {
+            name: "Version".to_string(),
+            value: "6".to_string(),
+            range: (offset, offset),
             expert: None,
         },
-    ];
+        PacketField {
+            name: "Payload Length".to_string(),
+            value: format!("{} bytes", payload_length),
+            range: (offset + 4, offset + 6),
+            expert: None,
+        },
+        PacketField {
+            name: "Next Header".to_string(),
+            value: format!(
+                "{} ({})",
+                ipv6ext::protocol_name(ipv6.get_next_header().0),
+                ipv6.get_next_header().0
+            ),
+            range: (offset + 6, offset + 7),
+            expert: None,
+        },
+        PacketField {
+            name: "Hop Limit".to_string(),
+            value: hop_limit.to_string(),
+            range: (offset + 7, offset + 8),
+            expert: if hop_limit < 10 {
+                Some("Very Low Hop Limit".to_string())
+            } else {
+                None
+            },
+        },
+        PacketField {
+            name: "Source".to_string(),
+            value: ipv6.get_source().to_string(),
+            range: (offset + 8, offset + 24),
+            expert: None,
+        },
+        PacketField {
+            name: "Destination".to_string(),
+            value: ipv6.get_destination().to_string(),
+            range: (offset + 24, offset + 40),
+            expert: None,
+        },
+    ]
+}
+
+fn udp_fields(udp: &UdpPacket, offset: usize) -> Vec<PacketField> {
+    vec![
+        PacketField {
+            name: "Source Port".to_string(),
+            value: udp.get_source().to_string(),
+            range: (offset, offset + 2),
+            expert: None,
+        },
+        PacketField {
+            name: "Destination Port".to_string(),
+            value: udp.get_destination().to_string(),
+            range: (offset + 2, offset + 4),
+            expert: None,
+        },
+        PacketField {
+            name: "Length".to_string(),
+            value: format!("{} bytes", udp.get_length()),
+            range: (offset + 4, offset + 6),
+            expert: None,
+        },
+        PacketField {
+            name: "Checksum".to_string(),
+            value: format!("0x{:04x}", udp.get_checksum()),
+            range: (offset + 6, offset + 8),
+            expert: None,
+        },
+    ]
+}
+
+/// Protocol layer for a VLAN/MPLS header, or a header of a tunnel carrying
+/// the innermost network layer.
+fn outer_layer(raw_data: &[u8], layer: &tunnel::Layer) -> Option<ProtocolLayer> {
+    let (name, fields) = match layer {
+        tunnel::Layer::Ethernet { offset } => {
+            let ethernet = EthernetPacket::new(&raw_data[*offset..])?;
+            (LAYER_ETHERNET, ethernet_fields(&ethernet, *offset))
+        }
+        tunnel::Layer::Tag(header) => {
+            let name = match header.tag {
+                encap::Tag::Vlan { .. } => LAYER_VLAN,
+                encap::Tag::Mpls { .. } => LAYER_MPLS,
+            };
+            (name, encap::fields(header))
+        }
+        tunnel::Layer::Ipv4 { offset } => {
+            let ipv4 = Ipv4Packet::new(&raw_data[*offset..])?;
+            (LAYER_IPV4, ipv4_fields(&ipv4, *offset))
+        }
+        tunnel::Layer::Ipv6 { offset } => {
+            let ipv6 = Ipv6Packet::new(&raw_data[*offset..])?;
+            (LAYER_IPV6, ipv6_fields(&ipv6, *offset))
+        }
+        tunnel::Layer::Udp { offset } => {
+            let udp = UdpPacket::new(&raw_data[*offset..])?;
+            (LAYER_UDP, udp_fields(&udp, *offset))
+        }
+        tunnel::Layer::Gre(_) => (LAYER_GRE, tunnel::fields(layer)),
+        tunnel::Layer::Erspan(_) => (LAYER_ERSPAN, tunnel::fields(layer)),
+        tunnel::Layer::Vxlan(_) => (LAYER_VXLAN, tunnel::fields(layer)),
+        tunnel::Layer::Geneve(_) => (LAYER_GENEVE, tunnel::fields(layer)),
+    };
+    Some(ProtocolLayer {
+        name: name.to_string(),
+        fields,
+    })
+}
+
+/// Options for the detail view dissection.
+#[derive(Debug, Clone, Default)]
+pub struct DissectOptions {
+    /// Verify the IPv4 header checksum and TCP, UDP and ICMP/ICMPv6 checksums
+    pub verify_checksums: bool,
+    /// Addresses of the capturing host, whose outgoing packets may be captured
+    /// before the NIC fills in offloaded checksums
+    pub local_addrs: Vec<IpAddr>,
+    /// Application dissector learned for the packet's flow
+    pub app_protocol: Option<String>,
+    /// Bases of the relative TCP Seq and Ack numbers in the info column
+    pub sequence_bases: SequenceBases,
+    /// File and offset read or written by each SMB2 message in the packet,
+    /// by message ID, for naming the transferred data
+    pub smb_files: HashMap<u64, String>,
+}
+
+/// Appends the verification result to the checksum field `name` of the
+/// innermost `layer`, flagging bad checksums as `label` ones.
+fn annotate_checksum(
+    layers: &mut [ProtocolLayer],
+    (layer, name, label): (&str, &str, &str),
+    status: checksum::Status,
+    expert_summary: &mut Vec<String>,
+) {
+    let Some(field) = layers
+        .iter_mut()
+        .rev()
+        .find(|l| l.name == layer)
+        .and_then(|l| l.fields.iter_mut().find(|f| f.name == name))
+    else {
+        return;
+    };
+    if let checksum::Status::Bad { expected } = status {
+        expert_summary.push(format!(
+            "Bad {} checksum {} (should be 0x{:04x})",
+            label, field.value, expected
+        ));
+        field.expert = Some(format!("Bad {} checksum", label));
+    }
+    field.value = format!("{} {}", field.value, status.describe());
+}
+
+/// Verifies the checksums of the innermost IP header and transport segment.
+fn verify_checksums(
+    frame: &tunnel::Frame,
+    layers: &mut [ProtocolLayer],
+    expert_summary: &mut Vec<String>,
+    options: &DissectOptions,
+) {
+    let is_local = |addr: IpAddr| options.local_addrs.contains(&addr);
+    let (src, dst, protocol, segment, length) = match frame.ethertype {
+        EtherTypes::Ipv4 => {
+            let Some(ipv4) = Ipv4Packet::new(frame.payload) else {
+                return;
+            };
+            let src = IpAddr::V4(ipv4.get_source());
+            let header_len = (ipv4.get_header_length() as usize) * 4;
+            let header = &frame.payload[..header_len.min(frame.payload.len())];
+            let status = checksum::ipv4_header(header, is_local(src));
+            let field = (LAYER_IPV4, "Header Checksum", "IPv4 header");
+            annotate_checksum(layers, field, status, expert_summary);
+            if is_fragment(&ipv4) {
+                return;
+            }
+            let length = (ipv4.get_total_length() as usize).saturating_sub(header_len);
+            (
+                src,
+                IpAddr::V4(ipv4.get_destination()),
+                ipv4.get_next_level_protocol(),
+                &frame.payload[header_len.min(frame.payload.len())..],
+                length,
+            )
+        }
+        EtherTypes::Ipv6 => {
+            let Some(ipv6) = Ipv6Packet::new(frame.payload) else {
+                return;
+            };
+            let (protocol, _, chain) = ipv6_upper_layer(&ipv6);
+            if chain.is_fragment() {
+                return;
+            }
+            let start = (40 + chain.payload_offset).min(frame.payload.len());
+            let length = (ipv6.get_payload_length() as usize).saturating_sub(chain.payload_offset);
+            (
+                IpAddr::V6(ipv6.get_source()),
+                IpAddr::V6(ipv6.get_destination()),
+                protocol,
+                &frame.payload[start..],
+                length,
+            )
+        }
+        _ => return,
+    };
+    let (layer, label) = match protocol {
+        IpNextHeaderProtocols::Tcp => (LAYER_TCP, PROTO_TCP),
+        IpNextHeaderProtocols::Udp => (LAYER_UDP, PROTO_UDP),
+        IpNextHeaderProtocols::Icmp => (LAYER_ICMP, PROTO_ICMP),
+        IpNextHeaderProtocols::Icmpv6 => (LAYER_ICMPV6, PROTO_ICMPV6),
+        _ => return,
+    };
+    let status = checksum::transport(src, dst, protocol.0, segment, length, is_local(src));
+    annotate_checksum(layers, (layer, "Checksum", label), status, expert_summary);
+}
+
+// Full packet dissection for detail view
+pub fn dissect_packet(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<PacketDetail> {
+    dissect_packet_with(raw_data, id, timestamp_ns, &DissectOptions::default())
+}
+
+/// Dissects a packet like [`dissect_packet`], with optional checks.
+pub fn dissect_packet_with(
+    raw_data: &[u8],
+    id: u64,
+    timestamp_ns: i64,
+    options: &DissectOptions,
+) -> Option<PacketDetail> {
+    let mut layers = Vec::new();
+    let mut expert_summary = Vec::new();
+
+    // Parse Ethernet layer (L2)
+    let ethernet = EthernetPacket::new(raw_data)?;
+    let manufacturer = get_manufacturer(&ethernet.get_source().to_string());
     layers.push(ProtocolLayer {
-        name: "Ethernet".to_string(),
-        fields: ethernet_fields,
+        name: LAYER_ETHERNET.to_string(),
+        fields: ethernet_fields(&ethernet, 0),
     });
 
-    let current_offset = 14;
+    // VLAN/MPLS headers and tunnels in front of the innermost network layer
+    let frame = tunnel::decapsulate(raw_data)?;
+    layers.extend(
+        frame
+            .layers
+            .iter()
+            .filter_map(|layer| outer_layer(raw_data, layer)),
+    );
+    let current_offset = frame.offset;
 
     // Parse IP layer (L3)
-    match ethernet.get_ethertype() {
+    match frame.ethertype {
         EtherTypes::Ipv4 => {
-            if let Some(ipv4) = Ipv4Packet::new(ethernet.payload()) {
+            if let Some(ipv4) = Ipv4Packet::new(frame.payload) {
                 let header_len = (ipv4.get_header_length() as usize) * 4;
                 let ttl = ipv4.get_ttl();
                 if ttl < 10 {
                     expert_summary.push("Suspiciously low TTL (Time To Live). Possible traceroute or network manipulation.".to_string());
                 }
 
-                let ip_fields = vec![
-                    PacketField {
-                        name: "Version".to_string(),
-                        value: "4".to_string(),
-                        range: (current_offset, current_offset),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Header Length".to_string(),
-                        value: format!("{} bytes", header_len),
-                        range: (current_offset, current_offset),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Total Length".to_string(),
-                        value: format!("{} bytes", ipv4.get_total_length()),
-                        range: (current_offset + 2, current_offset + 4),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Identification".to_string(),
-                        value: format!("0x{:04x}", ipv4.get_identification()),
-                        range: (current_offset + 4, current_offset + 6),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "TTL".to_string(),
-                        value: ttl.to_string(),
-                        range: (current_offset + 8, current_offset + 9),
-                        expert: if ttl < 10 {
-                            Some("Very Low TTL".to_string())
-                        } else {
-                            None
-                        },
-                    },
-                    PacketField {
-                        name: "Protocol".to_string(),
-                        value: format!(
-                            "{} ({})",
-                            ipv4.get_next_level_protocol().0,
-                            ipv4.get_next_level_protocol()
-                        ),
-                        range: (current_offset + 9, current_offset + 10),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Source".to_string(),
-                        value: ipv4.get_source().to_string(),
-                        range: (current_offset + 12, current_offset + 16),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Destination".to_string(),
-                        value: ipv4.get_destination().to_string(),
-                        range: (current_offset + 16, current_offset + 20),
-                        expert: None,
-                    },
-                ];
                 layers.push(ProtocolLayer {
-                    name: "Internet Protocol Version 4".to_string(),
-                    fields: ip_fields,
+                    name: LAYER_IPV4.to_string(),
+                    fields: ipv4_fields(&ipv4, current_offset),
                 });
 
                 let transport_offset = current_offset + header_len;
                 match ipv4.get_next_level_protocol() {
-                    IpNextHeaderProtocols::Tcp => {
-                        if let Some(tcp) = TcpPacket::new(ipv4.payload()) {
-                            let tcp_header_len = (tcp.get_data_offset() as usize) * 4;
-                            let window = tcp.get_window();
-                            if window == 0 {
-                                expert_summary.push(
-                                    "TCP Zero Window: Connection flow may be stalled.".to_string(),
-                                );
-                            }
-
-                            let tcp_fields = vec![
-                                PacketField {
-                                    name: "Source Port".to_string(),
-                                    value: tcp.get_source().to_string(),
-                                    range: (transport_offset, transport_offset + 2),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Destination Port".to_string(),
-                                    value: tcp.get_destination().to_string(),
-                                    range: (transport_offset + 2, transport_offset + 4),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Sequence Number".to_string(),
-                                    value: tcp.get_sequence().to_string(),
-                                    range: (transport_offset + 4, transport_offset + 8),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Acknowledgment Number".to_string(),
-                                    value: tcp.get_acknowledgement().to_string(),
-                                    range: (transport_offset + 8, transport_offset + 12),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Flags".to_string(),
-                                    value: format!("0x{:02x}", tcp.get_flags()),
-                                    range: (transport_offset + 13, transport_offset + 14),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Window Size".to_string(),
-                                    value: window.to_string(),
-                                    range: (transport_offset + 14, transport_offset + 16),
-                                    expert: if window == 0 {
-                                        Some("TCP Window Zero")
-                                    } else {
-                                        None
-                                    }
-                                    .map(|s| s.to_string()),
-                                },
-                            ];
-                            layers.push(ProtocolLayer {
-                                name: "Transmission Control Protocol".to_string(),
-                                fields: tcp_fields,
-                            });
-
-                            let app_offset = transport_offset + tcp_header_len;
-                            if !tcp.payload().is_empty() {
-                                parse_application_layer(
-                                    &mut layers,
-                                    tcp.get_source(),
-                                    tcp.get_destination(),
-                                    tcp.payload(),
-                                    true,
-                                    app_offset,
-                                );
-                            }
-                        }
-                    }
-                    IpNextHeaderProtocols::Udp => {
-                        if let Some(udp) = UdpPacket::new(ipv4.payload()) {
-                            let udp_fields = vec![
-                                PacketField {
-                                    name: "Source Port".to_string(),
-                                    value: udp.get_source().to_string(),
-                                    range: (transport_offset, transport_offset + 2),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Destination Port".to_string(),
-                                    value: udp.get_destination().to_string(),
-                                    range: (transport_offset + 2, transport_offset + 4),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Length".to_string(),
-                                    value: format!("{} bytes", udp.get_length()),
-                                    range: (transport_offset + 4, transport_offset + 6),
-                                    expert: None,
-                                },
-                            ];
-                            layers.push(ProtocolLayer {
-                                name: "User Datagram Protocol".to_string(),
-                                fields: udp_fields,
-                            });
-
-                            let app_offset = transport_offset + 8;
-                            if !udp.payload().is_empty() {
-                                parse_application_layer(
-                                    &mut layers,
-                                    udp.get_source(),
-                                    udp.get_destination(),
-                                    udp.payload(),
-                                    false,
-                                    app_offset,
-                                );
-                            }
-                        }
+                    // Transport and application layers are dissected on the reassembled datagram
+                    _ if is_fragment(&ipv4) => {
+                        layers.push(ProtocolLayer {
+                            name: LAYER_DATA.to_string(),
+                            fields: vec![PacketField {
+                                name: "Payload Length".to_string(),
+                                value: format!("{} bytes", ipv4.payload().len()),
+                                range: (transport_offset, transport_offset + ipv4.payload().len()),
+                                expert: None,
+                            }],
+                        });
                     }
-                    _ => {}
+                    protocol => dissect_transport(
+                        &mut layers,
+                        &mut expert_summary,
+                        protocol,
+                        ipv4.payload(),
+                        transport_offset,
+                        options.app_protocol.as_deref(),
+                    ),
                 }
             }
         }
         EtherTypes::Ipv6 => {
-            if let Some(ipv6) = Ipv6Packet::new(ethernet.payload()) {
-                let payload_length = ipv6.get_payload_length();
+            if let Some(ipv6) = Ipv6Packet::new(frame.payload) {
                 let hop_limit = ipv6.get_hop_limit();
                 if hop_limit < 10 {
                     expert_summary.push(
@@ -726,172 +1777,76 @@ pub fn dissect_packet(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<Pac
                     );
                 }
 
-                let ip_fields = vec![
-                    PacketField {
-                        name: "Version".to_string(),
-                        value: "6".to_string(),
-                        range: (current_offset, current_offset),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Payload Length".to_string(),
-                        value: format!("{} bytes", payload_length),
-                        range: (current_offset + 4, current_offset + 6),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Hop Limit".to_string(),
-                        value: hop_limit.to_string(),
-                        range: (current_offset + 7, current_offset + 8),
-                        expert: if hop_limit < 10 {
-                            Some("Very Low Hop Limit".to_string())
-                        } else {
-                            None
-                        },
-                    },
-                    PacketField {
-                        name: "Source".to_string(),
-                        value: ipv6.get_source().to_string(),
-                        range: (current_offset + 8, current_offset + 24),
-                        expert: None,
-                    },
-                    PacketField {
-                        name: "Destination".to_string(),
-                        value: ipv6.get_destination().to_string(),
-                        range: (current_offset + 24, current_offset + 40),
-                        expert: None,
-                    },
-                ];
                 layers.push(ProtocolLayer {
-                    name: "Internet Protocol Version 6".to_string(),
-                    fields: ip_fields,
+                    name: LAYER_IPV6.to_string(),
+                    fields: ipv6_fields(&ipv6, current_offset),
                 });
 
-                let transport_offset = current_offset + 40; // IPv6 header is always 40 bytes
-                match ipv6.get_next_header() {
-                    IpNextHeaderProtocols::Tcp => {
-                        if let Some(tcp) = TcpPacket::new(ipv6.payload()) {
-                            let tcp_header_len = (tcp.get_data_offset() as usize) * 4;
-                            let window = tcp.get_window();
-                            if window == 0 {
-                                expert_summary.push(
-                                    "TCP Zero Window: Connection flow may be stalled.".to_string(),
-                                );
-                            }
-
-                            let tcp_fields = vec![
-                                PacketField {
-                                    name: "Source Port".to_string(),
-                                    value: tcp.get_source().to_string(),
-                                    range: (transport_offset, transport_offset + 2),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Destination Port".to_string(),
-                                    value: tcp.get_destination().to_string(),
-                                    range: (transport_offset + 2, transport_offset + 4),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Sequence Number".to_string(),
-                                    value: tcp.get_sequence().to_string(),
-                                    range: (transport_offset + 4, transport_offset + 8),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Acknowledgment Number".to_string(),
-                                    value: tcp.get_acknowledgement().to_string(),
-                                    range: (transport_offset + 8, transport_offset + 12),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Flags".to_string(),
-                                    value: format!("0x{:02x}", tcp.get_flags()),
-                                    range: (transport_offset + 13, transport_offset + 14),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Window Size".to_string(),
-                                    value: window.to_string(),
-                                    range: (transport_offset + 14, transport_offset + 16),
-                                    expert: if window == 0 {
-                                        Some("TCP Window Zero".to_string())
-                                    } else {
-                                        None
-                                    },
-                                },
-                            ];
-                            layers.push(ProtocolLayer {
-                                name: "Transmission Control Protocol".to_string(),
-                                fields: tcp_fields,
-                            });
-
-                            let app_offset = transport_offset + tcp_header_len;
-                            if !tcp.payload().is_empty() {
-                                parse_application_layer(
-                                    &mut layers,
-                                    tcp.get_source(),
-                                    tcp.get_destination(),
-                                    tcp.payload(),
-                                    true,
-                                    app_offset,
-                                );
-                            }
-                        }
-                    }
-                    IpNextHeaderProtocols::Udp => {
-                        if let Some(udp) = UdpPacket::new(ipv6.payload()) {
-                            let udp_fields = vec![
-                                PacketField {
-                                    name: "Source Port".to_string(),
-                                    value: udp.get_source().to_string(),
-                                    range: (transport_offset, transport_offset + 2),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Destination Port".to_string(),
-                                    value: udp.get_destination().to_string(),
-                                    range: (transport_offset + 2, transport_offset + 4),
-                                    expert: None,
-                                },
-                                PacketField {
-                                    name: "Length".to_string(),
-                                    value: format!("{} bytes", udp.get_length()),
-                                    range: (transport_offset + 4, transport_offset + 6),
-                                    expert: None,
-                                },
-                            ];
-                            layers.push(ProtocolLayer {
-                                name: "User Datagram Protocol".to_string(),
-                                fields: udp_fields,
-                            });
+                let payload_offset = current_offset + 40; // IPv6 header is always 40 bytes
+                let (next_header, payload, chain) = ipv6_upper_layer(&ipv6);
+                for header in &chain.headers {
+                    layers.push(ProtocolLayer {
+                        name: extension_layer_name(header.kind).to_string(),
+                        fields: ipv6ext::fields(header, ipv6.payload(), payload_offset),
+                    });
+                }
+                expert_summary.extend(chain.expert());
 
-                            let app_offset = transport_offset + 8;
-                            if !udp.payload().is_empty() {
-                                parse_application_layer(
-                                    &mut layers,
-                                    udp.get_source(),
-                                    udp.get_destination(),
-                                    udp.payload(),
-                                    false,
-                                    app_offset,
-                                );
-                            }
-                        }
+                let transport_offset = payload_offset + chain.payload_offset;
+                match next_header {
+                    // Transport and application layers are dissected on the reassembled datagram
+                    _ if chain.is_fragment() => {
+                        layers.push(ProtocolLayer {
+                            name: LAYER_DATA.to_string(),
+                            fields: vec![PacketField {
+                                name: "Payload Length".to_string(),
+                                value: format!("{} bytes", payload.len()),
+                                range: (transport_offset, transport_offset + payload.len()),
+                                expert: None,
+                            }],
+                        });
                     }
-                    _ => {}
+                    protocol => dissect_transport(
+                        &mut layers,
+                        &mut expert_summary,
+                        protocol,
+                        payload,
+                        transport_offset,
+                        options.app_protocol.as_deref(),
+                    ),
                 }
             }
         }
+        EtherTypes::Arp => {
+            layers.push(ProtocolLayer {
+                name: LAYER_ARP.to_string(),
+                fields: arp::fields(frame.payload, current_offset),
+            });
+        }
         _ => {}
     }
 
+    if options.verify_checksums {
+        verify_checksums(&frame, &mut layers, &mut expert_summary, options);
+    }
+
     // Get summary
-    let summary = parse_summary(raw_data, id, timestamp_ns)?;
+    let summary = parse_flow_summary(
+        raw_data,
+        id,
+        timestamp_ns,
+        options.app_protocol.as_deref(),
+        options.sequence_bases,
+    )?;
 
     let entropy = calculate_entropy(raw_data);
     let narrative = generate_narrative(&summary, &layers);
-    let artifacts = detect_artifacts(raw_data);
+    let mut artifacts = detect_artifacts(raw_data);
+    if let Some(payload) = get_transport_payload(raw_data) {
+        artifacts.extend(smb::artifacts(&payload, &options.smb_files));
+    }
+    let tls_fingerprints = tls_fingerprints(raw_data);
+    let ssh_fingerprints = ssh_fingerprints(raw_data, options.app_protocol.as_deref());
 
     Some(PacketDetail {
         summary,
@@ -901,88 +1856,128 @@ pub fn dissect_packet(raw_data: &[u8], id: u64, timestamp_ns: i64) -> Option<Pac
         narrative,
         intelligence: ForensicIntelligence {
             entropy,
-            ja3_hash: None,
+            ja3_hash: tls_fingerprints.as_ref().and_then(|f| f.ja3.clone()),
             manufacturer,
             risk_score: if entropy > 7.5 { 70 } else { 10 },
+            tls_fingerprints,
+            ssh_fingerprints,
+            known_fingerprints: Vec::new(),
         },
         artifacts,
     })
 }
 
-// Application layer parsing
-fn parse_application_layer(
+/// Appends the transport layer of an IP packet at `offset` and the
+/// application layers above it.
+fn dissect_transport(
     layers: &mut Vec<ProtocolLayer>,
-    src_port: u16,
-    dst_port: u16,
-    payload: &[u8],
-    is_tcp: bool,
+    expert_summary: &mut Vec<String>,
+    protocol: IpNextHeaderProtocol,
+    segment: &[u8],
     offset: usize,
+    learned: Option<&str>,
 ) {
-    let payload_len = payload.len();
-    let range = (offset, offset + payload_len);
-
-    // DNS (port 53)
-    if src_port == 53 || dst_port == 53 {
-        layers.push(ProtocolLayer {
-            name: "Domain Name System".to_string(),
-            fields: vec![
-                PacketField {
-                    name: "Port".to_string(),
-                    value: if src_port == 53 {
-                        "53 (Response)".to_string()
-                    } else {
-                        "53 (Query)".to_string()
-                    },
-                    range: (offset, offset + 2),
-                    expert: None,
-                },
-                PacketField {
-                    name: "Payload Length".to_string(),
-                    value: format!("{} bytes", payload_len),
-                    range,
-                    expert: None,
-                },
-            ],
-        });
-        return;
-    }
-
-    // HTTP (port 80)
-    if is_tcp && (src_port == 80 || dst_port == 80) {
-        if let Ok(payload_str) = std::str::from_utf8(&payload[..payload_len.min(100)]) {
-            let mut http_fields = Vec::new();
-            if payload_str.starts_with("GET") || payload_str.starts_with("POST") {
-                http_fields.push(PacketField {
-                    name: "Method".to_string(),
-                    value: payload_str.split(' ').next().unwrap_or("").to_string(),
-                    range: (offset, offset + 4),
-                    expert: None,
-                });
+    let (transport, ports, header_len) = match protocol {
+        IpNextHeaderProtocols::Tcp => {
+            let Some(tcp) = TcpPacket::new(segment) else {
+                return;
+            };
+            if tcp.get_window() == 0 {
+                expert_summary.push("TCP Zero Window: Connection flow may be stalled.".to_string());
             }
-            http_fields.push(PacketField {
-                name: "Data".to_string(),
-                value: format!("{} bytes", payload_len),
-                range,
-                expert: None,
+            if let Some(note) = tcp::flags_expert(tcp.get_flags()) {
+                expert_summary.push(note.to_string());
+            }
+            layers.push(ProtocolLayer {
+                name: LAYER_TCP.to_string(),
+                fields: tcp::fields(&tcp, offset),
             });
+            (
+                registry::Transport::Tcp,
+                (tcp.get_source(), tcp.get_destination()),
+                segment.len() - tcp.payload().len(),
+            )
+        }
+        IpNextHeaderProtocols::Udp => {
+            let Some(udp) = UdpPacket::new(segment) else {
+                return;
+            };
+            layers.push(ProtocolLayer {
+                name: LAYER_UDP.to_string(),
+                fields: udp_fields(&udp, offset),
+            });
+            (
+                registry::Transport::Udp,
+                (udp.get_source(), udp.get_destination()),
+                segment.len() - udp.payload().len(),
+            )
+        }
+        IpNextHeaderProtocols::Icmp => {
+            layers.push(ProtocolLayer {
+                name: LAYER_ICMP.to_string(),
+                fields: icmp::fields(icmp::Version::V4, segment, offset),
+            });
+            return;
+        }
+        IpNextHeaderProtocols::Icmpv6 => {
             layers.push(ProtocolLayer {
-                name: "Hypertext Transfer Protocol".to_string(),
-                fields: http_fields,
+                name: LAYER_ICMPV6.to_string(),
+                fields: icmp::fields(icmp::Version::V6, segment, offset),
             });
             return;
         }
+        _ => return,
+    };
+    let payload = &segment[header_len..];
+    if payload.is_empty() {
+        return;
     }
+    let ctx = registry::Context {
+        transport,
+        src_port: ports.0,
+        dst_port: ports.1,
+        payload,
+        offset: offset + header_len,
+        transport_info: "",
+        parent: None,
+        learned,
+    };
+    registry::read().dissect(&ctx, layers);
+}
 
-    // Generic application layer
-    layers.push(ProtocolLayer {
-        name: "Application Data".to_string(),
-        fields: vec![PacketField {
-            name: "Payload Length".to_string(),
-            value: format!("{} bytes", payload_len),
-            range,
-            expert: None,
-        }],
-    });
+/// Probes a packet's payload for a known application protocol signature.
+///
+/// None when the packet carries no TCP or UDP payload; Some(None) when it
+/// does but no enabled dissector recognizes it.
+pub fn detect_application(raw_data: &[u8]) -> Option<Option<&'static str>> {
+    let (protocol, src_port, dst_port) = get_transport_endpoints(raw_data)?;
+    let transport = match IpNextHeaderProtocol(protocol) {
+        IpNextHeaderProtocols::Tcp => registry::Transport::Tcp,
+        IpNextHeaderProtocols::Udp => registry::Transport::Udp,
+        _ => return None,
+    };
+    let payload = get_transport_payload(raw_data).filter(|payload| !payload.is_empty())?;
+    let ctx = registry::Context {
+        transport,
+        src_port: src_port?,
+        dst_port: dst_port?,
+        payload: &payload,
+        offset: 0,
+        transport_info: "",
+        parent: None,
+        learned: None,
+    };
+    Some(registry::read().detect(&ctx))
+}
+
+/// The application-layer dissectors and whether each is enabled.
+pub fn list_dissectors() -> Vec<registry::DissectorInfo> {
+    registry::read().list()
+}
+
+/// Enables or disables an application-layer dissector by name.
+pub fn set_dissector_enabled(name: &str, enabled: bool) -> Result<(), String> {
+    registry::write().set_enabled(name, enabled)
 }
 
 #[cfg(test)]
@@ -1079,4 +2074,381 @@ mod tests {
         assert_eq!(detail.layers[0].fields[0].name, "Destination");
         assert_eq!(detail.layers[0].fields[0].range, (0, 6));
     }
+
+    #[test]
+    fn test_tcp_flags_and_narrative() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x08, 0x00]);
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x2C, 0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x40, 0x06, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x02]);
+        // SYN 40000 -> 5432 with MSS 1460
+        data.extend_from_slice(&[0x9C, 0x40, 0x15, 0x38, 0x00, 0x00, 0x00, 0x64]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x60, 0x02, 0x72, 0x10]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x02, 0x04, 0x05, 0xB4]);
+
+        let summary = parse_summary(&data, 1, 0).unwrap();
+        assert_eq!(summary.protocol, "TCP");
+        assert_eq!(
+            summary.info,
+            "40000 → 5432 [SYN] Seq=100 Win=29200 Len=0 MSS=1460"
+        );
+
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        assert!(detail.narrative.summary.starts_with("Connection attempt"));
+
+        // SYN-ACK
+        data[47] = 0x12;
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        assert!(detail
+            .narrative
+            .summary
+            .starts_with("Connection request acknowledged"));
+    }
+
+    #[test]
+    fn test_checksum_verification() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x08, 0x00]);
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x1E, 0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x02]);
+        data.extend_from_slice(&[0x30, 0x39, 0x30, 0x3A, 0x00, 0x0A, 0x12, 0x34, 0xAB, 0xCD]);
+        let options = DissectOptions {
+            verify_checksums: true,
+            ..DissectOptions::default()
+        };
+
+        let detail = dissect_packet_with(&data, 1, 0, &options).unwrap();
+        let checksum = |detail: &PacketDetail, layer: &str, name: &str| {
+            let layer = detail.layers.iter().find(|l| l.name == layer).unwrap();
+            layer
+                .fields
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap()
+                .clone()
+        };
+        let ip = checksum(&detail, LAYER_IPV4, "Header Checksum");
+        assert_eq!(ip.value, "0x0000 [incorrect, should be 0x66cc]");
+        assert_eq!(ip.expert.as_deref(), Some("Bad IPv4 header checksum"));
+        let udp = checksum(&detail, LAYER_UDP, "Checksum");
+        assert!(udp.value.starts_with("0x1234 [incorrect"));
+        assert_eq!(detail.expert_summary.len(), 2);
+
+        // Fixed up, and sent by this host with the UDP checksum left to the NIC
+        data[24..26].copy_from_slice(&[0x66, 0xCC]);
+        let options = DissectOptions {
+            local_addrs: vec!["10.0.0.1".parse().unwrap()],
+            ..options
+        };
+        let detail = dissect_packet_with(&data, 1, 0, &options).unwrap();
+        assert!(detail.expert_summary.is_empty());
+        assert_eq!(
+            checksum(&detail, LAYER_UDP, "Checksum").value,
+            "0x1234 [unverified, likely checksum offload]"
+        );
+
+        // Verification is off by default
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        assert_eq!(checksum(&detail, LAYER_UDP, "Checksum").value, "0x1234");
+    }
+
+    #[test]
+    fn test_vlan_tagged_frame() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x81, 0x00, 0x00, 0x2A, 0x08, 0x00]); // 802.1Q VLAN 42, IPv4
+
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x1C]);
+        data.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00]); // UDP
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x02]);
+        data.extend_from_slice(&[0x30, 0x39, 0x30, 0x3A, 0x00, 0x08, 0x00, 0x00]); // 12345 -> 12346
+
+        let summary = parse_summary(&data, 1, 0).unwrap();
+        assert_eq!(summary.source_addr, "10.0.0.1");
+        assert_eq!(summary.vlan_id, Some(42));
+        assert_eq!(
+            get_transport_endpoints(&data),
+            Some((17, Some(12345), Some(12346)))
+        );
+        assert!(get_flow_key(&data).is_some());
+
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        assert_eq!(detail.layers[1].name, LAYER_VLAN);
+        assert_eq!(detail.layers[2].name, LAYER_IPV4);
+        assert_eq!(detail.layers[2].fields[0].range.0, 18);
+    }
+
+    #[test]
+    fn test_vxlan_tunnel() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x08, 0x00]);
+
+        // Outer IPv4 and UDP to port 4789
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x5A, 0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00, 0xC0, 0xA8, 0x00, 0x01]);
+        data.extend_from_slice(&[0xC0, 0xA8, 0x00, 0x02]);
+        data.extend_from_slice(&[0xC3, 0x50, 0x12, 0xB5, 0x00, 0x46, 0x00, 0x00]);
+        // VXLAN, VNI 100
+        data.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00]);
+
+        // Inner Ethernet, IPv4 and UDP 12345 -> 53
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x08, 0x00]);
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x02]);
+        data.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0x00, 0x14, 0x00, 0x00]);
+        data.extend_from_slice(&[0x12, 0x34, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]); // DNS header
+
+        let summary = parse_summary(&data, 1, 0).unwrap();
+        assert_eq!(summary.source_addr, "10.0.0.1");
+        assert_eq!(summary.dest_addr, "10.0.0.2");
+
+        let key = get_flow_key(&data).unwrap();
+        assert_eq!(key.src_ip.to_string(), "10.0.0.1");
+        assert_eq!((key.src_port, key.dst_port), (12345, 53));
+        assert_eq!(tunnel_ids(&data), vec!["VXLAN VNI 100".to_string()]);
+
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        let names: Vec<&str> = detail.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                LAYER_ETHERNET,
+                LAYER_IPV4,
+                LAYER_UDP,
+                LAYER_VXLAN,
+                LAYER_ETHERNET,
+                LAYER_IPV4,
+                LAYER_UDP,
+                LAYER_DNS
+            ]
+        );
+        assert_eq!(detail.layers[5].fields[0].range.0, 64);
+    }
+
+    fn ipv4_fragment_frame(flags_offset: u16, payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x08, 0x00]);
+        data.extend_from_slice(&[0x45, 0x00]);
+        data.extend_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0xBE, 0xEF]); // ID
+        data.extend_from_slice(&flags_offset.to_be_bytes());
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00]); // UDP
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x0A, 0x00, 0x00, 0x02]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_quic_client_hello_across_initials() {
+        let body = tls::tests::client_hello_body();
+        let mut handshake = vec![tls::HANDSHAKE_CLIENT_HELLO, 0];
+        handshake.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        handshake.extend_from_slice(&body);
+        let split = handshake.len() / 2;
+        let datagram = |offset: usize, crypto: &[u8]| {
+            let initial =
+                quic::tests::client_initial(quic::VERSION_1, &[0x83, 0x94], offset, crypto);
+            let mut udp = vec![0xC7, 0x38, 0x01, 0xBB]; // 51000 -> 443
+            udp.extend_from_slice(&(8 + initial.len() as u16).to_be_bytes());
+            udp.extend_from_slice(&[0x00, 0x00]);
+            udp.extend(initial);
+            ipv4_fragment_frame(0, &udp)
+        };
+        let first = datagram(0, &handshake[..split]);
+        let second = datagram(split, &handshake[split..]);
+
+        // Neither Initial holds the whole ClientHello on its own
+        assert!(tls_fingerprints(&first).is_none());
+        assert!(tls_fingerprints(&second).is_none());
+
+        // Buffered per connection ID, the ClientHello completes in whichever order they arrive
+        let mut initials = QuicCryptoTable::default();
+        assert!(flow_tls_fingerprints(&second, &mut initials).is_none());
+        let fingerprints = flow_tls_fingerprints(&first, &mut initials).unwrap();
+        assert!(fingerprints.ja4.unwrap().starts_with("q13d0207h2_"));
+        assert!(fingerprints.ja3.is_some());
+    }
+
+    #[test]
+    fn test_fragment_version_mismatch() {
+        // Ethertype IPv4 with an IPv6 version nibble and MF set
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x00]);
+        frame.extend_from_slice(&[0x65, 0x00, 0x00, 0x30, 0x00, 0x01, 0x20, 0x00]);
+        frame.extend_from_slice(&[0x40, 0x11, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.resize(50, 0);
+        assert!(ip_fragment(&frame).is_none());
+
+        // A fragment whose headers are shorter than its IP version allows is ignored
+        let mut table = crate::state::FragmentTable::default();
+        let fragment = Fragment {
+            key: FragmentKey {
+                src: IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1)),
+                dst: IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2)),
+                protocol: 17,
+                id: 1,
+            },
+            offset: 0,
+            more_fragments: true,
+            payload: vec![0; 16],
+            headers: frame[..34].to_vec(),
+            ip_offset: 14,
+            version: 6,
+        };
+        assert!(table.record(1, 0, 50, fragment).is_none());
+    }
+
+    #[test]
+    fn test_ipv4_fragment_reassembly() {
+        // UDP 12345 -> 53 carrying a DNS header, split after 48 bytes
+        let mut datagram = vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x40, 0x00, 0x00];
+        datagram.extend_from_slice(&[0x12, 0x34, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]);
+        datagram.resize(64, 0);
+        let first = ipv4_fragment_frame(0x2000, &datagram[..48]); // MF, offset 0
+        let last = ipv4_fragment_frame(6, &datagram[48..]); // offset 48
+
+        let summary = parse_summary(&last, 2, 0).unwrap();
+        assert_eq!(summary.protocol, "IPv4");
+        assert!(summary.info.contains("off=48"));
+        assert!(get_flow_key(&first).is_none());
+        assert_eq!(get_transport_endpoints(&last), Some((17, None, None)));
+
+        let mut table = crate::state::FragmentTable::default();
+        let fragment = ip_fragment(&first).unwrap();
+        assert_eq!(fragment.payload.len(), 48);
+        assert!(table.record(1, 0, 82, fragment).is_none());
+        let reassembly = table
+            .record(2, 1_000, 50, ip_fragment(&last).unwrap())
+            .unwrap();
+        assert_eq!(reassembly.payload, datagram);
+
+        let frame = reassembled_frame(&reassembly);
+        let ipv4 = Ipv4Packet::new(&frame[14..]).unwrap();
+        assert_eq!(ipv4.get_total_length(), 84);
+        assert_eq!(ipv4.get_flags(), 0);
+        assert_eq!(ipv4.get_checksum(), ipv4::checksum(&ipv4));
+        let key = get_flow_key(&frame).unwrap();
+        assert_eq!((key.src_port, key.dst_port), (12345, 53));
+        // The last fragment is stored with the reassembled datagram's ports
+        assert_eq!(
+            get_transport_endpoints(&frame),
+            Some((17, Some(12345), Some(53)))
+        );
+
+        let detail =
+            dissect_reassembled(&reassembly, 2, 1_000, &DissectOptions::default()).unwrap();
+        let names: Vec<&str> = detail.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                LAYER_ETHERNET,
+                LAYER_IPV4,
+                LAYER_IPV4_FRAGMENTS,
+                LAYER_UDP,
+                LAYER_DNS
+            ]
+        );
+        assert_eq!(
+            detail.layers[2].fields[0].value,
+            "#1, payload 0-47 (48 bytes)"
+        );
+    }
+
+    fn ipv6_frame(next_header: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00; 12]);
+        data.extend_from_slice(&[0x86, 0xDD]);
+        data.extend_from_slice(&[0x60, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[next_header, 0x40]);
+        data.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_ipv6_extension_headers() {
+        // Hop-by-Hop (Router Alert) then UDP 12345 -> 53
+        let mut udp = vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x14, 0x00, 0x00];
+        udp.extend_from_slice(&[0x12, 0x34, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let hop_by_hop = |next_header: u8| [next_header, 0, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00];
+        let mut payload = hop_by_hop(17).to_vec();
+        payload.extend_from_slice(&udp);
+        let data = ipv6_frame(0, &payload);
+
+        let key = get_flow_key(&data).unwrap();
+        assert_eq!((key.protocol, key.src_port, key.dst_port), (17, 12345, 53));
+        assert_eq!(parse_summary(&data, 1, 0).unwrap().protocol, "DNS");
+        let detail = dissect_packet(&data, 1, 0).unwrap();
+        let names: Vec<&str> = detail.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                LAYER_ETHERNET,
+                LAYER_IPV6,
+                LAYER_IPV6_HOP_BY_HOP,
+                LAYER_UDP,
+                LAYER_DNS
+            ]
+        );
+        assert_eq!(
+            detail.layers[2].fields[2].value,
+            "Router Alert (0x05, 4 bytes)"
+        );
+        assert_eq!(detail.layers[3].fields[0].range.0, 62);
+
+        // The same datagram with the UDP part split across two fragments
+        let fragment = |offset_flags: u16, part: &[u8]| {
+            let mut payload = hop_by_hop(44).to_vec();
+            payload.extend_from_slice(&[17, 0]);
+            payload.extend_from_slice(&offset_flags.to_be_bytes());
+            payload.extend_from_slice(&[0xca, 0xfe, 0xf0, 0x0d]);
+            payload.extend_from_slice(part);
+            ipv6_frame(0, &payload)
+        };
+        let first = fragment(0x0001, &udp[..16]);
+        let last = fragment(0x0010, &udp[16..]);
+
+        assert!(get_flow_key(&first).is_none());
+        assert_eq!(get_transport_endpoints(&last), Some((17, None, None)));
+        assert!(parse_summary(&last, 2, 0).unwrap().info.contains("off=16"));
+        let detail = dissect_packet(&last, 2, 0).unwrap();
+        assert_eq!(detail.layers[3].name, LAYER_IPV6_FRAGMENT);
+        assert_eq!(detail.layers[4].name, LAYER_DATA);
+
+        let mut table = crate::state::FragmentTable::default();
+        let fragment = ip_fragment(&first).unwrap();
+        assert_eq!(fragment.key.id, 0xcafef00d);
+        assert!(table.record(1, 0, first.len() as u32, fragment).is_none());
+        let reassembly = table
+            .record(2, 0, last.len() as u32, ip_fragment(&last).unwrap())
+            .unwrap();
+        assert_eq!(reassembled_frame(&reassembly), data);
+
+        let detail = dissect_reassembled(&reassembly, 2, 0, &DissectOptions::default()).unwrap();
+        let names: Vec<&str> = detail.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                LAYER_ETHERNET,
+                LAYER_IPV6,
+                LAYER_IPV6_HOP_BY_HOP,
+                LAYER_IPV6_FRAGMENTS,
+                LAYER_UDP,
+                LAYER_DNS
+            ]
+        );
+    }
 }
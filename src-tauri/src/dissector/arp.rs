@@ -0,0 +1,207 @@
+//! ARP (RFC 826) over Ethernet and IPv4, including the gratuitous ARP and
+//! ARP probe/announcement forms of RFC 5227.
+
+use super::field;
+use crate::model::PacketField;
+use pnet::util::MacAddr;
+use std::net::Ipv4Addr;
+
+const PACKET_LEN: usize = 28;
+const HTYPE_ETHERNET: u16 = 1;
+const PTYPE_IPV4: u16 = 0x0800;
+
+pub const OP_REQUEST: u16 = 1;
+pub const OP_REPLY: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Request,
+    Reply,
+    /// Request from a host checking whether an address is in use (sender IP 0.0.0.0)
+    Probe,
+    /// Request or reply announcing the sender's own address (sender IP = target IP)
+    Gratuitous,
+    /// Reverse ARP and other opcodes
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub opcode: u16,
+    pub sender_mac: MacAddr,
+    pub sender_ip: Ipv4Addr,
+    pub target_mac: MacAddr,
+    pub target_ip: Ipv4Addr,
+}
+
+impl Packet {
+    pub fn kind(&self) -> Kind {
+        match self.opcode {
+            OP_REQUEST if self.sender_ip.is_unspecified() => Kind::Probe,
+            OP_REQUEST | OP_REPLY if self.sender_ip == self.target_ip => Kind::Gratuitous,
+            OP_REQUEST => Kind::Request,
+            OP_REPLY => Kind::Reply,
+            _ => Kind::Other,
+        }
+    }
+
+    /// Info column text in the Wireshark style.
+    pub fn summary(&self) -> String {
+        match self.kind() {
+            Kind::Request => format!("Who has {}? Tell {}", self.target_ip, self.sender_ip),
+            Kind::Reply => format!("{} is at {}", self.sender_ip, self.sender_mac),
+            Kind::Probe => format!("Who has {}? (ARP Probe)", self.target_ip),
+            Kind::Gratuitous => format!(
+                "Gratuitous ARP for {} ({})",
+                self.sender_ip,
+                opcode_name(self.opcode)
+            ),
+            Kind::Other => format!("{} ({})", opcode_name(self.opcode), self.sender_ip),
+        }
+    }
+}
+
+fn mac_at(data: &[u8], pos: usize) -> MacAddr {
+    MacAddr::new(
+        data[pos],
+        data[pos + 1],
+        data[pos + 2],
+        data[pos + 3],
+        data[pos + 4],
+        data[pos + 5],
+    )
+}
+
+fn ipv4_at(data: &[u8], pos: usize) -> Ipv4Addr {
+    Ipv4Addr::new(data[pos], data[pos + 1], data[pos + 2], data[pos + 3])
+}
+
+/// Parses an Ethernet/IPv4 ARP packet.
+pub fn parse(data: &[u8]) -> Result<Packet, String> {
+    if data.len() < PACKET_LEN {
+        return Err(format!("ARP packet too short ({} bytes)", data.len()));
+    }
+    let htype = u16::from_be_bytes([data[0], data[1]]);
+    let ptype = u16::from_be_bytes([data[2], data[3]]);
+    if htype != HTYPE_ETHERNET || ptype != PTYPE_IPV4 || data[4] != 6 || data[5] != 4 {
+        return Err(format!(
+            "Unsupported ARP hardware/protocol type 0x{:04x}/0x{:04x}",
+            htype, ptype
+        ));
+    }
+    Ok(Packet {
+        opcode: u16::from_be_bytes([data[6], data[7]]),
+        sender_mac: mac_at(data, 8),
+        sender_ip: ipv4_at(data, 14),
+        target_mac: mac_at(data, 18),
+        target_ip: ipv4_at(data, 24),
+    })
+}
+
+pub fn opcode_name(opcode: u16) -> &'static str {
+    match opcode {
+        OP_REQUEST => "Request",
+        OP_REPLY => "Reply",
+        3 => "Reverse Request",
+        4 => "Reverse Reply",
+        8 => "InARP Request",
+        9 => "InARP Reply",
+        _ => "Unknown",
+    }
+}
+
+/// Builds the detail view fields for an ARP packet starting at `offset`.
+pub fn fields(data: &[u8], offset: usize) -> Vec<PacketField> {
+    let at = |start: usize, len: usize| (offset + start, offset + start + len);
+    let packet = match parse(data) {
+        Ok(packet) => packet,
+        Err(e) => {
+            return vec![PacketField {
+                expert: Some("Malformed ARP packet".to_string()),
+                ..field("Error", e, at(0, data.len()))
+            }]
+        }
+    };
+
+    let mut fields = vec![
+        field("Hardware Type", "1 (Ethernet)".to_string(), at(0, 2)),
+        field("Protocol Type", "0x0800 (IPv4)".to_string(), at(2, 2)),
+        field("Hardware Size", "6".to_string(), at(4, 1)),
+        field("Protocol Size", "4".to_string(), at(5, 1)),
+        field(
+            "Opcode",
+            format!("{} ({})", packet.opcode, opcode_name(packet.opcode)),
+            at(6, 2),
+        ),
+        field(
+            "Sender MAC Address",
+            packet.sender_mac.to_string(),
+            at(8, 6),
+        ),
+        field("Sender IP Address", packet.sender_ip.to_string(), at(14, 4)),
+        field(
+            "Target MAC Address",
+            packet.target_mac.to_string(),
+            at(18, 6),
+        ),
+        field("Target IP Address", packet.target_ip.to_string(), at(24, 4)),
+    ];
+    match packet.kind() {
+        Kind::Gratuitous => fields.push(PacketField {
+            expert: Some(format!(
+                "Gratuitous ARP: {} announces {}",
+                packet.sender_mac, packet.sender_ip
+            )),
+            ..field("Gratuitous", "1".to_string(), at(6, 22))
+        }),
+        Kind::Probe => fields.push(PacketField {
+            expert: Some(format!(
+                "ARP probe: {} is checking whether {} is in use",
+                packet.sender_mac, packet.target_ip
+            )),
+            ..field("Probe", "1".to_string(), at(6, 22))
+        }),
+        _ => {}
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arp(opcode: u16, sender_ip: [u8; 4], target_ip: [u8; 4]) -> Vec<u8> {
+        let mut data = vec![0, 1, 8, 0, 6, 4];
+        data.extend_from_slice(&opcode.to_be_bytes());
+        data.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0x00, 0x11, 0x22]);
+        data.extend_from_slice(&sender_ip);
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&target_ip);
+        data
+    }
+
+    #[test]
+    fn test_classification_and_summary() {
+        let summary = |data: Vec<u8>| parse(&data).unwrap().summary();
+        assert_eq!(
+            summary(arp(OP_REQUEST, [10, 0, 0, 2], [10, 0, 0, 1])),
+            "Who has 10.0.0.1? Tell 10.0.0.2"
+        );
+        assert_eq!(
+            summary(arp(OP_REPLY, [10, 0, 0, 1], [10, 0, 0, 2])),
+            "10.0.0.1 is at aa:bb:cc:00:11:22"
+        );
+        assert_eq!(
+            summary(arp(OP_REQUEST, [0, 0, 0, 0], [10, 0, 0, 9])),
+            "Who has 10.0.0.9? (ARP Probe)"
+        );
+        assert_eq!(
+            summary(arp(OP_REPLY, [10, 0, 0, 5], [10, 0, 0, 5])),
+            "Gratuitous ARP for 10.0.0.5 (Reply)"
+        );
+
+        let fields = fields(&arp(OP_REQUEST, [0, 0, 0, 0], [10, 0, 0, 9]), 14);
+        assert!(fields.iter().any(|f| f.name == "Probe"));
+        assert!(parse(&[0; 27]).is_err());
+    }
+}
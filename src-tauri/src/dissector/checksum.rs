@@ -0,0 +1,227 @@
+//! Internet checksum (RFC 1071) verification for IPv4 headers and for TCP,
+//! UDP, ICMP and ICMPv6 with the IPv4 (RFC 793) and IPv6 (RFC 8200 section
+//! 8.1) pseudo-headers.
+
+use std::net::IpAddr;
+
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_UDP: u8 = 17;
+
+/// Outcome of checking a checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Good,
+    Bad {
+        expected: u16,
+    },
+    /// Wrong, but in a way checksum offload leaves it on outgoing packets:
+    /// sent by the capturing host, or only the pseudo-header sum
+    Offloaded,
+    /// UDP over IPv4 without a checksum (a zero checksum field)
+    Absent,
+    /// The capture does not hold the whole segment
+    Truncated,
+}
+
+impl Status {
+    /// Suffix for the checksum field value.
+    pub fn describe(self) -> String {
+        match self {
+            Status::Good => "[correct]".to_string(),
+            Status::Bad { expected } => format!("[incorrect, should be 0x{:04x}]", expected),
+            Status::Offloaded => "[unverified, likely checksum offload]".to_string(),
+            Status::Absent => "[none]".to_string(),
+            Status::Truncated => "[unverified, segment truncated]".to_string(),
+        }
+    }
+}
+
+/// Ones' complement sum of `data` as 16-bit words, added to `sum`.
+fn add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for word in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+    }
+    if let [last] = chunks.remainder() {
+        sum += u32::from(*last) << 8;
+    }
+    sum
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+fn pseudo_header(src: IpAddr, dst: IpAddr, protocol: u8, len: usize) -> u32 {
+    let sum = match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => add(add(0, &src.octets()), &dst.octets()),
+        (IpAddr::V6(src), IpAddr::V6(dst)) => add(add(0, &src.octets()), &dst.octets()),
+        _ => 0,
+    };
+    // IPv6 uses a 32-bit length; for anything under 64 KiB the sums agree
+    sum + u32::from(protocol) + (len as u32 & 0xffff) + (len as u32 >> 16)
+}
+
+/// The checksum `data` should carry, with its checksum field at `field` treated as zero.
+fn compute(initial: u32, data: &[u8], field: usize) -> u16 {
+    let sum = add(add(initial, &data[..field]), &data[field + 2..]);
+    !fold(sum)
+}
+
+fn stored(data: &[u8], field: usize) -> u16 {
+    u16::from_be_bytes([data[field], data[field + 1]])
+}
+
+/// Verifies an IPv4 header, checksum at bytes 10-11.
+pub fn ipv4_header(header: &[u8], local_source: bool) -> Status {
+    if header.len() < 20 {
+        return Status::Truncated;
+    }
+    let expected = compute(0, header, 10);
+    match stored(header, 10) {
+        actual if actual == expected => Status::Good,
+        0 if local_source => Status::Offloaded,
+        _ => Status::Bad { expected },
+    }
+}
+
+/// Verifies a TCP, UDP, ICMP or ICMPv6 segment of `length` bytes, of which
+/// `segment` holds what was captured. `local_source` marks packets the
+/// capturing host sent, whose checksums may be filled in by the NIC after capture.
+pub fn transport(
+    src: IpAddr,
+    dst: IpAddr,
+    protocol: u8,
+    segment: &[u8],
+    length: usize,
+    local_source: bool,
+) -> Status {
+    let field = match protocol {
+        IP_PROTO_UDP => 6,
+        IP_PROTO_ICMP | 58 => 2,
+        _ => 16,
+    };
+    if segment.len() < length || length < field + 2 {
+        return Status::Truncated;
+    }
+    let segment = &segment[..length];
+    let actual = stored(segment, field);
+    if protocol == IP_PROTO_UDP && actual == 0 && src.is_ipv4() {
+        return Status::Absent;
+    }
+    // ICMP over IPv4 has no pseudo-header
+    let pseudo = match (protocol, src) {
+        (IP_PROTO_ICMP, IpAddr::V4(_)) => 0,
+        _ => pseudo_header(src, dst, protocol, length),
+    };
+    let mut expected = compute(pseudo, segment, field);
+    // UDP sends a computed zero as all ones (RFC 768)
+    if protocol == IP_PROTO_UDP && expected == 0 {
+        expected = 0xffff;
+    }
+    if actual == expected {
+        Status::Good
+    } else if local_source || (pseudo != 0 && actual == fold(pseudo)) {
+        Status::Offloaded
+    } else {
+        Status::Bad { expected }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::packet::{ipv4, tcp, udp};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_matches_pnet() {
+        let mut header = [
+            0x45, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x40, 0x06, 0x00, 0x00, 0x0A, 0x00,
+            0x00, 0x01, 0x0A, 0x00, 0x00, 0x02,
+        ];
+        let expected = ipv4::checksum(&ipv4::Ipv4Packet::new(&header).unwrap());
+        assert_eq!(ipv4_header(&header, false), Status::Bad { expected });
+        header[10..12].copy_from_slice(&expected.to_be_bytes());
+        assert_eq!(ipv4_header(&header, false), Status::Good);
+
+        let (src, dst) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let mut segment = vec![0x9C, 0x40, 0x00, 0x50, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18];
+        segment.extend_from_slice(&[0x20, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        segment.extend_from_slice(b"GET / HTTP/1.1\r\n\r");
+        let sum = tcp::ipv4_checksum(&tcp::TcpPacket::new(&segment).unwrap(), &src, &dst);
+        segment[16..18].copy_from_slice(&sum.to_be_bytes());
+        let status = transport(src.into(), dst.into(), 6, &segment, segment.len(), false);
+        assert_eq!(status, Status::Good);
+        segment[20] ^= 0xff;
+        let status = transport(src.into(), dst.into(), 6, &segment, segment.len(), false);
+        assert!(matches!(status, Status::Bad { .. }));
+        // The same damage on a packet the capturing host sent is not flagged
+        let status = transport(src.into(), dst.into(), 6, &segment, segment.len(), true);
+        assert_eq!(status, Status::Offloaded);
+        let status = transport(
+            src.into(),
+            dst.into(),
+            6,
+            &segment[..30],
+            segment.len(),
+            false,
+        );
+        assert_eq!(status, Status::Truncated);
+
+        let (src, dst) = (
+            Ipv6Addr::LOCALHOST,
+            "2001:db8::1".parse::<Ipv6Addr>().unwrap(),
+        );
+        let mut datagram = vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x0B, 0x00, 0x00, 1, 2, 3];
+        let sum = udp::ipv6_checksum(&udp::UdpPacket::new(&datagram).unwrap(), &src, &dst);
+        datagram[6..8].copy_from_slice(&sum.to_be_bytes());
+        let status = transport(src.into(), dst.into(), 17, &datagram, datagram.len(), false);
+        assert_eq!(status, Status::Good);
+    }
+
+    #[test]
+    fn test_offload_and_absent() {
+        let (src, dst) = (
+            IpAddr::from([192, 168, 0, 1]),
+            IpAddr::from([192, 168, 0, 2]),
+        );
+        let mut datagram = vec![0x30, 0x39, 0x00, 0x35, 0x00, 0x0A, 0x00, 0x00, 0xAB, 0xCD];
+        assert_eq!(
+            transport(src, dst, 17, &datagram, 10, false),
+            Status::Absent
+        );
+
+        // Linux hands the NIC a checksum field holding only the pseudo-header sum
+        let partial = fold(pseudo_header(src, dst, 17, 10));
+        datagram[6..8].copy_from_slice(&partial.to_be_bytes());
+        assert_eq!(
+            transport(src, dst, 17, &datagram, 10, false),
+            Status::Offloaded
+        );
+
+        // A zero checksum is only expected from the capturing host, or for UDP over IPv4
+        let mut segment = vec![0x9C, 0x40, 0x00, 0x50, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18];
+        segment.extend_from_slice(&[0x20, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert!(matches!(
+            transport(src, dst, 6, &segment, 20, false),
+            Status::Bad { .. }
+        ));
+        assert_eq!(
+            transport(src, dst, 6, &segment, 20, true),
+            Status::Offloaded
+        );
+        let (src6, dst6) = (
+            "2001:db8::1".parse::<IpAddr>().unwrap(),
+            "2001:db8::2".parse::<IpAddr>().unwrap(),
+        );
+        datagram[6..8].copy_from_slice(&[0, 0]);
+        assert!(matches!(
+            transport(src6, dst6, 17, &datagram, 10, false),
+            Status::Bad { .. }
+        ));
+    }
+}
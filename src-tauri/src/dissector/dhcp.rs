@@ -0,0 +1,609 @@
+//! DHCP (RFC 2131) messages: the fixed BOOTP header and the options
+//! (RFC 2132) that carry the message type, requested hostname, vendor class,
+//! parameter request list and lease parameters.
+
+use super::field;
+use super::registry::{Context, Dissection, Dissector, Transport};
+use super::LAYER_DHCP;
+use crate::model::PacketField;
+use crate::state::LeaseEvent;
+use std::net::{IpAddr, Ipv4Addr};
+
+pub const SERVER_PORT: u16 = 67;
+pub const CLIENT_PORT: u16 = 68;
+
+const HEADER_LEN: usize = 236;
+const MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+
+const OPT_PAD: u8 = 0;
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS_SERVER: u8 = 6;
+const OPT_HOSTNAME: u8 = 12;
+const OPT_DOMAIN_NAME: u8 = 15;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_PARAMETER_REQUEST_LIST: u8 = 55;
+const OPT_VENDOR_CLASS: u8 = 60;
+const OPT_CLIENT_ID: u8 = 61;
+const OPT_CLIENT_FQDN: u8 = 81;
+const OPT_END: u8 = 255;
+
+const FQDN_FLAG_ENCODED: u8 = 0x04;
+
+pub const DHCPDISCOVER: u8 = 1;
+pub const DHCPREQUEST: u8 = 3;
+pub const DHCPDECLINE: u8 = 4;
+pub const DHCPACK: u8 = 5;
+pub const DHCPRELEASE: u8 = 7;
+pub const DHCPINFORM: u8 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhcpOption {
+    pub code: u8,
+    pub data: Vec<u8>,
+    /// Byte range within the message, including code and length
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// 1 for requests from clients, 2 for replies from servers
+    pub op: u8,
+    pub htype: u8,
+    pub hlen: u8,
+    pub hops: u8,
+    pub xid: u32,
+    pub secs: u16,
+    pub flags: u16,
+    pub ciaddr: Ipv4Addr,
+    pub yiaddr: Ipv4Addr,
+    pub siaddr: Ipv4Addr,
+    pub giaddr: Ipv4Addr,
+    pub chaddr: Vec<u8>,
+    pub sname: String,
+    pub file: String,
+    pub options: Vec<DhcpOption>,
+    /// Options ended without an End option or ran past the payload
+    pub truncated: bool,
+}
+
+fn ipv4(data: &[u8]) -> Option<Ipv4Addr> {
+    Some(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?))
+}
+
+/// NUL-terminated string field of the BOOTP header.
+fn c_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+impl Message {
+    pub fn option(&self, code: u8) -> Option<&[u8]> {
+        self.options
+            .iter()
+            .find(|o| o.code == code)
+            .map(|o| o.data.as_slice())
+    }
+
+    pub fn message_type(&self) -> Option<u8> {
+        self.option(OPT_MESSAGE_TYPE)?.first().copied()
+    }
+
+    /// Client hardware address as `aa:bb:cc:dd:ee:ff` for Ethernet clients.
+    pub fn client_mac(&self) -> Option<String> {
+        (self.htype == 1 && self.hlen == 6).then(|| format_mac(&self.chaddr[..6]))
+    }
+
+    /// Host Name option, or the name in the Client FQDN option.
+    pub fn hostname(&self) -> Option<String> {
+        if let Some(name) = self.option(OPT_HOSTNAME) {
+            return Some(
+                String::from_utf8_lossy(name)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            );
+        }
+        // Flags, two deprecated RCODE bytes, then the name; the E flag
+        // selects DNS wire format over ASCII (RFC 4702 §2.1)
+        let option = self.option(OPT_CLIENT_FQDN)?;
+        let fqdn = option.get(3..)?;
+        let name = if option[0] & FQDN_FLAG_ENCODED != 0 {
+            wire_name(fqdn)
+        } else {
+            String::from_utf8_lossy(fqdn).into_owned()
+        };
+        (!name.is_empty()).then_some(name)
+    }
+
+    pub fn requested_ip(&self) -> Option<Ipv4Addr> {
+        ipv4(self.option(OPT_REQUESTED_IP)?)
+    }
+
+    pub fn lease_time(&self) -> Option<u32> {
+        Some(u32::from_be_bytes(
+            self.option(OPT_LEASE_TIME)?.try_into().ok()?,
+        ))
+    }
+
+    pub fn vendor_class(&self) -> Option<String> {
+        Some(String::from_utf8_lossy(self.option(OPT_VENDOR_CLASS)?).into_owned())
+    }
+
+    pub fn parameter_request_list(&self) -> Vec<u8> {
+        self.option(OPT_PARAMETER_REQUEST_LIST)
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default()
+    }
+}
+
+pub fn format_mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Uncompressed DNS wire-format name, as used by the Client FQDN options.
+pub fn wire_name(data: &[u8]) -> String {
+    let mut labels = Vec::new();
+    let mut pos = 0;
+    while let Some(&len) = data.get(pos) {
+        let len = usize::from(len);
+        if len == 0 || len > 63 {
+            break;
+        }
+        let Some(label) = data.get(pos + 1..pos + 1 + len) else {
+            break;
+        };
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    labels.join(".")
+}
+
+pub fn parse(payload: &[u8]) -> Result<Message, String> {
+    if payload.len() < HEADER_LEN + MAGIC_COOKIE.len() {
+        return Err(format!("DHCP message too short ({} bytes)", payload.len()));
+    }
+    if payload[HEADER_LEN..HEADER_LEN + 4] != MAGIC_COOKIE {
+        return Err("Missing DHCP magic cookie".to_string());
+    }
+    let op = payload[0];
+    if op != 1 && op != 2 {
+        return Err(format!("Invalid BOOTP op {}", op));
+    }
+    let hlen = payload[2];
+    if hlen > 16 {
+        return Err(format!("Invalid hardware address length {}", hlen));
+    }
+
+    let mut options = Vec::new();
+    let mut truncated = true;
+    let mut pos = HEADER_LEN + MAGIC_COOKIE.len();
+    while pos < payload.len() {
+        let code = payload[pos];
+        if code == OPT_PAD {
+            pos += 1;
+            continue;
+        }
+        if code == OPT_END {
+            truncated = false;
+            break;
+        }
+        let Some(&len) = payload.get(pos + 1) else {
+            break;
+        };
+        let end = pos + 2 + usize::from(len);
+        let Some(data) = payload.get(pos + 2..end) else {
+            break;
+        };
+        options.push(DhcpOption {
+            code,
+            data: data.to_vec(),
+            range: (pos, end),
+        });
+        pos = end;
+    }
+
+    let addr = |at: usize| {
+        Ipv4Addr::new(
+            payload[at],
+            payload[at + 1],
+            payload[at + 2],
+            payload[at + 3],
+        )
+    };
+    Ok(Message {
+        op,
+        htype: payload[1],
+        hlen,
+        hops: payload[3],
+        xid: u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]),
+        secs: u16::from_be_bytes([payload[8], payload[9]]),
+        flags: u16::from_be_bytes([payload[10], payload[11]]),
+        ciaddr: addr(12),
+        yiaddr: addr(16),
+        siaddr: addr(20),
+        giaddr: addr(24),
+        chaddr: payload[28..28 + usize::from(hlen)].to_vec(),
+        sname: c_string(&payload[44..108]),
+        file: c_string(&payload[108..236]),
+        options,
+        truncated,
+    })
+}
+
+pub fn message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        DHCPDISCOVER => "Discover",
+        2 => "Offer",
+        DHCPREQUEST => "Request",
+        DHCPDECLINE => "Decline",
+        DHCPACK => "ACK",
+        6 => "NAK",
+        DHCPRELEASE => "Release",
+        DHCPINFORM => "Inform",
+        9 => "Force Renew",
+        10 => "Lease Query",
+        13 => "Lease Active",
+        _ => "Unknown",
+    }
+}
+
+fn option_name(code: u8) -> &'static str {
+    match code {
+        OPT_SUBNET_MASK => "Subnet Mask",
+        2 => "Time Offset",
+        OPT_ROUTER => "Router",
+        OPT_DNS_SERVER => "Domain Name Server",
+        OPT_HOSTNAME => "Host Name",
+        OPT_DOMAIN_NAME => "Domain Name",
+        28 => "Broadcast Address",
+        42 => "NTP Servers",
+        43 => "Vendor-Specific Information",
+        44 => "NetBIOS Name Server",
+        OPT_REQUESTED_IP => "Requested IP Address",
+        OPT_LEASE_TIME => "IP Address Lease Time",
+        52 => "Option Overload",
+        OPT_MESSAGE_TYPE => "DHCP Message Type",
+        OPT_SERVER_ID => "DHCP Server Identifier",
+        OPT_PARAMETER_REQUEST_LIST => "Parameter Request List",
+        56 => "Message",
+        57 => "Maximum DHCP Message Size",
+        58 => "Renewal Time Value",
+        59 => "Rebinding Time Value",
+        OPT_VENDOR_CLASS => "Vendor Class Identifier",
+        OPT_CLIENT_ID => "Client Identifier",
+        66 => "TFTP Server Name",
+        67 => "Bootfile Name",
+        77 => "User Class",
+        OPT_CLIENT_FQDN => "Client Fully Qualified Domain Name",
+        82 => "Relay Agent Information",
+        119 => "Domain Search",
+        121 => "Classless Static Route",
+        252 => "Private/Proxy autodiscovery",
+        _ => "Unknown",
+    }
+}
+
+/// Info column text, e.g. "DHCP Request - Transaction ID 0x3903f326".
+pub fn summarize(payload: &[u8]) -> Option<String> {
+    let message = parse(payload).ok()?;
+    let name = match message.message_type() {
+        Some(t) => format!("DHCP {}", message_type_name(t)),
+        None if message.op == 1 => "BOOTP Request".to_string(),
+        None => "BOOTP Reply".to_string(),
+    };
+    let mut info = format!("{} - Transaction ID 0x{:08x}", name, message.xid);
+    if message.message_type() == Some(DHCPACK) && !message.yiaddr.is_unspecified() {
+        info.push_str(&format!(" ({} assigned)", message.yiaddr));
+    } else if let Some(host) = message.hostname() {
+        info.push_str(&format!(" (host {})", host));
+    }
+    Some(info)
+}
+
+/// Lease table update carried by a DHCP message, if any.
+pub fn lease_event(payload: &[u8]) -> Option<LeaseEvent> {
+    let message = parse(payload).ok()?;
+    let mac = message.client_mac()?;
+    match message.message_type()? {
+        DHCPACK if !message.yiaddr.is_unspecified() => Some(LeaseEvent::Assigned {
+            mac,
+            ip: IpAddr::V4(message.yiaddr),
+            hostname: message.hostname(),
+            lease_secs: message.lease_time(),
+        }),
+        DHCPRELEASE => Some(LeaseEvent::Released {
+            mac,
+            ip: IpAddr::V4(message.ciaddr),
+        }),
+        DHCPDECLINE => Some(LeaseEvent::Released {
+            mac,
+            ip: IpAddr::V4(message.requested_ip()?),
+        }),
+        DHCPDISCOVER | DHCPREQUEST | DHCPINFORM => Some(LeaseEvent::Hostname {
+            mac,
+            hostname: message.hostname()?,
+        }),
+        _ => None,
+    }
+}
+
+fn ipv4_list(data: &[u8]) -> String {
+    data.chunks_exact(4)
+        .filter_map(ipv4)
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Builds the detail view fields for a DHCP message starting at `offset`.
+pub fn fields(payload: &[u8], offset: usize) -> Vec<PacketField> {
+    let at = |start: usize, len: usize| (offset + start, offset + start + len);
+    let message = match parse(payload) {
+        Ok(message) => message,
+        Err(e) => {
+            return vec![PacketField {
+                expert: Some("Malformed DHCP message".to_string()),
+                ..field("Error", e, at(0, payload.len()))
+            }]
+        }
+    };
+
+    let mut fields = vec![
+        field(
+            "Message Type",
+            format!(
+                "{} ({})",
+                message.op,
+                if message.op == 1 {
+                    "Boot Request"
+                } else {
+                    "Boot Reply"
+                }
+            ),
+            at(0, 1),
+        ),
+        field("Hardware Type", message.htype.to_string(), at(1, 1)),
+        field(
+            "Hardware Address Length",
+            message.hlen.to_string(),
+            at(2, 1),
+        ),
+        field("Hops", message.hops.to_string(), at(3, 1)),
+        field("Transaction ID", format!("0x{:08x}", message.xid), at(4, 4)),
+        field("Seconds Elapsed", message.secs.to_string(), at(8, 2)),
+        field(
+            "Bootp Flags",
+            format!(
+                "0x{:04x} ({})",
+                message.flags,
+                if message.flags & 0x8000 != 0 {
+                    "Broadcast"
+                } else {
+                    "Unicast"
+                }
+            ),
+            at(10, 2),
+        ),
+        field("Client IP Address", message.ciaddr.to_string(), at(12, 4)),
+        field("Your IP Address", message.yiaddr.to_string(), at(16, 4)),
+        field(
+            "Next Server IP Address",
+            message.siaddr.to_string(),
+            at(20, 4),
+        ),
+        field(
+            "Relay Agent IP Address",
+            message.giaddr.to_string(),
+            at(24, 4),
+        ),
+        field(
+            "Client Hardware Address",
+            format_mac(&message.chaddr),
+            at(28, message.chaddr.len()),
+        ),
+    ];
+    if !message.sname.is_empty() {
+        fields.push(field("Server Host Name", message.sname.clone(), at(44, 64)));
+    }
+    if !message.file.is_empty() {
+        fields.push(field("Boot File Name", message.file.clone(), at(108, 128)));
+    }
+
+    for option in &message.options {
+        let range = at(option.range.0, option.range.1 - option.range.0);
+        fields.push(field(
+            "Option",
+            format!(
+                "{} ({}, {} bytes)",
+                option.code,
+                option_name(option.code),
+                option.data.len()
+            ),
+            range,
+        ));
+        let data = option.data.as_slice();
+        let text = || {
+            String::from_utf8_lossy(data)
+                .trim_end_matches('\0')
+                .to_string()
+        };
+        match option.code {
+            OPT_MESSAGE_TYPE => {
+                if let Some(&t) = data.first() {
+                    fields.push(field(
+                        "DHCP Message Type",
+                        format!("{} ({})", t, message_type_name(t)),
+                        range,
+                    ));
+                }
+            }
+            OPT_HOSTNAME => fields.push(field("Host Name", text(), range)),
+            OPT_DOMAIN_NAME => fields.push(field("Domain Name", text(), range)),
+            OPT_VENDOR_CLASS => {
+                if let Some(vendor) = message.vendor_class() {
+                    fields.push(field("Vendor Class Identifier", vendor, range));
+                }
+            }
+            OPT_CLIENT_FQDN => {
+                if let Some(name) = message
+                    .hostname()
+                    .filter(|_| message.option(OPT_HOSTNAME).is_none())
+                {
+                    fields.push(field("Client FQDN", name, range));
+                }
+            }
+            OPT_CLIENT_ID => fields.push(field(
+                "Client Identifier",
+                data.iter().map(|b| format!("{:02x}", b)).collect(),
+                range,
+            )),
+            OPT_REQUESTED_IP => fields.push(field("Requested IP Address", ipv4_list(data), range)),
+            OPT_SERVER_ID => fields.push(field("DHCP Server Identifier", ipv4_list(data), range)),
+            OPT_SUBNET_MASK => fields.push(field("Subnet Mask", ipv4_list(data), range)),
+            OPT_ROUTER => fields.push(field("Router", ipv4_list(data), range)),
+            OPT_DNS_SERVER => fields.push(field("Domain Name Server", ipv4_list(data), range)),
+            OPT_LEASE_TIME => {
+                if let Some(secs) = message.lease_time() {
+                    fields.push(field("IP Address Lease Time", format!("{} s", secs), range));
+                }
+            }
+            OPT_PARAMETER_REQUEST_LIST => {
+                for code in message.parameter_request_list() {
+                    fields.push(field(
+                        "Parameter Request List Item",
+                        format!("{} ({})", code, option_name(code)),
+                        range,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    if message.truncated {
+        fields.push(PacketField {
+            expert: Some("DHCP options end without an End option".to_string()),
+            ..field("Truncated", "1".to_string(), at(payload.len(), 0))
+        });
+    }
+    fields
+}
+
+/// DHCP on the BOOTP server and client ports.
+pub struct Dhcp;
+
+impl Dissector for Dhcp {
+    fn name(&self) -> &'static str {
+        "dhcp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Dynamic Host Configuration Protocol"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[(Transport::Udp, SERVER_PORT), (Transport::Udp, CLIENT_PORT)]
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let info = summarize(ctx.payload).unwrap_or_else(|| "DHCP".to_string());
+        Some(("DHCP".to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        Some(Dissection::layer(
+            LAYER_DHCP,
+            fields(ctx.payload, ctx.offset),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A message from or to client aa:bb:cc:00:11:22; ACKs assign 192.168.1.50.
+    fn dhcp_message(message_type: u8, options: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut msg = vec![0u8; HEADER_LEN];
+        msg[0] = if message_type == DHCPACK { 2 } else { 1 };
+        msg[1] = 1;
+        msg[2] = 6;
+        msg[4..8].copy_from_slice(&0x3903_f326u32.to_be_bytes());
+        if message_type == DHCPACK {
+            msg[16..20].copy_from_slice(&[192, 168, 1, 50]);
+        }
+        msg[28..34].copy_from_slice(&[0xaa, 0xbb, 0xcc, 0x00, 0x11, 0x22]);
+        msg.extend_from_slice(&MAGIC_COOKIE);
+        msg.extend_from_slice(&[OPT_MESSAGE_TYPE, 1, message_type]);
+        for (code, data) in options {
+            msg.push(*code);
+            msg.push(data.len() as u8);
+            msg.extend_from_slice(data);
+        }
+        msg.push(OPT_END);
+        msg
+    }
+
+    #[test]
+    fn test_parse_request_options() {
+        let msg = dhcp_message(
+            DHCPREQUEST,
+            &[
+                (OPT_HOSTNAME, b"laptop"),
+                (OPT_VENDOR_CLASS, b"MSFT 5.0"),
+                (OPT_PARAMETER_REQUEST_LIST, &[1, 3, 6, 15]),
+                (OPT_REQUESTED_IP, &[192, 168, 1, 50]),
+            ],
+        );
+        let message = parse(&msg).unwrap();
+        assert_eq!(message.hostname().as_deref(), Some("laptop"));
+        assert_eq!(message.vendor_class().as_deref(), Some("MSFT 5.0"));
+        assert_eq!(message.parameter_request_list(), vec![1, 3, 6, 15]);
+        assert_eq!(
+            summarize(&msg).unwrap(),
+            "DHCP Request - Transaction ID 0x3903f326 (host laptop)"
+        );
+        assert_eq!(
+            lease_event(&msg),
+            Some(LeaseEvent::Hostname {
+                mac: "aa:bb:cc:00:11:22".to_string(),
+                hostname: "laptop".to_string(),
+            })
+        );
+
+        let fields = fields(&msg, 42);
+        let items: Vec<&str> = fields
+            .iter()
+            .filter(|f| f.name == "Parameter Request List Item")
+            .map(|f| f.value.as_str())
+            .collect();
+        assert_eq!(items[1], "3 (Router)");
+    }
+
+    #[test]
+    fn test_ack_assigns_lease() {
+        let msg = dhcp_message(
+            DHCPACK,
+            &[
+                (OPT_LEASE_TIME, &86400u32.to_be_bytes()),
+                (OPT_CLIENT_FQDN, b"\x05\x00\x00\x06laptop\x03lan\x00"),
+            ],
+        );
+        assert_eq!(
+            lease_event(&msg),
+            Some(LeaseEvent::Assigned {
+                mac: "aa:bb:cc:00:11:22".to_string(),
+                ip: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 50)),
+                hostname: Some("laptop.lan".to_string()),
+                lease_secs: Some(86400),
+            })
+        );
+        assert!(parse(&msg[..HEADER_LEN]).is_err());
+    }
+}
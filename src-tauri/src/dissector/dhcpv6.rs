@@ -0,0 +1,569 @@
+//! DHCPv6 (RFC 8415) client/server and relay messages and their options:
+//! DUIDs, identity associations with their addresses, the option request
+//! list, vendor class and client FQDN (RFC 4704).
+
+use super::dhcp::{format_mac, wire_name};
+use super::registry::{Context, Dissection, Dissector, Transport};
+use super::LAYER_DHCPV6;
+use super::{be_u16, be_u32, field, hex};
+use crate::model::PacketField;
+use crate::state::LeaseEvent;
+use std::net::{IpAddr, Ipv6Addr};
+
+pub const CLIENT_PORT: u16 = 546;
+pub const SERVER_PORT: u16 = 547;
+
+const OPT_CLIENTID: u16 = 1;
+const OPT_SERVERID: u16 = 2;
+const OPT_IA_NA: u16 = 3;
+const OPT_IA_TA: u16 = 4;
+const OPT_IAADDR: u16 = 5;
+const OPT_ORO: u16 = 6;
+const OPT_ELAPSED_TIME: u16 = 8;
+const OPT_RELAY_MSG: u16 = 9;
+const OPT_STATUS_CODE: u16 = 13;
+const OPT_VENDOR_CLASS: u16 = 16;
+const OPT_DNS_SERVERS: u16 = 23;
+const OPT_DOMAIN_LIST: u16 = 24;
+const OPT_IA_PD: u16 = 25;
+const OPT_IAPREFIX: u16 = 26;
+const OPT_CLIENT_FQDN: u16 = 39;
+
+pub const SOLICIT: u8 = 1;
+pub const REQUEST: u8 = 3;
+pub const RENEW: u8 = 5;
+pub const REBIND: u8 = 6;
+pub const REPLY: u8 = 7;
+pub const RELEASE: u8 = 8;
+pub const DECLINE: u8 = 9;
+pub const INFORMATION_REQUEST: u8 = 11;
+pub const RELAY_FORW: u8 = 12;
+pub const RELAY_REPL: u8 = 13;
+
+/// Relays nest one message per hop; deeper nesting is not followed.
+const MAX_RELAY_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dhcpv6Option {
+    pub code: u16,
+    pub data: Vec<u8>,
+    /// Byte range within the message, including code and length
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub msg_type: u8,
+    /// Transaction ID of client/server messages; None for relay messages
+    pub transaction_id: Option<u32>,
+    /// Link and peer address of relay messages
+    pub relay: Option<(Ipv6Addr, Ipv6Addr)>,
+    pub options: Vec<Dhcpv6Option>,
+}
+
+/// An address (IAADDR) or prefix (IAPREFIX) inside an identity association.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IaAddress {
+    pub address: Ipv6Addr,
+    pub prefix_len: Option<u8>,
+    pub preferred: u32,
+    pub valid: u32,
+}
+
+fn ipv6_at(data: &[u8], pos: usize) -> Option<Ipv6Addr> {
+    Some(Ipv6Addr::from(
+        <[u8; 16]>::try_from(data.get(pos..pos + 16)?).ok()?,
+    ))
+}
+
+/// Parses a sequence of options, with ranges offset by `base`.
+fn parse_options(data: &[u8], base: usize) -> Result<Vec<Dhcpv6Option>, String> {
+    let mut options = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (Some(code), Some(len)) = (be_u16(data, pos), be_u16(data, pos + 2)) else {
+            return Err(format!("Truncated option header at offset {}", base + pos));
+        };
+        let end = pos + 4 + usize::from(len);
+        let option = data
+            .get(pos + 4..end)
+            .ok_or_else(|| format!("Option {} runs past the message", code))?;
+        options.push(Dhcpv6Option {
+            code,
+            data: option.to_vec(),
+            range: (base + pos, base + end),
+        });
+        pos = end;
+    }
+    Ok(options)
+}
+
+pub fn parse(payload: &[u8]) -> Result<Message, String> {
+    let msg_type = *payload.first().ok_or("Empty DHCPv6 message")?;
+    if msg_type == 0 || msg_type > 36 {
+        return Err(format!("Invalid DHCPv6 message type {}", msg_type));
+    }
+    if msg_type == RELAY_FORW || msg_type == RELAY_REPL {
+        let link = ipv6_at(payload, 2).ok_or("Truncated relay message")?;
+        let peer = ipv6_at(payload, 18).ok_or("Truncated relay message")?;
+        return Ok(Message {
+            msg_type,
+            transaction_id: None,
+            relay: Some((link, peer)),
+            options: parse_options(&payload[34..], 34)?,
+        });
+    }
+    if payload.len() < 4 {
+        return Err("Truncated DHCPv6 header".to_string());
+    }
+    Ok(Message {
+        msg_type,
+        transaction_id: Some(u32::from_be_bytes([0, payload[1], payload[2], payload[3]])),
+        relay: None,
+        options: parse_options(&payload[4..], 4)?,
+    })
+}
+
+impl Message {
+    pub fn option(&self, code: u16) -> Option<&[u8]> {
+        self.options
+            .iter()
+            .find(|o| o.code == code)
+            .map(|o| o.data.as_slice())
+    }
+
+    /// The client/server message inside relay messages, and its offset.
+    pub fn innermost(&self) -> Option<(Message, usize)> {
+        let mut current = (self.clone(), 0);
+        for _ in 0..MAX_RELAY_DEPTH {
+            if current.0.relay.is_none() {
+                return Some(current);
+            }
+            let option = current.0.options.iter().find(|o| o.code == OPT_RELAY_MSG)?;
+            let offset = current.1 + option.range.0 + 4;
+            current = (parse(&option.data).ok()?, offset);
+        }
+        None
+    }
+
+    /// Addresses and prefixes granted in IA_NA, IA_TA and IA_PD options.
+    pub fn ia_addresses(&self) -> Vec<IaAddress> {
+        let mut addresses = Vec::new();
+        for option in &self.options {
+            let header = match option.code {
+                OPT_IA_NA | OPT_IA_PD => 12,
+                OPT_IA_TA => 4,
+                _ => continue,
+            };
+            let Some(inner) = option.data.get(header..) else {
+                continue;
+            };
+            for sub in parse_options(inner, 0).unwrap_or_default() {
+                let d = &sub.data;
+                let address = match sub.code {
+                    OPT_IAADDR => (|| {
+                        Some(IaAddress {
+                            address: ipv6_at(d, 0)?,
+                            prefix_len: None,
+                            preferred: be_u32(d, 16)?,
+                            valid: be_u32(d, 20)?,
+                        })
+                    })(),
+                    OPT_IAPREFIX => (|| {
+                        Some(IaAddress {
+                            address: ipv6_at(d, 9)?,
+                            prefix_len: Some(*d.get(8)?),
+                            preferred: be_u32(d, 0)?,
+                            valid: be_u32(d, 4)?,
+                        })
+                    })(),
+                    _ => None,
+                };
+                addresses.extend(address);
+            }
+        }
+        addresses
+    }
+
+    pub fn client_fqdn(&self) -> Option<String> {
+        // Flags byte, then the name in DNS wire format
+        let name = wire_name(self.option(OPT_CLIENT_FQDN)?.get(1..)?);
+        (!name.is_empty()).then_some(name)
+    }
+
+    /// Link-layer address embedded in the client DUID (DUID-LLT or DUID-LL
+    /// over Ethernet), which survives relaying unlike the frame's source.
+    pub fn client_mac(&self) -> Option<String> {
+        let duid = self.option(OPT_CLIENTID)?;
+        let (duid_type, hw_type) = (be_u16(duid, 0)?, be_u16(duid, 2)?);
+        let mac = match duid_type {
+            1 => duid.get(8..)?,
+            3 => duid.get(4..)?,
+            _ => return None,
+        };
+        (hw_type == 1 && mac.len() == 6).then(|| format_mac(mac))
+    }
+
+    pub fn vendor_class(&self) -> Option<String> {
+        let data = self.option(OPT_VENDOR_CLASS)?;
+        let enterprise = be_u32(data, 0)?;
+        let values: Vec<String> = parse_len16_list(data.get(4..)?)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v).into_owned())
+            .collect();
+        Some(format!("{} (enterprise {})", values.join(", "), enterprise))
+    }
+
+    pub fn option_request_list(&self) -> Vec<u16> {
+        self.option(OPT_ORO)
+            .map(|data| {
+                data.chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn parse_len16_list(data: &[u8]) -> Vec<&[u8]> {
+    let mut values = Vec::new();
+    let mut pos = 0;
+    while let Some(len) = be_u16(data, pos) {
+        let Some(value) = data.get(pos + 2..pos + 2 + usize::from(len)) else {
+            break;
+        };
+        values.push(value);
+        pos += 2 + usize::from(len);
+    }
+    values
+}
+
+pub fn message_type_name(msg_type: u8) -> &'static str {
+    match msg_type {
+        SOLICIT => "Solicit",
+        2 => "Advertise",
+        REQUEST => "Request",
+        4 => "Confirm",
+        RENEW => "Renew",
+        REBIND => "Rebind",
+        REPLY => "Reply",
+        RELEASE => "Release",
+        DECLINE => "Decline",
+        10 => "Reconfigure",
+        INFORMATION_REQUEST => "Information-request",
+        RELAY_FORW => "Relay-forw",
+        RELAY_REPL => "Relay-reply",
+        _ => "Unknown",
+    }
+}
+
+fn option_name(code: u16) -> &'static str {
+    match code {
+        OPT_CLIENTID => "Client Identifier",
+        OPT_SERVERID => "Server Identifier",
+        OPT_IA_NA => "Identity Association for Non-temporary Address",
+        OPT_IA_TA => "Identity Association for Temporary Address",
+        OPT_IAADDR => "IA Address",
+        OPT_ORO => "Option Request",
+        7 => "Preference",
+        OPT_ELAPSED_TIME => "Elapsed Time",
+        OPT_RELAY_MSG => "Relay Message",
+        OPT_STATUS_CODE => "Status Code",
+        14 => "Rapid Commit",
+        15 => "User Class",
+        OPT_VENDOR_CLASS => "Vendor Class",
+        17 => "Vendor-specific Information",
+        18 => "Interface-Id",
+        OPT_DNS_SERVERS => "DNS Recursive Name Server",
+        OPT_DOMAIN_LIST => "Domain Search List",
+        OPT_IA_PD => "Identity Association for Prefix Delegation",
+        OPT_IAPREFIX => "IA Prefix",
+        OPT_CLIENT_FQDN => "Fully Qualified Domain Name",
+        _ => "Unknown",
+    }
+}
+
+/// Info column text, e.g. "DHCPv6 Reply XID: 0x6b2f1a".
+pub fn summarize(payload: &[u8]) -> Option<String> {
+    let message = parse(payload).ok()?;
+    let mut info = format!("DHCPv6 {}", message_type_name(message.msg_type));
+    let Some((inner, _)) = message.innermost() else {
+        return Some(info);
+    };
+    if message.relay.is_some() {
+        info.push_str(&format!(" ({})", message_type_name(inner.msg_type)));
+    }
+    if let Some(xid) = inner.transaction_id {
+        info.push_str(&format!(" XID: 0x{:06x}", xid));
+    }
+    if let Some(address) = inner.ia_addresses().first() {
+        info.push_str(&format!(" IAA: {}", address.address));
+    }
+    if let Some(fqdn) = inner.client_fqdn() {
+        info.push_str(&format!(" FQDN: {}", fqdn));
+    }
+    Some(info)
+}
+
+/// Lease table update carried by a DHCPv6 message, if any. `client_mac` is
+/// the frame address of the client, used when its DUID holds no MAC.
+pub fn lease_event(payload: &[u8], client_mac: &str) -> Option<LeaseEvent> {
+    let (message, _) = parse(payload).ok()?.innermost()?;
+    let mac = message
+        .client_mac()
+        .unwrap_or_else(|| client_mac.to_string());
+    let address = || {
+        message
+            .ia_addresses()
+            .into_iter()
+            .find(|a| a.prefix_len.is_none())
+    };
+    match message.msg_type {
+        REPLY => {
+            let address = address().filter(|a| a.valid > 0)?;
+            Some(LeaseEvent::Assigned {
+                mac,
+                ip: IpAddr::V6(address.address),
+                hostname: message.client_fqdn(),
+                lease_secs: Some(address.valid),
+            })
+        }
+        RELEASE | DECLINE => Some(LeaseEvent::Released {
+            mac,
+            ip: IpAddr::V6(address()?.address),
+        }),
+        SOLICIT | REQUEST | RENEW | REBIND | INFORMATION_REQUEST => Some(LeaseEvent::Hostname {
+            mac,
+            hostname: message.client_fqdn()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Whether a message travels from client to server, so the frame source is
+/// the client.
+pub fn is_from_client(payload: &[u8]) -> bool {
+    parse(payload)
+        .ok()
+        .and_then(|m| m.innermost())
+        .is_some_and(|(m, _)| m.msg_type != REPLY && m.msg_type != 2 && m.msg_type != 10)
+}
+
+/// Builds the detail view fields for a DHCPv6 message starting at `offset`.
+pub fn fields(payload: &[u8], offset: usize) -> Vec<PacketField> {
+    let mut fields = Vec::new();
+    push_message_fields(&mut fields, payload, offset, 0);
+    fields
+}
+
+fn push_message_fields(fields: &mut Vec<PacketField>, payload: &[u8], offset: usize, depth: usize) {
+    let at = |(start, end): (usize, usize)| (offset + start, offset + end);
+    let message = match parse(payload) {
+        Ok(message) => message,
+        Err(e) => {
+            fields.push(PacketField {
+                expert: Some("Malformed DHCPv6 message".to_string()),
+                ..field("Error", e, at((0, payload.len())))
+            });
+            return;
+        }
+    };
+
+    fields.push(field(
+        "Message Type",
+        format!(
+            "{} ({})",
+            message.msg_type,
+            message_type_name(message.msg_type)
+        ),
+        at((0, 1)),
+    ));
+    if let Some(xid) = message.transaction_id {
+        fields.push(field(
+            "Transaction ID",
+            format!("0x{:06x}", xid),
+            at((1, 4)),
+        ));
+    }
+    if let Some((link, peer)) = message.relay {
+        fields.push(field("Hop Count", payload[1].to_string(), at((1, 2))));
+        fields.push(field("Link Address", link.to_string(), at((2, 18))));
+        fields.push(field("Peer Address", peer.to_string(), at((18, 34))));
+    }
+
+    for option in &message.options {
+        let range = at(option.range);
+        let data = option.data.as_slice();
+        fields.push(field(
+            "Option",
+            format!(
+                "{} ({}, {} bytes)",
+                option.code,
+                option_name(option.code),
+                data.len()
+            ),
+            range,
+        ));
+        match option.code {
+            OPT_CLIENTID => fields.push(field("Client DUID", hex(data), range)),
+            OPT_SERVERID => fields.push(field("Server DUID", hex(data), range)),
+            OPT_ELAPSED_TIME => {
+                if let Some(centis) = be_u16(data, 0) {
+                    fields.push(field(
+                        "Elapsed Time",
+                        format!("{} ms", u32::from(centis) * 10),
+                        range,
+                    ));
+                }
+            }
+            OPT_ORO => {
+                for code in message.option_request_list() {
+                    fields.push(field(
+                        "Requested Option Code",
+                        format!("{} ({})", code, option_name(code)),
+                        range,
+                    ));
+                }
+            }
+            OPT_VENDOR_CLASS => {
+                if let Some(vendor) = message.vendor_class() {
+                    fields.push(field("Vendor Class", vendor, range));
+                }
+            }
+            OPT_CLIENT_FQDN => {
+                if let Some(fqdn) = message.client_fqdn() {
+                    fields.push(field("Client FQDN", fqdn, range));
+                }
+            }
+            OPT_DNS_SERVERS => {
+                for server in data.chunks_exact(16).filter_map(|c| ipv6_at(c, 0)) {
+                    fields.push(field("DNS Server", server.to_string(), range));
+                }
+            }
+            OPT_STATUS_CODE => {
+                if let Some(code) = be_u16(data, 0) {
+                    let text = String::from_utf8_lossy(&data[2..]);
+                    fields.push(PacketField {
+                        expert: (code != 0).then(|| format!("DHCPv6 status {}: {}", code, text)),
+                        ..field(
+                            "Status Code",
+                            format!("{} {}", code, text).trim_end().to_string(),
+                            range,
+                        )
+                    });
+                }
+            }
+            OPT_RELAY_MSG if depth < MAX_RELAY_DEPTH => {
+                push_message_fields(fields, data, range.0 + 4, depth + 1);
+            }
+            _ => {}
+        }
+    }
+
+    for address in message.ia_addresses() {
+        let value = match address.prefix_len {
+            Some(len) => format!("{}/{}", address.address, len),
+            None => address.address.to_string(),
+        };
+        let name = if address.prefix_len.is_some() {
+            "IA Prefix"
+        } else {
+            "IA Address"
+        };
+        fields.push(field(name, value, at((4, payload.len()))));
+        fields.push(field(
+            "Valid Lifetime",
+            format!("{} s", address.valid),
+            at((4, payload.len())),
+        ));
+    }
+}
+
+/// DHCPv6 on the client and server/relay ports.
+pub struct Dhcpv6;
+
+impl Dissector for Dhcpv6 {
+    fn name(&self) -> &'static str {
+        "dhcpv6"
+    }
+
+    fn description(&self) -> &'static str {
+        "Dynamic Host Configuration Protocol for IPv6"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[(Transport::Udp, CLIENT_PORT), (Transport::Udp, SERVER_PORT)]
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let info = summarize(ctx.payload).unwrap_or_else(|| "DHCPv6".to_string());
+        Some(("DHCPv6".to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        Some(Dissection::layer(
+            LAYER_DHCPV6,
+            fields(ctx.payload, ctx.offset),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(code: u16, data: &[u8]) -> Vec<u8> {
+        [
+            &code.to_be_bytes()[..],
+            &(data.len() as u16).to_be_bytes(),
+            data,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_relayed_reply_assigns_lease() {
+        let address: Ipv6Addr = "2001:db8::50".parse().unwrap();
+        let iaaddr = option(
+            OPT_IAADDR,
+            &[
+                &address.octets()[..],
+                &3600u32.to_be_bytes(),
+                &7200u32.to_be_bytes(),
+            ]
+            .concat(),
+        );
+        let ia_na = option(OPT_IA_NA, &[&[0u8; 12][..], &iaaddr].concat());
+        let duid = option(OPT_CLIENTID, &[0, 3, 0, 1, 0xaa, 0xbb, 0xcc, 0, 0x11, 0x22]);
+        let fqdn = option(OPT_CLIENT_FQDN, b"\x01\x06laptop\x03lan\x00");
+        let reply = [&[REPLY, 0x6b, 0x2f, 0x1a][..], &duid, &ia_na, &fqdn].concat();
+        let relay = [
+            &[RELAY_REPL, 0][..],
+            &[0u8; 32],
+            &option(OPT_RELAY_MSG, &reply),
+        ]
+        .concat();
+
+        assert_eq!(
+            summarize(&relay).unwrap(),
+            "DHCPv6 Relay-reply (Reply) XID: 0x6b2f1a IAA: 2001:db8::50 FQDN: laptop.lan"
+        );
+        assert_eq!(
+            lease_event(&relay, "ff:ff:ff:ff:ff:ff"),
+            Some(LeaseEvent::Assigned {
+                mac: "aa:bb:cc:00:11:22".to_string(),
+                ip: IpAddr::V6(address),
+                hostname: Some("laptop.lan".to_string()),
+                lease_secs: Some(7200),
+            })
+        );
+        assert!(!is_from_client(&relay));
+
+        let fields = fields(&relay, 62);
+        let xid = fields.iter().find(|f| f.name == "Transaction ID").unwrap();
+        assert_eq!(xid.range, (62 + 34 + 4 + 1, 62 + 34 + 4 + 4));
+        assert!(fields
+            .iter()
+            .any(|f| f.name == "IA Address" && f.value == "2001:db8::50"));
+    }
+}
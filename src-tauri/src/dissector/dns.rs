@@ -0,0 +1,953 @@
+//! DNS message parsing (RFC 1035): header flags, questions and resource
+//! records with name compression, EDNS0 (RFC 6891) and the two-byte length
+//! prefix used over TCP.
+
+use super::field;
+use super::registry::{Context, Dissection, Dissector, Heuristic, Transport};
+use super::LAYER_DNS;
+use crate::model::PacketField;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub const DNS_PORT: u16 = 53;
+pub const MDNS_PORT: u16 = 5353;
+
+const HEADER_LEN: usize = 12;
+const MAX_NAME_LEN: usize = 255;
+const TYPE_OPT: u16 = 41;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const FLAG_RECURSION_AVAILABLE: u16 = 0x0080;
+const FLAG_Z: u16 = 0x0040;
+const EDNS_DNSSEC_OK: u32 = 0x8000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    /// Byte range within the message
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: RData,
+    /// Byte range within the message
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    /// NS, CNAME, PTR and DNAME targets
+    Name(String),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Txt(Vec<String>),
+    Soa {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Caa {
+        flags: u8,
+        tag: String,
+        value: String,
+    },
+    /// SVCB and HTTPS; service parameters are not decoded
+    Svcb {
+        priority: u16,
+        target: String,
+    },
+    /// EDNS0 options as (code, data)
+    Opt(Vec<(u16, Vec<u8>)>),
+    Other(Vec<u8>),
+}
+
+/// EDNS0 parameters carried in the OPT pseudo-record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edns {
+    pub udp_payload_size: u16,
+    pub extended_rcode: u8,
+    pub version: u8,
+    pub dnssec_ok: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: u16,
+    pub flags: u16,
+    /// Section counts as announced by the header
+    pub counts: [u16; 4],
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authorities: Vec<Record>,
+    pub additionals: Vec<Record>,
+    pub edns: Option<Edns>,
+}
+
+impl Message {
+    pub fn is_response(&self) -> bool {
+        self.flags & FLAG_RESPONSE != 0
+    }
+
+    pub fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0x0f) as u8
+    }
+
+    /// Response code, including the EDNS0 extended bits.
+    pub fn rcode(&self) -> u16 {
+        let extended = self.edns.as_ref().map_or(0, |e| e.extended_rcode as u16);
+        (extended << 4) | (self.flags & 0x0f)
+    }
+
+    /// One-line description for the packet list, e.g.
+    /// "Standard query response A example.com → 93.184.216.34".
+    pub fn summary(&self) -> String {
+        let mut info = opcode_name(self.opcode());
+        if self.is_response() {
+            info.push_str(" response");
+        }
+        for question in &self.questions {
+            info.push_str(&format!(" {} {}", type_name(question.qtype), question.name));
+        }
+        if self.is_response() {
+            let rcode = self.rcode();
+            if rcode != 0 {
+                info.push_str(&format!(" {}", rcode_name(rcode)));
+            } else if !self.answers.is_empty() {
+                let qtype = self.questions.first().map(|q| q.qtype);
+                let values: Vec<String> = self
+                    .answers
+                    .iter()
+                    .map(|rr| {
+                        if Some(rr.rtype) == qtype {
+                            rr.data.to_string()
+                        } else {
+                            format!("{} {}", type_name(rr.rtype), rr.data)
+                        }
+                    })
+                    .collect();
+                info.push_str(&format!(" → {}", values.join(", ")));
+            }
+        }
+        info
+    }
+}
+
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{}", addr),
+            RData::Aaaa(addr) => write!(f, "{}", addr),
+            RData::Name(name) => write!(f, "{}", name),
+            RData::Mx {
+                preference,
+                exchange,
+            } => write!(f, "{} {}", preference, exchange),
+            RData::Txt(strings) => {
+                let quoted: Vec<String> = strings.iter().map(|s| format!("{:?}", s)).collect();
+                write!(f, "{}", quoted.join(" "))
+            }
+            RData::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            RData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{} {} {} {}", priority, weight, port, target),
+            RData::Caa { flags, tag, value } => write!(f, "{} {} {:?}", flags, tag, value),
+            RData::Svcb { priority, target } => write!(f, "{} {}", priority, target),
+            RData::Opt(options) => write!(f, "{} options", options.len()),
+            RData::Other(data) => write!(f, "{} bytes", data.len()),
+        }
+    }
+}
+
+pub fn type_name(rtype: u16) -> String {
+    let name = match rtype {
+        1 => "A",
+        2 => "NS",
+        5 => "CNAME",
+        6 => "SOA",
+        12 => "PTR",
+        13 => "HINFO",
+        15 => "MX",
+        16 => "TXT",
+        28 => "AAAA",
+        33 => "SRV",
+        35 => "NAPTR",
+        39 => "DNAME",
+        41 => "OPT",
+        43 => "DS",
+        46 => "RRSIG",
+        47 => "NSEC",
+        48 => "DNSKEY",
+        50 => "NSEC3",
+        64 => "SVCB",
+        65 => "HTTPS",
+        251 => "IXFR",
+        252 => "AXFR",
+        255 => "ANY",
+        257 => "CAA",
+        // RFC 3597 notation for types without a mnemonic
+        _ => return format!("TYPE{}", rtype),
+    };
+    name.to_string()
+}
+
+fn class_name(class: u16) -> String {
+    // mDNS uses the top bit for cache-flush / unicast-response
+    match class & 0x7fff {
+        1 => "IN".to_string(),
+        3 => "CH".to_string(),
+        4 => "HS".to_string(),
+        254 => "NONE".to_string(),
+        255 => "ANY".to_string(),
+        other => format!("CLASS{}", other),
+    }
+}
+
+fn opcode_name(opcode: u8) -> String {
+    match opcode {
+        0 => "Standard query".to_string(),
+        1 => "Inverse query".to_string(),
+        2 => "Server status request".to_string(),
+        4 => "Zone change notification".to_string(),
+        5 => "Dynamic update".to_string(),
+        other => format!("Opcode {}", other),
+    }
+}
+
+pub fn rcode_name(rcode: u16) -> String {
+    match rcode {
+        0 => "No error".to_string(),
+        1 => "Format error".to_string(),
+        2 => "Server failure".to_string(),
+        3 => "No such name".to_string(),
+        4 => "Not implemented".to_string(),
+        5 => "Refused".to_string(),
+        6 => "Name exists".to_string(),
+        7 => "RRset exists".to_string(),
+        8 => "RRset does not exist".to_string(),
+        9 => "Not authoritative".to_string(),
+        10 => "Name not in zone".to_string(),
+        16 => "Bad EDNS version".to_string(),
+        other => format!("Unknown error {}", other),
+    }
+}
+
+fn edns_option_name(code: u16) -> String {
+    match code {
+        3 => "NSID".to_string(),
+        8 => "Client Subnet".to_string(),
+        10 => "Cookie".to_string(),
+        11 => "TCP Keepalive".to_string(),
+        12 => "Padding".to_string(),
+        15 => "Extended DNS Error".to_string(),
+        other => format!("Option {}", other),
+    }
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> Result<u16, String> {
+    msg.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| format!("Message truncated at offset {}", pos))
+}
+
+fn read_u32(msg: &[u8], pos: usize) -> Result<u32, String> {
+    msg.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| format!("Message truncated at offset {}", pos))
+}
+
+/// Escapes a label the way zone files do: dots, backslashes and
+/// non-printable bytes become `\.`, `\\` and `\ddd`.
+fn push_label(name: &mut String, label: &[u8]) {
+    for &byte in label {
+        match byte {
+            b'.' | b'\\' => {
+                name.push('\\');
+                name.push(byte as char);
+            }
+            0x21..=0x7e => name.push(byte as char),
+            _ => name.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+}
+
+/// Reads a possibly compressed domain name starting at `start`, returning it
+/// with the offset just past its in-place encoding.
+fn read_name(msg: &[u8], start: usize) -> Result<(String, usize), String> {
+    let mut name = String::new();
+    let mut pos = start;
+    let mut end = None;
+    // Pointers must go strictly backwards, which rules out loops
+    let mut pointer_limit = start;
+    let mut wire_len = 1;
+
+    loop {
+        let len = *msg
+            .get(pos)
+            .ok_or_else(|| format!("Name at offset {} runs past the end of the message", start))?;
+        match len & 0xc0 {
+            0x00 if len == 0 => {
+                pos += 1;
+                break;
+            }
+            0x00 => {
+                let label = msg
+                    .get(pos + 1..pos + 1 + len as usize)
+                    .ok_or_else(|| format!("Label at offset {} is truncated", pos))?;
+                wire_len += len as usize + 1;
+                if wire_len > MAX_NAME_LEN {
+                    return Err(format!("Name at offset {} exceeds 255 bytes", start));
+                }
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                push_label(&mut name, label);
+                pos += 1 + len as usize;
+            }
+            0xc0 => {
+                let low = *msg
+                    .get(pos + 1)
+                    .ok_or_else(|| format!("Compression pointer at offset {} is truncated", pos))?;
+                let target = ((len as usize & 0x3f) << 8) | low as usize;
+                if target >= pointer_limit {
+                    return Err(format!(
+                        "Compression pointer at offset {} does not point backwards",
+                        pos
+                    ));
+                }
+                end.get_or_insert(pos + 2);
+                pointer_limit = target;
+                pos = target;
+            }
+            _ => {
+                return Err(format!(
+                    "Unsupported label type 0x{:02x} at offset {}",
+                    len, pos
+                ))
+            }
+        }
+    }
+
+    if name.is_empty() {
+        name.push_str("<Root>");
+    }
+    Ok((name, end.unwrap_or(pos)))
+}
+
+fn parse_rdata(msg: &[u8], rtype: u16, start: usize, end: usize) -> Result<RData, String> {
+    let rdata = &msg[start..end];
+    let fixed = |len: usize| {
+        if rdata.len() == len {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} record data is {} bytes, expected {}",
+                type_name(rtype),
+                rdata.len(),
+                len
+            ))
+        }
+    };
+    let data = match rtype {
+        1 => {
+            fixed(4)?;
+            RData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]))
+        }
+        28 => {
+            fixed(16)?;
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(rdata);
+            RData::Aaaa(Ipv6Addr::from(octets))
+        }
+        2 | 5 | 12 | 39 => RData::Name(read_name(msg, start)?.0),
+        15 => RData::Mx {
+            preference: read_u16(rdata, 0)?,
+            exchange: read_name(msg, start + 2)?.0,
+        },
+        16 => {
+            let mut strings = Vec::new();
+            let mut pos = 0;
+            while pos < rdata.len() {
+                let len = rdata[pos] as usize;
+                let text = rdata
+                    .get(pos + 1..pos + 1 + len)
+                    .ok_or("TXT string runs past the record data")?;
+                strings.push(String::from_utf8_lossy(text).into_owned());
+                pos += 1 + len;
+            }
+            RData::Txt(strings)
+        }
+        6 => {
+            let (mname, pos) = read_name(msg, start)?;
+            let (rname, pos) = read_name(msg, pos)?;
+            RData::Soa {
+                mname,
+                rname,
+                serial: read_u32(msg, pos)?,
+                refresh: read_u32(msg, pos + 4)?,
+                retry: read_u32(msg, pos + 8)?,
+                expire: read_u32(msg, pos + 12)?,
+                minimum: read_u32(msg, pos + 16)?,
+            }
+        }
+        33 => RData::Srv {
+            priority: read_u16(rdata, 0)?,
+            weight: read_u16(rdata, 2)?,
+            port: read_u16(rdata, 4)?,
+            target: read_name(msg, start + 6)?.0,
+        },
+        257 => {
+            let flags = *rdata.first().ok_or("CAA record data is empty")?;
+            let tag_len = *rdata.get(1).ok_or("CAA record data is truncated")? as usize;
+            let tag = rdata
+                .get(2..2 + tag_len)
+                .ok_or("CAA tag runs past the record data")?;
+            RData::Caa {
+                flags,
+                tag: String::from_utf8_lossy(tag).into_owned(),
+                value: String::from_utf8_lossy(&rdata[2 + tag_len..]).into_owned(),
+            }
+        }
+        64 | 65 => RData::Svcb {
+            priority: read_u16(rdata, 0)?,
+            target: read_name(msg, start + 2)?.0,
+        },
+        TYPE_OPT => {
+            let mut options = Vec::new();
+            let mut pos = 0;
+            while pos < rdata.len() {
+                let code = read_u16(rdata, pos)?;
+                let len = read_u16(rdata, pos + 2)? as usize;
+                let data = rdata
+                    .get(pos + 4..pos + 4 + len)
+                    .ok_or("EDNS option runs past the record data")?;
+                options.push((code, data.to_vec()));
+                pos += 4 + len;
+            }
+            RData::Opt(options)
+        }
+        _ => RData::Other(rdata.to_vec()),
+    };
+    Ok(data)
+}
+
+fn parse_record(msg: &[u8], start: usize) -> Result<(Record, usize), String> {
+    let (name, pos) = read_name(msg, start)?;
+    let rtype = read_u16(msg, pos)?;
+    let class = read_u16(msg, pos + 2)?;
+    let ttl = read_u32(msg, pos + 4)?;
+    let rdlength = read_u16(msg, pos + 8)? as usize;
+    let rdata_start = pos + 10;
+    let end = rdata_start + rdlength;
+    if end > msg.len() {
+        return Err(format!(
+            "{} record at offset {} runs past the end of the message",
+            type_name(rtype),
+            start
+        ));
+    }
+    let data = parse_rdata(msg, rtype, rdata_start, end)?;
+    let record = Record {
+        name,
+        rtype,
+        class,
+        ttl,
+        data,
+        range: (start, end),
+    };
+    Ok((record, end))
+}
+
+/// Parses a DNS message as carried over UDP.
+pub fn parse(msg: &[u8]) -> Result<Message, String> {
+    if msg.len() < HEADER_LEN {
+        return Err(format!(
+            "Message is {} bytes, shorter than the 12-byte header",
+            msg.len()
+        ));
+    }
+    let counts = [
+        read_u16(msg, 4)?,
+        read_u16(msg, 6)?,
+        read_u16(msg, 8)?,
+        read_u16(msg, 10)?,
+    ];
+    let mut message = Message {
+        id: read_u16(msg, 0)?,
+        flags: read_u16(msg, 2)?,
+        counts,
+        questions: Vec::new(),
+        answers: Vec::new(),
+        authorities: Vec::new(),
+        additionals: Vec::new(),
+        edns: None,
+    };
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..counts[0] {
+        let (name, end) = read_name(msg, pos)?;
+        message.questions.push(Question {
+            name,
+            qtype: read_u16(msg, end)?,
+            qclass: read_u16(msg, end + 2)?,
+            range: (pos, end + 4),
+        });
+        pos = end + 4;
+    }
+    for (section, &count) in counts[1..].iter().enumerate() {
+        for _ in 0..count {
+            let (record, end) = parse_record(msg, pos)?;
+            pos = end;
+            if record.rtype == TYPE_OPT {
+                message.edns = Some(Edns {
+                    udp_payload_size: record.class,
+                    extended_rcode: (record.ttl >> 24) as u8,
+                    version: (record.ttl >> 16) as u8,
+                    dnssec_ok: record.ttl & EDNS_DNSSEC_OK != 0,
+                });
+            }
+            match section {
+                0 => message.answers.push(record),
+                1 => message.authorities.push(record),
+                _ => message.additionals.push(record),
+            }
+        }
+    }
+    Ok(message)
+}
+
+/// Strips the two-byte length prefix used over TCP (RFC 1035 4.2.2).
+/// Returns None when the segment does not hold a complete message.
+pub fn tcp_message(payload: &[u8]) -> Option<&[u8]> {
+    let len = u16::from_be_bytes([*payload.first()?, *payload.get(1)?]) as usize;
+    payload.get(2..2 + len)
+}
+
+/// Whether a message looks like DNS rather than other traffic: a single
+/// question of a known class, a known opcode, the reserved Z bit clear, and
+/// no answers in a query.
+pub fn is_plausible(msg: &[u8]) -> bool {
+    let Ok(message) = parse(msg) else {
+        return false;
+    };
+    let Some(question) = message.questions.first() else {
+        return false;
+    };
+    // The top class bit is mDNS's unicast-response flag (RFC 6762 section 5.4)
+    message.counts[0] == 1
+        && matches!(message.opcode(), 0 | 1 | 2 | 4 | 5)
+        && message.flags & FLAG_Z == 0
+        && (message.is_response() || message.counts[1] == 0)
+        && matches!(question.qclass & 0x7fff, 1 | 3 | 4 | 255)
+}
+
+/// Info column text for a DNS payload.
+pub fn summarize(payload: &[u8], is_tcp: bool) -> Option<String> {
+    let msg = if is_tcp {
+        tcp_message(payload)?
+    } else {
+        payload
+    };
+    parse(msg).ok().map(|message| message.summary())
+}
+
+fn flag(flags: u16, mask: u16) -> String {
+    if flags & mask != 0 { "1" } else { "0" }.to_string()
+}
+
+/// Builds the detail view fields for a DNS payload starting at `offset`.
+pub fn fields(payload: &[u8], is_tcp: bool, offset: usize) -> Vec<PacketField> {
+    let mut fields = vec![field(
+        "Payload Length",
+        format!("{} bytes", payload.len()),
+        (offset, offset + payload.len()),
+    )];
+
+    let (msg, base) = if is_tcp {
+        match tcp_message(payload) {
+            Some(msg) => {
+                fields.push(field(
+                    "Length",
+                    format!("{} bytes", msg.len()),
+                    (offset, offset + 2),
+                ));
+                (msg, offset + 2)
+            }
+            None => {
+                fields.push(PacketField {
+                    expert: Some("Segment does not hold a complete DNS message".to_string()),
+                    ..field(
+                        "Length",
+                        format!("{} bytes", read_u16(payload, 0).unwrap_or(0)),
+                        (offset, offset + payload.len().min(2)),
+                    )
+                });
+                return fields;
+            }
+        }
+    } else {
+        (payload, offset)
+    };
+    let at = |(start, end): (usize, usize)| (base + start, base + end);
+
+    let message = match parse(msg) {
+        Ok(message) => message,
+        Err(e) => {
+            fields.push(PacketField {
+                expert: Some("Malformed DNS message".to_string()),
+                ..field("Error", e, at((0, msg.len())))
+            });
+            return fields;
+        }
+    };
+
+    let flags = message.flags;
+    fields.push(field(
+        "Transaction ID",
+        format!("0x{:04x}", message.id),
+        at((0, 2)),
+    ));
+    fields.push(field("Flags", format!("0x{:04x}", flags), at((2, 4))));
+    fields.push(field(
+        "Response",
+        if message.is_response() {
+            "1 (Response)"
+        } else {
+            "0 (Query)"
+        }
+        .to_string(),
+        at((2, 3)),
+    ));
+    fields.push(field(
+        "Opcode",
+        format!("{} ({})", message.opcode(), opcode_name(message.opcode())),
+        at((2, 3)),
+    ));
+    if message.is_response() {
+        fields.push(field(
+            "Authoritative",
+            flag(flags, FLAG_AUTHORITATIVE),
+            at((2, 3)),
+        ));
+    }
+    fields.push(PacketField {
+        expert: (flags & FLAG_TRUNCATED != 0)
+            .then(|| "Message truncated; the client should retry over TCP".to_string()),
+        ..field("Truncated", flag(flags, FLAG_TRUNCATED), at((2, 3)))
+    });
+    fields.push(field(
+        "Recursion Desired",
+        flag(flags, FLAG_RECURSION_DESIRED),
+        at((2, 3)),
+    ));
+    if message.is_response() {
+        fields.push(field(
+            "Recursion Available",
+            flag(flags, FLAG_RECURSION_AVAILABLE),
+            at((3, 4)),
+        ));
+        let rcode = message.rcode();
+        fields.push(PacketField {
+            expert: (rcode != 0).then(|| rcode_name(rcode)),
+            ..field(
+                "Response Code",
+                format!("{} ({})", rcode, rcode_name(rcode)),
+                at((3, 4)),
+            )
+        });
+    }
+    for (i, name) in ["Questions", "Answer RRs", "Authority RRs", "Additional RRs"]
+        .iter()
+        .enumerate()
+    {
+        fields.push(field(
+            name,
+            message.counts[i].to_string(),
+            at((4 + i * 2, 6 + i * 2)),
+        ));
+    }
+
+    for question in &message.questions {
+        fields.push(field(
+            "Query Name",
+            question.name.clone(),
+            at(question.range),
+        ));
+        fields.push(field(
+            "Query Type",
+            format!("{} ({})", question.qtype, type_name(question.qtype)),
+            at(question.range),
+        ));
+        fields.push(field(
+            "Query Class",
+            class_name(question.qclass),
+            at(question.range),
+        ));
+    }
+
+    let sections = [
+        ("Answer", &message.answers),
+        ("Authority", &message.authorities),
+        ("Additional", &message.additionals),
+    ];
+    for (section, records) in sections {
+        for record in records {
+            if let RData::Opt(options) = &record.data {
+                push_edns_fields(&mut fields, &message, options, at(record.range));
+                continue;
+            }
+            fields.push(field(
+                section,
+                format!(
+                    "{}: {} {} {} (TTL {})",
+                    record.name,
+                    class_name(record.class),
+                    type_name(record.rtype),
+                    record.data,
+                    record.ttl
+                ),
+                at(record.range),
+            ));
+        }
+    }
+    fields
+}
+
+fn push_edns_fields(
+    fields: &mut Vec<PacketField>,
+    message: &Message,
+    options: &[(u16, Vec<u8>)],
+    range: (usize, usize),
+) {
+    let Some(edns) = &message.edns else {
+        return;
+    };
+    fields.push(field(
+        "EDNS UDP Payload Size",
+        format!("{} bytes", edns.udp_payload_size),
+        range,
+    ));
+    fields.push(PacketField {
+        expert: (edns.version != 0).then(|| format!("Unsupported EDNS version {}", edns.version)),
+        ..field("EDNS Version", edns.version.to_string(), range)
+    });
+    fields.push(field(
+        "EDNS DNSSEC OK",
+        if edns.dnssec_ok { "1" } else { "0" }.to_string(),
+        range,
+    ));
+    for (code, data) in options {
+        fields.push(field(
+            "EDNS Option",
+            format!("{} ({} bytes)", edns_option_name(*code), data.len()),
+            range,
+        ));
+    }
+}
+
+const PORTS: &[(Transport, u16)] = &[(Transport::Tcp, DNS_PORT), (Transport::Udp, DNS_PORT)];
+
+/// DNS on port 53 over UDP and TCP, and on other ports when the message is plausible.
+pub struct Dns;
+
+impl Dissector for Dns {
+    fn name(&self) -> &'static str {
+        "dns"
+    }
+
+    fn description(&self) -> &'static str {
+        "Domain Name System"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        PORTS
+    }
+
+    fn heuristic(&self, _transport: Transport) -> Option<Heuristic> {
+        Some(Heuristic::Last)
+    }
+
+    fn recognizes(&self, ctx: &Context) -> bool {
+        match ctx.transport {
+            Transport::Tcp => tcp_message(ctx.payload).is_some_and(is_plausible),
+            Transport::Udp => is_plausible(ctx.payload),
+        }
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let is_tcp = ctx.transport == Transport::Tcp;
+        // Bare TCP segments are left to the TCP info
+        if is_tcp && ctx.payload.is_empty() {
+            return None;
+        }
+        if !ctx.expects(self.name(), DNS_PORT) && !self.recognizes(ctx) {
+            return None;
+        }
+        let fallback = if is_tcp { "DNS (TCP segment)" } else { "DNS" };
+        let info = summarize(ctx.payload, is_tcp).unwrap_or_else(|| fallback.to_string());
+        Some(("DNS".to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        if !ctx.expects(self.name(), DNS_PORT) && !self.recognizes(ctx) {
+            return None;
+        }
+        let is_tcp = ctx.transport == Transport::Tcp;
+        Some(Dissection::layer(
+            LAYER_DNS,
+            fields(ctx.payload, is_tcp, ctx.offset),
+        ))
+    }
+}
+
+/// Multicast DNS (RFC 6762), which shares the DNS message format.
+pub struct Mdns;
+
+impl Dissector for Mdns {
+    fn name(&self) -> &'static str {
+        "mdns"
+    }
+
+    fn description(&self) -> &'static str {
+        "Multicast DNS"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[(Transport::Udp, MDNS_PORT)]
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let info = summarize(ctx.payload, false).unwrap_or_else(|| "Multicast DNS".to_string());
+        Some(("mDNS".to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        Some(Dissection::layer(
+            LAYER_DNS,
+            fields(ctx.payload, false, ctx.offset),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response for example.com A with a compressed answer name and an OPT record.
+    fn example_response() -> Vec<u8> {
+        let mut msg = vec![
+            0x12, 0x34, 0x81, 0x80, // id, flags: response, RD, RA
+            0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01,
+        ];
+        msg.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+        // www.example.com CNAME example.com, via pointers to offset 12
+        msg.extend_from_slice(b"\x03www\xc0\x0c\x00\x05\x00\x01\x00\x00\x00\x3c\x00\x02\xc0\x0c");
+        msg.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01]);
+        msg.extend_from_slice(&[0x00, 0x00, 0x01, 0x2c, 0x00, 0x04, 93, 184, 216, 34]);
+        // OPT: payload size 1232, DO bit, one padding option
+        msg.extend_from_slice(&[0x00, 0x00, 0x29, 0x04, 0xd0, 0x00, 0x00, 0x80, 0x00]);
+        msg.extend_from_slice(&[0x00, 0x06, 0x00, 0x0c, 0x00, 0x02, 0x00, 0x00]);
+        msg
+    }
+
+    #[test]
+    fn test_parse_response_with_compression_and_edns() {
+        let message = parse(&example_response()).unwrap();
+        assert!(message.is_response());
+        assert_eq!(message.rcode(), 0);
+        assert_eq!(message.questions[0].name, "example.com");
+        assert_eq!(message.answers[0].name, "www.example.com");
+        assert_eq!(
+            message.answers[0].data,
+            RData::Name("example.com".to_string())
+        );
+        assert_eq!(
+            message.answers[1].data,
+            RData::A(Ipv4Addr::new(93, 184, 216, 34))
+        );
+        let edns = message.edns.as_ref().unwrap();
+        assert_eq!(edns.udp_payload_size, 1232);
+        assert!(edns.dnssec_ok);
+        assert_eq!(
+            message.summary(),
+            "Standard query response A example.com → CNAME example.com, 93.184.216.34"
+        );
+    }
+
+    #[test]
+    fn test_tcp_framing_and_error_summary() {
+        let mut msg = vec![0xab, 0xcd, 0x81, 0x83, 0, 1, 0, 0, 0, 0, 0, 0];
+        msg.extend_from_slice(b"\x02nx\x07example\x00\x00\x1c\x00\x01");
+        let mut framed = (msg.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(&msg);
+        assert_eq!(
+            summarize(&framed, true).unwrap(),
+            "Standard query response AAAA nx.example No such name"
+        );
+        // Partial segment
+        assert_eq!(summarize(&framed[..10], true), None);
+
+        let query = [
+            &[0xab, 0xcd, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0][..],
+            &msg[12..],
+        ]
+        .concat();
+        assert_eq!(
+            summarize(&query, false).unwrap(),
+            "Standard query AAAA nx.example"
+        );
+    }
+
+    #[test]
+    fn test_rejects_pointer_loops_and_forward_pointers() {
+        let mut msg = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        // Name pointing at itself
+        msg.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01]);
+        assert!(parse(&msg)
+            .unwrap_err()
+            .contains("does not point backwards"));
+
+        // Fields of a malformed message still include the payload length
+        let fields = fields(&msg, false, 42);
+        assert_eq!(fields[0].name, "Payload Length");
+        assert_eq!(fields[1].expert.as_deref(), Some("Malformed DNS message"));
+    }
+}
@@ -0,0 +1,206 @@
+//! 802.1Q VLAN tags, 802.1ad (QinQ) service tags and MPLS label stacks
+//! between the Ethernet header and the network layer.
+
+use super::field;
+use crate::model::PacketField;
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+
+pub const TPID_8021Q: u16 = 0x8100;
+pub const TPID_8021AD: u16 = 0x88a8;
+/// Pre-standard QinQ TPID still used by some switches
+pub const TPID_QINQ_LEGACY: u16 = 0x9100;
+pub const ETHERTYPE_MPLS: u16 = 0x8847;
+pub const ETHERTYPE_MPLS_MULTICAST: u16 = 0x8848;
+
+pub const ETHERNET_HEADER_LEN: usize = 14;
+const TAG_LEN: usize = 4;
+/// Upper bound on peeled headers, so a malformed frame cannot loop forever.
+const MAX_TAGS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    Vlan {
+        tpid: u16,
+        priority: u8,
+        dei: bool,
+        id: u16,
+        /// EtherType following the tag
+        ethertype: u16,
+    },
+    Mpls {
+        label: u32,
+        traffic_class: u8,
+        bottom: bool,
+        ttl: u8,
+    },
+}
+
+/// A tag and the offset of its 4 bytes in the frame. For VLAN tags the
+/// offset points at the TCI, after the TPID carried in the preceding header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub offset: usize,
+    pub tag: Tag,
+}
+
+/// An Ethernet frame with its VLAN and MPLS headers removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link<'a> {
+    /// EtherType of the network layer, guessed from the IP version after MPLS
+    pub ethertype: EtherType,
+    pub payload: &'a [u8],
+    /// Offset of `payload` in the frame
+    pub offset: usize,
+    /// Peeled headers, outermost first
+    pub tags: Vec<Header>,
+}
+
+/// Peels the VLAN tags and MPLS labels of an Ethernet frame. Truncated or
+/// over-long stacks leave the remaining header's EtherType in place.
+pub fn decapsulate(raw_data: &[u8]) -> Option<Link<'_>> {
+    decapsulate_at(raw_data, 0)
+}
+
+/// Like [`decapsulate`], for an Ethernet header starting at `start`, e.g.
+/// inside a tunnel. Offsets stay relative to the whole of `raw_data`.
+pub fn decapsulate_at(raw_data: &[u8], start: usize) -> Option<Link<'_>> {
+    if raw_data.len() < start + ETHERNET_HEADER_LEN {
+        return None;
+    }
+    let ethertype = u16::from_be_bytes([raw_data[start + 12], raw_data[start + 13]]);
+    Some(peel(raw_data, ethertype, start + ETHERNET_HEADER_LEN))
+}
+
+/// Peels the VLAN tags and MPLS labels starting at `offset`, where a header
+/// announced `ethertype`. Any other EtherType is returned as is.
+pub fn peel(raw_data: &[u8], mut ethertype: u16, mut offset: usize) -> Link<'_> {
+    let mut tags = Vec::new();
+
+    while tags.len() < MAX_TAGS && offset + TAG_LEN <= raw_data.len() {
+        let word = &raw_data[offset..offset + TAG_LEN];
+        match ethertype {
+            TPID_8021Q | TPID_8021AD | TPID_QINQ_LEGACY => {
+                let tci = u16::from_be_bytes([word[0], word[1]]);
+                let inner = u16::from_be_bytes([word[2], word[3]]);
+                tags.push(Header {
+                    offset,
+                    tag: Tag::Vlan {
+                        tpid: ethertype,
+                        priority: (tci >> 13) as u8,
+                        dei: tci & 0x1000 != 0,
+                        id: tci & 0x0fff,
+                        ethertype: inner,
+                    },
+                });
+                ethertype = inner;
+            }
+            ETHERTYPE_MPLS | ETHERTYPE_MPLS_MULTICAST => {
+                let entry = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+                let bottom = entry & 0x100 != 0;
+                tags.push(Header {
+                    offset,
+                    tag: Tag::Mpls {
+                        label: entry >> 12,
+                        traffic_class: ((entry >> 9) & 0x7) as u8,
+                        bottom,
+                        ttl: (entry & 0xff) as u8,
+                    },
+                });
+                if bottom {
+                    // MPLS carries no next-protocol field; go by the IP version nibble
+                    ethertype = match raw_data.get(offset + TAG_LEN).map(|b| b >> 4) {
+                        Some(4) => EtherTypes::Ipv4.0,
+                        Some(6) => EtherTypes::Ipv6.0,
+                        _ => 0,
+                    };
+                }
+            }
+            _ => break,
+        }
+        offset += TAG_LEN;
+    }
+
+    Link {
+        ethertype: EtherType(ethertype),
+        payload: &raw_data[offset.min(raw_data.len())..],
+        offset,
+        tags,
+    }
+}
+
+/// Builds the detail view fields for a VLAN tag or MPLS label.
+pub fn fields(header: &Header) -> Vec<PacketField> {
+    let at = |start: usize, len: usize| (header.offset + start, header.offset + start + len);
+    match header.tag {
+        Tag::Vlan {
+            priority,
+            dei,
+            id,
+            ethertype,
+            ..
+        } => vec![
+            field("Priority", priority.to_string(), at(0, 1)),
+            field("DEI", u8::from(dei).to_string(), at(0, 1)),
+            field("ID", id.to_string(), at(0, 2)),
+            field("Type", format!("0x{:04x}", ethertype), at(2, 2)),
+        ],
+        Tag::Mpls {
+            label,
+            traffic_class,
+            bottom,
+            ttl,
+        } => vec![
+            field("Label", label.to_string(), at(0, 3)),
+            field("Traffic Class", traffic_class.to_string(), at(2, 1)),
+            field("Bottom of Stack", u8::from(bottom).to_string(), at(2, 1)),
+            field("TTL", ttl.to_string(), at(3, 1)),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qinq_and_mpls() {
+        // QinQ: S-tag 100, C-tag 200 (priority 5), then IPv4
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0xa0, 0xc8, 0x08, 0x00]);
+        frame.extend_from_slice(&[0x45, 0, 0, 20]);
+        let link = decapsulate(&frame).unwrap();
+        assert_eq!(link.ethertype, EtherTypes::Ipv4);
+        assert_eq!(link.offset, 22);
+        assert!(matches!(link.tags[0].tag, Tag::Vlan { id: 100, .. }));
+        assert!(matches!(
+            link.tags[1].tag,
+            Tag::Vlan {
+                id: 200,
+                priority: 5,
+                ..
+            }
+        ));
+
+        // Two MPLS labels (16, 17 with bottom of stack), then IPv6
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x88, 0x47, 0x00, 0x01, 0x00, 0x40, 0x00, 0x01, 0x11, 0x3f]);
+        frame.extend_from_slice(&[0x60, 0, 0, 0]);
+        let link = decapsulate(&frame).unwrap();
+        assert_eq!(link.ethertype, EtherTypes::Ipv6);
+        assert_eq!(link.tags.len(), 2);
+        assert_eq!(
+            link.tags[1].tag,
+            Tag::Mpls {
+                label: 17,
+                traffic_class: 0,
+                bottom: true,
+                ttl: 63
+            }
+        );
+
+        // A truncated tag leaves the TPID as the EtherType
+        let link = decapsulate(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x81, 0x00, 0x00]).unwrap();
+        assert_eq!(link.ethertype.0, TPID_8021Q);
+        assert!(link.tags.is_empty());
+    }
+}
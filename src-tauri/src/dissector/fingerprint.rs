@@ -0,0 +1,251 @@
+//! TLS client and server fingerprints: JA3/JA3S (Salesforce) and JA4/JA4S
+//! (FoxIO). GREASE values are ignored throughout, as both specs require.
+//! Also the SSH client and server fingerprints HASSH and HASSHServer
+//! (Salesforce).
+
+use super::ssh::KexInit;
+use super::tls::{is_grease, ClientHello, ServerHello, EXT_ALPN, EXT_SERVER_NAME};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// JA4 hash used when the hashed list is empty.
+const JA4_EMPTY_HASH: &str = "000000000000";
+
+/// Transport the hello was carried over, the first character of JA4/JA4S.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Quic,
+}
+
+impl Transport {
+    fn code(self) -> char {
+        match self {
+            Transport::Tcp => 't',
+            Transport::Quic => 'q',
+        }
+    }
+}
+
+fn md5_hex(text: &str) -> String {
+    Md5::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// First 12 hex characters of the SHA-256 of `text`.
+fn sha256_12(text: &str) -> String {
+    if text.is_empty() {
+        return JA4_EMPTY_HASH.to_string();
+    }
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn join_decimal<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn join_hex(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|v| format!("{:04x}", v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn non_grease(values: impl IntoIterator<Item = u16>) -> Vec<u16> {
+    values.into_iter().filter(|&v| !is_grease(v)).collect()
+}
+
+/// JA3 input string, `version,ciphers,extensions,groups,point_formats`.
+pub fn ja3_string(hello: &ClientHello) -> String {
+    format!(
+        "{},{},{},{},{}",
+        hello.version,
+        join_decimal(non_grease(hello.cipher_suites.iter().copied())),
+        join_decimal(non_grease(hello.extensions.iter().map(|e| e.ext_type))),
+        join_decimal(non_grease(hello.supported_groups())),
+        join_decimal(hello.ec_point_formats())
+    )
+}
+
+pub fn ja3(hello: &ClientHello) -> String {
+    md5_hex(&ja3_string(hello))
+}
+
+/// JA3S input string, `version,cipher,extensions`.
+pub fn ja3s_string(hello: &ServerHello) -> String {
+    format!(
+        "{},{},{}",
+        hello.version,
+        hello.cipher_suite,
+        join_decimal(non_grease(hello.extensions.iter().map(|e| e.ext_type)))
+    )
+}
+
+pub fn ja3s(hello: &ServerHello) -> String {
+    md5_hex(&ja3s_string(hello))
+}
+
+fn ja4_version(version: u16) -> &'static str {
+    match version {
+        0x0304 => "13",
+        0x0303 => "12",
+        0x0302 => "11",
+        0x0301 => "10",
+        0x0300 => "s3",
+        0xfeff => "d1",
+        0xfefd => "d2",
+        0xfefc => "d3",
+        _ => "00",
+    }
+}
+
+/// First and last character of the first ALPN value, or "00" without one.
+/// Non-alphanumeric values use the first and last hex digits instead.
+fn ja4_alpn(alpn: Option<&str>) -> String {
+    let Some(value) = alpn.filter(|v| !v.is_empty()) else {
+        return "00".to_string();
+    };
+    let bytes = value.as_bytes();
+    let (first, last) = (bytes[0], bytes[bytes.len() - 1]);
+    if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+        format!("{}{}", first as char, last as char)
+    } else {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}{}", &hex[..1], &hex[hex.len() - 1..])
+    }
+}
+
+fn two_digits(count: usize) -> String {
+    format!("{:02}", count.min(99))
+}
+
+pub fn ja4(hello: &ClientHello, transport: Transport) -> String {
+    let version = non_grease(hello.supported_versions())
+        .into_iter()
+        .max()
+        .unwrap_or(hello.version);
+    let sni = if hello.extension(EXT_SERVER_NAME).is_some() {
+        'd'
+    } else {
+        'i'
+    };
+    let mut ciphers = non_grease(hello.cipher_suites.iter().copied());
+    let extensions = non_grease(hello.extensions.iter().map(|e| e.ext_type));
+    let alpn = hello.alpn();
+
+    let prefix = format!(
+        "{}{}{}{}{}{}",
+        transport.code(),
+        ja4_version(version),
+        sni,
+        two_digits(ciphers.len()),
+        two_digits(extensions.len()),
+        ja4_alpn(alpn.first().map(String::as_str))
+    );
+
+    ciphers.sort_unstable();
+    // SNI and ALPN are already reflected in the prefix
+    let mut hashed_extensions: Vec<u16> = extensions
+        .into_iter()
+        .filter(|&e| e != EXT_SERVER_NAME && e != EXT_ALPN)
+        .collect();
+    hashed_extensions.sort_unstable();
+    let mut extension_input = join_hex(&hashed_extensions);
+    let signature_algorithms = hello.signature_algorithms();
+    if !signature_algorithms.is_empty() && !extension_input.is_empty() {
+        extension_input.push('_');
+        extension_input.push_str(&join_hex(&signature_algorithms));
+    }
+
+    format!(
+        "{}_{}_{}",
+        prefix,
+        sha256_12(&join_hex(&ciphers)),
+        sha256_12(&extension_input)
+    )
+}
+
+pub fn ja4s(hello: &ServerHello, transport: Transport) -> String {
+    let extensions: Vec<u16> = hello.extensions.iter().map(|e| e.ext_type).collect();
+    format!(
+        "{}{}{}{}_{:04x}_{}",
+        transport.code(),
+        ja4_version(hello.selected_version().unwrap_or(hello.version)),
+        two_digits(extensions.len()),
+        ja4_alpn(hello.alpn().as_deref()),
+        hello.cipher_suite,
+        sha256_12(&join_hex(&extensions))
+    )
+}
+
+/// HASSH of a client KEXINIT: MD5 of its key exchange, encryption, MAC and
+/// compression algorithms.
+pub fn hassh(kex_init: &KexInit) -> String {
+    md5_hex(&kex_init.hassh_algorithms())
+}
+
+/// HASSHServer of a server KEXINIT.
+pub fn hassh_server(kex_init: &KexInit) -> String {
+    md5_hex(&kex_init.hassh_server_algorithms())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tls::tests::client_hello_body;
+    use super::super::tls::{Extension, EXT_SUPPORTED_VERSIONS};
+    use super::*;
+
+    #[test]
+    fn test_client_fingerprints_skip_grease() {
+        let hello = ClientHello::parse(&client_hello_body()).unwrap();
+        assert_eq!(
+            ja3_string(&hello),
+            "771,4865-49199,0-10-11-13-16-43-51,29-23,0"
+        );
+        assert_eq!(ja3(&hello).len(), 32);
+
+        let ja4 = ja4(&hello, Transport::Tcp);
+        let parts: Vec<&str> = ja4.split('_').collect();
+        assert_eq!(parts[0], "t13d0207h2");
+        assert_eq!(parts[1], sha256_12("1301,c02f"));
+        assert_eq!(parts[2], sha256_12("000a,000b,000d,002b,0033_0403,0804"));
+    }
+
+    #[test]
+    fn test_server_fingerprints() {
+        let extension = |ext_type: u16, data: &[u8]| Extension {
+            ext_type,
+            data: data.to_vec(),
+            range: (0, 0),
+        };
+        let hello = ServerHello {
+            version: 0x0303,
+            random: vec![0; 32],
+            session_id: Vec::new(),
+            cipher_suite: 0x1301,
+            compression_method: 0,
+            extensions: vec![
+                extension(EXT_SUPPORTED_VERSIONS, &[0x03, 0x04]),
+                extension(EXT_ALPN, b"\x00\x03\x02h2"),
+            ],
+            truncated: false,
+        };
+        assert_eq!(ja3s_string(&hello), "771,4865,43-16");
+        assert_eq!(
+            ja4s(&hello, Transport::Tcp),
+            format!("t1302h2_1301_{}", sha256_12("002b,0010"))
+        );
+        assert_eq!(ja4_alpn(Some("\u{1}x")), "08");
+    }
+}
@@ -0,0 +1,397 @@
+//! HTTP/1.x message heads (RFC 9112): request and status lines, header
+//! fields and the framing headers that say how the body is delimited.
+//!
+//! HTTP is recognized by content, so it is found on any TCP port.
+
+use super::field;
+use super::registry::{Context, Dissection, Dissector, Heuristic, Transport};
+use super::{LAYER_HTTP, PROTO_HTTP};
+use crate::model::PacketField;
+
+pub const HTTP_PORT: u16 = 80;
+
+const MAX_METHOD_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartLine {
+    Request {
+        method: String,
+        target: String,
+        version: String,
+    },
+    Response {
+        version: String,
+        status: u16,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+    /// Byte range within the payload, including continuation lines
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub start: StartLine,
+    pub start_range: (usize, usize),
+    pub headers: Vec<Header>,
+    /// Offset of the body, None when the head continues in a later segment
+    pub body_offset: Option<usize>,
+}
+
+impl Message {
+    /// First value of a header, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str())
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        self.headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("Transfer-Encoding"))
+            .flat_map(|h| h.value.split(','))
+            .last()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+    }
+
+    /// Declared body length. Conflicting Content-Length values yield an error.
+    pub fn content_length(&self) -> Result<Option<u64>, String> {
+        let mut length = None;
+        for header in &self.headers {
+            if !header.name.eq_ignore_ascii_case("Content-Length") {
+                continue;
+            }
+            for value in header.value.split(',') {
+                let value: u64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid Content-Length '{}'", header.value))?;
+                if length.is_some_and(|l| l != value) {
+                    return Err("Conflicting Content-Length headers".to_string());
+                }
+                length = Some(value);
+            }
+        }
+        Ok(length)
+    }
+
+    /// Info column text, e.g. "GET /login HTTP/1.1 Host: x" or "HTTP/1.1 302 Found".
+    pub fn summary(&self) -> String {
+        match &self.start {
+            StartLine::Request {
+                method,
+                target,
+                version,
+            } => match self.header("Host") {
+                Some(host) => format!("{} {} {} Host: {}", method, target, version, host),
+                None => format!("{} {} {}", method, target, version),
+            },
+            StartLine::Response {
+                version,
+                status,
+                reason,
+            } => format!("{} {} {}", version, status, reason)
+                .trim_end()
+                .to_string(),
+        }
+    }
+}
+
+fn is_version(text: &str) -> bool {
+    matches!(text, "HTTP/1.0" | "HTTP/1.1")
+}
+
+fn is_method(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= MAX_METHOD_LEN
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b == b'-' || b == b'_')
+}
+
+fn parse_start_line(line: &str) -> Option<StartLine> {
+    if line.starts_with("HTTP/") {
+        let (version, rest) = line.split_once(' ')?;
+        if !is_version(version) {
+            return None;
+        }
+        let (status, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+        if status.len() != 3 || !status.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        return Some(StartLine::Response {
+            version: version.to_string(),
+            status: status.parse().ok()?,
+            reason: reason.to_string(),
+        });
+    }
+    let mut parts = line.split(' ');
+    let (method, target, version) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || !is_method(method) || target.is_empty() || !is_version(version) {
+        return None;
+    }
+    Some(StartLine::Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        version: version.to_string(),
+    })
+}
+
+/// Splits off the next line, accepting bare LF as well as CRLF. Returns the
+/// line and the offset just past its terminator, or None if it is unterminated.
+fn next_line(payload: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    let len = payload[start..].iter().position(|&b| b == b'\n')?;
+    let line = &payload[start..start + len];
+    Some((line.strip_suffix(b"\r").unwrap_or(line), start + len + 1))
+}
+
+/// Parses the head of an HTTP/1.x message at the start of a TCP payload.
+/// Returns None unless the payload opens with a request or status line.
+pub fn parse(payload: &[u8]) -> Option<Message> {
+    let (line, mut pos) = next_line(payload, 0)?;
+    let start = parse_start_line(std::str::from_utf8(line).ok()?)?;
+    let start_range = (0, line.len());
+
+    let mut headers: Vec<Header> = Vec::new();
+    let mut body_offset = None;
+    while let Some((line, next)) = next_line(payload, pos) {
+        if line.is_empty() {
+            body_offset = Some(next);
+            break;
+        }
+        let text = String::from_utf8_lossy(line);
+        match (text.starts_with([' ', '\t']), headers.last_mut()) {
+            // Obsolete line folding continues the previous value
+            (true, Some(previous)) => {
+                previous.value.push(' ');
+                previous.value.push_str(text.trim());
+                previous.range.1 = pos + line.len();
+            }
+            _ => {
+                if let Some((name, value)) = text.split_once(':') {
+                    headers.push(Header {
+                        name: name.trim().to_string(),
+                        value: value.trim().to_string(),
+                        range: (pos, pos + line.len()),
+                    });
+                }
+            }
+        }
+        pos = next;
+    }
+
+    Some(Message {
+        start,
+        start_range,
+        headers,
+        body_offset,
+    })
+}
+
+/// Info column text when the payload starts an HTTP/1.x message.
+pub fn summarize(payload: &[u8]) -> Option<String> {
+    parse(payload).map(|message| message.summary())
+}
+
+/// Detail field label for a header, in canonical case ("content-type" →
+/// "Content-Type") so filters match regardless of how the peer spelled it.
+fn header_label(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                }
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Builds the detail view fields for an HTTP payload starting at `offset`.
+/// Segments that do not start a message (continued bodies) only get a length.
+pub fn fields(payload: &[u8], offset: usize) -> Vec<PacketField> {
+    let at = |(start, end): (usize, usize)| (offset + start, offset + end);
+    let mut fields = Vec::new();
+
+    if let Some(message) = parse(payload) {
+        let line = at(message.start_range);
+        match &message.start {
+            StartLine::Request {
+                method,
+                target,
+                version,
+            } => {
+                fields.push(field("Method", method.clone(), line));
+                fields.push(field("Request URI", target.clone(), line));
+                fields.push(field("Version", version.clone(), line));
+            }
+            StartLine::Response {
+                version,
+                status,
+                reason,
+            } => {
+                fields.push(field("Version", version.clone(), line));
+                fields.push(PacketField {
+                    expert: (*status >= 400).then(|| format!("HTTP error {} {}", status, reason)),
+                    ..field("Status Code", status.to_string(), line)
+                });
+                fields.push(field("Reason Phrase", reason.clone(), line));
+            }
+        }
+
+        for header in &message.headers {
+            fields.push(field(
+                &header_label(&header.name),
+                header.value.clone(),
+                at(header.range),
+            ));
+        }
+
+        let content_length = message.content_length();
+        let chunked = message.is_chunked();
+        let framing_expert = match (&content_length, chunked) {
+            (Err(e), _) => Some(e.clone()),
+            (Ok(Some(_)), true) => Some(
+                "Both Content-Length and chunked Transfer-Encoding; possible request smuggling"
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        let framing = if chunked {
+            "Chunked".to_string()
+        } else {
+            match content_length {
+                Ok(Some(length)) => format!("Content-Length {} bytes", length),
+                _ => "Until connection close".to_string(),
+            }
+        };
+        fields.push(PacketField {
+            expert: framing_expert,
+            ..field("Body Framing", framing, line)
+        });
+
+        match message.body_offset {
+            Some(body) => fields.push(field(
+                "Body",
+                format!("{} bytes", payload.len() - body),
+                at((body, payload.len())),
+            )),
+            None => fields.push(PacketField {
+                expert: Some("Message head continues in the next segment".to_string()),
+                ..field(
+                    "Body",
+                    "0 bytes".to_string(),
+                    at((payload.len(), payload.len())),
+                )
+            }),
+        }
+    }
+
+    fields.push(field(
+        "Data",
+        format!("{} bytes", payload.len()),
+        at((0, payload.len())),
+    ));
+    fields
+}
+
+/// HTTP on port 80, and by content on any other port.
+pub struct Http;
+
+impl Dissector for Http {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hypertext Transfer Protocol"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[(Transport::Tcp, HTTP_PORT)]
+    }
+
+    fn heuristic(&self, transport: Transport) -> Option<Heuristic> {
+        Some(match transport {
+            Transport::Tcp => Heuristic::First,
+            Transport::Udp => Heuristic::Last,
+        })
+    }
+
+    fn recognizes(&self, ctx: &Context) -> bool {
+        parse(ctx.payload).is_some()
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        if let Some(info) = summarize(ctx.payload) {
+            return Some((PROTO_HTTP.to_string(), info));
+        }
+        if ctx.transport != Transport::Tcp || !ctx.expects(self.name(), HTTP_PORT) {
+            return None;
+        }
+        // Bare handshake and ACK segments say more as TCP info than as "HTTP"
+        let info = if ctx.payload.is_empty() {
+            ctx.transport_info.to_string()
+        } else {
+            "HTTP".to_string()
+        };
+        Some((PROTO_HTTP.to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        // Continuation segments on port 80, or of a flow known to be HTTP, are shown as body
+        let expected = ctx.transport == Transport::Tcp && ctx.expects(self.name(), HTTP_PORT);
+        (expected || self.recognizes(ctx))
+            .then(|| Dissection::layer(LAYER_HTTP, fields(ctx.payload, ctx.offset)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_and_response() {
+        let request = b"PROPFIND /dav HTTP/1.1\r\nhost: x\r\nX-Long: a\r\n b\r\n\r\nbody";
+        let message = parse(request).unwrap();
+        assert_eq!(message.summary(), "PROPFIND /dav HTTP/1.1 Host: x");
+        assert_eq!(message.header("X-Long"), Some("a b"));
+        assert_eq!(message.body_offset, Some(request.len() - 4));
+
+        let response = b"HTTP/1.1 302 Found\nLocation: /home\nTransfer-Encoding: gzip, chunked\n\n";
+        let message = parse(response).unwrap();
+        assert_eq!(message.summary(), "HTTP/1.1 302 Found");
+        assert!(message.is_chunked());
+        assert_eq!(message.content_length(), Ok(None));
+
+        // Not HTTP: lowercase method, unknown version, TLS record
+        assert!(parse(b"get / HTTP/1.1\r\n").is_none());
+        assert!(parse(b"GET / HTTP/2.0\r\n").is_none());
+        assert!(parse(&[0x16, 0x03, 0x01, 0x00, 0x05, b'\n']).is_none());
+    }
+
+    #[test]
+    fn test_fields_flag_ambiguous_framing() {
+        let request =
+            b"POST /login HTTP/1.1\r\ncontent-length: 5\r\nTransfer-Encoding: chunked\r\n";
+        let fields = fields(request, 54);
+        let find = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+
+        assert_eq!(find("Method").value, "POST");
+        assert_eq!(find("Content-Length").value, "5");
+        assert_eq!(find("Content-Length").range, (54 + 22, 54 + 39));
+        assert!(find("Body Framing").expert.is_some());
+        assert!(find("Body").expert.is_some());
+        assert_eq!(find("Data").value, format!("{} bytes", request.len()));
+    }
+}
@@ -0,0 +1,695 @@
+//! ICMP (RFC 792) and ICMPv6 (RFC 4443) messages, including IPv6 Neighbor
+//! Discovery (RFC 4861) with the prefix, MTU and RDNSS (RFC 8106) options.
+
+use super::{be_u32, field};
+use crate::model::PacketField;
+use pnet::util::MacAddr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const HEADER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V4,
+    V6,
+}
+
+pub const ECHO_REPLY: u8 = 0;
+pub const DEST_UNREACHABLE: u8 = 3;
+pub const REDIRECT: u8 = 5;
+pub const ECHO_REQUEST: u8 = 8;
+pub const TIME_EXCEEDED: u8 = 11;
+
+pub const V6_DEST_UNREACHABLE: u8 = 1;
+pub const V6_PACKET_TOO_BIG: u8 = 2;
+pub const V6_TIME_EXCEEDED: u8 = 3;
+pub const V6_PARAMETER_PROBLEM: u8 = 4;
+pub const V6_ECHO_REQUEST: u8 = 128;
+pub const V6_ECHO_REPLY: u8 = 129;
+pub const ND_ROUTER_SOLICIT: u8 = 133;
+pub const ND_ROUTER_ADVERT: u8 = 134;
+pub const ND_NEIGHBOR_SOLICIT: u8 = 135;
+pub const ND_NEIGHBOR_ADVERT: u8 = 136;
+pub const ND_REDIRECT: u8 = 137;
+
+pub fn type_name(version: Version, icmp_type: u8) -> &'static str {
+    match (version, icmp_type) {
+        (Version::V4, ECHO_REPLY) => "Echo (ping) reply",
+        (Version::V4, DEST_UNREACHABLE) => "Destination unreachable",
+        (Version::V4, 4) => "Source quench",
+        (Version::V4, REDIRECT) => "Redirect",
+        (Version::V4, ECHO_REQUEST) => "Echo (ping) request",
+        (Version::V4, 9) => "Router advertisement",
+        (Version::V4, 10) => "Router solicitation",
+        (Version::V4, TIME_EXCEEDED) => "Time-to-live exceeded",
+        (Version::V4, 12) => "Parameter problem",
+        (Version::V4, 13) => "Timestamp request",
+        (Version::V4, 14) => "Timestamp reply",
+        (Version::V6, V6_DEST_UNREACHABLE) => "Destination Unreachable",
+        (Version::V6, V6_PACKET_TOO_BIG) => "Packet Too Big",
+        (Version::V6, V6_TIME_EXCEEDED) => "Time Exceeded",
+        (Version::V6, V6_PARAMETER_PROBLEM) => "Parameter Problem",
+        (Version::V6, V6_ECHO_REQUEST) => "Echo (ping) request",
+        (Version::V6, V6_ECHO_REPLY) => "Echo (ping) reply",
+        (Version::V6, 130) => "Multicast Listener Query",
+        (Version::V6, 131) => "Multicast Listener Report",
+        (Version::V6, 132) => "Multicast Listener Done",
+        (Version::V6, ND_ROUTER_SOLICIT) => "Router Solicitation",
+        (Version::V6, ND_ROUTER_ADVERT) => "Router Advertisement",
+        (Version::V6, ND_NEIGHBOR_SOLICIT) => "Neighbor Solicitation",
+        (Version::V6, ND_NEIGHBOR_ADVERT) => "Neighbor Advertisement",
+        (Version::V6, ND_REDIRECT) => "Redirect",
+        (Version::V6, 143) => "Multicast Listener Report Message v2",
+        _ => "Unknown",
+    }
+}
+
+/// Reason given by an error message's code, if the type defines codes.
+pub fn code_name(version: Version, icmp_type: u8, code: u8) -> Option<&'static str> {
+    let name = match (version, icmp_type, code) {
+        (Version::V4, DEST_UNREACHABLE, 0) => "Network unreachable",
+        (Version::V4, DEST_UNREACHABLE, 1) => "Host unreachable",
+        (Version::V4, DEST_UNREACHABLE, 2) => "Protocol unreachable",
+        (Version::V4, DEST_UNREACHABLE, 3) => "Port unreachable",
+        (Version::V4, DEST_UNREACHABLE, 4) => "Fragmentation needed",
+        (Version::V4, DEST_UNREACHABLE, 5) => "Source route failed",
+        (Version::V4, DEST_UNREACHABLE, 6) => "Destination network unknown",
+        (Version::V4, DEST_UNREACHABLE, 7) => "Destination host unknown",
+        (Version::V4, DEST_UNREACHABLE, 9) => "Network administratively prohibited",
+        (Version::V4, DEST_UNREACHABLE, 10) => "Host administratively prohibited",
+        (Version::V4, DEST_UNREACHABLE, 13) => "Communication administratively filtered",
+        (Version::V4, REDIRECT, 0) => "Redirect for network",
+        (Version::V4, REDIRECT, 1) => "Redirect for host",
+        (Version::V4, REDIRECT, 2) => "Redirect for TOS and network",
+        (Version::V4, REDIRECT, 3) => "Redirect for TOS and host",
+        (Version::V4, TIME_EXCEEDED, 0) => "Time to live exceeded in transit",
+        (Version::V4, TIME_EXCEEDED, 1) => "Fragment reassembly time exceeded",
+        (Version::V4, 12, 0) => "Pointer indicates the error",
+        (Version::V4, 12, 1) => "Missing a required option",
+        (Version::V4, 12, 2) => "Bad length",
+        (Version::V6, V6_DEST_UNREACHABLE, 0) => "No route to destination",
+        (Version::V6, V6_DEST_UNREACHABLE, 1) => "Administratively prohibited",
+        (Version::V6, V6_DEST_UNREACHABLE, 2) => "Beyond scope of source address",
+        (Version::V6, V6_DEST_UNREACHABLE, 3) => "Address unreachable",
+        (Version::V6, V6_DEST_UNREACHABLE, 4) => "Port unreachable",
+        (Version::V6, V6_DEST_UNREACHABLE, 5) => "Source address failed ingress/egress policy",
+        (Version::V6, V6_DEST_UNREACHABLE, 6) => "Reject route to destination",
+        (Version::V6, V6_TIME_EXCEEDED, 0) => "Hop limit exceeded in transit",
+        (Version::V6, V6_TIME_EXCEEDED, 1) => "Fragment reassembly time exceeded",
+        (Version::V6, V6_PARAMETER_PROBLEM, 0) => "Erroneous header field",
+        (Version::V6, V6_PARAMETER_PROBLEM, 1) => "Unrecognized Next Header type",
+        (Version::V6, V6_PARAMETER_PROBLEM, 2) => "Unrecognized IPv6 option",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Echo request or reply identification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Echo {
+    pub is_request: bool,
+    pub identifier: u16,
+    pub sequence: u16,
+}
+
+/// Returns the identifier and sequence number of an echo request or reply.
+pub fn echo(version: Version, data: &[u8]) -> Option<Echo> {
+    if data.len() < HEADER_LEN {
+        return None;
+    }
+    let is_request = match (version, data[0]) {
+        (Version::V4, ECHO_REQUEST) | (Version::V6, V6_ECHO_REQUEST) => true,
+        (Version::V4, ECHO_REPLY) | (Version::V6, V6_ECHO_REPLY) => false,
+        _ => return None,
+    };
+    Some(Echo {
+        is_request,
+        identifier: u16::from_be_bytes([data[4], data[5]]),
+        sequence: u16::from_be_bytes([data[6], data[7]]),
+    })
+}
+
+/// Whether the message quotes the datagram that caused it.
+fn is_error(version: Version, icmp_type: u8) -> bool {
+    match version {
+        Version::V4 => matches!(icmp_type, 3 | 4 | 5 | 11 | 12),
+        Version::V6 => (1..=4).contains(&icmp_type),
+    }
+}
+
+/// Header of the datagram quoted by an error message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Original {
+    pub source: String,
+    pub destination: String,
+    pub protocol: u8,
+    /// Source and destination ports for TCP and UDP
+    pub ports: Option<(u16, u16)>,
+    /// Offset of the IP header within the ICMP message
+    pub header_start: usize,
+    pub header_len: usize,
+}
+
+fn original(version: Version, data: &[u8]) -> Option<Original> {
+    let quoted = data.get(HEADER_LEN..)?;
+    let (source, destination, protocol, header_len) = match version {
+        Version::V4 => {
+            if quoted.len() < 20 || quoted[0] >> 4 != 4 {
+                return None;
+            }
+            let header_len = usize::from(quoted[0] & 0x0f) * 4;
+            (
+                ipv4_at(quoted, 12).to_string(),
+                ipv4_at(quoted, 16).to_string(),
+                quoted[9],
+                header_len.max(20),
+            )
+        }
+        Version::V6 => {
+            if quoted.len() < 40 || quoted[0] >> 4 != 6 {
+                return None;
+            }
+            (
+                ipv6_at(quoted, 8).to_string(),
+                ipv6_at(quoted, 24).to_string(),
+                quoted[6],
+                40,
+            )
+        }
+    };
+    let ports = match (protocol, quoted.get(header_len..header_len + 4)) {
+        (6 | 17, Some(p)) => Some((
+            u16::from_be_bytes([p[0], p[1]]),
+            u16::from_be_bytes([p[2], p[3]]),
+        )),
+        _ => None,
+    };
+    Some(Original {
+        source,
+        destination,
+        protocol,
+        ports,
+        header_start: HEADER_LEN,
+        header_len,
+    })
+}
+
+fn protocol_name(protocol: u8) -> &'static str {
+    match protocol {
+        1 => "ICMP",
+        6 => "TCP",
+        17 => "UDP",
+        58 => "ICMPv6",
+        _ => "Unknown",
+    }
+}
+
+fn ipv4_at(data: &[u8], pos: usize) -> Ipv4Addr {
+    Ipv4Addr::new(data[pos], data[pos + 1], data[pos + 2], data[pos + 3])
+}
+
+fn ipv6_at(data: &[u8], pos: usize) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(&data[pos..pos + 16]);
+    Ipv6Addr::from(octets)
+}
+
+fn mac_at(data: &[u8], pos: usize) -> MacAddr {
+    MacAddr::new(
+        data[pos],
+        data[pos + 1],
+        data[pos + 2],
+        data[pos + 3],
+        data[pos + 4],
+        data[pos + 5],
+    )
+}
+
+/// Offset of the options of a Neighbor Discovery message.
+fn nd_options_start(icmp_type: u8) -> Option<usize> {
+    match icmp_type {
+        ND_ROUTER_SOLICIT => Some(8),
+        ND_ROUTER_ADVERT => Some(16),
+        ND_NEIGHBOR_SOLICIT | ND_NEIGHBOR_ADVERT => Some(24),
+        ND_REDIRECT => Some(40),
+        _ => None,
+    }
+}
+
+/// Iterates the `(type, start, len)` of each Neighbor Discovery option. Stops
+/// at a zero-length or truncated option.
+fn nd_options(data: &[u8], start: usize) -> Vec<(u8, usize, usize)> {
+    let mut options = Vec::new();
+    let mut pos = start;
+    while pos + 2 <= data.len() {
+        let len = usize::from(data[pos + 1]) * 8;
+        if len == 0 || pos + len > data.len() {
+            break;
+        }
+        options.push((data[pos], pos, len));
+        pos += len;
+    }
+    options
+}
+
+/// Link-layer address carried in a source/target link-layer address option.
+fn nd_link_address(data: &[u8], option_type: u8) -> Option<MacAddr> {
+    let start = nd_options_start(data[0])?;
+    nd_options(data, start)
+        .into_iter()
+        .find(|&(t, _, len)| t == option_type && len >= 8)
+        .map(|(_, pos, _)| mac_at(data, pos + 2))
+}
+
+/// Info column text in the Wireshark style.
+pub fn summarize(version: Version, data: &[u8]) -> String {
+    if data.len() < HEADER_LEN {
+        return "Malformed ICMP message".to_string();
+    }
+    let (icmp_type, code) = (data[0], data[1]);
+    let name = type_name(version, icmp_type);
+    if let Some(echo) = echo(version, data) {
+        return format!(
+            "{} id=0x{:04x}, seq={}",
+            name, echo.identifier, echo.sequence
+        );
+    }
+    if version == Version::V6 && nd_options_start(icmp_type).is_some() {
+        let target = (data.len() >= 24).then(|| ipv6_at(data, 8));
+        return match (icmp_type, target) {
+            (ND_ROUTER_SOLICIT, _) | (ND_ROUTER_ADVERT, _) => match nd_link_address(data, 1) {
+                Some(mac) => format!("{} from {}", name, mac),
+                None => name.to_string(),
+            },
+            (ND_NEIGHBOR_SOLICIT, Some(target)) => match nd_link_address(data, 1) {
+                Some(mac) => format!("{} for {} from {}", name, target, mac),
+                None => format!("{} for {}", name, target),
+            },
+            (ND_NEIGHBOR_ADVERT, Some(target)) => {
+                let flags = data[4];
+                let names: Vec<&str> = [(0x80, "rtr"), (0x40, "sol"), (0x20, "ovr")]
+                    .iter()
+                    .filter(|(bit, _)| flags & bit != 0)
+                    .map(|&(_, name)| name)
+                    .collect();
+                let mut info = format!("{} {}", name, target);
+                if !names.is_empty() {
+                    info.push_str(&format!(" ({})", names.join(", ")));
+                }
+                if let Some(mac) = nd_link_address(data, 2) {
+                    info.push_str(&format!(" is at {}", mac));
+                }
+                info
+            }
+            (ND_REDIRECT, Some(target)) => format!("{} to {}", name, target),
+            _ => name.to_string(),
+        };
+    }
+    match code_name(version, icmp_type, code) {
+        Some(reason) => format!("{} ({})", name, reason),
+        None => name.to_string(),
+    }
+}
+
+fn seconds(value: u32) -> String {
+    if value == u32::MAX {
+        "infinity".to_string()
+    } else {
+        format!("{} s", value)
+    }
+}
+
+/// Builds the detail view fields for an ICMP or ICMPv6 message starting at `offset`.
+pub fn fields(version: Version, data: &[u8], offset: usize) -> Vec<PacketField> {
+    let at = |start: usize, len: usize| (offset + start, offset + start + len);
+    if data.len() < HEADER_LEN {
+        return vec![PacketField {
+            expert: Some("Malformed ICMP message".to_string()),
+            ..field(
+                "Error",
+                format!("ICMP message too short ({} bytes)", data.len()),
+                at(0, data.len()),
+            )
+        }];
+    }
+    let (icmp_type, code) = (data[0], data[1]);
+
+    let mut fields = vec![
+        field(
+            "Type",
+            format!("{} ({})", icmp_type, type_name(version, icmp_type)),
+            at(0, 1),
+        ),
+        field(
+            "Code",
+            match code_name(version, icmp_type, code) {
+                Some(reason) => format!("{} ({})", code, reason),
+                None => code.to_string(),
+            },
+            at(1, 1),
+        ),
+        field(
+            "Checksum",
+            format!("0x{:04x}", u16::from_be_bytes([data[2], data[3]])),
+            at(2, 2),
+        ),
+    ];
+
+    if let Some(echo) = echo(version, data) {
+        fields.push(field(
+            "Identifier",
+            format!("{} (0x{:04x})", echo.identifier, echo.identifier),
+            at(4, 2),
+        ));
+        fields.push(field(
+            "Sequence Number",
+            format!("{} (0x{:04x})", echo.sequence, echo.sequence),
+            at(6, 2),
+        ));
+        return fields;
+    }
+
+    match (version, icmp_type) {
+        (Version::V4, DEST_UNREACHABLE) if code == 4 => fields.push(field(
+            "Next-Hop MTU",
+            u16::from_be_bytes([data[6], data[7]]).to_string(),
+            at(6, 2),
+        )),
+        (Version::V4, REDIRECT) => fields.push(PacketField {
+            expert: Some(format!(
+                "ICMP redirect to gateway {}; can be used to hijack traffic",
+                ipv4_at(data, 4)
+            )),
+            ..field("Gateway Address", ipv4_at(data, 4).to_string(), at(4, 4))
+        }),
+        (Version::V4, 12) => fields.push(field("Pointer", data[4].to_string(), at(4, 1))),
+        (Version::V6, V6_PACKET_TOO_BIG) => fields.push(field(
+            "MTU",
+            be_u32(data, 4).unwrap_or_default().to_string(),
+            at(4, 4),
+        )),
+        (Version::V6, V6_PARAMETER_PROBLEM) => fields.push(field(
+            "Pointer",
+            be_u32(data, 4).unwrap_or_default().to_string(),
+            at(4, 4),
+        )),
+        (Version::V6, _) if nd_options_start(icmp_type).is_some() => {
+            push_nd_fields(&mut fields, data, offset);
+        }
+        _ => {}
+    }
+
+    if is_error(version, icmp_type) {
+        if let Some(name) = code_name(version, icmp_type, code) {
+            if let Some(first) = fields.get_mut(1) {
+                first.expert = Some(format!("{}: {}", type_name(version, icmp_type), name));
+            }
+        }
+        if let Some(orig) = original(version, data) {
+            let hdr = |start: usize, len: usize| at(orig.header_start + start, len);
+            let (src_range, dst_range, proto_range) = match version {
+                Version::V4 => (hdr(12, 4), hdr(16, 4), hdr(9, 1)),
+                Version::V6 => (hdr(8, 16), hdr(24, 16), hdr(6, 1)),
+            };
+            fields.push(field("Original Source", orig.source.clone(), src_range));
+            fields.push(field(
+                "Original Destination",
+                orig.destination.clone(),
+                dst_range,
+            ));
+            fields.push(field(
+                "Original Protocol",
+                format!("{} ({})", orig.protocol, protocol_name(orig.protocol)),
+                proto_range,
+            ));
+            if let Some((src_port, dst_port)) = orig.ports {
+                fields.push(field(
+                    "Original Source Port",
+                    src_port.to_string(),
+                    hdr(orig.header_len, 2),
+                ));
+                fields.push(field(
+                    "Original Destination Port",
+                    dst_port.to_string(),
+                    hdr(orig.header_len + 2, 2),
+                ));
+            }
+        }
+    }
+    fields
+}
+
+fn push_nd_fields(fields: &mut Vec<PacketField>, data: &[u8], offset: usize) {
+    let at = |start: usize, len: usize| (offset + start, offset + start + len);
+    let icmp_type = data[0];
+    let Some(options_start) = nd_options_start(icmp_type) else {
+        return;
+    };
+    if data.len() < options_start {
+        fields.push(PacketField {
+            expert: Some("Truncated Neighbor Discovery message".to_string()),
+            ..field(
+                "Error",
+                format!("{} too short", type_name(Version::V6, icmp_type)),
+                at(0, data.len()),
+            )
+        });
+        return;
+    }
+
+    match icmp_type {
+        ND_ROUTER_ADVERT => {
+            let flags = data[5];
+            let mut names = Vec::new();
+            if flags & 0x80 != 0 {
+                names.push("Managed");
+            }
+            if flags & 0x40 != 0 {
+                names.push("Other");
+            }
+            fields.push(field("Cur Hop Limit", data[4].to_string(), at(4, 1)));
+            fields.push(field(
+                "Flags",
+                format!("0x{:02x} ({})", flags, names.join(", ")),
+                at(5, 1),
+            ));
+            fields.push(field(
+                "Router Lifetime",
+                format!("{} s", u16::from_be_bytes([data[6], data[7]])),
+                at(6, 2),
+            ));
+            fields.push(field(
+                "Reachable Time",
+                format!("{} ms", be_u32(data, 8).unwrap_or_default()),
+                at(8, 4),
+            ));
+            fields.push(field(
+                "Retrans Timer",
+                format!("{} ms", be_u32(data, 12).unwrap_or_default()),
+                at(12, 4),
+            ));
+        }
+        ND_NEIGHBOR_SOLICIT => {
+            fields.push(field(
+                "Target Address",
+                ipv6_at(data, 8).to_string(),
+                at(8, 16),
+            ));
+        }
+        ND_NEIGHBOR_ADVERT => {
+            let flags = data[4];
+            let mut names = Vec::new();
+            if flags & 0x80 != 0 {
+                names.push("Router");
+            }
+            if flags & 0x40 != 0 {
+                names.push("Solicited");
+            }
+            if flags & 0x20 != 0 {
+                names.push("Override");
+            }
+            fields.push(field(
+                "Flags",
+                format!("0x{:02x} ({})", flags, names.join(", ")),
+                at(4, 1),
+            ));
+            fields.push(field(
+                "Target Address",
+                ipv6_at(data, 8).to_string(),
+                at(8, 16),
+            ));
+        }
+        ND_REDIRECT => {
+            fields.push(field(
+                "Target Address",
+                ipv6_at(data, 8).to_string(),
+                at(8, 16),
+            ));
+            fields.push(PacketField {
+                expert: Some("ICMPv6 redirect; can be used to hijack traffic".to_string()),
+                ..field(
+                    "Destination Address",
+                    ipv6_at(data, 24).to_string(),
+                    at(24, 16),
+                )
+            });
+        }
+        _ => {}
+    }
+
+    let options = nd_options(data, options_start);
+    let parsed_len = options
+        .last()
+        .map_or(options_start, |&(_, pos, len)| pos + len);
+    for (option_type, pos, len) in options {
+        match option_type {
+            1 | 2 if len >= 8 => fields.push(field(
+                if option_type == 1 {
+                    "Source Link-layer Address"
+                } else {
+                    "Target Link-layer Address"
+                },
+                mac_at(data, pos + 2).to_string(),
+                at(pos + 2, 6),
+            )),
+            3 if len >= 32 => {
+                let flags = data[pos + 3];
+                fields.push(field(
+                    "Prefix",
+                    format!("{}/{}", ipv6_at(data, pos + 16), data[pos + 2]),
+                    at(pos + 16, 16),
+                ));
+                fields.push(field(
+                    "Prefix Flags",
+                    format!(
+                        "0x{:02x} (on-link={}, autonomous={})",
+                        flags,
+                        flags & 0x80 != 0,
+                        flags & 0x40 != 0
+                    ),
+                    at(pos + 3, 1),
+                ));
+                fields.push(field(
+                    "Valid Lifetime",
+                    seconds(be_u32(data, pos + 4).unwrap_or_default()),
+                    at(pos + 4, 4),
+                ));
+                fields.push(field(
+                    "Preferred Lifetime",
+                    seconds(be_u32(data, pos + 8).unwrap_or_default()),
+                    at(pos + 8, 4),
+                ));
+            }
+            5 if len >= 8 => fields.push(field(
+                "MTU",
+                be_u32(data, pos + 4).unwrap_or_default().to_string(),
+                at(pos + 4, 4),
+            )),
+            25 if len >= 24 => {
+                fields.push(field(
+                    "RDNSS Lifetime",
+                    seconds(be_u32(data, pos + 4).unwrap_or_default()),
+                    at(pos + 4, 4),
+                ));
+                for server in (pos + 8..pos + len).step_by(16) {
+                    if server + 16 <= pos + len {
+                        fields.push(field(
+                            "Recursive DNS Server",
+                            ipv6_at(data, server).to_string(),
+                            at(server, 16),
+                        ));
+                    }
+                }
+            }
+            _ => fields.push(field(
+                "Option",
+                format!("{} ({} bytes)", option_type, len),
+                at(pos, len),
+            )),
+        }
+    }
+    if parsed_len < data.len() {
+        fields.push(PacketField {
+            expert: Some("Malformed Neighbor Discovery option".to_string()),
+            ..field(
+                "Error",
+                format!("{} trailing bytes", data.len() - parsed_len),
+                at(parsed_len, data.len() - parsed_len),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_and_port_unreachable() {
+        let ping = [8, 0, 0xf7, 0xfd, 0x00, 0x01, 0x00, 0x02, b'a', b'b'];
+        assert_eq!(
+            summarize(Version::V4, &ping),
+            "Echo (ping) request id=0x0001, seq=2"
+        );
+        let echo = echo(Version::V4, &ping).unwrap();
+        assert!(echo.is_request);
+        assert_eq!((echo.identifier, echo.sequence), (1, 2));
+
+        // Port unreachable quoting a UDP datagram from 10.0.0.1:5000 to 10.0.0.2:53
+        let mut unreachable = vec![3, 3, 0, 0, 0, 0, 0, 0];
+        unreachable.extend_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+        unreachable.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        unreachable.extend_from_slice(&[0x13, 0x88, 0x00, 0x35, 0, 8, 0, 0]);
+        assert_eq!(
+            summarize(Version::V4, &unreachable),
+            "Destination unreachable (Port unreachable)"
+        );
+        let fields = fields(Version::V4, &unreachable, 34);
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.value.clone())
+        };
+        assert_eq!(value("Original Destination").as_deref(), Some("10.0.0.2"));
+        assert_eq!(value("Original Protocol").as_deref(), Some("17 (UDP)"));
+        assert_eq!(value("Original Destination Port").as_deref(), Some("53"));
+        assert!(fields[1].expert.is_some());
+    }
+
+    #[test]
+    fn test_neighbor_discovery() {
+        let target: Ipv6Addr = "fe80::1".parse().unwrap();
+        let mut advert = vec![ND_NEIGHBOR_ADVERT, 0, 0, 0, 0xe0, 0, 0, 0];
+        advert.extend_from_slice(&target.octets());
+        advert.extend_from_slice(&[2, 1, 0xaa, 0xbb, 0xcc, 0, 0x11, 0x22]);
+        assert_eq!(
+            summarize(Version::V6, &advert),
+            "Neighbor Advertisement fe80::1 (rtr, sol, ovr) is at aa:bb:cc:00:11:22"
+        );
+
+        let prefix: Ipv6Addr = "2001:db8::".parse().unwrap();
+        let mut router = vec![ND_ROUTER_ADVERT, 0, 0, 0, 64, 0x40, 0x07, 0x08];
+        router.extend_from_slice(&[0; 8]);
+        router.extend_from_slice(&[3, 4, 64, 0xc0]);
+        router.extend_from_slice(&86400u32.to_be_bytes());
+        router.extend_from_slice(&14400u32.to_be_bytes());
+        router.extend_from_slice(&[0; 4]);
+        router.extend_from_slice(&prefix.octets());
+        router.extend_from_slice(&[5, 1, 0, 0, 0, 0, 0x05, 0xdc]);
+        router.extend_from_slice(&[25, 3, 0, 0, 0, 0, 0x0e, 0x10]);
+        router.extend_from_slice(&prefix.octets());
+        let fields = fields(Version::V6, &router, 54);
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.value.clone())
+        };
+        assert_eq!(value("Router Lifetime").as_deref(), Some("1800 s"));
+        assert_eq!(value("Prefix").as_deref(), Some("2001:db8::/64"));
+        assert_eq!(value("MTU").as_deref(), Some("1500"));
+        assert_eq!(value("Recursive DNS Server").as_deref(), Some("2001:db8::"));
+        assert!(fields.iter().all(|f| f.name != "Error"));
+    }
+}
@@ -0,0 +1,487 @@
+//! IPv6 extension header chains (RFC 8200): Hop-by-Hop and Destination
+//! Options, Routing (including the deprecated type 0 of RFC 5095 and Segment
+//! Routing of RFC 8754), Fragment, and the IPsec AH (RFC 4302) and ESP
+//! (RFC 4303) headers.
+
+use super::field;
+use crate::model::PacketField;
+
+pub const HOP_BY_HOP: u8 = 0;
+pub const ROUTING: u8 = 43;
+pub const FRAGMENT: u8 = 44;
+pub const ESP: u8 = 50;
+pub const AH: u8 = 51;
+pub const NO_NEXT_HEADER: u8 = 59;
+pub const DESTINATION_OPTIONS: u8 = 60;
+
+const FRAGMENT_HEADER_LEN: usize = 8;
+const ESP_HEADER_LEN: usize = 8;
+/// Next Header, length, reserved, SPI and sequence number, before the ICV
+const AH_MIN_LEN: usize = 12;
+/// A chain in the order RFC 8200 section 4.1 recommends holds at most seven
+/// headers (Destination Options may appear twice); anything longer is unusual
+/// and a known way to push the transport header past middlebox inspection.
+const MAX_EXTENSION_HEADERS: usize = 7;
+/// The walk gives up here rather than follow an arbitrarily long chain.
+const MAX_WALK: usize = 32;
+/// Padding beyond what aligning the next option needs (RFC 4942 section 2.1.9.5).
+const MAX_PADN_LEN: usize = 5;
+const ROUTING_TYPE_0: u8 = 0;
+const ROUTING_TYPE_SRH: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentHeader {
+    /// Offset of the fragment's data in the original payload, in bytes
+    pub offset: usize,
+    pub more_fragments: bool,
+    pub identification: u32,
+}
+
+impl FragmentHeader {
+    /// Whether the packet is part of a larger datagram; atomic fragments
+    /// (RFC 6946) carry the whole datagram.
+    pub fn is_fragment(&self) -> bool {
+        self.more_fragments || self.offset != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    HopByHop,
+    Routing { routing_type: u8, segments_left: u8 },
+    Fragment(FragmentHeader),
+    DestinationOptions,
+    Authentication,
+    EncapsulatingSecurityPayload,
+}
+
+/// One extension header, at `offset` in the IPv6 payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub kind: Kind,
+    pub offset: usize,
+    pub len: usize,
+    pub next_header: u8,
+}
+
+/// The extension headers of an IPv6 packet and what follows them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chain {
+    pub headers: Vec<Header>,
+    /// Upper-layer protocol after the last header (ESP's is encrypted, so ESP itself)
+    pub protocol: u8,
+    /// Offset of the upper-layer header in the IPv6 payload
+    pub payload_offset: usize,
+    /// The chain ran past the end of the packet or the walk limit
+    pub truncated: bool,
+}
+
+impl Chain {
+    pub fn fragment(&self) -> Option<FragmentHeader> {
+        self.headers.iter().find_map(|header| match header.kind {
+            Kind::Fragment(fragment) => Some(fragment),
+            _ => None,
+        })
+    }
+
+    /// Whether the upper-layer header and payload are only partially here.
+    pub fn is_fragment(&self) -> bool {
+        self.fragment().is_some_and(|f| f.is_fragment())
+    }
+
+    /// Expert notes on suspicious or malformed chains.
+    pub fn expert(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if self.headers.len() > MAX_EXTENSION_HEADERS {
+            notes.push(format!(
+                "Excessive IPv6 extension headers: {} in one chain",
+                self.headers.len()
+            ));
+        }
+        for (i, header) in self.headers.iter().enumerate() {
+            let repeats = self.headers[..i]
+                .iter()
+                .filter(|h| std::mem::discriminant(&h.kind) == std::mem::discriminant(&header.kind))
+                .count();
+            match header.kind {
+                Kind::HopByHop if i > 0 => notes.push(
+                    "Hop-by-Hop Options header not directly after the IPv6 header".to_string(),
+                ),
+                Kind::Routing {
+                    routing_type: ROUTING_TYPE_0,
+                    ..
+                } => notes.push(
+                    "Routing header type 0 (deprecated by RFC 5095, enables traffic amplification)"
+                        .to_string(),
+                ),
+                Kind::Fragment(fragment) if !fragment.is_fragment() => {
+                    notes.push("Atomic fragment (RFC 6946)".to_string())
+                }
+                _ => {}
+            }
+            let allowed = match header.kind {
+                Kind::DestinationOptions => 2,
+                _ => 1,
+            };
+            if repeats == allowed {
+                notes.push(format!("Repeated IPv6 {} header", kind_name(header.kind)));
+            }
+        }
+        if self.truncated {
+            notes.push(match self.fragment() {
+                // RFC 7112: the first fragment must carry the whole header chain
+                Some(fragment) if fragment.offset == 0 => {
+                    "First fragment does not contain the whole header chain (RFC 7112)".to_string()
+                }
+                _ => "Truncated IPv6 extension header chain".to_string(),
+            });
+        }
+        notes
+    }
+}
+
+pub fn is_extension_header(next_header: u8) -> bool {
+    matches!(
+        next_header,
+        HOP_BY_HOP | ROUTING | FRAGMENT | ESP | AH | DESTINATION_OPTIONS
+    )
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::HopByHop => "Hop-by-Hop Options",
+        Kind::Routing { .. } => "Routing",
+        Kind::Fragment(_) => "Fragment",
+        Kind::DestinationOptions => "Destination Options",
+        Kind::Authentication => "Authentication",
+        Kind::EncapsulatingSecurityPayload => "ESP",
+    }
+}
+
+/// Walks the extension headers at the start of an IPv6 payload, where
+/// `next_header` is the IPv6 header's Next Header field.
+pub fn walk(next_header: u8, payload: &[u8]) -> Chain {
+    let mut chain = Chain {
+        headers: Vec::new(),
+        protocol: next_header,
+        payload_offset: 0,
+        truncated: false,
+    };
+    while is_extension_header(chain.protocol) {
+        if chain.headers.len() == MAX_WALK {
+            chain.truncated = true;
+            break;
+        }
+        let Some(header) = parse(chain.protocol, payload, chain.payload_offset) else {
+            chain.truncated = true;
+            break;
+        };
+        chain.headers.push(header);
+        chain.payload_offset += header.len;
+        match header.kind {
+            // ESP encrypts everything after it
+            Kind::EncapsulatingSecurityPayload => break,
+            // Only the first fragment continues with headers
+            Kind::Fragment(fragment) if fragment.offset != 0 => {
+                chain.protocol = header.next_header;
+                break;
+            }
+            _ => chain.protocol = header.next_header,
+        }
+    }
+    chain
+}
+
+fn parse(protocol: u8, payload: &[u8], offset: usize) -> Option<Header> {
+    let data = payload.get(offset..)?;
+    let (kind, len, next_header) = match protocol {
+        FRAGMENT => {
+            let header = data.get(..FRAGMENT_HEADER_LEN)?;
+            let offset_flags = u16::from_be_bytes([header[2], header[3]]);
+            let fragment = FragmentHeader {
+                offset: (offset_flags & 0xfff8) as usize,
+                more_fragments: offset_flags & 1 != 0,
+                identification: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+            };
+            (Kind::Fragment(fragment), FRAGMENT_HEADER_LEN, header[0])
+        }
+        ESP => {
+            data.get(..ESP_HEADER_LEN)?;
+            (Kind::EncapsulatingSecurityPayload, ESP_HEADER_LEN, ESP)
+        }
+        AH => {
+            let len = (*data.get(1)? as usize + 2) * 4;
+            if len < AH_MIN_LEN {
+                return None;
+            }
+            (Kind::Authentication, len, data[0])
+        }
+        _ => {
+            let len = (*data.get(1)? as usize + 1) * 8;
+            let kind = match protocol {
+                HOP_BY_HOP => Kind::HopByHop,
+                DESTINATION_OPTIONS => Kind::DestinationOptions,
+                _ => Kind::Routing {
+                    routing_type: *data.get(2)?,
+                    segments_left: *data.get(3)?,
+                },
+            };
+            (kind, len, data[0])
+        }
+    };
+    if data.len() < len {
+        return None;
+    }
+    Some(Header {
+        kind,
+        offset,
+        len,
+        next_header,
+    })
+}
+
+fn option_name(option_type: u8) -> &'static str {
+    match option_type {
+        0x00 => "Pad1",
+        0x01 => "PadN",
+        0x04 => "Tunnel Encapsulation Limit",
+        0x05 => "Router Alert",
+        0x07 => "CALIPSO",
+        0x26 => "Quick-Start",
+        0x63 => "RPL Option",
+        0xc2 => "Jumbo Payload",
+        0xc9 => "Home Address",
+        _ => "Unknown",
+    }
+}
+
+fn routing_type_name(routing_type: u8) -> &'static str {
+    match routing_type {
+        0 => "Type 0 (deprecated)",
+        2 => "Type 2 (Mobile IPv6)",
+        3 => "RPL Source Route",
+        4 => "Segment Routing",
+        _ => "Unknown",
+    }
+}
+
+pub fn protocol_name(next_header: u8) -> &'static str {
+    match next_header {
+        HOP_BY_HOP => "Hop-by-Hop Options",
+        ROUTING => "Routing",
+        FRAGMENT => "Fragment",
+        ESP => "ESP",
+        AH => "AH",
+        NO_NEXT_HEADER => "No Next Header",
+        DESTINATION_OPTIONS => "Destination Options",
+        1 => "ICMP",
+        4 => "IPv4",
+        6 => "TCP",
+        17 => "UDP",
+        41 => "IPv6",
+        47 => "GRE",
+        58 => "ICMPv6",
+        _ => "Unknown",
+    }
+}
+
+/// Builds the detail view fields for `header`, whose IPv6 payload starts at
+/// `base` in the frame.
+pub fn fields(header: &Header, payload: &[u8], base: usize) -> Vec<PacketField> {
+    let start = base + header.offset;
+    let at = |from: usize, len: usize| (start + from, start + from + len);
+    let data = &payload[header.offset..header.offset + header.len];
+    let u32_at = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    let next_header = field(
+        "Next Header",
+        format!(
+            "{} ({})",
+            protocol_name(header.next_header),
+            header.next_header
+        ),
+        at(0, 1),
+    );
+
+    match header.kind {
+        Kind::HopByHop | Kind::DestinationOptions => {
+            let mut fields = vec![
+                next_header,
+                field("Length", format!("{} bytes", header.len), at(1, 1)),
+            ];
+            let mut i = 2;
+            while i < data.len() {
+                let option_type = data[i];
+                let len = match option_type {
+                    0 => 1,
+                    _ => match data.get(i + 1) {
+                        Some(&len) => 2 + len as usize,
+                        None => break,
+                    },
+                };
+                let mut option = field(
+                    "Option",
+                    format!(
+                        "{} (0x{:02x}, {} bytes)",
+                        option_name(option_type),
+                        option_type,
+                        len
+                    ),
+                    at(i, len.min(data.len() - i)),
+                );
+                if option_type == 1 && len > MAX_PADN_LEN + 2 {
+                    option.expert = Some(format!(
+                        "PadN of {} bytes is longer than alignment needs (possible covert channel)",
+                        len - 2
+                    ));
+                }
+                fields.push(option);
+                i += len;
+            }
+            fields
+        }
+        Kind::Routing {
+            routing_type,
+            segments_left,
+        } => {
+            let mut fields = vec![
+                next_header,
+                field("Length", format!("{} bytes", header.len), at(1, 1)),
+                PacketField {
+                    expert: (routing_type == ROUTING_TYPE_0)
+                        .then(|| "Deprecated Routing Header type 0 (RFC 5095)".to_string()),
+                    ..field(
+                        "Routing Type",
+                        format!("{} ({})", routing_type_name(routing_type), routing_type),
+                        at(2, 1),
+                    )
+                },
+                field("Segments Left", segments_left.to_string(), at(3, 1)),
+            ];
+            let count = match routing_type {
+                ROUTING_TYPE_SRH => data[4] as usize + 1,
+                0 | 2 => (header.len - 8) / 16,
+                _ => 0,
+            };
+            for i in 0..count {
+                let from = 8 + i * 16;
+                let Some(bytes) = data.get(from..from + 16) else {
+                    break;
+                };
+                let address: [u8; 16] = bytes.try_into().unwrap_or_default();
+                fields.push(field(
+                    "Address",
+                    std::net::Ipv6Addr::from(address).to_string(),
+                    at(from, 16),
+                ));
+            }
+            fields
+        }
+        Kind::Fragment(fragment) => vec![
+            next_header,
+            field(
+                "Fragment Offset",
+                format!("{} bytes", fragment.offset),
+                at(2, 2),
+            ),
+            field(
+                "More Fragments",
+                if fragment.more_fragments { "Yes" } else { "No" }.to_string(),
+                at(3, 1),
+            ),
+            field(
+                "Identification",
+                format!("0x{:08x}", fragment.identification),
+                at(4, 4),
+            ),
+        ],
+        Kind::Authentication => vec![
+            next_header,
+            field("Length", format!("{} bytes", header.len), at(1, 1)),
+            field("SPI", format!("0x{:08x}", u32_at(4)), at(4, 4)),
+            field("Sequence Number", u32_at(8).to_string(), at(8, 4)),
+            field(
+                "ICV",
+                format!("{} bytes", header.len - 12),
+                at(12, header.len - 12),
+            ),
+        ],
+        Kind::EncapsulatingSecurityPayload => vec![
+            field("SPI", format!("0x{:08x}", u32_at(0)), at(0, 4)),
+            field("Sequence Number", u32_at(4).to_string(), at(4, 4)),
+            field(
+                "Encrypted Data",
+                format!("{} bytes", payload.len() - header.offset - header.len),
+                (start + header.len, base + payload.len()),
+            ),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_chain() {
+        let mut payload = vec![ROUTING, 0, 0x01, 0x04, 0, 0, 0, 0]; // Hop-by-Hop, PadN
+        payload.extend_from_slice(&[FRAGMENT, 2, 0, 1]); // Routing type 0, one address
+        payload.extend_from_slice(&[0; 4]);
+        payload.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        payload.extend_from_slice(&[17, 0, 0x00, 0x01, 0, 0, 0x12, 0x34]); // Fragment, M=1
+        payload.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00]);
+
+        let chain = walk(HOP_BY_HOP, &payload);
+        assert_eq!(chain.headers.len(), 3);
+        assert_eq!(chain.protocol, 17);
+        assert_eq!(chain.payload_offset, 40);
+        assert!(!chain.truncated);
+        assert_eq!(
+            chain.fragment(),
+            Some(FragmentHeader {
+                offset: 0,
+                more_fragments: true,
+                identification: 0x1234,
+            })
+        );
+        assert!(chain.is_fragment());
+        assert_eq!(
+            chain.expert(),
+            vec![
+                "Routing header type 0 (deprecated by RFC 5095, enables traffic amplification)"
+                    .to_string()
+            ]
+        );
+
+        let fields = fields(&chain.headers[1], &payload, 54);
+        assert_eq!(fields[2].value, "Type 0 (deprecated) (0)");
+        assert_eq!(fields[4].value, "fe80::1");
+        assert_eq!(fields[4].range, (70, 86));
+    }
+
+    #[test]
+    fn test_suspicious_chains() {
+        // Nine Destination Options headers ending in a truncated one
+        let mut payload = Vec::new();
+        for _ in 0..9 {
+            payload.extend_from_slice(&[DESTINATION_OPTIONS, 0, 0x01, 0x04, 0, 0, 0, 0]);
+        }
+        payload.extend_from_slice(&[6, 1]);
+        let chain = walk(DESTINATION_OPTIONS, &payload);
+        assert_eq!(chain.headers.len(), 9);
+        assert!(chain.truncated);
+        let notes = chain.expert();
+        assert_eq!(notes[0], "Excessive IPv6 extension headers: 9 in one chain");
+        assert!(notes.contains(&"Repeated IPv6 Destination Options header".to_string()));
+        assert_eq!(
+            notes.last().unwrap(),
+            "Truncated IPv6 extension header chain"
+        );
+
+        // A later fragment stops the walk at the fragment header
+        let chain = walk(FRAGMENT, &[ROUTING, 0, 0x05, 0xa0, 0, 0, 0, 1, 0xff, 0xff]);
+        assert_eq!(chain.protocol, ROUTING);
+        assert_eq!(chain.payload_offset, 8);
+        assert_eq!(chain.fragment().unwrap().offset, 0x5a0);
+        assert!(chain.expert().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! NTLMSSP (MS-NLMP) AUTHENTICATE messages, as carried in SMB session setup
+//! security blobs, usually wrapped in SPNEGO.
+//!
+//! Only the account fields are read: the domain, user and workstation names
+//! a client authenticated with.
+
+use super::LAYER_NTLMSSP;
+use super::{field, le_u16, le_u32};
+use crate::model::ProtocolLayer;
+
+const SIGNATURE: &[u8] = b"NTLMSSP\0";
+const MESSAGE_AUTHENTICATE: u32 = 3;
+const DOMAIN_FIELDS: usize = 28;
+const USER_FIELDS: usize = 36;
+const WORKSTATION_FIELDS: usize = 44;
+const NEGOTIATE_FLAGS: usize = 60;
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+
+/// A name from an AUTHENTICATE message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name {
+    pub value: String,
+    /// Byte range within the blob the message was found in
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Auth {
+    pub domain: Name,
+    pub user: Name,
+    pub host: Name,
+    /// Offset of the message within the blob
+    pub start: usize,
+}
+
+impl Auth {
+    /// "DOMAIN\user", or the user alone without a domain.
+    pub fn account(&self) -> String {
+        if self.domain.value.is_empty() {
+            self.user.value.clone()
+        } else {
+            format!("{}\\{}", self.domain.value, self.user.value)
+        }
+    }
+}
+
+/// Decodes UTF-16LE text, replacing invalid code units.
+pub fn utf16(data: &[u8]) -> String {
+    let units = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Reads the length/offset pair at `pos` and the name it points to.
+fn name(message: &[u8], start: usize, pos: usize, unicode: bool) -> Option<Name> {
+    let len = usize::from(le_u16(message, pos)?);
+    let offset = le_u32(message, pos + 4)? as usize;
+    let bytes = message.get(offset..offset + len)?;
+    let value = if unicode {
+        utf16(bytes)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    Some(Name {
+        value,
+        range: (start + offset, start + offset + len),
+    })
+}
+
+/// Finds an AUTHENTICATE message in a security blob.
+pub fn auth(blob: &[u8]) -> Option<Auth> {
+    let start = blob
+        .windows(SIGNATURE.len())
+        .position(|window| window == SIGNATURE)?;
+    let message = &blob[start..];
+    if le_u32(message, SIGNATURE.len())? != MESSAGE_AUTHENTICATE {
+        return None;
+    }
+    let unicode = le_u32(message, NEGOTIATE_FLAGS)? & NEGOTIATE_UNICODE != 0;
+    Some(Auth {
+        domain: name(message, start, DOMAIN_FIELDS, unicode)?,
+        user: name(message, start, USER_FIELDS, unicode)?,
+        host: name(message, start, WORKSTATION_FIELDS, unicode)?,
+        start,
+    })
+}
+
+/// Builds the detail view layer for a message in a blob starting at `offset`.
+pub fn layer(auth: &Auth, offset: usize) -> ProtocolLayer {
+    let at = |name: &Name| (offset + name.range.0, offset + name.range.1);
+    let start = offset + auth.start;
+    ProtocolLayer {
+        name: LAYER_NTLMSSP.to_string(),
+        fields: vec![
+            field(
+                "Message Type",
+                "NTLMSSP_AUTH (0x00000003)".to_string(),
+                (start + SIGNATURE.len(), start + SIGNATURE.len() + 4),
+            ),
+            field("Domain", auth.domain.value.clone(), at(&auth.domain)),
+            field("User", auth.user.value.clone(), at(&auth.user)),
+            field("Host", auth.host.value.clone(), at(&auth.host)),
+        ],
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// An AUTHENTICATE message with Unicode names.
+    pub fn authenticate(domain: &str, user: &str, host: &str) -> Vec<u8> {
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&MESSAGE_AUTHENTICATE.to_le_bytes());
+        message.resize(64, 0);
+        message[NEGOTIATE_FLAGS..NEGOTIATE_FLAGS + 4]
+            .copy_from_slice(&NEGOTIATE_UNICODE.to_le_bytes());
+        for (pos, text) in [
+            (DOMAIN_FIELDS, domain),
+            (USER_FIELDS, user),
+            (WORKSTATION_FIELDS, host),
+        ] {
+            let bytes = utf16le(text);
+            let offset = message.len() as u32;
+            message[pos..pos + 2].copy_from_slice(&(bytes.len() as u16).to_le_bytes());
+            message[pos + 2..pos + 4].copy_from_slice(&(bytes.len() as u16).to_le_bytes());
+            message[pos + 4..pos + 8].copy_from_slice(&offset.to_le_bytes());
+            message.extend(bytes);
+        }
+        message
+    }
+
+    #[test]
+    fn test_authenticate() {
+        // SPNEGO wrapping in front of the message
+        let mut blob = vec![0xa1, 0x82, 0x01, 0x00];
+        blob.extend(authenticate("CORP", "alice", "WS01"));
+        let auth = auth(&blob).unwrap();
+        assert_eq!(auth.account(), "CORP\\alice");
+        assert_eq!(auth.host.value, "WS01");
+        assert_eq!(auth.domain.range, (68, 76));
+
+        let layer = layer(&auth, 100);
+        assert_eq!(layer.fields[2].value, "alice");
+        assert_eq!(layer.fields[2].range, (176, 186));
+        assert!(super::auth(b"NTLMSSP\0\x01\x00\x00\x00").is_none());
+    }
+}
@@ -0,0 +1,802 @@
+//! QUIC (RFC 9000) packet headers, and decryption of client Initial packets.
+//!
+//! Initial packets are protected with keys derived from the client's
+//! Destination Connection ID and a version-specific salt (RFC 9001 §5.2,
+//! RFC 9369 §3.3), so the ClientHello they carry can be read without any
+//! secrets. Server Initials use the same keys, but the Connection ID they are
+//! derived from is not in the server's packet, so only client Initials are
+//! decrypted here.
+
+use super::fingerprint::Transport;
+use super::registry::{self, Context, Dissection, Dissector};
+use super::tls;
+use super::{field, hex};
+use super::{LAYER_QUIC, LAYER_TLS, PROTO_HTTPS, PROTO_QUIC};
+use crate::model::{PacketField, ProtocolLayer};
+use crate::state::CryptoChunk;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes128Gcm, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+pub const QUIC_PORT: u16 = 443;
+
+pub const VERSION_1: u32 = 0x0000_0001;
+pub const VERSION_2: u32 = 0x6b33_43cf;
+
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+const INITIAL_SALT_V2: [u8; 20] = [
+    0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe, 0x6e, 0x26, 0x9d, 0xcb,
+    0xf9, 0xbd, 0x2e, 0xd9,
+];
+
+const MAX_CID_LEN: usize = 20;
+const SAMPLE_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+
+const FRAME_PADDING: u64 = 0x00;
+const FRAME_PING: u64 = 0x01;
+const FRAME_ACK: u64 = 0x02;
+const FRAME_ACK_ECN: u64 = 0x03;
+const FRAME_CRYPTO: u64 = 0x06;
+const FRAME_CONNECTION_CLOSE: u64 = 0x1c;
+const FRAME_CONNECTION_CLOSE_APP: u64 = 0x1d;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+    /// Short header packet
+    OneRtt,
+    /// Long header packet of a version we cannot parse
+    Unknown,
+}
+
+impl PacketType {
+    fn name(self) -> &'static str {
+        match self {
+            PacketType::Initial => "Initial",
+            PacketType::ZeroRtt => "0-RTT",
+            PacketType::Handshake => "Handshake",
+            PacketType::Retry => "Retry",
+            PacketType::VersionNegotiation => "Version Negotiation",
+            PacketType::OneRtt => "1-RTT",
+            PacketType::Unknown => "Unknown",
+        }
+    }
+}
+
+fn long_packet_type(version: u32, bits: u8) -> PacketType {
+    // QUIC v2 rotates the type codes so middleboxes cannot ossify on them
+    let bits = if version == VERSION_2 {
+        (bits + 3) % 4
+    } else {
+        bits
+    };
+    match bits {
+        0 => PacketType::Initial,
+        1 => PacketType::ZeroRtt,
+        2 => PacketType::Handshake,
+        _ => PacketType::Retry,
+    }
+}
+
+pub fn version_name(version: u32) -> String {
+    match version {
+        VERSION_1 => "QUIC v1".to_string(),
+        VERSION_2 => "QUIC v2".to_string(),
+        0 => "Version Negotiation".to_string(),
+        v if v & 0x0f0f_0f0f == 0x0a0a_0a0a => "Reserved (forcing negotiation)".to_string(),
+        v if v >> 8 == 0xff_0000 => format!("draft-{}", v & 0xff),
+        _ => "Unknown".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameKind {
+    Padding {
+        length: usize,
+    },
+    Ping,
+    Ack {
+        largest: u64,
+    },
+    Crypto {
+        offset: u64,
+        data: Vec<u8>,
+    },
+    ConnectionClose {
+        error_code: u64,
+        reason: String,
+    },
+    /// A frame type not expected in Initial packets; parsing stops here
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub frame_type: u64,
+    /// Byte range within the decrypted payload
+    pub range: (usize, usize),
+    pub kind: FrameKind,
+}
+
+/// A decrypted client Initial packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decrypted {
+    /// Truncated packet number as sent on the wire
+    pub packet_number: u64,
+    /// Offset of the payload within the datagram
+    pub payload_offset: usize,
+    pub frames: Vec<Frame>,
+}
+
+impl Decrypted {
+    /// Offset and data of each CRYPTO frame, in packet order.
+    pub fn crypto_chunks(&self) -> Vec<(u64, &[u8])> {
+        self.frames
+            .iter()
+            .filter_map(|f| match &f.kind {
+                FrameKind::Crypto { offset, data } => Some((*offset, data.as_slice())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The contiguous CRYPTO stream data from offset 0, and whether it was
+    /// assembled from more than one frame (so it no longer maps 1:1 onto
+    /// packet bytes). Clients may send CRYPTO frames out of order.
+    pub fn crypto_stream(&self) -> Option<(Vec<u8>, bool)> {
+        let (stream, used) = assemble_crypto(self.crypto_chunks());
+        (!stream.is_empty()).then_some((stream, used > 1))
+    }
+}
+
+/// Joins CRYPTO data into the contiguous stream from offset 0, returning it
+/// and the number of chunks that contributed to it.
+pub fn assemble_crypto<'a>(chunks: impl IntoIterator<Item = (u64, &'a [u8])>) -> (Vec<u8>, usize) {
+    let mut chunks: Vec<(u64, &[u8])> = chunks.into_iter().collect();
+    chunks.sort_by_key(|c| c.0);
+    let mut stream: Vec<u8> = Vec::new();
+    let mut used = 0;
+    for (offset, data) in chunks {
+        let end = offset + data.len() as u64;
+        if offset > stream.len() as u64 {
+            break;
+        }
+        if end > stream.len() as u64 {
+            stream.extend_from_slice(&data[(stream.len() as u64 - offset) as usize..]);
+            used += 1;
+        }
+    }
+    (stream, used)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub packet_type: PacketType,
+    /// None for short header packets
+    pub version: Option<u32>,
+    /// Empty for short header packets, whose length is not on the wire
+    pub dcid: Vec<u8>,
+    pub scid: Vec<u8>,
+    pub token_length: Option<u64>,
+    /// Value of the Length field (packet number and payload)
+    pub length: Option<u64>,
+    /// Byte range within the datagram
+    pub range: (usize, usize),
+    /// Range of the long header up to the Length field
+    pub header_range: (usize, usize),
+    pub decrypted: Option<Decrypted>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u8(&mut self) -> Option<u8> {
+        let value = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(value)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let value = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(value)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    /// Variable-length integer (RFC 9000 §16).
+    fn varint(&mut self) -> Option<u64> {
+        let first = self.u8()?;
+        let len = 1usize << (first >> 6);
+        let mut value = u64::from(first & 0x3f);
+        for &b in self.bytes(len - 1)? {
+            value = (value << 8) | u64::from(b);
+        }
+        Some(value)
+    }
+
+    fn cid(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()? as usize;
+        (len <= MAX_CID_LEN).then_some(())?;
+        self.bytes(len)
+    }
+}
+
+/// HKDF-Expand-Label from TLS 1.3 (RFC 8446 §7.1), with an empty context.
+fn expand_label(secret: &[u8], label: &str, len: usize) -> Option<Vec<u8>> {
+    let hkdf = Hkdf::<Sha256>::from_prk(secret).ok()?;
+    let label = format!("tls13 {}", label);
+    let mut info = Vec::with_capacity(4 + label.len());
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push(label.len() as u8);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+    let mut out = vec![0; len];
+    hkdf.expand(&info, &mut out).ok()?;
+    Some(out)
+}
+
+#[derive(Debug, PartialEq)]
+struct InitialKeys {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    hp: Vec<u8>,
+}
+
+/// Client Initial packet protection keys for a Destination Connection ID.
+fn client_initial_keys(version: u32, dcid: &[u8]) -> Option<InitialKeys> {
+    let (salt, prefix) = match version {
+        VERSION_1 => (&INITIAL_SALT_V1, "quic"),
+        VERSION_2 => (&INITIAL_SALT_V2, "quicv2"),
+        _ => return None,
+    };
+    let (initial_secret, _) = Hkdf::<Sha256>::extract(Some(salt), dcid);
+    let secret = expand_label(&initial_secret, "client in", 32)?;
+    Some(InitialKeys {
+        key: expand_label(&secret, &format!("{} key", prefix), 16)?,
+        iv: expand_label(&secret, &format!("{} iv", prefix), 12)?,
+        hp: expand_label(&secret, &format!("{} hp", prefix), 16)?,
+    })
+}
+
+/// Header protection mask: AES-ECB of the ciphertext sample (RFC 9001 §5.4.3).
+fn header_mask(hp: &[u8], sample: &[u8]) -> Option<[u8; 16]> {
+    let cipher = Aes128::new_from_slice(hp).ok()?;
+    let mut block = aes::Block::from(<[u8; 16]>::try_from(sample).ok()?);
+    cipher.encrypt_block(&mut block);
+    Some(block.into())
+}
+
+/// Removes header protection and decrypts an Initial packet occupying
+/// `datagram[start..end]`, whose packet number starts at `pn_offset`.
+fn decrypt_initial(
+    datagram: &[u8],
+    start: usize,
+    pn_offset: usize,
+    end: usize,
+    version: u32,
+    dcid: &[u8],
+) -> Option<Decrypted> {
+    let keys = client_initial_keys(version, dcid)?;
+    let sample = datagram.get(pn_offset + 4..pn_offset + 4 + SAMPLE_LEN)?;
+    if pn_offset + 4 + SAMPLE_LEN > end {
+        return None;
+    }
+    let mask = header_mask(&keys.hp, sample)?;
+
+    let mut header = datagram[start..pn_offset].to_vec();
+    header[0] ^= mask[0] & 0x0f;
+    let pn_len = (header[0] & 0x03) as usize + 1;
+    let mut packet_number = 0u64;
+    for (i, &b) in datagram[pn_offset..pn_offset + pn_len].iter().enumerate() {
+        let b = b ^ mask[1 + i];
+        header.push(b);
+        packet_number = (packet_number << 8) | u64::from(b);
+    }
+
+    let payload_offset = pn_offset + pn_len;
+    if payload_offset + TAG_LEN > end {
+        return None;
+    }
+    let mut nonce = <[u8; 12]>::try_from(keys.iv.as_slice()).ok()?;
+    for (n, p) in nonce[4..].iter_mut().zip(packet_number.to_be_bytes()) {
+        *n ^= p;
+    }
+    let cipher = Aes128Gcm::new_from_slice(&keys.key).ok()?;
+    let plaintext = cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &datagram[payload_offset..end],
+                aad: &header,
+            },
+        )
+        .ok()?;
+
+    Some(Decrypted {
+        packet_number,
+        payload_offset,
+        frames: parse_frames(&plaintext),
+    })
+}
+
+fn parse_frames(payload: &[u8]) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    let mut r = Reader {
+        data: payload,
+        pos: 0,
+    };
+    while r.pos < payload.len() {
+        let start = r.pos;
+        let Some(frame_type) = r.varint() else {
+            break;
+        };
+        let kind = match frame_type {
+            FRAME_PADDING => {
+                while r.data.get(r.pos) == Some(&0) {
+                    r.pos += 1;
+                }
+                Some(FrameKind::Padding {
+                    length: r.pos - start,
+                })
+            }
+            FRAME_PING => Some(FrameKind::Ping),
+            FRAME_ACK | FRAME_ACK_ECN => (|| {
+                let largest = r.varint()?;
+                let _delay = r.varint()?;
+                let ranges = r.varint()?;
+                r.varint()?;
+                for _ in 0..ranges.min(payload.len() as u64) {
+                    r.varint()?;
+                    r.varint()?;
+                }
+                if frame_type == FRAME_ACK_ECN {
+                    for _ in 0..3 {
+                        r.varint()?;
+                    }
+                }
+                Some(FrameKind::Ack { largest })
+            })(),
+            FRAME_CRYPTO => (|| {
+                let offset = r.varint()?;
+                let length = usize::try_from(r.varint()?).ok()?;
+                let data = r.bytes(length)?.to_vec();
+                Some(FrameKind::Crypto { offset, data })
+            })(),
+            FRAME_CONNECTION_CLOSE | FRAME_CONNECTION_CLOSE_APP => (|| {
+                let error_code = r.varint()?;
+                if frame_type == FRAME_CONNECTION_CLOSE {
+                    r.varint()?;
+                }
+                let length = usize::try_from(r.varint()?).ok()?;
+                let reason = String::from_utf8_lossy(r.bytes(length)?).into_owned();
+                Some(FrameKind::ConnectionClose { error_code, reason })
+            })(),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => frames.push(Frame {
+                frame_type,
+                range: (start, r.pos),
+                kind,
+            }),
+            None => {
+                frames.push(Frame {
+                    frame_type,
+                    range: (start, payload.len()),
+                    kind: FrameKind::Other,
+                });
+                break;
+            }
+        }
+    }
+    frames
+}
+
+/// Parses the header of the packet at `start`, returning it and the offset of
+/// the next coalesced packet.
+fn parse_packet(datagram: &[u8], start: usize) -> Option<(Packet, usize)> {
+    let mut r = Reader {
+        data: datagram,
+        pos: start,
+    };
+    let first = r.u8()?;
+    if first & 0x80 == 0 {
+        // Short header: the DCID length is only known to the endpoints
+        (first & 0x40 != 0).then_some(())?;
+        let packet = Packet {
+            packet_type: PacketType::OneRtt,
+            version: None,
+            dcid: Vec::new(),
+            scid: Vec::new(),
+            token_length: None,
+            length: None,
+            range: (start, datagram.len()),
+            header_range: (start, start + 1),
+            decrypted: None,
+        };
+        return Some((packet, datagram.len()));
+    }
+
+    let version = r.u32()?;
+    let dcid = r.cid()?.to_vec();
+    let scid = r.cid()?.to_vec();
+    let mut packet = Packet {
+        packet_type: PacketType::Unknown,
+        version: Some(version),
+        dcid,
+        scid,
+        token_length: None,
+        length: None,
+        range: (start, datagram.len()),
+        header_range: (start, r.pos),
+        decrypted: None,
+    };
+    if version == 0 {
+        packet.packet_type = PacketType::VersionNegotiation;
+        return Some((packet, datagram.len()));
+    }
+    if version != VERSION_1 && version != VERSION_2 {
+        return Some((packet, datagram.len()));
+    }
+
+    packet.packet_type = long_packet_type(version, (first >> 4) & 0x03);
+    match packet.packet_type {
+        PacketType::Retry => return Some((packet, datagram.len())),
+        PacketType::Initial => {
+            let token_length = r.varint()?;
+            r.bytes(usize::try_from(token_length).ok()?)?;
+            packet.token_length = Some(token_length);
+        }
+        _ => {}
+    }
+    let length = r.varint()?;
+    packet.header_range.1 = r.pos;
+    let pn_offset = r.pos;
+    let end = pn_offset
+        .checked_add(usize::try_from(length).ok()?)?
+        .min(datagram.len());
+    packet.length = Some(length);
+    packet.range.1 = end;
+    if packet.packet_type == PacketType::Initial {
+        packet.decrypted = decrypt_initial(datagram, start, pn_offset, end, version, &packet.dcid);
+    }
+    Some((packet, end))
+}
+
+/// Parses the packets coalesced in a UDP datagram. Returns an empty list when
+/// the datagram does not start with a QUIC packet.
+pub fn parse(datagram: &[u8]) -> Vec<Packet> {
+    let mut packets = Vec::new();
+    let mut pos = 0;
+    // Datagrams carrying Initials may be padded with zeros after the packets
+    while pos < datagram.len() && datagram[pos] != 0 {
+        let Some((packet, next)) = parse_packet(datagram, pos) else {
+            break;
+        };
+        packets.push(packet);
+        pos = next;
+    }
+    packets
+}
+
+/// CRYPTO frames of the first client Initial in a datagram that carries any,
+/// with the Destination Connection ID the Initial was sent to.
+pub fn client_crypto(datagram: &[u8]) -> Option<(Vec<u8>, Vec<CryptoChunk>)> {
+    parse(datagram).into_iter().find_map(|packet| {
+        let chunks: Vec<CryptoChunk> = packet
+            .decrypted?
+            .crypto_chunks()
+            .into_iter()
+            .map(|(offset, data)| (offset, data.to_vec()))
+            .collect();
+        (!chunks.is_empty()).then_some((packet.dcid, chunks))
+    })
+}
+
+fn packet_summary(packet: &Packet) -> String {
+    let name = packet.packet_type.name();
+    let Some(decrypted) = &packet.decrypted else {
+        return match packet.packet_type {
+            PacketType::OneRtt => "Protected Payload".to_string(),
+            PacketType::Initial | PacketType::Handshake | PacketType::ZeroRtt => {
+                format!("{}, DCID={}", name, hex(&packet.dcid))
+            }
+            _ => name.to_string(),
+        };
+    };
+    let handshake = decrypted
+        .crypto_stream()
+        .and_then(|(stream, _)| tls::parse_handshakes(&stream))
+        .and_then(|messages| messages.first().map(tls::handshake_summary));
+    match handshake {
+        Some(handshake) => format!("{}, {}", name, handshake),
+        None => format!("{}, PKN: {}", name, decrypted.packet_number),
+    }
+}
+
+/// Info column text when the datagram carries QUIC packets, e.g.
+/// "Initial, Client Hello (SNI=example.com)".
+pub fn summarize(datagram: &[u8]) -> Option<String> {
+    let packets = parse(datagram);
+    if packets.is_empty() {
+        return None;
+    }
+    let parts: Vec<String> = packets.iter().map(packet_summary).collect();
+    Some(parts.join(", "))
+}
+
+fn frame_field(frame: &Frame, range: (usize, usize)) -> PacketField {
+    let (value, expert) = match &frame.kind {
+        FrameKind::Padding { length } => (format!("PADDING ({} bytes)", length), None),
+        FrameKind::Ping => ("PING".to_string(), None),
+        FrameKind::Ack { largest } => (format!("ACK (largest {})", largest), None),
+        FrameKind::Crypto { offset, data } => (
+            format!("CRYPTO (offset {}, {} bytes)", offset, data.len()),
+            None,
+        ),
+        FrameKind::ConnectionClose { error_code, reason } => (
+            format!("CONNECTION_CLOSE (error 0x{:x}) {}", error_code, reason)
+                .trim_end()
+                .to_string(),
+            Some(format!("Connection closed with error 0x{:x}", error_code)),
+        ),
+        FrameKind::Other => (
+            format!("0x{:02x} (unexpected in Initial)", frame.frame_type),
+            Some("Unexpected frame type in Initial packet".to_string()),
+        ),
+    };
+    PacketField {
+        expert,
+        ..field("Frame", value, range)
+    }
+}
+
+/// Builds the detail view fields for the QUIC packets in a datagram starting
+/// at `offset`, plus the TLS fields of a decrypted ClientHello.
+pub fn fields(datagram: &[u8], offset: usize) -> (Vec<PacketField>, Vec<PacketField>) {
+    let at = |(start, end): (usize, usize)| (offset + start, offset + end);
+    let mut fields = Vec::new();
+    let mut tls_fields = Vec::new();
+
+    for packet in parse(datagram) {
+        let range = at(packet.range);
+        let header = at(packet.header_range);
+        let form = if packet.version.is_some() {
+            "Long Header"
+        } else {
+            "Short Header"
+        };
+        fields.push(field("Header Form", form.to_string(), header));
+        fields.push(field(
+            "Packet Type",
+            packet.packet_type.name().to_string(),
+            header,
+        ));
+        if let Some(version) = packet.version {
+            fields.push(field(
+                "Version",
+                format!("0x{:08x} ({})", version, version_name(version)),
+                header,
+            ));
+            fields.push(field(
+                "Destination Connection ID",
+                hex(&packet.dcid),
+                header,
+            ));
+            fields.push(field("Source Connection ID", hex(&packet.scid), header));
+        }
+        if let Some(token_length) = packet.token_length {
+            fields.push(field("Token Length", token_length.to_string(), header));
+        }
+        if let Some(length) = packet.length {
+            fields.push(field("Length", format!("{} bytes", length), header));
+        }
+
+        let Some(decrypted) = &packet.decrypted else {
+            let expert = (packet.packet_type == PacketType::Initial).then(|| {
+                "Initial not decryptable from this packet alone (server Initial?)".to_string()
+            });
+            fields.push(PacketField {
+                expert,
+                ..field(
+                    "Protected Payload",
+                    format!("{} bytes", packet.range.1 - packet.header_range.1),
+                    (header.1, range.1),
+                )
+            });
+            continue;
+        };
+
+        let payload = decrypted.payload_offset;
+        fields.push(field(
+            "Packet Number",
+            decrypted.packet_number.to_string(),
+            (header.1, at((payload, payload)).1),
+        ));
+        let in_payload = |(start, end): (usize, usize)| at((payload + start, payload + end));
+        for frame in &decrypted.frames {
+            fields.push(frame_field(frame, in_payload(frame.range)));
+        }
+
+        if let Some((stream, reassembled)) = decrypted.crypto_stream() {
+            // Plaintext lines up with the ciphertext, so a single CRYPTO frame
+            // can be highlighted exactly; reassembled data maps to the payload
+            let single = decrypted
+                .frames
+                .iter()
+                .find(|f| matches!(f.kind, FrameKind::Crypto { offset: 0, .. }));
+            let (data_start, clamp) = match single {
+                Some(frame) if !reassembled => {
+                    let len = stream.len();
+                    (in_payload((frame.range.1 - len, frame.range.1)).0, None)
+                }
+                _ => (
+                    at((payload, payload)).0,
+                    Some((at((payload, payload)).0, range.1)),
+                ),
+            };
+            for mut tls_field in tls::handshake_fields(&stream, data_start, Transport::Quic) {
+                if let Some((low, high)) = clamp {
+                    tls_field.range = (low, high);
+                }
+                tls_fields.push(tls_field);
+            }
+        }
+    }
+    (fields, tls_fields)
+}
+
+/// QUIC on UDP port 443.
+pub struct Quic;
+
+impl Dissector for Quic {
+    fn name(&self) -> &'static str {
+        "quic"
+    }
+
+    fn description(&self) -> &'static str {
+        "QUIC, with client Initials decrypted"
+    }
+
+    fn ports(&self) -> &[(registry::Transport, u16)] {
+        &[(registry::Transport::Udp, QUIC_PORT)]
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        Some(match summarize(ctx.payload) {
+            Some(info) => (PROTO_QUIC.to_string(), info),
+            None => (PROTO_HTTPS.to_string(), "TLS/SSL".to_string()),
+        })
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        if parse(ctx.payload).is_empty() {
+            return None;
+        }
+        let (quic_fields, tls_fields) = fields(ctx.payload, ctx.offset);
+        let mut dissection = Dissection::layer(LAYER_QUIC, quic_fields);
+        if !tls_fields.is_empty() {
+            dissection.layers.push(ProtocolLayer {
+                name: LAYER_TLS.to_string(),
+                fields: tls_fields,
+            });
+        }
+        Some(dissection)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::super::tls::tests::client_hello_body;
+    use super::*;
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc9001_initial_keys() {
+        let dcid = unhex("8394c8f03e515708");
+        let keys = client_initial_keys(VERSION_1, &dcid).unwrap();
+        assert_eq!(keys.key, unhex("1f369613dd76d5467730efcbe3b1a22d"));
+        assert_eq!(keys.iv, unhex("fa044b2f42a3fd3b46fb255c"));
+        assert_eq!(keys.hp, unhex("9f50449e04a0e810283a1e9933adedd2"));
+
+        let mask = header_mask(&keys.hp, &unhex("d1b1c98dd7689fb8ec11d242b123dc9b")).unwrap();
+        assert_eq!(mask[..5], unhex("437b9aec36")[..]);
+    }
+
+    /// Builds a protected client Initial carrying `crypto` at stream `offset`
+    /// in one CRYPTO frame.
+    pub fn client_initial(version: u32, dcid: &[u8], offset: usize, crypto: &[u8]) -> Vec<u8> {
+        let keys = client_initial_keys(version, dcid).unwrap();
+        let type_bits = if version == VERSION_2 { 1 } else { 0 };
+        let mut plaintext = vec![FRAME_CRYPTO as u8];
+        plaintext.extend_from_slice(&(0x4000 | offset as u16).to_be_bytes());
+        plaintext.extend_from_slice(&(0x4000 | crypto.len() as u16).to_be_bytes());
+        plaintext.extend_from_slice(crypto);
+        plaintext.resize(plaintext.len() + 40, 0);
+
+        let pn = 2u8;
+        let mut header = vec![0xc0 | (type_bits << 4)];
+        header.extend_from_slice(&version.to_be_bytes());
+        header.push(dcid.len() as u8);
+        header.extend_from_slice(dcid);
+        header.extend_from_slice(&[0, 0]); // empty SCID and token
+        let length = 1 + plaintext.len() + TAG_LEN;
+        header.extend_from_slice(&(0x4000 | length as u16).to_be_bytes());
+        let pn_offset = header.len();
+        header.push(pn);
+
+        let mut nonce = <[u8; 12]>::try_from(keys.iv.as_slice()).unwrap();
+        nonce[11] ^= pn;
+        let cipher = Aes128Gcm::new_from_slice(&keys.key).unwrap();
+        let ciphertext = cipher
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &header,
+                },
+            )
+            .unwrap();
+
+        let mut packet = [header, ciphertext].concat();
+        let sample = packet[pn_offset + 4..pn_offset + 4 + SAMPLE_LEN].to_vec();
+        let mask = header_mask(&keys.hp, &sample).unwrap();
+        packet[0] ^= mask[0] & 0x0f;
+        packet[pn_offset] ^= mask[1];
+        packet
+    }
+
+    #[test]
+    fn test_decrypt_client_initial() {
+        let body = client_hello_body();
+        let mut handshake = vec![tls::HANDSHAKE_CLIENT_HELLO, 0];
+        handshake.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        handshake.extend_from_slice(&body);
+
+        for version in [VERSION_1, VERSION_2] {
+            let datagram = client_initial(version, &[0x83, 0x94, 0xc8, 0xf0], 0, &handshake);
+            let packets = parse(&datagram);
+            assert_eq!(packets.len(), 1);
+            assert_eq!(packets[0].packet_type, PacketType::Initial);
+            let decrypted = packets[0].decrypted.as_ref().unwrap();
+            assert_eq!(decrypted.packet_number, 2);
+            assert_eq!(
+                summarize(&datagram).unwrap(),
+                "Initial, Client Hello (SNI=example.com)"
+            );
+
+            let (_, tls_fields) = fields(&datagram, 42);
+            let ja4 = tls_fields.iter().find(|f| f.name == "JA4").unwrap();
+            assert!(ja4.value.starts_with("q13d0207h2_"));
+        }
+
+        // Tampering with the payload fails authentication
+        let mut datagram = client_initial(VERSION_1, &[1, 2, 3, 4], 0, &handshake);
+        let last = datagram.len() - 1;
+        datagram[last] ^= 1;
+        assert!(parse(&datagram)[0].decrypted.is_none());
+    }
+}
@@ -0,0 +1,681 @@
+//! Application-layer dissectors and the registry that chooses between them.
+//!
+//! A protocol implements [`Dissector`] and claims well-known ports, a
+//! heuristic that recognizes its payload on any port, or both. For each
+//! payload the registry lines up candidates (heuristics that run first,
+//! then port matches with the lower port tried first, then the remaining
+//! heuristics) and the first one that accepts the payload wins, so a port
+//! dissector can still decline traffic it does not understand. A dissector
+//! can hand the rest of its payload on to another one by name, which is how
+//! layers are chained.
+//!
+//! Dissectors are enabled or disabled at runtime by name through the global
+//! registry behind [`read`] and [`write`].
+
+use super::{dhcp, dhcpv6, dns, http, quic, smb, ssh, tls, LAYER_DATA};
+use crate::model::{PacketField, ProtocolLayer};
+use serde::Serialize;
+use std::sync::{LazyLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Longest chain of hand-offs followed below the transport layer.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// When a heuristic dissector is tried, relative to port-based ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Before port matches: the content cannot be mistaken for anything else
+    First,
+    /// Only when no port dissector accepted the payload
+    Last,
+}
+
+/// The payload being dissected and the layers beneath it.
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    pub transport: Transport,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub payload: &'a [u8],
+    /// Offset of the payload within the frame
+    pub offset: usize,
+    /// Info column text for the transport layer, for bare segments
+    pub transport_info: &'a str,
+    /// Dissector that handed this payload on, None directly above TCP or UDP
+    pub parent: Option<&'static str>,
+    /// Dissector learned for the flow from its first payload packets
+    pub learned: Option<&'a str>,
+}
+
+impl Context<'_> {
+    pub fn has_port(&self, port: u16) -> bool {
+        self.src_port == port || self.dst_port == port
+    }
+
+    /// Whether the flow is known to carry the dissector `name`, by its
+    /// well-known `port` or from what it carried earlier.
+    pub fn expects(&self, name: &str, port: u16) -> bool {
+        self.has_port(port) || self.learned == Some(name)
+    }
+}
+
+/// Layers produced by a dissector that accepted its payload.
+#[derive(Debug, Clone, Default)]
+pub struct Dissection {
+    pub layers: Vec<ProtocolLayer>,
+    /// Dissector for the payload from this offset on, when it carries another protocol
+    pub next: Option<(&'static str, usize)>,
+}
+
+impl Dissection {
+    pub fn layer(name: &str, fields: Vec<PacketField>) -> Self {
+        Dissection {
+            layers: vec![ProtocolLayer {
+                name: name.to_string(),
+                fields,
+            }],
+            next: None,
+        }
+    }
+}
+
+pub trait Dissector: Send + Sync {
+    /// Stable lowercase identifier, used to enable and disable the dissector
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// Ports claimed on each transport
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[]
+    }
+
+    /// Whether, and when, the dissector is tried on payloads on other ports
+    fn heuristic(&self, _transport: Transport) -> Option<Heuristic> {
+        None
+    }
+
+    /// Whether the payload carries the protocol's signature, which is enough
+    /// to remember the protocol for the rest of the flow
+    fn recognizes(&self, _ctx: &Context) -> bool {
+        false
+    }
+
+    /// Protocol and info columns, None when the payload is not this protocol.
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)>;
+
+    /// Detail layers, None when the payload is not this protocol.
+    fn dissect(&self, _ctx: &Context) -> Option<Dissection> {
+        None
+    }
+}
+
+/// A dissector as listed to the user.
+#[derive(Debug, Clone, Serialize)]
+pub struct DissectorInfo {
+    pub name: String,
+    pub description: String,
+    pub ports: Vec<String>,
+    pub heuristic: bool,
+    pub enabled: bool,
+}
+
+struct Entry {
+    dissector: Box<dyn Dissector>,
+    enabled: bool,
+}
+
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// The built-in dissectors, in the order they are tried.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(dns::Dns));
+        registry.register(Box::new(dns::Mdns));
+        registry.register(Box::new(dhcp::Dhcp));
+        registry.register(Box::new(dhcpv6::Dhcpv6));
+        registry.register(Box::new(tls::Tls));
+        registry.register(Box::new(quic::Quic));
+        registry.register(Box::new(http::Http));
+        registry.register(Box::new(ssh::Ssh));
+        registry.register(Box::new(smb::Smb));
+        for label in WELL_KNOWN {
+            registry.register(Box::new(label));
+        }
+        registry.register(Box::new(Json));
+        registry
+    }
+
+    pub fn empty() -> Self {
+        Registry {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a dissector, replacing any registered under the same name.
+    pub fn register(&mut self, dissector: Box<dyn Dissector>) {
+        let entry = Entry {
+            dissector,
+            enabled: true,
+        };
+        match self
+            .entries
+            .iter()
+            .position(|e| e.dissector.name() == entry.dissector.name())
+        {
+            Some(i) => self.entries[i] = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.dissector.name() == name)
+            .ok_or_else(|| format!("Unknown dissector: {}", name))?;
+        entry.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<DissectorInfo> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let dissector = &entry.dissector;
+                DissectorInfo {
+                    name: dissector.name().to_string(),
+                    description: dissector.description().to_string(),
+                    ports: dissector
+                        .ports()
+                        .iter()
+                        .map(|(transport, port)| match transport {
+                            Transport::Tcp => format!("tcp/{}", port),
+                            Transport::Udp => format!("udp/{}", port),
+                        })
+                        .collect(),
+                    heuristic: [Transport::Tcp, Transport::Udp]
+                        .iter()
+                        .any(|&t| dissector.heuristic(t).is_some()),
+                    enabled: entry.enabled,
+                }
+            })
+            .collect()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &dyn Dissector> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.dissector.as_ref())
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Dissector> {
+        self.enabled().find(|dissector| dissector.name() == name)
+    }
+
+    /// Enabled dissectors to try on a payload, in order: the one learned for
+    /// the flow, if any, then the usual order.
+    fn candidates(&self, ctx: &Context) -> Vec<&dyn Dissector> {
+        let heuristics = |when| {
+            self.enabled()
+                .filter(move |d| d.heuristic(ctx.transport) == Some(when))
+        };
+        let low = ctx.src_port.min(ctx.dst_port);
+        let high = ctx.src_port.max(ctx.dst_port);
+        let on_port = |port| {
+            self.enabled()
+                .filter(move |d| d.ports().contains(&(ctx.transport, port)))
+        };
+
+        let learned = ctx.learned.and_then(|name| self.get(name));
+        let mut candidates: Vec<&dyn Dissector> = Vec::new();
+        for dissector in learned
+            .into_iter()
+            .chain(heuristics(Heuristic::First))
+            .chain(on_port(low))
+            .chain(on_port(high))
+            .chain(heuristics(Heuristic::Last))
+        {
+            if !candidates.iter().any(|c| c.name() == dissector.name()) {
+                candidates.push(dissector);
+            }
+        }
+        candidates
+    }
+
+    /// Name of the first dissector whose signature the payload carries.
+    pub fn detect(&self, ctx: &Context) -> Option<&'static str> {
+        self.candidates(ctx)
+            .into_iter()
+            .find(|dissector| dissector.recognizes(ctx))
+            .map(|dissector| dissector.name())
+    }
+
+    /// Protocol and info columns from the first dissector that accepts the payload.
+    pub fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        self.candidates(ctx)
+            .into_iter()
+            .find_map(|dissector| dissector.summarize(ctx))
+    }
+
+    /// Appends the application layers, following hand-offs between
+    /// dissectors; whatever no dissector accepts is shown as data.
+    pub fn dissect(&self, ctx: &Context, layers: &mut Vec<ProtocolLayer>) {
+        let mut ctx = *ctx;
+        let accept = |dissector: &dyn Dissector, ctx: &Context| {
+            dissector
+                .dissect(ctx)
+                .map(|dissection| (dissector.name(), dissection))
+        };
+        let mut found = self
+            .candidates(&ctx)
+            .into_iter()
+            .find_map(|dissector| accept(dissector, &ctx));
+        for _ in 0..MAX_DEPTH {
+            let Some((
+                parent,
+                Dissection {
+                    layers: found_layers,
+                    next,
+                },
+            )) = found.take()
+            else {
+                break;
+            };
+            layers.extend(found_layers);
+            let Some((name, start)) = next else {
+                return;
+            };
+            let start = start.min(ctx.payload.len());
+            ctx = Context {
+                payload: &ctx.payload[start..],
+                offset: ctx.offset + start,
+                parent: Some(parent),
+                ..ctx
+            };
+            if ctx.payload.is_empty() {
+                return;
+            }
+            found = self.get(name).and_then(|dissector| accept(dissector, &ctx));
+        }
+        layers.push(ProtocolLayer {
+            name: LAYER_DATA.to_string(),
+            fields: vec![PacketField {
+                name: "Payload Length".to_string(),
+                value: format!("{} bytes", ctx.payload.len()),
+                range: (ctx.offset, ctx.offset + ctx.payload.len()),
+                expert: None,
+            }],
+        });
+    }
+}
+
+/// The registry used for packet summaries and details.
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| RwLock::new(Registry::new()));
+
+pub fn read() -> RwLockReadGuard<'static, Registry> {
+    // Entries are replaced whole, so a panicking writer cannot leave one half-updated
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn write() -> RwLockWriteGuard<'static, Registry> {
+    REGISTRY.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A protocol recognized by port alone, named in the packet list but shown as data.
+pub struct PortLabel {
+    pub name: &'static str,
+    pub protocol: &'static str,
+    pub description: &'static str,
+    pub ports: &'static [(Transport, u16)],
+    /// Info text for the port matched
+    pub info: fn(u16) -> &'static str,
+}
+
+impl Dissector for &PortLabel {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        self.ports
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let port = [
+            ctx.src_port.min(ctx.dst_port),
+            ctx.src_port.max(ctx.dst_port),
+        ]
+        .into_iter()
+        .find(|&port| self.ports.contains(&(ctx.transport, port)))?;
+        // Bare handshake and ACK segments say more as TCP info
+        let info = if ctx.transport == Transport::Tcp && ctx.payload.is_empty() {
+            ctx.transport_info.to_string()
+        } else {
+            (self.info)(port).to_string()
+        };
+        Some((self.protocol.to_string(), info))
+    }
+}
+
+const fn udp(port: u16) -> (Transport, u16) {
+    (Transport::Udp, port)
+}
+
+static WELL_KNOWN: &[PortLabel] = &[
+    PortLabel {
+        name: "https",
+        protocol: "HTTPS",
+        description: "HTTP over TLS on port 443, when not recognized as TLS",
+        ports: &[(Transport::Tcp, 443)],
+        info: |_| "TLS/SSL",
+    },
+    PortLabel {
+        name: "tftp",
+        protocol: "TFTP",
+        description: "Trivial File Transfer Protocol",
+        ports: &[udp(69)],
+        info: |_| "TFTP",
+    },
+    PortLabel {
+        name: "ntp",
+        protocol: "NTP",
+        description: "Network Time Protocol",
+        ports: &[udp(123)],
+        info: |_| "NTP Request",
+    },
+    PortLabel {
+        name: "netbios",
+        protocol: "NetBIOS",
+        description: "NetBIOS name and datagram services",
+        ports: &[udp(137), udp(138)],
+        info: |port| {
+            if port == 137 {
+                "NetBIOS Name Query"
+            } else {
+                "NetBIOS Datagram"
+            }
+        },
+    },
+    PortLabel {
+        name: "snmp",
+        protocol: "SNMP",
+        description: "Simple Network Management Protocol",
+        ports: &[udp(161), udp(162)],
+        info: |port| if port == 161 { "SNMP Get" } else { "SNMP Trap" },
+    },
+    PortLabel {
+        name: "ldap",
+        protocol: "LDAP",
+        description: "Lightweight Directory Access Protocol",
+        ports: &[udp(389)],
+        info: |_| "LDAP Query",
+    },
+    PortLabel {
+        name: "syslog",
+        protocol: "Syslog",
+        description: "Syslog",
+        ports: &[udp(514)],
+        info: |_| "Syslog",
+    },
+    PortLabel {
+        name: "ipp",
+        protocol: "IPP",
+        description: "Internet Printing Protocol",
+        ports: &[udp(631)],
+        info: |_| "Printer (IPP)",
+    },
+    PortLabel {
+        name: "ssdp",
+        protocol: "SSDP",
+        description: "Simple Service Discovery Protocol",
+        ports: &[udp(1900)],
+        info: |_| "UPnP Discovery",
+    },
+    PortLabel {
+        name: "sip",
+        protocol: "SIP",
+        description: "Session Initiation Protocol",
+        ports: &[udp(7070)],
+        info: |_| "SIP Invite",
+    },
+    PortLabel {
+        name: "http-proxy",
+        protocol: "HTTP",
+        description: "HTTP proxy port",
+        ports: &[udp(8080)],
+        info: |_| "HTTP Proxy",
+    },
+    PortLabel {
+        name: "https-alt",
+        protocol: "HTTPS",
+        description: "Alternate HTTPS port",
+        ports: &[udp(8443)],
+        info: |_| "TLS Alt",
+    },
+    PortLabel {
+        name: "elasticsearch",
+        protocol: "Elasticsearch",
+        description: "Elasticsearch",
+        ports: &[udp(9200)],
+        info: |_| "ES Query",
+    },
+    PortLabel {
+        name: "mongodb",
+        protocol: "MongoDB",
+        description: "MongoDB wire protocol",
+        ports: &[udp(27017)],
+        info: |_| "MongoDB Query",
+    },
+];
+
+/// JSON documents sent as bare UDP datagrams.
+struct Json;
+
+impl Dissector for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn description(&self) -> &'static str {
+        "JSON documents in UDP datagrams"
+    }
+
+    fn heuristic(&self, transport: Transport) -> Option<Heuristic> {
+        (transport == Transport::Udp).then_some(Heuristic::Last)
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let payload = ctx.payload;
+        (payload.len() >= 4 && (payload.starts_with(b"{\"") || payload.starts_with(b"[{\"")))
+            .then(|| ("JSON".to_string(), "JSON Data".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dissector::LAYER_HTTP;
+
+    fn context(transport: Transport, src_port: u16, dst_port: u16, payload: &[u8]) -> Context<'_> {
+        Context {
+            transport,
+            src_port,
+            dst_port,
+            payload,
+            offset: 42,
+            transport_info: "transport",
+            parent: None,
+            learned: None,
+        }
+    }
+
+    /// Wraps its payload in a length prefix and hands the rest to "inner".
+    struct Framed;
+
+    impl Dissector for Framed {
+        fn name(&self) -> &'static str {
+            "framed"
+        }
+
+        fn description(&self) -> &'static str {
+            "Length-prefixed test frames"
+        }
+
+        fn ports(&self) -> &[(Transport, u16)] {
+            &[(Transport::Tcp, 9000)]
+        }
+
+        fn summarize(&self, _ctx: &Context) -> Option<(String, String)> {
+            Some(("Framed".to_string(), "frame".to_string()))
+        }
+
+        fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+            let len = *ctx.payload.first()? as usize;
+            let mut dissection = Dissection::layer("Framed", Vec::new());
+            dissection.next = Some(("inner", 1)).filter(|_| len > 0);
+            Some(dissection)
+        }
+    }
+
+    struct Inner;
+
+    impl Dissector for Inner {
+        fn name(&self) -> &'static str {
+            "inner"
+        }
+
+        fn description(&self) -> &'static str {
+            "Payload of test frames"
+        }
+
+        fn summarize(&self, _ctx: &Context) -> Option<(String, String)> {
+            None
+        }
+
+        fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+            assert_eq!(ctx.parent, Some("framed"));
+            Some(Dissection::layer(
+                "Inner",
+                vec![PacketField {
+                    name: "Data".to_string(),
+                    value: String::from_utf8_lossy(ctx.payload).to_string(),
+                    range: (ctx.offset, ctx.offset + ctx.payload.len()),
+                    expert: None,
+                }],
+            ))
+        }
+    }
+
+    #[test]
+    fn test_candidate_order() {
+        let registry = Registry::new();
+        // Heuristics flagged to run first beat the port: TLS records on port 53
+        let record = [0x16, 0x03, 0x01, 0x00, 0x04, 0x0e, 0x00, 0x00, 0x00];
+        let ctx = context(Transport::Tcp, 50000, 53, &record);
+        let names: Vec<_> = registry.candidates(&ctx).iter().map(|d| d.name()).collect();
+        assert_eq!(names, ["tls", "http", "ssh", "smb", "dns"]);
+        assert_eq!(registry.summarize(&ctx).unwrap().0, "TLS");
+
+        // A port dissector that declines falls through to the remaining candidates
+        let ctx = context(Transport::Udp, 443, 50000, &[0; 8]);
+        assert_eq!(registry.summarize(&ctx).unwrap().0, "HTTPS");
+        let mut layers = Vec::new();
+        registry.dissect(&ctx, &mut layers);
+        assert_eq!(layers[0].name, LAYER_DATA);
+        assert_eq!(layers[0].fields[0].range, (42, 50));
+
+        let ctx = context(Transport::Udp, 40000, 40001, b"{\"ok\":1}");
+        assert_eq!(registry.summarize(&ctx).unwrap().0, "JSON");
+        // Bare TCP segments on a labelled port keep the transport info
+        let ctx = context(Transport::Tcp, 443, 40000, b"");
+        assert_eq!(
+            registry.summarize(&ctx),
+            Some(("HTTPS".to_string(), "transport".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_detection_and_learned_flows() {
+        let registry = Registry::new();
+        let detect = |transport, port, payload: &[u8]| {
+            registry.detect(&context(transport, 50000, port, payload))
+        };
+        assert_eq!(
+            detect(Transport::Tcp, 2222, b"SSH-2.0-OpenSSH_9.6\r\n"),
+            Some("ssh")
+        );
+        assert_eq!(
+            detect(Transport::Tcp, 8000, b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"),
+            Some("http")
+        );
+        let smb = b"\x00\x00\x00\x44\xfeSMB\x40\x00\x00\x00\x00\x00\x00\x00\x03\x00";
+        assert_eq!(detect(Transport::Tcp, 4455, smb), Some("smb"));
+        let query = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\x07example\x03com\x00\x00\x01\x00\x01";
+        assert_eq!(detect(Transport::Udp, 5300, query), Some("dns"));
+        assert_eq!(detect(Transport::Udp, 5300, &[0x12; 40]), None);
+        let ctx = context(Transport::Udp, 50000, 5300, query);
+        assert_eq!(registry.summarize(&ctx).unwrap().0, "DNS");
+
+        // Later segments of a learned flow keep its protocol
+        let body = context(Transport::Tcp, 50000, 8000, b"<html></html>");
+        assert_eq!(registry.summarize(&body), None);
+        let learned = Context {
+            learned: Some("http"),
+            ..body
+        };
+        assert_eq!(registry.summarize(&learned).unwrap().0, "HTTP");
+        let mut layers = Vec::new();
+        registry.dissect(&learned, &mut layers);
+        assert_eq!(layers[0].name, LAYER_HTTP);
+    }
+
+    #[test]
+    fn test_enable_and_chain() {
+        let mut registry = Registry::new();
+        let ctx = context(Transport::Udp, 40000, 53, &[0; 12]);
+        assert_eq!(registry.summarize(&ctx).unwrap().0, "DNS");
+        registry.set_enabled("dns", false).unwrap();
+        assert_eq!(registry.summarize(&ctx), None);
+        assert!(registry.set_enabled("nonexistent", false).is_err());
+        let info = registry.list();
+        assert!(info.iter().any(|d| d.name == "dns" && !d.enabled));
+        assert!(info.iter().any(|d| d.name == "http" && d.heuristic));
+
+        registry.register(Box::new(Framed));
+        registry.register(Box::new(Inner));
+        let ctx = context(Transport::Tcp, 40000, 9000, b"\x05hello");
+        let mut layers = Vec::new();
+        registry.dissect(&ctx, &mut layers);
+        let names: Vec<_> = layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["Framed", "Inner"]);
+        assert_eq!(layers[1].fields[0].value, "hello");
+        assert_eq!(layers[1].fields[0].range, (43, 48));
+
+        // A disabled inner dissector leaves the rest of the payload as data
+        registry.set_enabled("inner", false).unwrap();
+        let mut layers = Vec::new();
+        registry.dissect(&ctx, &mut layers);
+        assert_eq!(layers[1].name, LAYER_DATA);
+        assert_eq!(layers[1].fields[0].range, (43, 48));
+    }
+}
@@ -0,0 +1,886 @@
+//! SMB (MS-SMB, MS-SMB2) messages in NetBIOS session service framing
+//! (RFC 1002 section 4.3), as used on port 445 and port 139.
+//!
+//! Every message starts with a protocol magic: 0xFF "SMB" for SMB1, 0xFE
+//! "SMB" for SMB2 and SMB3, 0xFD "SMB" for an encrypted SMB3 transform and
+//! 0xFC "SMB" for a compressed one. Together with the session message header
+//! in front of it that identifies SMB on any TCP port.
+//!
+//! SMB2 bodies are read for the commands that tell who touched which file:
+//! negotiate, session setup (the NTLMSSP account), tree connect (the share),
+//! create (the file name), read, write and close.
+
+use super::registry::{Context, Dissection, Dissector, Heuristic, Transport};
+use super::{compute_sha256, detect_artifacts, ntlmssp};
+use super::{field, le_u16, le_u32, le_u64};
+use super::{LAYER_NBSS, LAYER_SMB, LAYER_SMB2};
+use crate::model::{Artifact, PacketField, ProtocolLayer};
+use std::collections::HashMap;
+
+pub const SMB_PORT: u16 = 445;
+pub const NETBIOS_SESSION_PORT: u16 = 139;
+
+const NBSS_HEADER_LEN: usize = 4;
+const NBSS_SESSION_MESSAGE: u8 = 0x00;
+const SMB2_HEADER_LEN: usize = 64;
+const SMB2_FLAGS_SERVER_TO_REDIR: u32 = 0x0000_0001;
+const SMB2_FLAGS_ASYNC_COMMAND: u32 = 0x0000_0002;
+const SMB2_MAGIC: &[u8] = &[0xfe, b'S', b'M', b'B'];
+const SMB1_FLAGS_REPLY: u8 = 0x80;
+const SMB1_COM_NEGOTIATE: u8 = 0x72;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Smb1,
+    Smb2,
+    /// SMB3 transform header: the message is encrypted
+    Encrypted,
+    /// SMB3 compression transform header
+    Compressed,
+}
+
+impl Dialect {
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0xff, b'S', b'M', b'B'] => Some(Dialect::Smb1),
+            [0xfe, b'S', b'M', b'B'] => Some(Dialect::Smb2),
+            [0xfd, b'S', b'M', b'B'] => Some(Dialect::Encrypted),
+            [0xfc, b'S', b'M', b'B'] => Some(Dialect::Compressed),
+            _ => None,
+        }
+    }
+
+    /// Protocol column text.
+    pub fn protocol(self) -> &'static str {
+        match self {
+            Dialect::Smb1 => "SMB",
+            Dialect::Smb2 => "SMB2",
+            Dialect::Encrypted | Dialect::Compressed => "SMB3",
+        }
+    }
+}
+
+/// The first SMB message of a segment and its NetBIOS session framing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message<'a> {
+    pub dialect: Dialect,
+    /// Length announced by the session message header
+    pub length: usize,
+    /// The SMB message, possibly cut short by the end of the segment
+    pub data: &'a [u8],
+}
+
+/// Splits off the session message header, if the payload starts with one
+/// framing an SMB message.
+pub fn message(payload: &[u8]) -> Option<Message<'_>> {
+    let header = payload.get(..NBSS_HEADER_LEN)?;
+    if header[0] != NBSS_SESSION_MESSAGE {
+        return None;
+    }
+    // 17-bit length in RFC 1002, 24-bit in MS-SMB2 direct TCP transport
+    let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+    let data = &payload[NBSS_HEADER_LEN..];
+    let dialect = Dialect::from_magic(data.get(..4)?)?;
+    Some(Message {
+        dialect,
+        length,
+        data: &data[..data.len().min(length)],
+    })
+}
+
+/// SMB2 command names (MS-SMB2 section 2.2.1.2).
+pub fn command_name(command: u16) -> &'static str {
+    match command {
+        0x00 => "Negotiate Protocol",
+        0x01 => "Session Setup",
+        0x02 => "Session Logoff",
+        0x03 => "Tree Connect",
+        0x04 => "Tree Disconnect",
+        0x05 => "Create",
+        0x06 => "Close",
+        0x07 => "Flush",
+        0x08 => "Read",
+        0x09 => "Write",
+        0x0a => "Lock",
+        0x0b => "Ioctl",
+        0x0c => "Cancel",
+        0x0d => "KeepAlive",
+        0x0e => "Find",
+        0x0f => "Notify",
+        0x10 => "GetInfo",
+        0x11 => "SetInfo",
+        0x12 => "Break",
+        _ => "Unknown",
+    }
+}
+
+pub const SMB2_NEGOTIATE: u16 = 0x00;
+pub const SMB2_SESSION_SETUP: u16 = 0x01;
+pub const SMB2_TREE_CONNECT: u16 = 0x03;
+pub const SMB2_CREATE: u16 = 0x05;
+pub const SMB2_CLOSE: u16 = 0x06;
+pub const SMB2_READ: u16 = 0x08;
+pub const SMB2_WRITE: u16 = 0x09;
+
+pub const STATUS_SUCCESS: u32 = 0x0000_0000;
+pub const STATUS_PENDING: u32 = 0x0000_0103;
+const STATUS_BUFFER_OVERFLOW: u32 = 0x8000_0005;
+const STATUS_MORE_PROCESSING_REQUIRED: u32 = 0xc000_0016;
+
+/// Names of the NT status codes most seen in SMB2 (MS-ERREF section 2.3).
+pub fn status_name(status: u32) -> Option<&'static str> {
+    let name = match status {
+        STATUS_SUCCESS => "STATUS_SUCCESS",
+        STATUS_PENDING => "STATUS_PENDING",
+        STATUS_BUFFER_OVERFLOW => "STATUS_BUFFER_OVERFLOW",
+        0x8000_0006 => "STATUS_NO_MORE_FILES",
+        0xc000_000d => "STATUS_INVALID_PARAMETER",
+        0xc000_000f => "STATUS_NO_SUCH_FILE",
+        0xc000_0011 => "STATUS_END_OF_FILE",
+        STATUS_MORE_PROCESSING_REQUIRED => "STATUS_MORE_PROCESSING_REQUIRED",
+        0xc000_0022 => "STATUS_ACCESS_DENIED",
+        0xc000_0034 => "STATUS_OBJECT_NAME_NOT_FOUND",
+        0xc000_0035 => "STATUS_OBJECT_NAME_COLLISION",
+        0xc000_003a => "STATUS_OBJECT_PATH_NOT_FOUND",
+        0xc000_0043 => "STATUS_SHARING_VIOLATION",
+        0xc000_0064 => "STATUS_NO_SUCH_USER",
+        0xc000_006d => "STATUS_LOGON_FAILURE",
+        0xc000_0072 => "STATUS_ACCOUNT_DISABLED",
+        0xc000_00ba => "STATUS_FILE_IS_A_DIRECTORY",
+        0xc000_00cc => "STATUS_BAD_NETWORK_NAME",
+        0xc000_0103 => "STATUS_NOT_A_DIRECTORY",
+        0xc000_0120 => "STATUS_CANCELLED",
+        0xc000_0203 => "STATUS_USER_SESSION_DELETED",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn status_text(status: u32) -> String {
+    status_name(status)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("0x{:08x}", status))
+}
+
+/// SMB2 dialect revisions (MS-SMB2 section 2.2.3).
+pub fn dialect_name(dialect: u16) -> &'static str {
+    match dialect {
+        0x0202 => "SMB 2.0.2",
+        0x0210 => "SMB 2.1",
+        0x02ff => "SMB2 wildcard",
+        0x0300 => "SMB 3.0",
+        0x0302 => "SMB 3.0.2",
+        0x0311 => "SMB 3.1.1",
+        _ => "Unknown",
+    }
+}
+
+/// Read or written file data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Data<'a> {
+    /// The data, possibly cut short by the end of the segment
+    pub bytes: &'a [u8],
+    /// Length announced by the message
+    pub length: u32,
+    /// Offset of the data within the segment payload
+    pub start: usize,
+}
+
+/// The parts of an SMB2 body used for file activity tracking. Error
+/// responses and other commands are `Other`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Body<'a> {
+    NegotiateRequest {
+        dialects: Vec<u16>,
+    },
+    NegotiateResponse {
+        dialect: u16,
+    },
+    SessionSetupRequest {
+        auth: Option<ntlmssp::Auth>,
+    },
+    TreeConnectRequest {
+        path: String,
+    },
+    CreateRequest {
+        name: String,
+    },
+    CreateResponse {
+        file_id: u128,
+        end_of_file: u64,
+    },
+    CloseRequest {
+        file_id: u128,
+    },
+    ReadRequest {
+        file_id: u128,
+        offset: u64,
+        length: u32,
+    },
+    ReadResponse {
+        data: Data<'a>,
+    },
+    WriteRequest {
+        file_id: u128,
+        offset: u64,
+        data: Data<'a>,
+    },
+    WriteResponse {
+        count: u32,
+    },
+    Other,
+}
+
+/// An SMB2 message, header and body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smb2Message<'a> {
+    pub command: u16,
+    pub status: u32,
+    pub response: bool,
+    pub message_id: u64,
+    /// Zero in async responses, which carry an async ID in its place
+    pub tree_id: u32,
+    pub session_id: u64,
+    /// Offset of the header within the segment payload
+    pub start: usize,
+    /// The message from its header on, possibly cut short by the end of the segment
+    pub data: &'a [u8],
+    pub body: Body<'a>,
+}
+
+impl Smb2Message<'_> {
+    /// Whether the status is an error rather than success or a status that
+    /// comes with a regular body.
+    pub fn is_error(&self) -> bool {
+        self.response
+            && !matches!(
+                (self.command, self.status),
+                (_, STATUS_SUCCESS)
+                    | (SMB2_SESSION_SETUP, STATUS_MORE_PROCESSING_REQUIRED)
+                    | (SMB2_READ, STATUS_BUFFER_OVERFLOW)
+            )
+    }
+
+    /// Info column text, e.g. "Create Request File: q3.xlsx".
+    pub fn info(&self) -> String {
+        let direction = if self.response { "Response" } else { "Request" };
+        let mut info = format!("{} {}", command_name(self.command), direction);
+        if self.is_error() {
+            info.push_str(&format!(", Error: {}", status_text(self.status)));
+        }
+        match &self.body {
+            Body::NegotiateResponse { dialect } => {
+                info.push_str(&format!(", Dialect: {}", dialect_name(*dialect)));
+            }
+            Body::SessionSetupRequest { auth: Some(auth) } => {
+                info.push_str(&format!(", User: {}", auth.account()));
+            }
+            Body::TreeConnectRequest { path } => info.push_str(&format!(" Tree: {}", path)),
+            Body::CreateRequest { name } => info.push_str(&format!(" File: {}", name)),
+            Body::ReadRequest { offset, length, .. } => {
+                info.push_str(&format!(" Len:{} Off:{}", length, offset));
+            }
+            Body::WriteRequest { offset, data, .. } => {
+                info.push_str(&format!(" Len:{} Off:{}", data.length, offset));
+            }
+            _ => {}
+        }
+        info
+    }
+}
+
+fn file_id_at(data: &[u8], pos: usize) -> Option<u128> {
+    Some(u128::from_be_bytes(
+        data.get(pos..pos + 16)?.try_into().ok()?,
+    ))
+}
+
+/// Text at an offset from the header and length given by the u16 pair at `pos`.
+fn utf16_at(data: &[u8], pos: usize) -> Option<String> {
+    let offset = usize::from(le_u16(data, pos)?);
+    let len = usize::from(le_u16(data, pos + 2)?);
+    if len == 0 {
+        return Some(String::new());
+    }
+    Some(ntlmssp::utf16(data.get(offset..offset + len)?))
+}
+
+/// File data at an offset from the header.
+fn data_at(data: &[u8], offset: usize, length: u32, start: usize) -> Data<'_> {
+    let bytes = data.get(offset..).unwrap_or_default();
+    Data {
+        bytes: &bytes[..bytes.len().min(length as usize)],
+        length,
+        start: start + offset,
+    }
+}
+
+fn parse_body<'a>(message: &Smb2Message<'a>) -> Option<Body<'a>> {
+    if message.is_error() {
+        return Some(Body::Other);
+    }
+    let data = message.data;
+    let at = |pos: usize| SMB2_HEADER_LEN + pos;
+    let body = match (message.command, message.response) {
+        (SMB2_NEGOTIATE, false) => {
+            let count = usize::from(le_u16(data, at(2))?);
+            let dialects = (0..count)
+                .map_while(|i| le_u16(data, at(36 + 2 * i)))
+                .collect();
+            Body::NegotiateRequest { dialects }
+        }
+        (SMB2_NEGOTIATE, true) => Body::NegotiateResponse {
+            dialect: le_u16(data, at(4))?,
+        },
+        (SMB2_SESSION_SETUP, false) => {
+            let offset = usize::from(le_u16(data, at(12))?);
+            let len = usize::from(le_u16(data, at(14))?);
+            let blob = data.get(offset..offset + len).unwrap_or_default();
+            Body::SessionSetupRequest {
+                auth: ntlmssp::auth(blob),
+            }
+        }
+        (SMB2_TREE_CONNECT, false) => Body::TreeConnectRequest {
+            path: utf16_at(data, at(4))?,
+        },
+        (SMB2_CREATE, false) => Body::CreateRequest {
+            name: utf16_at(data, at(44))?,
+        },
+        (SMB2_CREATE, true) => Body::CreateResponse {
+            end_of_file: le_u64(data, at(48))?,
+            file_id: file_id_at(data, at(64))?,
+        },
+        (SMB2_CLOSE, false) => Body::CloseRequest {
+            file_id: file_id_at(data, at(8))?,
+        },
+        (SMB2_READ, false) => Body::ReadRequest {
+            length: le_u32(data, at(4))?,
+            offset: le_u64(data, at(8))?,
+            file_id: file_id_at(data, at(16))?,
+        },
+        (SMB2_READ, true) => {
+            let offset = usize::from(*data.get(at(2))?);
+            let length = le_u32(data, at(4))?;
+            Body::ReadResponse {
+                data: data_at(data, offset, length, message.start),
+            }
+        }
+        (SMB2_WRITE, false) => {
+            let offset = usize::from(le_u16(data, at(2))?);
+            let length = le_u32(data, at(4))?;
+            Body::WriteRequest {
+                offset: le_u64(data, at(8))?,
+                file_id: file_id_at(data, at(16))?,
+                data: data_at(data, offset, length, message.start),
+            }
+        }
+        (SMB2_WRITE, true) => Body::WriteResponse {
+            count: le_u32(data, at(4))?,
+        },
+        _ => Body::Other,
+    };
+    Some(body)
+}
+
+/// Parses the SMB2 message whose header starts `data`, `start` being its
+/// offset within the segment payload.
+fn smb2_message(data: &[u8], start: usize) -> Option<Smb2Message<'_>> {
+    if data.get(..4)? != SMB2_MAGIC || data.len() < SMB2_HEADER_LEN {
+        return None;
+    }
+    let flags = le_u32(data, 16)?;
+    let mut message = Smb2Message {
+        command: le_u16(data, 12)?,
+        status: le_u32(data, 8)?,
+        response: flags & SMB2_FLAGS_SERVER_TO_REDIR != 0,
+        message_id: le_u64(data, 24)?,
+        tree_id: if flags & SMB2_FLAGS_ASYNC_COMMAND != 0 {
+            0
+        } else {
+            le_u32(data, 36)?
+        },
+        session_id: le_u64(data, 40)?,
+        start,
+        data,
+        body: Body::Other,
+    };
+    message.body = parse_body(&message).unwrap_or(Body::Other);
+    Some(message)
+}
+
+/// The SMB2 messages of a segment payload: those of every session message
+/// in it, including compounded ones (MS-SMB2 section 3.2.4.1.4).
+pub fn smb2_messages(payload: &[u8]) -> Vec<Smb2Message<'_>> {
+    let mut messages = Vec::new();
+    let mut pos = 0;
+    while let Some(nbss) = payload.get(pos..).and_then(message) {
+        if nbss.dialect == Dialect::Smb2 {
+            let base = pos + NBSS_HEADER_LEN;
+            let mut start = 0;
+            while let Some(parsed) = smb2_message(&nbss.data[start..], base + start) {
+                let next = le_u32(parsed.data, 20).unwrap_or_default() as usize;
+                messages.push(parsed);
+                if next < SMB2_HEADER_LEN || start + next >= nbss.data.len() {
+                    break;
+                }
+                start += next;
+            }
+        }
+        pos += NBSS_HEADER_LEN + nbss.length;
+    }
+    messages
+}
+
+/// Artifacts in the data of SMB2 reads and writes: a recognized file type,
+/// or the raw data otherwise. `files` names the file and offset a message
+/// read or wrote, by message ID; data cut short by the end of the segment
+/// is marked with the number of bytes captured.
+pub fn artifacts(payload: &[u8], files: &HashMap<u64, String>) -> Vec<Artifact> {
+    let mut artifacts = Vec::new();
+    for message in smb2_messages(payload) {
+        let (kind, data) = match &message.body {
+            Body::ReadResponse { data } => ("Read", data),
+            Body::WriteRequest { data, .. } => ("Write", data),
+            _ => continue,
+        };
+        if data.bytes.is_empty() {
+            continue;
+        }
+        let mut detected = detect_artifacts(data.bytes);
+        if detected.is_empty() {
+            detected.push(Artifact {
+                name: format!("SMB {} data", kind),
+                mime_type: "application/octet-stream".to_string(),
+                size: data.bytes.len(),
+                hash_sha256: compute_sha256(data.bytes),
+            });
+        }
+        for mut artifact in detected {
+            if data.bytes.len() < data.length as usize {
+                artifact.name = format!(
+                    "{}, first {} of {} bytes",
+                    artifact.name,
+                    data.bytes.len(),
+                    data.length
+                );
+            }
+            if let Some(file) = files.get(&message.message_id) {
+                artifact.name = format!("{} ({})", file, artifact.name);
+            }
+            artifacts.push(artifact);
+        }
+    }
+    artifacts
+}
+
+/// Info column text, e.g. "Tree Connect Request" or "Encrypted SMB3".
+pub fn summarize(payload: &[u8]) -> Option<(Dialect, String)> {
+    let message = message(payload)?;
+    let data = message.data;
+    let info = match message.dialect {
+        Dialect::Smb2 => {
+            let messages = smb2_messages(payload);
+            if messages.is_empty() {
+                // A header cut short by the end of the segment
+                let command = le_u16(data, 12)?;
+                let response = le_u32(data, 16)? & SMB2_FLAGS_SERVER_TO_REDIR != 0;
+                let direction = if response { "Response" } else { "Request" };
+                format!("{} {}", command_name(command), direction)
+            } else {
+                messages
+                    .iter()
+                    .map(Smb2Message::info)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            }
+        }
+        Dialect::Smb1 => {
+            let command = *data.get(4)?;
+            let response = data.get(9)? & SMB1_FLAGS_REPLY != 0;
+            let direction = if response { "Response" } else { "Request" };
+            match command {
+                SMB1_COM_NEGOTIATE => format!("Negotiate Protocol {}", direction),
+                _ => format!("Command 0x{:02x} {}", command, direction),
+            }
+        }
+        Dialect::Encrypted => "Encrypted SMB3".to_string(),
+        Dialect::Compressed => "Compressed SMB3".to_string(),
+    };
+    Some((message.dialect, info))
+}
+
+fn file_id_text(file_id: u128) -> String {
+    format!("{:032x}", file_id)
+}
+
+/// Fields of an SMB2 message body, `base` being where its header starts.
+fn smb2_body_fields(message: &Smb2Message, base: usize) -> Vec<PacketField> {
+    let at = |pos: usize, len: usize| {
+        (
+            base + SMB2_HEADER_LEN + pos,
+            base + SMB2_HEADER_LEN + pos + len,
+        )
+    };
+    let data = message.data;
+    let text_range = |pos: usize| {
+        let offset = usize::from(le_u16(data, SMB2_HEADER_LEN + pos).unwrap_or_default());
+        let len = usize::from(le_u16(data, SMB2_HEADER_LEN + pos + 2).unwrap_or_default());
+        (base + offset, base + offset + len)
+    };
+    let data_range = |data: &Data| {
+        let start = data.start - message.start + base;
+        (start, start + data.bytes.len())
+    };
+    let dialect = |dialect: u16| format!("0x{:04x} ({})", dialect, dialect_name(dialect));
+    match &message.body {
+        Body::NegotiateRequest { dialects } => dialects
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| field("Dialect", dialect(d), at(36 + 2 * i, 2)))
+            .collect(),
+        Body::NegotiateResponse { dialect: d } => vec![field("Dialect", dialect(*d), at(4, 2))],
+        Body::TreeConnectRequest { path } => vec![field("Tree", path.clone(), text_range(4))],
+        Body::CreateRequest { name } => vec![field("Filename", name.clone(), text_range(44))],
+        Body::CreateResponse {
+            file_id,
+            end_of_file,
+        } => vec![
+            field("End Of File", end_of_file.to_string(), at(48, 8)),
+            field("File Id", file_id_text(*file_id), at(64, 16)),
+        ],
+        Body::CloseRequest { file_id } => vec![field("File Id", file_id_text(*file_id), at(8, 16))],
+        Body::ReadRequest {
+            file_id,
+            offset,
+            length,
+        } => vec![
+            field("Read Length", length.to_string(), at(4, 4)),
+            field("File Offset", offset.to_string(), at(8, 8)),
+            field("File Id", file_id_text(*file_id), at(16, 16)),
+        ],
+        Body::ReadResponse { data } => vec![
+            field("Data Length", data.length.to_string(), at(4, 4)),
+            field(
+                "File Data",
+                format!("{} bytes", data.bytes.len()),
+                data_range(data),
+            ),
+        ],
+        Body::WriteRequest {
+            file_id,
+            offset,
+            data,
+        } => vec![
+            field("Write Length", data.length.to_string(), at(4, 4)),
+            field("File Offset", offset.to_string(), at(8, 8)),
+            field("File Id", file_id_text(*file_id), at(16, 16)),
+            field(
+                "File Data",
+                format!("{} bytes", data.bytes.len()),
+                data_range(data),
+            ),
+        ],
+        Body::WriteResponse { count } => vec![field("Count", count.to_string(), at(4, 4))],
+        Body::SessionSetupRequest { .. } | Body::Other => Vec::new(),
+    }
+}
+
+/// Fields of an SMB2 header starting at `base`.
+fn smb2_header_fields(data: &[u8], base: usize) -> Vec<PacketField> {
+    let mut fields = vec![field(
+        "Protocol Id",
+        "0xfe534d42".to_string(),
+        (base, base + 4),
+    )];
+    if data.len() < SMB2_HEADER_LEN {
+        return fields;
+    }
+    let at = |pos: usize, len: usize| (base + pos, base + pos + len);
+    let command = le_u16(data, 12).unwrap_or_default();
+    let flags = le_u32(data, 16).unwrap_or_default();
+    fields.push(field(
+        "Command",
+        format!("{} ({})", command, command_name(command)),
+        at(12, 2),
+    ));
+    let status = le_u32(data, 8).unwrap_or_default();
+    let mut status_field = field("NT Status", format!("0x{:08x}", status), at(8, 4));
+    if let Some(name) = status_name(status) {
+        status_field.value = format!("0x{:08x} ({})", status, name);
+    }
+    fields.push(status_field);
+    fields.push(field("Flags", format!("0x{:08x}", flags), at(16, 4)));
+    fields.push(field(
+        "Response",
+        u8::from(flags & SMB2_FLAGS_SERVER_TO_REDIR != 0).to_string(),
+        at(16, 4),
+    ));
+    fields.push(field(
+        "Message ID",
+        le_u64(data, 24).unwrap_or_default().to_string(),
+        at(24, 8),
+    ));
+    fields.push(field(
+        "Tree Id",
+        format!("0x{:08x}", le_u32(data, 36).unwrap_or_default()),
+        at(36, 4),
+    ));
+    fields.push(field(
+        "Session Id",
+        format!("0x{:016x}", le_u64(data, 40).unwrap_or_default()),
+        at(40, 8),
+    ));
+    fields
+}
+
+/// Builds the detail view layers for a payload starting at `offset`.
+pub fn layers(payload: &[u8], offset: usize) -> Option<Vec<ProtocolLayer>> {
+    let message = message(payload)?;
+    let nbss = ProtocolLayer {
+        name: LAYER_NBSS.to_string(),
+        fields: vec![
+            field(
+                "Message Type",
+                "Session message".to_string(),
+                (offset, offset + 1),
+            ),
+            field(
+                "Length",
+                format!("{} bytes", message.length),
+                (offset + 1, offset + NBSS_HEADER_LEN),
+            ),
+        ],
+    };
+    let base = offset + NBSS_HEADER_LEN;
+    let smb = match message.dialect {
+        Dialect::Smb2 => {
+            let messages = smb2_messages(payload);
+            if messages.is_empty() {
+                ProtocolLayer {
+                    name: LAYER_SMB2.to_string(),
+                    fields: smb2_header_fields(message.data, base),
+                }
+            } else {
+                let mut layers = vec![nbss];
+                for message in &messages {
+                    let base = offset + message.start;
+                    let mut fields = smb2_header_fields(message.data, base);
+                    fields.extend(smb2_body_fields(message, base));
+                    layers.push(ProtocolLayer {
+                        name: LAYER_SMB2.to_string(),
+                        fields,
+                    });
+                    if let Body::SessionSetupRequest { auth: Some(auth) } = &message.body {
+                        let blob_offset =
+                            le_u16(message.data, SMB2_HEADER_LEN + 12).map_or(0, usize::from);
+                        layers.push(ntlmssp::layer(auth, base + blob_offset));
+                    }
+                }
+                return Some(layers);
+            }
+        }
+        Dialect::Smb1 => ProtocolLayer {
+            name: LAYER_SMB.to_string(),
+            fields: vec![field(
+                "SMB Command",
+                format!("0x{:02x}", message.data.get(4).copied().unwrap_or_default()),
+                (base + 4, base + 5),
+            )],
+        },
+        Dialect::Encrypted | Dialect::Compressed => ProtocolLayer {
+            name: LAYER_SMB2.to_string(),
+            fields: vec![field(
+                "Transform",
+                summarize(payload).map(|(_, info)| info).unwrap_or_default(),
+                (base, base + message.data.len()),
+            )],
+        },
+    };
+    Some(vec![nbss, smb])
+}
+
+/// SMB over TCP on ports 445 and 139, and by its framing on any other port.
+pub struct Smb;
+
+impl Dissector for Smb {
+    fn name(&self) -> &'static str {
+        "smb"
+    }
+
+    fn description(&self) -> &'static str {
+        "Server Message Block"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[
+            (Transport::Tcp, SMB_PORT),
+            (Transport::Tcp, NETBIOS_SESSION_PORT),
+        ]
+    }
+
+    fn heuristic(&self, transport: Transport) -> Option<Heuristic> {
+        (transport == Transport::Tcp).then_some(Heuristic::First)
+    }
+
+    fn recognizes(&self, ctx: &Context) -> bool {
+        message(ctx.payload).is_some()
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        summarize(ctx.payload).map(|(dialect, info)| (dialect.protocol().to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        layers(ctx.payload, ctx.offset).map(|layers| Dissection { layers, next: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framing_and_summary() {
+        let mut smb2 = vec![0x00, 0x00, 0x00, 0x40, 0xfe, b'S', b'M', b'B'];
+        smb2.resize(4 + SMB2_HEADER_LEN, 0);
+        smb2[4 + 12] = 0x03; // Tree Connect
+        smb2[4 + 16] = 0x01; // Server to redirector
+        assert_eq!(
+            summarize(&smb2),
+            Some((Dialect::Smb2, "Tree Connect Response".to_string()))
+        );
+        let layers = layers(&smb2, 54).unwrap();
+        assert_eq!(layers[0].fields[1].value, "64 bytes");
+        assert_eq!(layers[1].fields[1].value, "3 (Tree Connect)");
+        assert_eq!(layers[1].fields[1].range, (70, 72));
+
+        let smb1 = [
+            0x00, 0x00, 0x00, 0x23, 0xff, b'S', b'M', b'B', 0x72, 0, 0, 0, 0, 0x18,
+        ];
+        assert_eq!(
+            summarize(&smb1),
+            Some((Dialect::Smb1, "Negotiate Protocol Request".to_string()))
+        );
+        // A session keepalive carries no SMB message
+        assert!(message(&[0x85, 0x00, 0x00, 0x00]).is_none());
+        assert!(message(b"\x00\x00\x00\x08GET / HT").is_none());
+    }
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// An SMB2 message with `body`, without session message framing.
+    fn smb2(command: u16, response: bool, status: u32, body: &[u8]) -> Vec<u8> {
+        let mut data = SMB2_MAGIC.to_vec();
+        data.resize(SMB2_HEADER_LEN, 0);
+        data[4] = 64; // StructureSize
+        data[8..12].copy_from_slice(&status.to_le_bytes());
+        data[12..14].copy_from_slice(&command.to_le_bytes());
+        data[16] = u8::from(response);
+        data[24] = 7; // MessageId
+        data[36] = 1; // TreeId
+        data[40] = 0x41; // SessionId
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn framed(messages: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for message in messages {
+            payload.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            payload.extend_from_slice(message);
+        }
+        payload
+    }
+
+    #[test]
+    fn test_file_operations() {
+        // Tree connect request, path right after the 8-byte body
+        let path = utf16le("\\\\fs01\\finance");
+        let mut body = vec![9, 0, 0, 0, 72, 0];
+        body.extend_from_slice(&(path.len() as u16).to_le_bytes());
+        body.extend_from_slice(&path);
+        let payload = framed(&[smb2(SMB2_TREE_CONNECT, false, 0, &body)]);
+        assert_eq!(
+            summarize(&payload).unwrap().1,
+            "Tree Connect Request Tree: \\\\fs01\\finance"
+        );
+        let layers = layers(&payload, 54).unwrap();
+        let tree = layers[1].fields.iter().find(|f| f.name == "Tree").unwrap();
+        assert_eq!(tree.range, (54 + 4 + 72, 54 + 4 + 72 + path.len()));
+
+        // Create request for a file, then a write of a PDF to it
+        let name = utf16le("q3\\report.pdf");
+        let mut body = vec![0u8; 56];
+        body[0] = 57;
+        body[44..46].copy_from_slice(&120u16.to_le_bytes());
+        body[46..48].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        body.extend_from_slice(&name);
+        let create = smb2(SMB2_CREATE, false, 0, &body);
+        let mut body = vec![0u8; 48];
+        body[0] = 49;
+        body[2..4].copy_from_slice(&112u16.to_le_bytes());
+        body[4..8].copy_from_slice(&9u32.to_le_bytes());
+        body[8..16].copy_from_slice(&4096u64.to_le_bytes());
+        body[16..32].copy_from_slice(&[0x11; 16]);
+        body.extend_from_slice(b"%PDF-1.7\n");
+        let write = smb2(SMB2_WRITE, false, 0, &body);
+        let payload = framed(&[create, write]);
+        let messages = smb2_messages(&payload);
+        assert_eq!(
+            messages.iter().map(Smb2Message::info).collect::<Vec<_>>(),
+            [
+                "Create Request File: q3\\report.pdf",
+                "Write Request Len:9 Off:4096"
+            ]
+        );
+        assert_eq!(messages[1].session_id, 0x41);
+        let Body::WriteRequest { file_id, data, .. } = &messages[1].body else {
+            panic!("expected a write request");
+        };
+        assert_eq!(*file_id, u128::from_be_bytes([0x11; 16]));
+        assert_eq!(&payload[data.start..data.start + 9], b"%PDF-1.7\n");
+        let files = HashMap::from([(7, "q3\\report.pdf at offset 4096".to_string())]);
+        let artifacts = artifacts(&payload, &files);
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].mime_type, "application/pdf");
+        assert_eq!(
+            artifacts[0].name,
+            "q3\\report.pdf at offset 4096 (Document.pdf)"
+        );
+
+        // Data cut short by the end of the segment is marked as such
+        body[4..8].copy_from_slice(&1460u32.to_le_bytes());
+        let payload = framed(&[smb2(SMB2_WRITE, false, 0, &body)]);
+        let partial = super::artifacts(&payload, &HashMap::new());
+        assert_eq!(partial[0].name, "Document.pdf, first 9 of 1460 bytes");
+        assert_eq!(partial[0].size, 9);
+
+        // Error responses carry no body worth reading
+        let payload = framed(&[smb2(SMB2_CREATE, true, 0xc000_0022, &[9, 0, 0, 0])]);
+        assert_eq!(
+            summarize(&payload).unwrap().1,
+            "Create Response, Error: STATUS_ACCESS_DENIED"
+        );
+        assert_eq!(smb2_messages(&payload)[0].body, Body::Other);
+    }
+
+    #[test]
+    fn test_session_setup_account() {
+        let blob = ntlmssp::tests::authenticate("CORP", "alice", "WS01");
+        let mut body = vec![0u8; 24];
+        body[0] = 25;
+        body[12..14].copy_from_slice(&88u16.to_le_bytes());
+        body[14..16].copy_from_slice(&(blob.len() as u16).to_le_bytes());
+        body.extend_from_slice(&blob);
+        let payload = framed(&[smb2(SMB2_SESSION_SETUP, false, 0, &body)]);
+        assert_eq!(
+            summarize(&payload).unwrap().1,
+            "Session Setup Request, User: CORP\\alice"
+        );
+        let layers = layers(&payload, 0).unwrap();
+        assert_eq!(layers[2].name, crate::dissector::LAYER_NTLMSSP);
+        assert_eq!(layers[2].fields[2].value, "alice");
+    }
+}
@@ -0,0 +1,658 @@
+//! SSH (RFC 4253) protocol version exchange and binary packets.
+//!
+//! Both sides open with an identification string,
+//! "SSH-protoversion-softwareversion SP comments CR LF", which identifies SSH
+//! on any TCP port. A server may send other lines of text before it (section 4.2).
+//!
+//! Binary packets (section 6) are sent in the clear until each side's NEWKEYS.
+//! Cleartext packets are padded to a multiple of 8 bytes and carry a
+//! transport or key exchange message; once encrypted, the length and message
+//! code are hidden or padded to the cipher block size, so payload bytes that
+//! do not frame such a packet are taken to be encrypted. The KEXINIT messages
+//! give the HASSH and HASSHServer fingerprints (Salesforce).
+
+use super::field;
+use super::fingerprint;
+use super::registry::{Context, Dissection, Dissector, Heuristic, Transport};
+use super::LAYER_SSH;
+use crate::model::{PacketField, SshFingerprints};
+
+pub const SSH_PORT: u16 = 22;
+
+/// Longest identification string, CR LF included (RFC 4253 section 4.2)
+const MAX_BANNER_LEN: usize = 255;
+/// Lines a server may send before its identification string that are looked past
+const MAX_PRELUDE_LINES: usize = 8;
+/// Longest binary packet to accept, length field included (section 6.1)
+const MAX_PACKET_LEN: usize = 35000;
+/// Smallest packet_length of a cleartext packet: padding length, message code
+/// and at least 4 bytes of padding, rounded up to the block size
+const MIN_PACKET_LEN: usize = 12;
+const MIN_PADDING_LEN: u8 = 4;
+/// Block size cleartext packets are padded to (section 6)
+const CLEARTEXT_BLOCK_SIZE: usize = 8;
+/// packet_length, padding_length and the message code
+const PACKET_HEADER_LEN: usize = 6;
+const COOKIE_LEN: usize = 16;
+
+pub const MSG_KEXINIT: u8 = 20;
+pub const MSG_NEWKEYS: u8 = 21;
+
+/// Names of the KEXINIT algorithm name-lists, in order (section 7.1).
+const NAME_LISTS: [&str; 10] = [
+    "Key Exchange Algorithms",
+    "Server Host Key Algorithms",
+    "Encryption Algorithms (client to server)",
+    "Encryption Algorithms (server to client)",
+    "MAC Algorithms (client to server)",
+    "MAC Algorithms (server to client)",
+    "Compression Algorithms (client to server)",
+    "Compression Algorithms (server to client)",
+    "Languages (client to server)",
+    "Languages (server to client)",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Banner {
+    pub proto_version: String,
+    pub software_version: String,
+    pub comments: Option<String>,
+    /// Byte range of the identification string within the payload, without CR LF
+    pub range: (usize, usize),
+}
+
+impl Banner {
+    /// The identification string as sent, e.g. "SSH-2.0-OpenSSH_9.6 Ubuntu-3".
+    pub fn text(&self) -> String {
+        let mut text = format!("SSH-{}-{}", self.proto_version, self.software_version);
+        if let Some(comments) = &self.comments {
+            text.push(' ');
+            text.push_str(comments);
+        }
+        text
+    }
+}
+
+fn parse_line(line: &[u8], start: usize) -> Option<Banner> {
+    let text = std::str::from_utf8(line.strip_prefix(b"SSH-")?).ok()?;
+    if text.chars().any(|c| c.is_ascii_control()) {
+        return None;
+    }
+    let (proto_version, rest) = text.split_once('-')?;
+    let (software_version, comments) = match rest.split_once(' ') {
+        Some((software, comments)) => (software, Some(comments.to_string())),
+        None => (rest, None),
+    };
+    // "1.99" is a server that speaks both 1.x and 2.0 (RFC 4253 section 5.1)
+    let known_version = proto_version == "2.0" || proto_version.starts_with("1.");
+    if !known_version || software_version.is_empty() {
+        return None;
+    }
+    Some(Banner {
+        proto_version: proto_version.to_string(),
+        software_version: software_version.to_string(),
+        comments,
+        range: (start, start + line.len()),
+    })
+}
+
+/// Finds the identification string at the start of a payload, past any
+/// lines of text a server sends first.
+pub fn banner(payload: &[u8]) -> Option<Banner> {
+    let mut start = 0;
+    for _ in 0..=MAX_PRELUDE_LINES {
+        let rest = &payload[start..];
+        let end = rest
+            .iter()
+            .take(MAX_BANNER_LEN)
+            .position(|&b| b == b'\n')
+            .unwrap_or(rest.len().min(MAX_BANNER_LEN));
+        let line = &rest[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.starts_with(b"SSH-") {
+            return parse_line(line, start);
+        }
+        // Only text lines may come first
+        if end == rest.len() || line.iter().any(|&b| b.is_ascii_control() && b != b'\t') {
+            return None;
+        }
+        start += end + 1;
+    }
+    None
+}
+
+/// Names of the messages sent before encryption starts (RFC 4250 section
+/// 4.1.2). Codes 30 to 49 belong to the negotiated key exchange method.
+pub fn message_name(code: u8) -> Option<&'static str> {
+    let name = match code {
+        1 => "Disconnect",
+        2 => "Ignore",
+        3 => "Unimplemented",
+        4 => "Debug",
+        MSG_KEXINIT => "Key Exchange Init",
+        MSG_NEWKEYS => "New Keys",
+        30 => "Diffie-Hellman Key Exchange Init",
+        31 => "Diffie-Hellman Key Exchange Reply",
+        32 => "Diffie-Hellman Group Exchange Init",
+        33 => "Diffie-Hellman Group Exchange Reply",
+        34 => "Diffie-Hellman Group Exchange Request",
+        35..=49 => "Key Exchange",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// A cleartext binary packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet<'a> {
+    pub packet_length: usize,
+    pub padding_length: u8,
+    pub msg_code: u8,
+    /// Message after the code, possibly cut short by the end of the segment
+    pub payload: &'a [u8],
+    /// Byte range of the packet within the segment payload
+    pub range: (usize, usize),
+}
+
+impl Packet<'_> {
+    pub fn name(&self) -> &'static str {
+        message_name(self.msg_code).unwrap_or("Unknown")
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.range.1 - self.range.0 == 4 + self.packet_length
+    }
+
+    /// The algorithm lists of a complete KEXINIT.
+    pub fn kex_init(&self) -> Option<KexInit> {
+        if self.msg_code != MSG_KEXINIT || !self.is_complete() {
+            return None;
+        }
+        kex_init(self.payload)
+    }
+}
+
+fn parse_packet(payload: &[u8], start: usize) -> Option<Packet<'_>> {
+    let rest = &payload[start..];
+    let header = rest.get(..PACKET_HEADER_LEN)?;
+    let packet_length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let padding_length = header[4];
+    let msg_code = header[5];
+    let valid = (MIN_PACKET_LEN..=MAX_PACKET_LEN).contains(&packet_length)
+        && (4 + packet_length).is_multiple_of(CLEARTEXT_BLOCK_SIZE)
+        && padding_length >= MIN_PADDING_LEN
+        && usize::from(padding_length) + 1 < packet_length
+        && message_name(msg_code).is_some();
+    if !valid {
+        return None;
+    }
+    let end = rest.len().min(4 + packet_length);
+    let payload_end = end.min(4 + packet_length - usize::from(padding_length));
+    Some(Packet {
+        packet_length,
+        padding_length,
+        msg_code,
+        payload: &rest[PACKET_HEADER_LEN..payload_end],
+        range: (start, start + end),
+    })
+}
+
+/// A KEXINIT algorithm name-list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameList {
+    pub value: String,
+    /// Byte range of the list within the KEXINIT message, after the code
+    pub range: (usize, usize),
+}
+
+/// KEXINIT message (section 7.1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KexInit {
+    /// The ten name-lists, in the order of `NAME_LISTS`
+    pub name_lists: Vec<NameList>,
+    pub first_kex_packet_follows: bool,
+}
+
+impl KexInit {
+    fn joined(&self, lists: [usize; 4]) -> String {
+        lists
+            .iter()
+            .map(|&i| self.name_lists[i].value.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// HASSH input: the key exchange, client to server encryption, MAC and
+    /// compression algorithms, separated by ";".
+    pub fn hassh_algorithms(&self) -> String {
+        self.joined([0, 2, 4, 6])
+    }
+
+    /// HASSHServer input: as for HASSH, with the server to client lists.
+    pub fn hassh_server_algorithms(&self) -> String {
+        self.joined([0, 3, 5, 7])
+    }
+}
+
+fn kex_init(message: &[u8]) -> Option<KexInit> {
+    message.get(..COOKIE_LEN)?;
+    let mut pos = COOKIE_LEN;
+    let mut name_lists = Vec::with_capacity(NAME_LISTS.len());
+    for _ in NAME_LISTS {
+        let len = u32::from_be_bytes(message.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let start = pos + 4;
+        let value = std::str::from_utf8(message.get(start..start + len)?).ok()?;
+        name_lists.push(NameList {
+            value: value.to_string(),
+            range: (start, start + len),
+        });
+        pos = start + len;
+    }
+    Some(KexInit {
+        name_lists,
+        first_kex_packet_follows: *message.get(pos)? != 0,
+    })
+}
+
+/// The SSH content of a segment payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment<'a> {
+    pub banner: Option<Banner>,
+    pub packets: Vec<Packet<'a>>,
+    /// Byte range of what follows the cleartext part, taken to be encrypted
+    pub encrypted: (usize, usize),
+}
+
+/// Splits a segment payload into an identification string, cleartext binary
+/// packets and encrypted data, each of which may be missing.
+pub fn segment(payload: &[u8]) -> Segment<'_> {
+    let banner = banner(payload);
+    let mut start = banner.as_ref().map_or(0, |banner| {
+        payload[banner.range.1..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(payload.len(), |end| banner.range.1 + end + 1)
+    });
+    let mut packets = Vec::new();
+    while let Some(packet) = parse_packet(payload, start) {
+        start = packet.range.1;
+        let complete = packet.is_complete();
+        packets.push(packet);
+        if !complete {
+            break;
+        }
+    }
+    Segment {
+        banner,
+        packets,
+        encrypted: (start, payload.len()),
+    }
+}
+
+/// Whether the payload was sent by the server: from port 22, or else from
+/// the lower port.
+pub fn is_server(src_port: u16, dst_port: u16) -> bool {
+    src_port == SSH_PORT || (dst_port != SSH_PORT && src_port < dst_port)
+}
+
+/// Whether a payload holds SSH: an identification string, or any payload in
+/// a flow on port 22 or learned to be SSH.
+fn is_ssh(ctx: &Context, segment: &Segment) -> bool {
+    segment.banner.is_some() || (!ctx.payload.is_empty() && ctx.expects("ssh", SSH_PORT))
+}
+
+/// The identification string, HASSH or HASSHServer and end of the cleartext
+/// phase seen in a payload.
+pub fn fingerprints(ctx: &Context) -> SshFingerprints {
+    let segment = segment(ctx.payload);
+    let mut fingerprints = SshFingerprints::default();
+    if !is_ssh(ctx, &segment) {
+        return fingerprints;
+    }
+    let server = is_server(ctx.src_port, ctx.dst_port);
+    if let Some(banner) = &segment.banner {
+        let text = Some(banner.text());
+        if server {
+            fingerprints.server_banner = text;
+        } else {
+            fingerprints.client_banner = text;
+        }
+    }
+    for packet in &segment.packets {
+        if let Some(kex_init) = packet.kex_init() {
+            if server {
+                fingerprints.hassh_server = Some(fingerprint::hassh_server(&kex_init));
+            } else {
+                fingerprints.hassh = Some(fingerprint::hassh(&kex_init));
+            }
+        }
+        fingerprints.encrypted |= packet.msg_code == MSG_NEWKEYS;
+    }
+    fingerprints
+}
+
+/// Builds the detail view fields for an identification string, `offset` being
+/// where the payload starts.
+pub fn banner_fields(banner: &Banner, offset: usize) -> Vec<PacketField> {
+    let range = (offset + banner.range.0, offset + banner.range.1);
+    let mut fields = vec![
+        field("Protocol", banner.text(), range),
+        field("Protocol Version", banner.proto_version.clone(), range),
+        field("Software Version", banner.software_version.clone(), range),
+    ];
+    if let Some(comments) = &banner.comments {
+        fields.push(field("Comments", comments.clone(), range));
+    }
+    fields
+}
+
+fn kex_init_fields(
+    kex_init: &KexInit,
+    packet: &Packet,
+    base: usize,
+    from_server: bool,
+) -> Vec<PacketField> {
+    let mut fields = vec![field(
+        "Cookie",
+        packet.payload[..COOKIE_LEN]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+        (base, base + COOKIE_LEN),
+    )];
+    for (name, list) in NAME_LISTS.iter().zip(&kex_init.name_lists) {
+        fields.push(field(
+            name,
+            list.value.clone(),
+            (base + list.range.0, base + list.range.1),
+        ));
+    }
+    let end = base
+        + kex_init
+            .name_lists
+            .last()
+            .map_or(COOKIE_LEN, |list| list.range.1);
+    fields.push(field(
+        "First KEX Packet Follows",
+        u8::from(kex_init.first_kex_packet_follows).to_string(),
+        (end, end + 1),
+    ));
+    let range = (base, base + packet.payload.len());
+    if from_server {
+        fields.push(field(
+            "HASSHServer Algorithms",
+            kex_init.hassh_server_algorithms(),
+            range,
+        ));
+        fields.push(field(
+            "HASSHServer",
+            fingerprint::hassh_server(kex_init),
+            range,
+        ));
+    } else {
+        fields.push(field(
+            "HASSH Algorithms",
+            kex_init.hassh_algorithms(),
+            range,
+        ));
+        fields.push(field("HASSH", fingerprint::hassh(kex_init), range));
+    }
+    fields
+}
+
+/// Builds the detail view fields for a segment, `offset` being where the
+/// payload starts.
+pub fn segment_fields(segment: &Segment, offset: usize, from_server: bool) -> Vec<PacketField> {
+    let mut fields = segment
+        .banner
+        .as_ref()
+        .map(|banner| banner_fields(banner, offset))
+        .unwrap_or_default();
+    for packet in &segment.packets {
+        let start = offset + packet.range.0;
+        fields.push(field(
+            "Packet Length",
+            packet.packet_length.to_string(),
+            (start, start + 4),
+        ));
+        fields.push(field(
+            "Padding Length",
+            packet.padding_length.to_string(),
+            (start + 4, start + 5),
+        ));
+        fields.push(field(
+            "Message Code",
+            format!("{} ({})", packet.msg_code, packet.name()),
+            (start + 5, start + 6),
+        ));
+        if let Some(kex_init) = packet.kex_init() {
+            fields.extend(kex_init_fields(
+                &kex_init,
+                packet,
+                start + PACKET_HEADER_LEN,
+                from_server,
+            ));
+        }
+    }
+    let (start, end) = segment.encrypted;
+    if end > start {
+        fields.push(field(
+            "Encrypted Packet",
+            format!("{} bytes", end - start),
+            (offset + start, offset + end),
+        ));
+    }
+    fields
+}
+
+/// Which side of the connection sent the payload, when the port tells.
+fn direction(ctx: &Context) -> &'static str {
+    if ctx.dst_port == SSH_PORT {
+        "Client: "
+    } else if ctx.src_port == SSH_PORT {
+        "Server: "
+    } else {
+        ""
+    }
+}
+
+/// SSH on port 22, and by its identification string on any other port.
+pub struct Ssh;
+
+impl Dissector for Ssh {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn description(&self) -> &'static str {
+        "Secure Shell"
+    }
+
+    fn ports(&self) -> &[(Transport, u16)] {
+        &[(Transport::Tcp, SSH_PORT)]
+    }
+
+    fn heuristic(&self, transport: Transport) -> Option<Heuristic> {
+        (transport == Transport::Tcp).then_some(Heuristic::First)
+    }
+
+    fn recognizes(&self, ctx: &Context) -> bool {
+        banner(ctx.payload).is_some()
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        let segment = segment(ctx.payload);
+        // Bare handshake and ACK segments say more as TCP info
+        if !is_ssh(ctx, &segment) {
+            return None;
+        }
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(banner) = &segment.banner {
+            parts.push(format!("Protocol ({})", banner.text()));
+        }
+        parts.extend(
+            segment
+                .packets
+                .iter()
+                .map(|packet| packet.name().to_string()),
+        );
+        let (start, end) = segment.encrypted;
+        if end > start {
+            parts.push(format!("Encrypted packet (len={})", end - start));
+        }
+        let info = format!("{}{}", direction(ctx), parts.join(", "));
+        Some(("SSH".to_string(), info))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        let segment = segment(ctx.payload);
+        if !is_ssh(ctx, &segment) {
+            return None;
+        }
+        let from_server = is_server(ctx.src_port, ctx.dst_port);
+        Some(Dissection::layer(
+            LAYER_SSH,
+            segment_fields(&segment, ctx.offset, from_server),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner() {
+        let banner = banner(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n").unwrap();
+        assert_eq!(banner.proto_version, "2.0");
+        assert_eq!(banner.software_version, "OpenSSH_9.6p1");
+        assert_eq!(banner.comments.as_deref(), Some("Ubuntu-3ubuntu13"));
+        assert_eq!(banner.range, (0, 38));
+        assert_eq!(banner.text(), "SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13");
+
+        // Servers may greet with other lines first
+        let banner = super::banner(b"Welcome\r\nSSH-1.99-Cisco-1.25\r\n").unwrap();
+        assert_eq!(banner.software_version, "Cisco-1.25");
+        assert_eq!(banner.range, (9, 28));
+
+        assert!(super::banner(b"SSH-3.0-Future\r\n").is_none());
+        assert!(super::banner(b"GET / HTTP/1.1\r\n\r\n").is_none());
+        assert!(super::banner(b"\x00\x00\x01\x0c\x0aSSH-2.0-x\r\n").is_none());
+    }
+
+    /// A binary packet with `msg` padded to a multiple of 8 bytes.
+    fn packet(msg: &[u8]) -> Vec<u8> {
+        let mut padding = CLEARTEXT_BLOCK_SIZE - (5 + msg.len()) % CLEARTEXT_BLOCK_SIZE;
+        if padding < 4 {
+            padding += CLEARTEXT_BLOCK_SIZE;
+        }
+        let mut data = ((1 + msg.len() + padding) as u32).to_be_bytes().to_vec();
+        data.push(padding as u8);
+        data.extend_from_slice(msg);
+        data.resize(data.len() + padding, 0);
+        data
+    }
+
+    fn kex_init_message(lists: [&str; 10]) -> Vec<u8> {
+        let mut msg = vec![MSG_KEXINIT];
+        msg.extend_from_slice(&[0xab; COOKIE_LEN]);
+        for list in lists {
+            msg.extend_from_slice(&(list.len() as u32).to_be_bytes());
+            msg.extend_from_slice(list.as_bytes());
+        }
+        msg.extend_from_slice(&[0, 0, 0, 0, 0]);
+        msg
+    }
+
+    fn context(payload: &[u8], src_port: u16, dst_port: u16) -> Context<'_> {
+        Context {
+            transport: Transport::Tcp,
+            src_port,
+            dst_port,
+            payload,
+            offset: 66,
+            transport_info: "",
+            parent: None,
+            learned: None,
+        }
+    }
+
+    #[test]
+    fn test_kex_init_and_hassh() {
+        let lists = [
+            "curve25519-sha256,ext-info-c",
+            "ssh-ed25519",
+            "chacha20-poly1305@openssh.com",
+            "aes128-ctr",
+            "umac-64-etm@openssh.com",
+            "hmac-sha2-256",
+            "none,zlib@openssh.com",
+            "none",
+            "",
+            "",
+        ];
+        let mut payload = b"SSH-2.0-OpenSSH_9.6\r\n".to_vec();
+        payload.extend(packet(&kex_init_message(lists)));
+        let segment = segment(&payload);
+        assert_eq!(segment.packets.len(), 1);
+        assert_eq!(segment.encrypted, (payload.len(), payload.len()));
+        let kex_init = segment.packets[0].kex_init().unwrap();
+        assert_eq!(
+            kex_init.hassh_algorithms(),
+            "curve25519-sha256,ext-info-c;chacha20-poly1305@openssh.com;umac-64-etm@openssh.com;none,zlib@openssh.com"
+        );
+        assert_eq!(
+            kex_init.hassh_server_algorithms(),
+            "curve25519-sha256,ext-info-c;aes128-ctr;hmac-sha2-256;none"
+        );
+
+        let ctx = context(&payload, 50022, SSH_PORT);
+        assert_eq!(
+            Ssh.summarize(&ctx).unwrap().1,
+            "Client: Protocol (SSH-2.0-OpenSSH_9.6), Key Exchange Init"
+        );
+        let fingerprints = fingerprints(&ctx);
+        assert_eq!(
+            fingerprints.client_banner.as_deref(),
+            Some("SSH-2.0-OpenSSH_9.6")
+        );
+        assert_eq!(fingerprints.hassh, Some(fingerprint::hassh(&kex_init)));
+        assert!(fingerprints.hassh_server.is_none() && !fingerprints.encrypted);
+
+        let fields = Ssh.dissect(&ctx).unwrap().layers.remove(0).fields;
+        let value = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(value("Message Code").value, "20 (Key Exchange Init)");
+        assert_eq!(value("Server Host Key Algorithms").value, "ssh-ed25519");
+        // Identification string (21 bytes), header (6) and cookie (16), then the first list
+        assert_eq!(value("Key Exchange Algorithms").range, (66 + 47, 66 + 75));
+        assert_eq!(value("HASSH").value, fingerprint::hassh(&kex_init));
+    }
+
+    #[test]
+    fn test_cleartext_and_encrypted_phases() {
+        // NEWKEYS followed by the first encrypted packet in the same segment
+        let mut payload = packet(&[MSG_NEWKEYS]);
+        let newkeys_len = payload.len();
+        payload.extend_from_slice(&[0x5c, 0x1e, 0x9a, 0x03, 0x77, 0xd0, 0x42, 0x18, 0xee]);
+        let ctx = context(&payload, SSH_PORT, 50022);
+        assert_eq!(
+            Ssh.summarize(&ctx).unwrap().1,
+            "Server: New Keys, Encrypted packet (len=9)"
+        );
+        assert_eq!(segment(&payload).encrypted, (newkeys_len, payload.len()));
+        assert!(fingerprints(&ctx).encrypted);
+
+        // AES-GCM keeps the length in the clear, but it is not a cleartext packet
+        let gcm = [0, 0, 0, 0x20, 0x9f, 0x14, 0x3a, 0x01];
+        let ctx = context(&gcm, 50022, SSH_PORT);
+        assert_eq!(
+            Ssh.summarize(&ctx).unwrap().1,
+            "Client: Encrypted packet (len=8)"
+        );
+
+        // Away from port 22 only an identification string makes a payload SSH
+        let ctx = context(&payload, 40000, 50022);
+        assert!(Ssh.summarize(&ctx).is_none());
+        assert_eq!(fingerprints(&ctx), SshFingerprints::default());
+    }
+}
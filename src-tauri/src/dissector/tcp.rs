@@ -0,0 +1,422 @@
+//! TCP (RFC 9293) flags and options: MSS, window scale (RFC 7323), SACK
+//! (RFC 2018), timestamps (RFC 7323), TCP Fast Open (RFC 7413) and
+//! Multipath TCP (RFC 8684).
+
+use super::{field, hex};
+use crate::model::PacketField;
+use crate::state::SequenceBases;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::Packet;
+
+const HEADER_LEN: usize = 20;
+const OPTION_EOL: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+const OPTION_WINDOW_SCALE: u8 = 3;
+const OPTION_SACK_PERMITTED: u8 = 4;
+const OPTION_SACK: u8 = 5;
+const OPTION_TIMESTAMPS: u8 = 8;
+const OPTION_MPTCP: u8 = 30;
+const OPTION_TFO: u8 = 34;
+/// Experimental option kind used by TFO before kind 34 was assigned (RFC 6994)
+const OPTION_EXPERIMENTAL: u8 = 254;
+const TFO_EXPERIMENT_ID: u16 = 0xf989;
+/// Shifts above 14 are treated as 14 (RFC 7323 section 2.3)
+const MAX_WINDOW_SCALE: u8 = 14;
+
+/// Flag bits in header order, with their display names.
+pub const FLAGS: [(u8, &str); 8] = [
+    (TcpFlags::CWR, "CWR"),
+    (TcpFlags::ECE, "ECE"),
+    (TcpFlags::URG, "URG"),
+    (TcpFlags::ACK, "ACK"),
+    (TcpFlags::PSH, "PSH"),
+    (TcpFlags::RST, "RST"),
+    (TcpFlags::SYN, "SYN"),
+    (TcpFlags::FIN, "FIN"),
+];
+
+/// Names of the flags set, in the `SYN, ACK` order Wireshark lists them.
+pub fn flag_names(flags: u8) -> Vec<&'static str> {
+    const ORDER: [(u8, &str); 8] = [
+        (TcpFlags::FIN, "FIN"),
+        (TcpFlags::SYN, "SYN"),
+        (TcpFlags::RST, "RST"),
+        (TcpFlags::PSH, "PSH"),
+        (TcpFlags::ACK, "ACK"),
+        (TcpFlags::URG, "URG"),
+        (TcpFlags::ECE, "ECE"),
+        (TcpFlags::CWR, "CWR"),
+    ];
+    ORDER
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Flag combinations no conforming stack sends, as used by scanners.
+pub fn flags_expert(flags: u8) -> Option<&'static str> {
+    let has = |bit: u8| flags & bit != 0;
+    if flags == 0 {
+        Some("TCP segment with no flags set (NULL scan)")
+    } else if has(TcpFlags::FIN) && has(TcpFlags::PSH) && has(TcpFlags::URG) && !has(TcpFlags::ACK)
+    {
+        Some("TCP FIN, PSH and URG set together (Xmas scan)")
+    } else if has(TcpFlags::SYN) && has(TcpFlags::FIN) {
+        Some("TCP SYN and FIN set together")
+    } else if has(TcpFlags::SYN) && has(TcpFlags::RST) {
+        Some("TCP SYN and RST set together")
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    EndOfList,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    Sack(Vec<(u32, u32)>),
+    Timestamps {
+        value: u32,
+        echo_reply: u32,
+    },
+    /// An empty cookie is a cookie request
+    FastOpen(Vec<u8>),
+    Multipath {
+        subtype: u8,
+    },
+    Unknown,
+    /// The option's length runs past the header or is too short for its kind
+    Malformed,
+}
+
+/// A TCP option at `offset` in the options area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpOption {
+    pub kind: u8,
+    pub offset: usize,
+    pub len: usize,
+    pub value: Value,
+}
+
+/// Parses the options area of a TCP header.
+pub fn options(data: &[u8]) -> Vec<TcpOption> {
+    let mut options = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let kind = data[i];
+        let (len, value) = match kind {
+            OPTION_EOL => (data.len() - i, Value::EndOfList),
+            OPTION_NOP => (1, Value::NoOperation),
+            _ => match data.get(i + 1).map(|&len| len as usize) {
+                Some(len) if len >= 2 && i + len <= data.len() => {
+                    (len, option_value(kind, &data[i + 2..i + len]))
+                }
+                _ => (data.len() - i, Value::Malformed),
+            },
+        };
+        options.push(TcpOption {
+            kind,
+            offset: i,
+            len,
+            value,
+        });
+        i += len;
+    }
+    options
+}
+
+fn option_value(kind: u8, body: &[u8]) -> Value {
+    let u32_at = |i: usize| u32::from_be_bytes([body[i], body[i + 1], body[i + 2], body[i + 3]]);
+    match (kind, body.len()) {
+        (OPTION_MSS, 2) => Value::MaximumSegmentSize(u16::from_be_bytes([body[0], body[1]])),
+        (OPTION_WINDOW_SCALE, 1) => Value::WindowScale(body[0]),
+        (OPTION_SACK_PERMITTED, 0) => Value::SackPermitted,
+        (OPTION_SACK, len) if len % 8 == 0 && len > 0 => Value::Sack(
+            (0..len / 8)
+                .map(|block| (u32_at(block * 8), u32_at(block * 8 + 4)))
+                .collect(),
+        ),
+        (OPTION_TIMESTAMPS, 8) => Value::Timestamps {
+            value: u32_at(0),
+            echo_reply: u32_at(4),
+        },
+        (OPTION_TFO, _) => Value::FastOpen(body.to_vec()),
+        (OPTION_EXPERIMENTAL, len)
+            if len >= 2 && u16::from_be_bytes([body[0], body[1]]) == TFO_EXPERIMENT_ID =>
+        {
+            Value::FastOpen(body[2..].to_vec())
+        }
+        (OPTION_MPTCP, len) if len >= 1 => Value::Multipath {
+            subtype: body[0] >> 4,
+        },
+        (OPTION_MSS | OPTION_WINDOW_SCALE | OPTION_SACK_PERMITTED | OPTION_SACK, _)
+        | (OPTION_TIMESTAMPS | OPTION_MPTCP, _) => Value::Malformed,
+        _ => Value::Unknown,
+    }
+}
+
+fn mptcp_subtype_name(subtype: u8) -> &'static str {
+    match subtype {
+        0 => "MP_CAPABLE",
+        1 => "MP_JOIN",
+        2 => "DSS",
+        3 => "ADD_ADDR",
+        4 => "REMOVE_ADDR",
+        5 => "MP_PRIO",
+        6 => "MP_FAIL",
+        7 => "MP_FASTCLOSE",
+        8 => "MP_TCPRST",
+        _ => "Unknown",
+    }
+}
+
+/// Info column text in the familiar `[SYN, ACK] Seq=0 Ack=1 Win=65535 Len=0` form,
+/// with Seq and Ack relative to `bases`.
+pub fn summarize(tcp: &TcpPacket, bases: SequenceBases) -> String {
+    let relative = |number: u32, base: Option<u32>| number.wrapping_sub(base.unwrap_or(0));
+    let flags = tcp.get_flags();
+    let mut info = format!(
+        "{} → {} [{}] Seq={}",
+        tcp.get_source(),
+        tcp.get_destination(),
+        flag_names(flags).join(", "),
+        relative(tcp.get_sequence(), bases.seq)
+    );
+    if flags & TcpFlags::ACK != 0 {
+        let ack = relative(tcp.get_acknowledgement(), bases.ack);
+        info.push_str(&format!(" Ack={}", ack));
+    }
+    info.push_str(&format!(
+        " Win={} Len={}",
+        tcp.get_window(),
+        tcp.payload().len()
+    ));
+    for option in options(tcp.get_options_raw()) {
+        match option.value {
+            Value::MaximumSegmentSize(mss) => info.push_str(&format!(" MSS={}", mss)),
+            Value::WindowScale(shift) => {
+                info.push_str(&format!(" WS={}", 1u32 << shift.min(MAX_WINDOW_SCALE)))
+            }
+            Value::SackPermitted => info.push_str(" SACK_PERM"),
+            Value::Sack(blocks) => {
+                for (left, right) in blocks {
+                    info.push_str(&format!(" SLE={} SRE={}", left, right));
+                }
+            }
+            Value::Timestamps { value, echo_reply } => {
+                info.push_str(&format!(" TSval={} TSecr={}", value, echo_reply))
+            }
+            Value::FastOpen(cookie) if cookie.is_empty() => info.push_str(" TFO=R"),
+            Value::FastOpen(_) => info.push_str(" TFO=C"),
+            Value::Multipath { subtype } => {
+                info.push_str(&format!(" {}", mptcp_subtype_name(subtype)))
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
+fn bit(set: bool) -> String {
+    if set { "1" } else { "0" }.to_string()
+}
+
+/// Builds the detail view fields for a TCP header starting at `offset`.
+pub fn fields(tcp: &TcpPacket, offset: usize) -> Vec<PacketField> {
+    let at = |start: usize, len: usize| (offset + start, offset + start + len);
+    let flags = tcp.get_flags();
+    let window = tcp.get_window();
+    let header_len = tcp.get_data_offset() as usize * 4;
+
+    let mut fields = vec![
+        field("Source Port", tcp.get_source().to_string(), at(0, 2)),
+        field(
+            "Destination Port",
+            tcp.get_destination().to_string(),
+            at(2, 2),
+        ),
+        field("Sequence Number", tcp.get_sequence().to_string(), at(4, 4)),
+        field(
+            "Acknowledgment Number",
+            tcp.get_acknowledgement().to_string(),
+            at(8, 4),
+        ),
+        PacketField {
+            expert: (header_len < HEADER_LEN)
+                .then(|| "TCP header length below the 20-byte minimum".to_string()),
+            ..field("Header Length", format!("{} bytes", header_len), at(12, 1))
+        },
+        PacketField {
+            expert: flags_expert(flags).map(str::to_string),
+            ..field(
+                "Flags",
+                format!("0x{:02x} ({})", flags, flag_names(flags).join(", ")),
+                at(13, 1),
+            )
+        },
+    ];
+    fields.extend(
+        FLAGS
+            .iter()
+            .map(|(mask, name)| field(name, bit(flags & mask != 0), at(13, 1))),
+    );
+    fields.push(PacketField {
+        expert: (window == 0).then(|| "TCP Window Zero".to_string()),
+        ..field("Window Size", window.to_string(), at(14, 2))
+    });
+    fields.push(field(
+        "Checksum",
+        format!("0x{:04x}", tcp.get_checksum()),
+        at(16, 2),
+    ));
+
+    for option in options(tcp.get_options_raw()) {
+        let range = at(HEADER_LEN + option.offset, option.len);
+        let (name, value) = match option.value {
+            Value::EndOfList | Value::NoOperation => continue,
+            Value::MaximumSegmentSize(mss) => ("Maximum Segment Size", format!("{} bytes", mss)),
+            Value::WindowScale(shift) => (
+                "Window Scale",
+                format!(
+                    "{} (multiply by {})",
+                    shift,
+                    1u32 << shift.min(MAX_WINDOW_SCALE)
+                ),
+            ),
+            Value::SackPermitted => ("SACK Permitted", "Yes".to_string()),
+            Value::Sack(blocks) => {
+                for (i, (left, right)) in blocks.into_iter().enumerate() {
+                    let start = HEADER_LEN + option.offset + 2 + i * 8;
+                    fields.push(field(
+                        "SACK Block",
+                        format!("{}-{}", left, right),
+                        at(start, 8),
+                    ));
+                }
+                continue;
+            }
+            Value::Timestamps { value, echo_reply } => {
+                let start = HEADER_LEN + option.offset;
+                fields.push(field(
+                    "Timestamp Value",
+                    value.to_string(),
+                    at(start + 2, 4),
+                ));
+                fields.push(field(
+                    "Timestamp Echo Reply",
+                    echo_reply.to_string(),
+                    at(start + 6, 4),
+                ));
+                continue;
+            }
+            Value::FastOpen(cookie) if cookie.is_empty() => {
+                ("TFO Cookie", "Cookie request".to_string())
+            }
+            Value::FastOpen(cookie) => ("TFO Cookie", hex(&cookie)),
+            Value::Multipath { subtype } => (
+                "MPTCP Subtype",
+                format!("{} ({})", mptcp_subtype_name(subtype), subtype),
+            ),
+            Value::Unknown => (
+                "Option",
+                format!("Kind {} ({} bytes)", option.kind, option.len),
+            ),
+            Value::Malformed => {
+                fields.push(PacketField {
+                    expert: Some(format!("Malformed TCP option (kind {})", option.kind)),
+                    ..field("Option", format!("Kind {}", option.kind), range)
+                });
+                continue;
+            }
+        };
+        fields.push(field(name, value, range));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syn_ack_options() {
+        let mut data = vec![0x00, 0x50, 0xD4, 0x31]; // 80 -> 54321
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0xA0, 0x12, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]); // SYN, ACK
+        data.extend_from_slice(&[0x02, 0x04, 0x05, 0xB4]); // MSS 1460
+        data.extend_from_slice(&[0x04, 0x02, 0x08, 0x0A]); // SACK permitted, timestamps
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x03]);
+        data.extend_from_slice(&[0x01, 0x03, 0x03, 0x07]); // NOP, window scale 7
+        let tcp = TcpPacket::new(&data).unwrap();
+
+        assert_eq!(
+            summarize(&tcp, SequenceBases::default()),
+            "80 → 54321 [SYN, ACK] Seq=0 Ack=1 Win=65535 Len=0 MSS=1460 SACK_PERM TSval=7 TSecr=3 WS=128"
+        );
+        let fields = fields(&tcp, 34);
+        let get = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(get("Flags").value, "0x12 (SYN, ACK)");
+        assert_eq!(get("SYN").value, "1");
+        assert_eq!(get("FIN").value, "0");
+        assert_eq!(get("Maximum Segment Size").range, (54, 58));
+        assert_eq!(get("Timestamp Echo Reply").value, "3");
+        assert_eq!(get("Window Scale").value, "7 (multiply by 128)");
+    }
+
+    #[test]
+    fn test_relative_sequence_numbers() {
+        // 54321 -> 80, Seq=1001 Ack=5001 [ACK] after a handshake with ISNs 1000 and 5000
+        let mut data = vec![0xD4, 0x31, 0x00, 0x50];
+        data.extend_from_slice(&1001u32.to_be_bytes());
+        data.extend_from_slice(&5001u32.to_be_bytes());
+        data.extend_from_slice(&[0x50, 0x10, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let tcp = TcpPacket::new(&data).unwrap();
+
+        let bases = SequenceBases {
+            seq: Some(1000),
+            ack: Some(5000),
+        };
+        assert_eq!(
+            summarize(&tcp, bases),
+            "54321 → 80 [ACK] Seq=1 Ack=1 Win=256 Len=0"
+        );
+        assert_eq!(
+            summarize(&tcp, SequenceBases::default()),
+            "54321 → 80 [ACK] Seq=1001 Ack=5001 Win=256 Len=0"
+        );
+        // Sequence numbers wrap around 2^32
+        let wrapped = SequenceBases {
+            seq: Some(u32::MAX),
+            ack: None,
+        };
+        assert!(summarize(&tcp, wrapped).contains("Seq=1002 Ack=5001"));
+    }
+
+    #[test]
+    fn test_option_variants() {
+        let mut data = vec![0x05, 0x0A, 0, 0, 0x03, 0xE8, 0, 0, 0x07, 0xD0]; // SACK 1000-2000
+        data.extend_from_slice(&[0x22, 0x02]); // TFO cookie request
+        data.extend_from_slice(&[0x1E, 0x04, 0x20, 0x00]); // MPTCP DSS
+        data.extend_from_slice(&[0x02, 0x09]); // MSS with a bad length
+        let options = options(&data);
+        assert_eq!(options[0].value, Value::Sack(vec![(1000, 2000)]));
+        assert_eq!(options[1].value, Value::FastOpen(Vec::new()));
+        assert_eq!(options[2].value, Value::Multipath { subtype: 2 });
+        assert_eq!(options[3].value, Value::Malformed);
+
+        assert_eq!(
+            flags_expert(0),
+            Some("TCP segment with no flags set (NULL scan)")
+        );
+        assert_eq!(
+            flags_expert(TcpFlags::FIN | TcpFlags::PSH | TcpFlags::URG),
+            Some("TCP FIN, PSH and URG set together (Xmas scan)")
+        );
+        assert_eq!(flags_expert(TcpFlags::SYN | TcpFlags::ACK), None);
+    }
+}
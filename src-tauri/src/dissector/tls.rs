@@ -0,0 +1,1153 @@
+//! TLS record layer and handshake parsing (RFC 5246, RFC 8446): ClientHello
+//! and ServerHello with their extensions, and the certificate chain that TLS
+//! 1.2 and earlier send in the clear.
+//!
+//! TLS is recognized by its record header, so it is found on any TCP port.
+//! Handshake messages are parsed leniently: a large ClientHello or
+//! certificate chain usually spans several segments, and whatever arrived in
+//! this one is still shown.
+
+use super::fingerprint::{self, Transport};
+use super::registry::{self, Context, Dissection, Dissector, Heuristic};
+use super::x509::{self, Certificate};
+use super::{field, hex};
+use super::{LAYER_TLS, PROTO_TLS};
+use crate::model::PacketField;
+
+const RECORD_HEADER_LEN: usize = 5;
+/// Largest ciphertext fragment allowed by RFC 5246 6.2.3
+const MAX_RECORD_LEN: usize = 16384 + 2048;
+const HANDSHAKE_HEADER_LEN: usize = 4;
+
+pub const CONTENT_CHANGE_CIPHER_SPEC: u8 = 20;
+pub const CONTENT_ALERT: u8 = 21;
+pub const CONTENT_HANDSHAKE: u8 = 22;
+pub const CONTENT_APPLICATION_DATA: u8 = 23;
+pub const CONTENT_HEARTBEAT: u8 = 24;
+
+pub const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+pub const HANDSHAKE_SERVER_HELLO: u8 = 2;
+pub const HANDSHAKE_CERTIFICATE: u8 = 11;
+
+pub const EXT_SERVER_NAME: u16 = 0;
+pub const EXT_SUPPORTED_GROUPS: u16 = 10;
+pub const EXT_EC_POINT_FORMATS: u16 = 11;
+pub const EXT_SIGNATURE_ALGORITHMS: u16 = 13;
+pub const EXT_ALPN: u16 = 16;
+pub const EXT_SUPPORTED_VERSIONS: u16 = 43;
+pub const EXT_KEY_SHARE: u16 = 51;
+
+/// ServerHello.random of a HelloRetryRequest (SHA-256 of "HelloRetryRequest")
+const HELLO_RETRY_REQUEST_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+/// Big-endian cursor over handshake data.
+pub(super) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub(super) fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("Truncated at offset {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(super) fn u24(&mut self) -> Result<usize, String> {
+        let b = self.bytes(3)?;
+        Ok(((b[0] as usize) << 16) | ((b[1] as usize) << 8) | b[2] as usize)
+    }
+
+    /// Reads a vector with a one-byte length prefix.
+    pub(super) fn vec8(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    /// Reads a vector with a two-byte length prefix.
+    pub(super) fn vec16(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u16()? as usize;
+        self.bytes(len)
+    }
+}
+
+/// GREASE values (RFC 8701) are reserved to keep peers tolerant of unknown values.
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn u16_list(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    pub ext_type: u16,
+    pub data: Vec<u8>,
+    /// Byte range within the handshake message body
+    pub range: (usize, usize),
+}
+
+/// Reads an extension block, keeping whatever arrived before a truncation.
+fn parse_extensions(r: &mut Reader) -> (Vec<Extension>, bool) {
+    let mut extensions = Vec::new();
+    if r.is_empty() {
+        return (extensions, false);
+    }
+    // The block length is ignored so that a truncated block still yields its
+    // leading extensions
+    if r.u16().is_err() {
+        return (extensions, true);
+    }
+    while !r.is_empty() {
+        let start = r.position();
+        let parsed = r.u16().and_then(|ext_type| Ok((ext_type, r.vec16()?)));
+        match parsed {
+            Ok((ext_type, data)) => extensions.push(Extension {
+                ext_type,
+                data: data.to_vec(),
+                range: (start, r.position()),
+            }),
+            Err(_) => return (extensions, true),
+        }
+    }
+    (extensions, false)
+}
+
+fn find_extension(extensions: &[Extension], ext_type: u16) -> Option<&[u8]> {
+    extensions
+        .iter()
+        .find(|ext| ext.ext_type == ext_type)
+        .map(|ext| ext.data.as_slice())
+}
+
+/// ALPN protocol names from an `application_layer_protocol_negotiation` extension.
+fn alpn_protocols(data: &[u8]) -> Vec<String> {
+    let mut r = Reader::new(data);
+    let Ok(list) = r.vec16() else {
+        return Vec::new();
+    };
+    let mut r = Reader::new(list);
+    let mut protocols = Vec::new();
+    while let Ok(name) = r.vec8() {
+        protocols.push(String::from_utf8_lossy(name).into_owned());
+    }
+    protocols
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientHello {
+    pub version: u16,
+    pub random: Vec<u8>,
+    pub session_id: Vec<u8>,
+    pub cipher_suites: Vec<u16>,
+    pub compression_methods: Vec<u8>,
+    /// Extensions in the order sent
+    pub extensions: Vec<Extension>,
+    /// The message continues beyond the data available
+    pub truncated: bool,
+}
+
+impl ClientHello {
+    pub fn parse(body: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(body);
+        let version = r.u16()?;
+        let random = r.bytes(32)?.to_vec();
+        let session_id = r.vec8()?.to_vec();
+        let cipher_suites = u16_list(r.vec16()?);
+        let compression_methods = r.vec8()?.to_vec();
+        let (extensions, truncated) = parse_extensions(&mut r);
+        Ok(ClientHello {
+            version,
+            random,
+            session_id,
+            cipher_suites,
+            compression_methods,
+            extensions,
+            truncated,
+        })
+    }
+
+    pub fn extension(&self, ext_type: u16) -> Option<&[u8]> {
+        find_extension(&self.extensions, ext_type)
+    }
+
+    /// First `host_name` entry of the `server_name` extension (SNI).
+    pub fn server_name(&self) -> Option<String> {
+        let mut r = Reader::new(self.extension(EXT_SERVER_NAME)?);
+        let mut list = Reader::new(r.vec16().ok()?);
+        while !list.is_empty() {
+            let name_type = list.u8().ok()?;
+            let name = list.vec16().ok()?;
+            if name_type == 0 {
+                return Some(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+        None
+    }
+
+    pub fn alpn(&self) -> Vec<String> {
+        self.extension(EXT_ALPN)
+            .map(alpn_protocols)
+            .unwrap_or_default()
+    }
+
+    pub fn supported_versions(&self) -> Vec<u16> {
+        self.extension(EXT_SUPPORTED_VERSIONS)
+            .and_then(|data| Reader::new(data).vec8().ok())
+            .map(u16_list)
+            .unwrap_or_default()
+    }
+
+    pub fn supported_groups(&self) -> Vec<u16> {
+        self.extension(EXT_SUPPORTED_GROUPS)
+            .and_then(|data| Reader::new(data).vec16().ok())
+            .map(u16_list)
+            .unwrap_or_default()
+    }
+
+    pub fn signature_algorithms(&self) -> Vec<u16> {
+        self.extension(EXT_SIGNATURE_ALGORITHMS)
+            .and_then(|data| Reader::new(data).vec16().ok())
+            .map(u16_list)
+            .unwrap_or_default()
+    }
+
+    pub fn ec_point_formats(&self) -> Vec<u8> {
+        self.extension(EXT_EC_POINT_FORMATS)
+            .and_then(|data| Reader::new(data).vec8().ok())
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    /// Groups the client sent key shares for.
+    pub fn key_share_groups(&self) -> Vec<u16> {
+        let Some(Ok(shares)) = self
+            .extension(EXT_KEY_SHARE)
+            .map(|data| Reader::new(data).vec16())
+        else {
+            return Vec::new();
+        };
+        let mut r = Reader::new(shares);
+        let mut groups = Vec::new();
+        while let Ok(group) = r.u16() {
+            if r.vec16().is_err() {
+                break;
+            }
+            groups.push(group);
+        }
+        groups
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerHello {
+    pub version: u16,
+    pub random: Vec<u8>,
+    pub session_id: Vec<u8>,
+    pub cipher_suite: u16,
+    pub compression_method: u8,
+    pub extensions: Vec<Extension>,
+    pub truncated: bool,
+}
+
+impl ServerHello {
+    pub fn parse(body: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(body);
+        let version = r.u16()?;
+        let random = r.bytes(32)?.to_vec();
+        let session_id = r.vec8()?.to_vec();
+        let cipher_suite = r.u16()?;
+        let compression_method = r.u8()?;
+        let (extensions, truncated) = parse_extensions(&mut r);
+        Ok(ServerHello {
+            version,
+            random,
+            session_id,
+            cipher_suite,
+            compression_method,
+            extensions,
+            truncated,
+        })
+    }
+
+    pub fn extension(&self, ext_type: u16) -> Option<&[u8]> {
+        find_extension(&self.extensions, ext_type)
+    }
+
+    pub fn is_hello_retry_request(&self) -> bool {
+        self.random == HELLO_RETRY_REQUEST_RANDOM
+    }
+
+    /// Version chosen through `supported_versions` (TLS 1.3).
+    pub fn selected_version(&self) -> Option<u16> {
+        Reader::new(self.extension(EXT_SUPPORTED_VERSIONS)?)
+            .u16()
+            .ok()
+    }
+
+    pub fn key_share_group(&self) -> Option<u16> {
+        Reader::new(self.extension(EXT_KEY_SHARE)?).u16().ok()
+    }
+
+    pub fn alpn(&self) -> Option<String> {
+        self.extension(EXT_ALPN)
+            .map(alpn_protocols)
+            .and_then(|protocols| protocols.into_iter().next())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateEntry {
+    /// Byte range within the handshake message body
+    pub range: (usize, usize),
+    pub certificate: Result<Certificate, String>,
+}
+
+/// Certificate chain of a TLS 1.2 Certificate message.
+fn parse_certificate_list(body: &[u8]) -> (Vec<CertificateEntry>, bool) {
+    let mut r = Reader::new(body);
+    let mut entries = Vec::new();
+    if r.u24().is_err() {
+        return (entries, true);
+    }
+    while !r.is_empty() {
+        let start = r.position();
+        let Ok(len) = r.u24() else {
+            return (entries, true);
+        };
+        let Ok(der) = r.bytes(len) else {
+            return (entries, true);
+        };
+        entries.push(CertificateEntry {
+            range: (start, r.position()),
+            certificate: x509::parse_certificate(der),
+        });
+    }
+    (entries, false)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<'a> {
+    pub content_type: u8,
+    pub version: u16,
+    /// Declared fragment length
+    pub length: usize,
+    /// Offset of the record header within the payload
+    pub offset: usize,
+    /// The part of the fragment present in this segment
+    pub fragment: &'a [u8],
+}
+
+impl Record<'_> {
+    pub fn is_complete(&self) -> bool {
+        self.fragment.len() == self.length
+    }
+}
+
+fn record_header(payload: &[u8], pos: usize) -> Option<(u8, u16, usize)> {
+    let header = payload.get(pos..pos + RECORD_HEADER_LEN)?;
+    let content_type = header[0];
+    let version = u16::from_be_bytes([header[1], header[2]]);
+    let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+    let plausible = (CONTENT_CHANGE_CIPHER_SPEC..=CONTENT_HEARTBEAT).contains(&content_type)
+        && (0x0300..=0x0304).contains(&version)
+        && length > 0
+        && length <= MAX_RECORD_LEN;
+    plausible.then_some((content_type, version, length))
+}
+
+/// Splits a TCP payload into TLS records. Empty unless the payload starts
+/// with a plausible record header; the last record may be partial.
+pub fn parse_records(payload: &[u8]) -> Vec<Record<'_>> {
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while let Some((content_type, version, length)) = record_header(payload, pos) {
+        let start = pos + RECORD_HEADER_LEN;
+        let end = (start + length).min(payload.len());
+        records.push(Record {
+            content_type,
+            version,
+            length,
+            offset: pos,
+            fragment: &payload[start..end],
+        });
+        pos = start + length;
+    }
+    records
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandshakeBody {
+    ClientHello(ClientHello),
+    ServerHello(ServerHello),
+    Certificate {
+        entries: Vec<CertificateEntry>,
+        truncated: bool,
+    },
+    Other,
+    Malformed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake {
+    pub msg_type: u8,
+    /// Declared body length
+    pub length: usize,
+    /// Offset of the message header within the record fragment
+    pub offset: usize,
+    /// The whole body is present in this record
+    pub complete: bool,
+    pub body: HandshakeBody,
+}
+
+/// Parses the handshake messages of a plaintext handshake record. Returns
+/// None when the fragment does not look like plaintext handshake data, e.g.
+/// the encrypted Finished message of TLS 1.2.
+pub fn parse_handshakes(fragment: &[u8]) -> Option<Vec<Handshake>> {
+    let mut messages = Vec::new();
+    let mut pos = 0;
+    while pos + HANDSHAKE_HEADER_LEN <= fragment.len() {
+        let mut r = Reader::new(&fragment[pos..]);
+        let msg_type = r.u8().ok()?;
+        let length = r.u24().ok()?;
+        handshake_type_name(msg_type)?;
+        let start = pos + HANDSHAKE_HEADER_LEN;
+        let body = &fragment[start..(start + length).min(fragment.len())];
+        let parsed = match msg_type {
+            HANDSHAKE_CLIENT_HELLO => ClientHello::parse(body).map(HandshakeBody::ClientHello),
+            HANDSHAKE_SERVER_HELLO => ServerHello::parse(body).map(HandshakeBody::ServerHello),
+            HANDSHAKE_CERTIFICATE => {
+                let (entries, truncated) = parse_certificate_list(body);
+                Ok(HandshakeBody::Certificate { entries, truncated })
+            }
+            _ => Ok(HandshakeBody::Other),
+        };
+        messages.push(Handshake {
+            msg_type,
+            length,
+            offset: pos,
+            complete: body.len() == length,
+            body: parsed.unwrap_or_else(HandshakeBody::Malformed),
+        });
+        pos = start + length;
+    }
+    (!messages.is_empty()).then_some(messages)
+}
+
+pub fn version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        v if is_grease(v) => "GREASE".to_string(),
+        v => format!("Unknown (0x{:04x})", v),
+    }
+}
+
+fn content_type_name(content_type: u8) -> &'static str {
+    match content_type {
+        CONTENT_CHANGE_CIPHER_SPEC => "Change Cipher Spec",
+        CONTENT_ALERT => "Alert",
+        CONTENT_HANDSHAKE => "Handshake",
+        CONTENT_APPLICATION_DATA => "Application Data",
+        CONTENT_HEARTBEAT => "Heartbeat",
+        _ => "Unknown",
+    }
+}
+
+fn handshake_type_name(msg_type: u8) -> Option<&'static str> {
+    Some(match msg_type {
+        0 => "Hello Request",
+        1 => "Client Hello",
+        2 => "Server Hello",
+        4 => "New Session Ticket",
+        5 => "End Of Early Data",
+        8 => "Encrypted Extensions",
+        11 => "Certificate",
+        12 => "Server Key Exchange",
+        13 => "Certificate Request",
+        14 => "Server Hello Done",
+        15 => "Certificate Verify",
+        16 => "Client Key Exchange",
+        20 => "Finished",
+        22 => "Certificate Status",
+        24 => "Key Update",
+        _ => return None,
+    })
+}
+
+pub fn cipher_suite_name(suite: u16) -> Option<&'static str> {
+    Some(match suite {
+        0x000a => "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+        0x002f => "TLS_RSA_WITH_AES_128_CBC_SHA",
+        0x0035 => "TLS_RSA_WITH_AES_256_CBC_SHA",
+        0x003c => "TLS_RSA_WITH_AES_128_CBC_SHA256",
+        0x009c => "TLS_RSA_WITH_AES_128_GCM_SHA256",
+        0x009d => "TLS_RSA_WITH_AES_256_GCM_SHA384",
+        0x009e => "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256",
+        0x009f => "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384",
+        0x00ff => "TLS_EMPTY_RENEGOTIATION_INFO_SCSV",
+        0x1301 => "TLS_AES_128_GCM_SHA256",
+        0x1302 => "TLS_AES_256_GCM_SHA384",
+        0x1303 => "TLS_CHACHA20_POLY1305_SHA256",
+        0x1304 => "TLS_AES_128_CCM_SHA256",
+        0x1305 => "TLS_AES_128_CCM_8_SHA256",
+        0x5600 => "TLS_FALLBACK_SCSV",
+        0xc009 => "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+        0xc00a => "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+        0xc013 => "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+        0xc014 => "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+        0xc023 => "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256",
+        0xc024 => "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384",
+        0xc027 => "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256",
+        0xc028 => "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384",
+        0xc02b => "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        0xc02c => "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        0xc02f => "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        0xc030 => "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        0xcca8 => "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xcca9 => "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        _ => return None,
+    })
+}
+
+pub fn group_name(group: u16) -> Option<&'static str> {
+    Some(match group {
+        23 => "secp256r1",
+        24 => "secp384r1",
+        25 => "secp521r1",
+        29 => "x25519",
+        30 => "x448",
+        256 => "ffdhe2048",
+        257 => "ffdhe3072",
+        258 => "ffdhe4096",
+        0x11ec => "X25519MLKEM768",
+        0x6399 => "X25519Kyber768Draft00",
+        _ => return None,
+    })
+}
+
+pub fn extension_name(ext_type: u16) -> Option<&'static str> {
+    Some(match ext_type {
+        0 => "server_name",
+        5 => "status_request",
+        10 => "supported_groups",
+        11 => "ec_point_formats",
+        13 => "signature_algorithms",
+        16 => "application_layer_protocol_negotiation",
+        18 => "signed_certificate_timestamp",
+        21 => "padding",
+        23 => "extended_master_secret",
+        27 => "compress_certificate",
+        35 => "session_ticket",
+        41 => "pre_shared_key",
+        42 => "early_data",
+        43 => "supported_versions",
+        45 => "psk_key_exchange_modes",
+        51 => "key_share",
+        57 => "quic_transport_parameters",
+        17513 => "application_settings",
+        0xfe0d => "encrypted_client_hello",
+        0xff01 => "renegotiation_info",
+        _ => return None,
+    })
+}
+
+fn alert_description_name(description: u8) -> Option<&'static str> {
+    Some(match description {
+        0 => "Close Notify",
+        10 => "Unexpected Message",
+        20 => "Bad Record MAC",
+        40 => "Handshake Failure",
+        42 => "Bad Certificate",
+        43 => "Unsupported Certificate",
+        44 => "Certificate Revoked",
+        45 => "Certificate Expired",
+        46 => "Certificate Unknown",
+        47 => "Illegal Parameter",
+        48 => "Unknown CA",
+        50 => "Decode Error",
+        51 => "Decrypt Error",
+        70 => "Protocol Version",
+        71 => "Insufficient Security",
+        80 => "Internal Error",
+        86 => "Inappropriate Fallback",
+        90 => "User Canceled",
+        109 => "Missing Extension",
+        110 => "Unsupported Extension",
+        112 => "Unrecognized Name",
+        116 => "Certificate Required",
+        120 => "No Application Protocol",
+        _ => return None,
+    })
+}
+
+/// "0x1301 (TLS_AES_128_GCM_SHA256)", keeping the number first for filters.
+fn hex_named(value: u16, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("0x{:04x} ({})", value, name),
+        None if is_grease(value) => format!("0x{:04x} (GREASE)", value),
+        None => format!("0x{:04x}", value),
+    }
+}
+
+/// Decoded alert, or None when the alert is encrypted.
+fn plaintext_alert(record: &Record) -> Option<(u8, u8, &'static str)> {
+    match record.fragment {
+        [level @ (1 | 2), description] if record.length == 2 => {
+            Some((*level, *description, alert_description_name(*description)?))
+        }
+        _ => None,
+    }
+}
+
+/// Info column text when the payload starts with a TLS record, e.g.
+/// "Client Hello (SNI=example.com)" or "Server Hello, Change Cipher Spec".
+pub fn summarize(payload: &[u8]) -> Option<String> {
+    let records = parse_records(payload);
+    if records.is_empty() {
+        return None;
+    }
+    let mut parts: Vec<String> = Vec::new();
+    let mut encrypted = false;
+    for record in &records {
+        let names = match record.content_type {
+            CONTENT_HANDSHAKE if encrypted => vec!["Encrypted Handshake Message".to_string()],
+            CONTENT_HANDSHAKE => match parse_handshakes(record.fragment) {
+                Some(messages) => messages.iter().map(handshake_summary).collect(),
+                None => vec!["Encrypted Handshake Message".to_string()],
+            },
+            CONTENT_ALERT => vec![match plaintext_alert(record) {
+                Some((level, _, description)) => format!(
+                    "Alert ({}, {})",
+                    if level == 2 { "Fatal" } else { "Warning" },
+                    description
+                ),
+                None => "Encrypted Alert".to_string(),
+            }],
+            other => vec![content_type_name(other).to_string()],
+        };
+        if record.content_type == CONTENT_CHANGE_CIPHER_SPEC {
+            encrypted = true;
+        }
+        for name in names {
+            if parts.last() != Some(&name) {
+                parts.push(name);
+            }
+        }
+    }
+    Some(parts.join(", "))
+}
+
+pub fn handshake_summary(message: &Handshake) -> String {
+    match &message.body {
+        HandshakeBody::ClientHello(hello) => match hello.server_name() {
+            Some(sni) => format!("Client Hello (SNI={})", sni),
+            None => "Client Hello".to_string(),
+        },
+        HandshakeBody::ServerHello(hello) if hello.is_hello_retry_request() => {
+            "Hello Retry Request".to_string()
+        }
+        _ => handshake_type_name(message.msg_type)
+            .unwrap_or("Handshake")
+            .to_string(),
+    }
+}
+
+/// Builds the detail view fields for the TLS records in a TCP payload
+/// starting at `offset`.
+pub fn fields(payload: &[u8], offset: usize) -> Vec<PacketField> {
+    let mut fields = Vec::new();
+    let mut encrypted = false;
+    for record in parse_records(payload) {
+        let header = (
+            offset + record.offset,
+            offset + record.offset + RECORD_HEADER_LEN,
+        );
+        let fragment_start = header.1;
+        let fragment = (fragment_start, fragment_start + record.fragment.len());
+
+        fields.push(field(
+            "Record Type",
+            format!(
+                "{} ({})",
+                record.content_type,
+                content_type_name(record.content_type)
+            ),
+            header,
+        ));
+        fields.push(field(
+            "Record Version",
+            hex_named(record.version, Some(&version_name(record.version))),
+            header,
+        ));
+        fields.push(PacketField {
+            expert: (!record.is_complete())
+                .then(|| "Record continues in the next segment".to_string()),
+            ..field("Record Length", format!("{} bytes", record.length), header)
+        });
+
+        match record.content_type {
+            CONTENT_HANDSHAKE => match parse_handshakes(record.fragment).filter(|_| !encrypted) {
+                Some(messages) => {
+                    for message in &messages {
+                        push_handshake_fields(&mut fields, message, fragment_start, Transport::Tcp);
+                    }
+                }
+                None => fields.push(field(
+                    "Encrypted Handshake Message",
+                    format!("{} bytes", record.fragment.len()),
+                    fragment,
+                )),
+            },
+            CONTENT_ALERT => match plaintext_alert(&record) {
+                Some((level, description, name)) => {
+                    let level_name = if level == 2 { "Fatal" } else { "Warning" };
+                    fields.push(field(
+                        "Alert Level",
+                        format!("{} ({})", level, level_name),
+                        fragment,
+                    ));
+                    fields.push(PacketField {
+                        expert: (level == 2).then(|| format!("Fatal alert: {}", name)),
+                        ..field(
+                            "Alert Description",
+                            format!("{} ({})", description, name),
+                            fragment,
+                        )
+                    });
+                }
+                None => fields.push(field(
+                    "Encrypted Alert",
+                    format!("{} bytes", record.fragment.len()),
+                    fragment,
+                )),
+            },
+            CONTENT_APPLICATION_DATA => fields.push(field(
+                "Encrypted Application Data",
+                format!("{} bytes", record.fragment.len()),
+                fragment,
+            )),
+            _ => {}
+        }
+        if record.content_type == CONTENT_CHANGE_CIPHER_SPEC {
+            encrypted = true;
+        }
+    }
+    fields
+}
+
+/// Builds the detail view fields for handshake messages carried outside TLS
+/// records, such as in QUIC CRYPTO frames, starting at `offset`.
+pub fn handshake_fields(data: &[u8], offset: usize, transport: Transport) -> Vec<PacketField> {
+    let mut fields = Vec::new();
+    for message in parse_handshakes(data).unwrap_or_default() {
+        push_handshake_fields(&mut fields, &message, offset, transport);
+    }
+    fields
+}
+
+fn push_handshake_fields(
+    fields: &mut Vec<PacketField>,
+    message: &Handshake,
+    fragment_start: usize,
+    transport: Transport,
+) {
+    let header_start = fragment_start + message.offset;
+    let body_start = header_start + HANDSHAKE_HEADER_LEN;
+    let range = (header_start, body_start + message.length);
+    let at = |(start, end): (usize, usize)| (body_start + start, body_start + end);
+
+    fields.push(field(
+        "Handshake Type",
+        format!(
+            "{} ({})",
+            message.msg_type,
+            handshake_type_name(message.msg_type).unwrap_or("Unknown")
+        ),
+        range,
+    ));
+    fields.push(field(
+        "Handshake Length",
+        format!("{} bytes", message.length),
+        (header_start + 1, body_start),
+    ));
+
+    match &message.body {
+        HandshakeBody::ClientHello(hello) => {
+            fields.push(field(
+                "Version",
+                hex_named(hello.version, Some(&version_name(hello.version))),
+                range,
+            ));
+            fields.push(field("Session ID", hex(&hello.session_id), range));
+            fields.push(field(
+                "Cipher Suites",
+                format!("{} suites", hello.cipher_suites.len()),
+                range,
+            ));
+            for &suite in &hello.cipher_suites {
+                fields.push(field(
+                    "Cipher Suite",
+                    hex_named(suite, cipher_suite_name(suite)),
+                    range,
+                ));
+            }
+            push_extension_fields(fields, &hello.extensions, &at);
+            if let Some(sni) = hello.server_name() {
+                fields.push(field("Server Name", sni, range));
+            }
+            for protocol in hello.alpn() {
+                fields.push(field("ALPN Protocol", protocol, range));
+            }
+            for version in hello.supported_versions() {
+                fields.push(field(
+                    "Supported Version",
+                    hex_named(version, Some(&version_name(version))),
+                    range,
+                ));
+            }
+            for group in hello.supported_groups() {
+                fields.push(field(
+                    "Supported Group",
+                    hex_named(group, group_name(group)),
+                    range,
+                ));
+            }
+            for algorithm in hello.signature_algorithms() {
+                fields.push(field(
+                    "Signature Algorithm",
+                    hex_named(algorithm, None),
+                    range,
+                ));
+            }
+            for group in hello.key_share_groups() {
+                fields.push(field(
+                    "Key Share Group",
+                    hex_named(group, group_name(group)),
+                    range,
+                ));
+            }
+            if message.complete {
+                let ja3 = fingerprint::ja3_string(hello);
+                fields.push(field("JA3", fingerprint::ja3(hello), range));
+                fields.push(field("JA3 Fullstring", ja3, range));
+                fields.push(field("JA4", fingerprint::ja4(hello, transport), range));
+            } else {
+                fields.push(PacketField {
+                    expert: Some(
+                        "Client Hello continues in a later packet; no fingerprint".to_string(),
+                    ),
+                    ..field("Truncated", "1".to_string(), range)
+                });
+            }
+        }
+        HandshakeBody::ServerHello(hello) => {
+            fields.push(PacketField {
+                expert: hello.is_hello_retry_request().then(|| {
+                    "Hello Retry Request: the client must send a new key share".to_string()
+                }),
+                ..field(
+                    "Version",
+                    hex_named(hello.version, Some(&version_name(hello.version))),
+                    range,
+                )
+            });
+            fields.push(field("Session ID", hex(&hello.session_id), range));
+            fields.push(field(
+                "Cipher Suite",
+                hex_named(hello.cipher_suite, cipher_suite_name(hello.cipher_suite)),
+                range,
+            ));
+            push_extension_fields(fields, &hello.extensions, &at);
+            if let Some(version) = hello.selected_version() {
+                fields.push(field(
+                    "Supported Version",
+                    hex_named(version, Some(&version_name(version))),
+                    range,
+                ));
+            }
+            if let Some(group) = hello.key_share_group() {
+                fields.push(field(
+                    "Key Share Group",
+                    hex_named(group, group_name(group)),
+                    range,
+                ));
+            }
+            if let Some(protocol) = hello.alpn() {
+                fields.push(field("ALPN Protocol", protocol, range));
+            }
+            if message.complete {
+                let ja3s = fingerprint::ja3s_string(hello);
+                fields.push(field("JA3S", fingerprint::ja3s(hello), range));
+                fields.push(field("JA3S Fullstring", ja3s, range));
+                fields.push(field("JA4S", fingerprint::ja4s(hello, transport), range));
+            }
+        }
+        HandshakeBody::Certificate { entries, truncated } => {
+            for entry in entries {
+                let cert_range = at(entry.range);
+                fields.push(field(
+                    "Certificate",
+                    format!("{} bytes", entry.range.1 - entry.range.0 - 3),
+                    cert_range,
+                ));
+                match &entry.certificate {
+                    Ok(cert) => {
+                        fields.push(PacketField {
+                            expert: cert
+                                .is_self_signed()
+                                .then(|| "Self-signed certificate".to_string()),
+                            ..field("Subject", cert.subject.clone(), cert_range)
+                        });
+                        fields.push(field("Issuer", cert.issuer.clone(), cert_range));
+                        fields.push(field("Serial Number", cert.serial.clone(), cert_range));
+                        fields.push(field("Not Before", cert.not_before.clone(), cert_range));
+                        fields.push(field("Not After", cert.not_after.clone(), cert_range));
+                        for name in &cert.subject_alt_names {
+                            fields.push(field("Subject Alt Name", name.clone(), cert_range));
+                        }
+                    }
+                    Err(e) => fields.push(PacketField {
+                        expert: Some("Malformed certificate".to_string()),
+                        ..field("Error", e.clone(), cert_range)
+                    }),
+                }
+            }
+            if *truncated {
+                fields.push(PacketField {
+                    expert: Some("Certificate chain continues in the next segment".to_string()),
+                    ..field("Truncated", "1".to_string(), range)
+                });
+            }
+        }
+        HandshakeBody::Malformed(e) => fields.push(PacketField {
+            expert: Some("Malformed handshake message".to_string()),
+            ..field("Error", e.clone(), range)
+        }),
+        HandshakeBody::Other => {}
+    }
+}
+
+fn push_extension_fields(
+    fields: &mut Vec<PacketField>,
+    extensions: &[Extension],
+    at: &dyn Fn((usize, usize)) -> (usize, usize),
+) {
+    for ext in extensions {
+        let name = extension_name(ext.ext_type)
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                if is_grease(ext.ext_type) {
+                    "GREASE".to_string()
+                } else {
+                    "unknown".to_string()
+                }
+            });
+        fields.push(field(
+            "Extension",
+            format!("{} ({}, {} bytes)", ext.ext_type, name, ext.data.len()),
+            at(ext.range),
+        ));
+    }
+}
+
+/// TLS over TCP, recognized by its record header on any port.
+pub struct Tls;
+
+impl Dissector for Tls {
+    fn name(&self) -> &'static str {
+        "tls"
+    }
+
+    fn description(&self) -> &'static str {
+        "Transport Layer Security"
+    }
+
+    fn heuristic(&self, transport: registry::Transport) -> Option<Heuristic> {
+        (transport == registry::Transport::Tcp).then_some(Heuristic::First)
+    }
+
+    fn recognizes(&self, ctx: &Context) -> bool {
+        !parse_records(ctx.payload).is_empty()
+    }
+
+    fn summarize(&self, ctx: &Context) -> Option<(String, String)> {
+        if let Some(info) = summarize(ctx.payload) {
+            return Some((PROTO_TLS.to_string(), info));
+        }
+        // Segments in the middle of a record, once the flow is known to be TLS
+        (!ctx.payload.is_empty() && ctx.learned == Some(self.name()))
+            .then(|| (PROTO_TLS.to_string(), "Continuation Data".to_string()))
+    }
+
+    fn dissect(&self, ctx: &Context) -> Option<Dissection> {
+        self.recognizes(ctx)
+            .then(|| Dissection::layer(LAYER_TLS, fields(ctx.payload, ctx.offset)))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    fn with_len16(data: &[u8]) -> Vec<u8> {
+        [&(data.len() as u16).to_be_bytes()[..], data].concat()
+    }
+
+    fn extension(ext_type: u16, data: &[u8]) -> Vec<u8> {
+        [&ext_type.to_be_bytes()[..], &with_len16(data)].concat()
+    }
+
+    fn handshake(msg_type: u8, body: &[u8]) -> Vec<u8> {
+        let len = (body.len() as u32).to_be_bytes();
+        [&[msg_type, len[1], len[2], len[3]][..], body].concat()
+    }
+
+    fn record(content_type: u8, fragment: &[u8]) -> Vec<u8> {
+        [&[content_type, 0x03, 0x01][..], &with_len16(fragment)].concat()
+    }
+
+    /// ClientHello with GREASE, SNI, ALPN, supported_versions and key_share.
+    pub(in crate::dissector) fn client_hello_body() -> Vec<u8> {
+        let sni = with_len16(&[&[0u8][..], &with_len16(b"example.com")].concat());
+        let alpn = with_len16(b"\x02h2\x08http/1.1");
+        let key_share = with_len16(&[&[0x00, 0x1d][..], &with_len16(&[0xab; 32])].concat());
+        let extensions = [
+            extension(0x0a0a, &[]),
+            extension(EXT_SERVER_NAME, &sni),
+            extension(
+                EXT_SUPPORTED_GROUPS,
+                &with_len16(&[0x0a, 0x0a, 0x00, 0x1d, 0x00, 0x17]),
+            ),
+            extension(EXT_EC_POINT_FORMATS, &[1, 0]),
+            extension(
+                EXT_SIGNATURE_ALGORITHMS,
+                &with_len16(&[0x04, 0x03, 0x08, 0x04]),
+            ),
+            extension(EXT_ALPN, &alpn),
+            extension(EXT_SUPPORTED_VERSIONS, &[4, 0x03, 0x04, 0x03, 0x03]),
+            extension(EXT_KEY_SHARE, &key_share),
+        ]
+        .concat();
+        [
+            &[0x03, 0x03][..],
+            &[0x11; 32],
+            &[0],
+            &with_len16(&[0x1a, 0x1a, 0x13, 0x01, 0xc0, 0x2f]),
+            &[1, 0],
+            &with_len16(&extensions),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_client_hello() {
+        let payload = record(
+            CONTENT_HANDSHAKE,
+            &handshake(HANDSHAKE_CLIENT_HELLO, &client_hello_body()),
+        );
+        assert_eq!(
+            summarize(&payload).unwrap(),
+            "Client Hello (SNI=example.com)"
+        );
+
+        let messages = parse_handshakes(parse_records(&payload)[0].fragment).unwrap();
+        let HandshakeBody::ClientHello(hello) = &messages[0].body else {
+            panic!("expected a ClientHello");
+        };
+        assert_eq!(hello.cipher_suites, vec![0x1a1a, 0x1301, 0xc02f]);
+        assert_eq!(hello.alpn(), vec!["h2", "http/1.1"]);
+        assert_eq!(hello.supported_versions(), vec![0x0304, 0x0303]);
+        assert_eq!(hello.key_share_groups(), vec![0x001d]);
+        assert!(!hello.truncated);
+
+        // A ClientHello cut short by the segment boundary keeps its leading extensions
+        let cut = &payload[..payload.len() - 20];
+        let fields = fields(cut, 54);
+        let find = |name: &str| fields.iter().find(|f| f.name == name);
+        assert_eq!(find("Server Name").unwrap().value, "example.com");
+        assert!(find("Record Length").unwrap().expert.is_some());
+        assert!(find("Truncated").is_some());
+        assert!(find("Key Share Group").is_none());
+    }
+
+    #[test]
+    fn test_server_flight_and_alerts() {
+        let server_hello = [
+            &[0x03, 0x03][..],
+            &HELLO_RETRY_REQUEST_RANDOM,
+            &[0],
+            &[0x13, 0x01, 0],
+            &with_len16(
+                &[
+                    extension(EXT_SUPPORTED_VERSIONS, &[0x03, 0x04]),
+                    extension(EXT_KEY_SHARE, &[0x00, 0x17]),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let payload = [
+            record(
+                CONTENT_HANDSHAKE,
+                &handshake(HANDSHAKE_SERVER_HELLO, &server_hello),
+            ),
+            record(CONTENT_CHANGE_CIPHER_SPEC, &[1]),
+            record(CONTENT_HANDSHAKE, &[0x8f; 40]),
+            record(CONTENT_ALERT, &[2, 40]),
+        ]
+        .concat();
+        assert_eq!(
+            summarize(&payload).unwrap(),
+            "Hello Retry Request, Change Cipher Spec, Encrypted Handshake Message, Alert (Fatal, Handshake Failure)"
+        );
+
+        let fields = fields(&payload, 0);
+        let values = |name: &str| -> Vec<String> {
+            fields
+                .iter()
+                .filter(|f| f.name == name)
+                .map(|f| f.value.clone())
+                .collect()
+        };
+        assert_eq!(values("Supported Version"), vec!["0x0304 (TLS 1.3)"]);
+        assert_eq!(values("Key Share Group"), vec!["0x0017 (secp256r1)"]);
+        assert_eq!(values("Alert Description"), vec!["40 (Handshake Failure)"]);
+
+        assert!(summarize(b"GET / HTTP/1.1\r\n").is_none());
+        assert!(summarize(&[0x17, 0x03, 0x03]).is_none());
+    }
+}
@@ -0,0 +1,583 @@
+//! Tunnels carrying packets or whole frames over IP: GRE (RFC 2784/2890),
+//! ERSPAN over GRE, VXLAN (RFC 7348), Geneve (RFC 8926), IP-in-IP (RFC 2003)
+//! and 6in4 (RFC 4213). Tunnels are followed recursively, so the network
+//! layer returned by [`decapsulate`] is the innermost one.
+
+use super::encap::{self, Header, Tag};
+use super::{be_u16, be_u32, field};
+use crate::model::PacketField;
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+
+pub const VXLAN_PORT: u16 = 4789;
+pub const GENEVE_PORT: u16 = 6081;
+
+const IP_PROTO_IPIP: u8 = 4;
+const IP_PROTO_UDP: u8 = 17;
+const IP_PROTO_IPV6: u8 = 41;
+const IP_PROTO_GRE: u8 = 47;
+
+/// Transparent Ethernet Bridging: the payload is an Ethernet frame
+const ETHERTYPE_TEB: u16 = 0x6558;
+/// ERSPAN Type I (no header) and Type II
+const ETHERTYPE_ERSPAN: u16 = 0x88be;
+const ETHERTYPE_ERSPAN_III: u16 = 0x22eb;
+
+const GRE_CHECKSUM: u16 = 0x8000;
+const GRE_ROUTING: u16 = 0x4000;
+const GRE_KEY: u16 = 0x2000;
+const GRE_SEQUENCE: u16 = 0x1000;
+const GRE_VERSION: u16 = 0x0007;
+
+const IPV4_MIN_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+const VXLAN_HEADER_LEN: usize = 8;
+const VXLAN_FLAG_VNI: u8 = 0x08;
+const GENEVE_HEADER_LEN: usize = 8;
+const ERSPAN_II_HEADER_LEN: usize = 8;
+const ERSPAN_III_HEADER_LEN: usize = 12;
+/// Optional platform-specific subheader of ERSPAN Type III
+const ERSPAN_III_SUBHEADER_LEN: usize = 8;
+
+/// Upper bound on nested tunnels, so a crafted packet cannot recurse forever.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gre {
+    pub offset: usize,
+    /// Flags and version word
+    pub flags: u16,
+    pub protocol: u16,
+    pub checksum: Option<u16>,
+    pub key: Option<u32>,
+    pub sequence: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erspan {
+    pub offset: usize,
+    /// Header version: 1 for Type II, 2 for Type III
+    pub version: u8,
+    pub vlan: u16,
+    pub cos: u8,
+    pub session_id: u16,
+    /// Type II port index, or Type III timestamp
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vxlan {
+    pub offset: usize,
+    pub flags: u8,
+    pub vni: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geneve {
+    pub offset: usize,
+    pub version: u8,
+    /// Length of the options in bytes
+    pub options_len: usize,
+    pub oam: bool,
+    pub critical: bool,
+    pub protocol: u16,
+    pub vni: u32,
+}
+
+/// A header in front of the innermost network layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    /// Ethernet frame carried by a tunnel
+    Ethernet {
+        offset: usize,
+    },
+    Tag(Header),
+    /// Outer IPv4 header of a tunnel
+    Ipv4 {
+        offset: usize,
+    },
+    /// Outer IPv6 header of a tunnel
+    Ipv6 {
+        offset: usize,
+    },
+    /// Outer UDP header of a VXLAN or Geneve tunnel
+    Udp {
+        offset: usize,
+    },
+    Gre(Gre),
+    Erspan(Erspan),
+    Vxlan(Vxlan),
+    Geneve(Geneve),
+}
+
+/// A frame with its VLAN/MPLS headers and tunnels removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame<'a> {
+    /// Headers after the outer Ethernet header, outermost first
+    pub layers: Vec<Layer>,
+    /// EtherType of the innermost network layer
+    pub ethertype: EtherType,
+    pub payload: &'a [u8],
+    /// Offset of `payload` in the frame
+    pub offset: usize,
+    /// Offset of the innermost Ethernet header
+    pub ethernet_offset: usize,
+}
+
+impl Frame<'_> {
+    /// ID of the outermost VLAN tag.
+    pub fn vlan_id(&self) -> Option<u16> {
+        self.layers.iter().find_map(|layer| match layer {
+            Layer::Tag(Header {
+                tag: Tag::Vlan { id, .. },
+                ..
+            }) => Some(*id),
+            _ => None,
+        })
+    }
+
+    /// Tunnel identifiers (VXLAN/Geneve VNI, GRE key, ERSPAN session), outermost first.
+    pub fn tunnel_ids(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::Gre(gre) => gre.key.map(|key| format!("GRE key {}", key)),
+                Layer::Erspan(erspan) => Some(format!("ERSPAN session {}", erspan.session_id)),
+                Layer::Vxlan(vxlan) => Some(format!("VXLAN VNI {}", vxlan.vni)),
+                Layer::Geneve(geneve) => Some(format!("Geneve VNI {}", geneve.vni)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// What a tunnel carries.
+enum Inner {
+    Network(u16, usize),
+    Ethernet(usize),
+}
+
+fn u24_at(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 3)
+        .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]))
+}
+
+/// Peels VLAN/MPLS headers and tunnels down to the innermost network layer.
+pub fn decapsulate(raw_data: &[u8]) -> Option<Frame<'_>> {
+    let link = encap::decapsulate(raw_data)?;
+    let mut layers: Vec<Layer> = link.tags.into_iter().map(Layer::Tag).collect();
+    let mut ethertype = link.ethertype;
+    let mut offset = link.offset;
+    let mut ethernet_offset = 0;
+
+    for _ in 0..MAX_DEPTH {
+        let Some((outer, inner)) = enter(raw_data, ethertype, offset) else {
+            break;
+        };
+        let (ethernet, link) = match inner {
+            Inner::Network(next, at) => (None, encap::peel(raw_data, next, at)),
+            Inner::Ethernet(at) => match encap::decapsulate_at(raw_data, at) {
+                Some(link) => (Some(at), link),
+                None => break,
+            },
+        };
+        layers.extend(outer);
+        if let Some(at) = ethernet {
+            ethernet_offset = at;
+            layers.push(Layer::Ethernet { offset: at });
+        }
+        layers.extend(link.tags.into_iter().map(Layer::Tag));
+        ethertype = link.ethertype;
+        offset = link.offset;
+    }
+
+    Some(Frame {
+        layers,
+        ethertype,
+        payload: &raw_data[offset.min(raw_data.len())..],
+        offset,
+        ethernet_offset,
+    })
+}
+
+/// Opens the tunnel whose outer IP header starts at `offset`, if any.
+fn enter(raw_data: &[u8], ethertype: EtherType, offset: usize) -> Option<(Vec<Layer>, Inner)> {
+    let (outer, protocol, start) = match ethertype {
+        EtherTypes::Ipv4 => {
+            let header = raw_data.get(offset..offset + IPV4_MIN_HEADER_LEN)?;
+            let header_len = (header[0] & 0x0f) as usize * 4;
+            // Fragments are left alone; only a whole datagram carries a whole inner packet
+            let fragment = u16::from_be_bytes([header[6], header[7]]) & 0x3fff;
+            if header[0] >> 4 != 4 || header_len < IPV4_MIN_HEADER_LEN || fragment != 0 {
+                return None;
+            }
+            (Layer::Ipv4 { offset }, header[9], offset + header_len)
+        }
+        EtherTypes::Ipv6 => {
+            let header = raw_data.get(offset..offset + IPV6_HEADER_LEN)?;
+            if header[0] >> 4 != 6 {
+                return None;
+            }
+            (Layer::Ipv6 { offset }, header[6], offset + IPV6_HEADER_LEN)
+        }
+        _ => return None,
+    };
+
+    let version = raw_data.get(start).map(|b| b >> 4);
+    match protocol {
+        IP_PROTO_IPIP if version == Some(4) => {
+            Some((vec![outer], Inner::Network(EtherTypes::Ipv4.0, start)))
+        }
+        IP_PROTO_IPV6 if version == Some(6) => {
+            Some((vec![outer], Inner::Network(EtherTypes::Ipv6.0, start)))
+        }
+        IP_PROTO_GRE => {
+            let (mut layers, inner) = gre(raw_data, start)?;
+            layers.insert(0, outer);
+            Some((layers, inner))
+        }
+        IP_PROTO_UDP => {
+            let at = start + UDP_HEADER_LEN;
+            let (layer, inner) = match be_u16(raw_data, start + 2)? {
+                VXLAN_PORT => vxlan(raw_data, at)?,
+                GENEVE_PORT => geneve(raw_data, at)?,
+                _ => return None,
+            };
+            Some((vec![outer, Layer::Udp { offset: start }, layer], inner))
+        }
+        _ => None,
+    }
+}
+
+fn gre(raw_data: &[u8], offset: usize) -> Option<(Vec<Layer>, Inner)> {
+    let flags = be_u16(raw_data, offset)?;
+    let protocol = be_u16(raw_data, offset + 2)?;
+    // Version 1 is PPTP's enhanced GRE, which carries PPP rather than packets
+    if flags & (GRE_ROUTING | GRE_VERSION) != 0 {
+        return None;
+    }
+    let mut pos = offset + 4;
+    let mut optional = |present: bool| {
+        if !present {
+            return None;
+        }
+        let value = be_u32(raw_data, pos);
+        pos += 4;
+        value
+    };
+    let checksum = optional(flags & GRE_CHECKSUM != 0).map(|word| (word >> 16) as u16);
+    let key = optional(flags & GRE_KEY != 0);
+    let sequence = optional(flags & GRE_SEQUENCE != 0);
+    if pos > raw_data.len() {
+        return None;
+    }
+
+    let header = Layer::Gre(Gre {
+        offset,
+        flags,
+        protocol,
+        checksum,
+        key,
+        sequence,
+    });
+    match protocol {
+        ETHERTYPE_TEB => Some((vec![header], Inner::Ethernet(pos))),
+        // Type I has no ERSPAN header, and is told from Type II by the missing sequence number
+        ETHERTYPE_ERSPAN if sequence.is_none() => Some((vec![header], Inner::Ethernet(pos))),
+        ETHERTYPE_ERSPAN | ETHERTYPE_ERSPAN_III => {
+            let (erspan, inner) = erspan(raw_data, pos)?;
+            Some((vec![header, erspan], inner))
+        }
+        _ if protocol == EtherTypes::Ipv4.0
+            || protocol == EtherTypes::Ipv6.0
+            || protocol == encap::ETHERTYPE_MPLS
+            || protocol == encap::ETHERTYPE_MPLS_MULTICAST =>
+        {
+            Some((vec![header], Inner::Network(protocol, pos)))
+        }
+        _ => None,
+    }
+}
+
+fn erspan(raw_data: &[u8], offset: usize) -> Option<(Layer, Inner)> {
+    let word = be_u16(raw_data, offset)?;
+    let session = be_u16(raw_data, offset + 2)?;
+    let version = (word >> 12) as u8;
+    let header_len = match version {
+        1 => ERSPAN_II_HEADER_LEN,
+        2 => {
+            // The O flag announces the platform-specific subheader
+            let options = be_u16(raw_data, offset + 10)?;
+            ERSPAN_III_HEADER_LEN
+                + if options & 1 != 0 {
+                    ERSPAN_III_SUBHEADER_LEN
+                } else {
+                    0
+                }
+        }
+        _ => return None,
+    };
+    let index = match version {
+        1 => be_u32(raw_data, offset + 4)? & 0x000f_ffff,
+        _ => be_u32(raw_data, offset + 4)?,
+    };
+    let layer = Layer::Erspan(Erspan {
+        offset,
+        version,
+        vlan: word & 0x0fff,
+        cos: (session >> 13) as u8,
+        session_id: session & 0x03ff,
+        index,
+    });
+    Some((layer, Inner::Ethernet(offset + header_len)))
+}
+
+fn vxlan(raw_data: &[u8], offset: usize) -> Option<(Layer, Inner)> {
+    let flags = *raw_data.get(offset)?;
+    let vni = u24_at(raw_data, offset + 4)?;
+    if flags & VXLAN_FLAG_VNI == 0 {
+        return None;
+    }
+    let layer = Layer::Vxlan(Vxlan { offset, flags, vni });
+    Some((layer, Inner::Ethernet(offset + VXLAN_HEADER_LEN)))
+}
+
+fn geneve(raw_data: &[u8], offset: usize) -> Option<(Layer, Inner)> {
+    let header = raw_data.get(offset..offset + GENEVE_HEADER_LEN)?;
+    let version = header[0] >> 6;
+    if version != 0 {
+        return None;
+    }
+    let options_len = (header[0] & 0x3f) as usize * 4;
+    let protocol = u16::from_be_bytes([header[2], header[3]]);
+    let layer = Layer::Geneve(Geneve {
+        offset,
+        version,
+        options_len,
+        oam: header[1] & 0x80 != 0,
+        critical: header[1] & 0x40 != 0,
+        protocol,
+        vni: u32::from_be_bytes([0, header[4], header[5], header[6]]),
+    });
+    let payload = offset + GENEVE_HEADER_LEN + options_len;
+    if payload > raw_data.len() {
+        return None;
+    }
+    let inner = if protocol == ETHERTYPE_TEB {
+        Inner::Ethernet(payload)
+    } else if protocol == EtherTypes::Ipv4.0 || protocol == EtherTypes::Ipv6.0 {
+        Inner::Network(protocol, payload)
+    } else {
+        return None;
+    };
+    Some((layer, inner))
+}
+
+/// Builds the detail view fields for a GRE, ERSPAN, VXLAN or Geneve header.
+/// Other layers have no tunnel fields.
+pub fn fields(layer: &Layer) -> Vec<PacketField> {
+    match layer {
+        Layer::Gre(gre) => {
+            let at = |start: usize, len: usize| (gre.offset + start, gre.offset + start + len);
+            let mut fields = vec![
+                field(
+                    "Flags and Version",
+                    format!("0x{:04x}", gre.flags),
+                    at(0, 2),
+                ),
+                field("Protocol Type", format!("0x{:04x}", gre.protocol), at(2, 2)),
+            ];
+            let mut pos = 4;
+            if let Some(checksum) = gre.checksum {
+                fields.push(field("Checksum", format!("0x{:04x}", checksum), at(pos, 2)));
+                pos += 4;
+            }
+            if let Some(key) = gre.key {
+                fields.push(field("Key", key.to_string(), at(pos, 4)));
+                pos += 4;
+            }
+            if let Some(sequence) = gre.sequence {
+                fields.push(field("Sequence Number", sequence.to_string(), at(pos, 4)));
+            }
+            fields
+        }
+        Layer::Erspan(erspan) => {
+            let at =
+                |start: usize, len: usize| (erspan.offset + start, erspan.offset + start + len);
+            let (kind, index) = if erspan.version == 1 {
+                (
+                    "Type II",
+                    field("Index", erspan.index.to_string(), at(5, 3)),
+                )
+            } else {
+                (
+                    "Type III",
+                    field("Timestamp", erspan.index.to_string(), at(4, 4)),
+                )
+            };
+            vec![
+                field(
+                    "Version",
+                    format!("{} ({})", erspan.version, kind),
+                    at(0, 1),
+                ),
+                field("VLAN", erspan.vlan.to_string(), at(0, 2)),
+                field("COS", erspan.cos.to_string(), at(2, 1)),
+                field("Session ID", erspan.session_id.to_string(), at(2, 2)),
+                index,
+            ]
+        }
+        Layer::Vxlan(vxlan) => {
+            let at = |start: usize, len: usize| (vxlan.offset + start, vxlan.offset + start + len);
+            vec![
+                field("Flags", format!("0x{:02x}", vxlan.flags), at(0, 1)),
+                field("VNI", vxlan.vni.to_string(), at(4, 3)),
+            ]
+        }
+        Layer::Geneve(geneve) => {
+            let at =
+                |start: usize, len: usize| (geneve.offset + start, geneve.offset + start + len);
+            let mut fields = vec![
+                field("Version", geneve.version.to_string(), at(0, 1)),
+                field(
+                    "Options Length",
+                    format!("{} bytes", geneve.options_len),
+                    at(0, 1),
+                ),
+                field("OAM", u8::from(geneve.oam).to_string(), at(1, 1)),
+                field(
+                    "Critical Options",
+                    u8::from(geneve.critical).to_string(),
+                    at(1, 1),
+                ),
+                field(
+                    "Protocol Type",
+                    format!("0x{:04x}", geneve.protocol),
+                    at(2, 2),
+                ),
+                field("VNI", geneve.vni.to_string(), at(4, 3)),
+            ];
+            if geneve.options_len > 0 {
+                fields.push(field(
+                    "Options",
+                    format!("{} bytes", geneve.options_len),
+                    at(GENEVE_HEADER_LEN, geneve.options_len),
+                ));
+            }
+            fields
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_header(protocol: u8, payload_len: usize) -> Vec<u8> {
+        let total = (20 + payload_len) as u16;
+        let mut header = vec![0x45, 0, 0, 0, 0, 1, 0, 0, 64, protocol, 0, 0];
+        header[2..4].copy_from_slice(&total.to_be_bytes());
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        header
+    }
+
+    fn ethernet(ethertype: u16) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_vxlan_gre_and_ipip() {
+        // VXLAN VNI 4096 carrying a VLAN-tagged inner frame with IPv4/UDP
+        let inner_ip = ipv4_header(IP_PROTO_UDP, 8);
+        let mut inner = ethernet(encap::TPID_8021Q);
+        inner.extend_from_slice(&[0x00, 0x07, 0x08, 0x00]);
+        inner.extend_from_slice(&inner_ip);
+        inner.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0, 8, 0, 0]);
+        let mut udp = vec![0xc0, 0x00, 0x12, 0xb5, 0, 0, 0, 0];
+        udp.extend_from_slice(&[0x08, 0, 0, 0, 0x00, 0x10, 0x00, 0]);
+        udp.extend_from_slice(&inner);
+        let mut frame = ethernet(EtherTypes::Ipv4.0);
+        frame.extend_from_slice(&ipv4_header(IP_PROTO_UDP, udp.len()));
+        frame.extend_from_slice(&udp);
+
+        let decoded = decapsulate(&frame).unwrap();
+        assert_eq!(decoded.ethertype, EtherTypes::Ipv4);
+        assert_eq!(decoded.ethernet_offset, 50);
+        assert_eq!(decoded.offset, 68);
+        assert_eq!(decoded.tunnel_ids(), vec!["VXLAN VNI 4096".to_string()]);
+        assert_eq!(decoded.vlan_id(), Some(7));
+        assert!(matches!(
+            decoded.layers[..4],
+            [
+                Layer::Ipv4 { offset: 14 },
+                Layer::Udp { offset: 34 },
+                Layer::Vxlan(Vxlan { vni: 4096, .. }),
+                Layer::Ethernet { offset: 50 }
+            ]
+        ));
+
+        // GRE with a key, carrying IP-in-IP
+        let innermost = ipv4_header(IP_PROTO_UDP, 0);
+        let mut gre = vec![0x20, 0x00, 0x08, 0x00, 0, 0, 0, 42];
+        gre.extend_from_slice(&ipv4_header(IP_PROTO_IPIP, innermost.len()));
+        gre.extend_from_slice(&innermost);
+        let mut frame = ethernet(EtherTypes::Ipv4.0);
+        frame.extend_from_slice(&ipv4_header(IP_PROTO_GRE, gre.len()));
+        frame.extend_from_slice(&gre);
+
+        let decoded = decapsulate(&frame).unwrap();
+        assert_eq!(decoded.offset, 14 + 20 + 8 + 20);
+        assert_eq!(decoded.ethernet_offset, 0);
+        assert_eq!(decoded.tunnel_ids(), vec!["GRE key 42".to_string()]);
+        assert_eq!(decoded.layers.len(), 3);
+
+        // A fragment is not decapsulated
+        frame[14 + 6] = 0x20;
+        let decoded = decapsulate(&frame).unwrap();
+        assert_eq!(decoded.offset, 14);
+        assert!(decoded.layers.is_empty());
+    }
+
+    #[test]
+    fn test_erspan_and_geneve() {
+        // ERSPAN Type II, session 5, over GRE with a sequence number
+        let mut gre = vec![0x10, 0x00, 0x88, 0xbe, 0, 0, 0, 1];
+        gre.extend_from_slice(&[0x10, 0x64, 0x00, 0x05, 0, 0, 0, 3]);
+        gre.extend_from_slice(&ethernet(EtherTypes::Arp.0));
+        gre.extend_from_slice(&[0u8; 28]);
+        let mut frame = ethernet(EtherTypes::Ipv4.0);
+        frame.extend_from_slice(&ipv4_header(IP_PROTO_GRE, gre.len()));
+        frame.extend_from_slice(&gre);
+
+        let decoded = decapsulate(&frame).unwrap();
+        assert_eq!(decoded.ethertype, EtherTypes::Arp);
+        assert_eq!(decoded.ethernet_offset, 14 + 20 + 8 + 8);
+        assert!(matches!(
+            decoded.layers[2],
+            Layer::Erspan(Erspan {
+                version: 1,
+                vlan: 100,
+                session_id: 5,
+                index: 3,
+                ..
+            })
+        ));
+
+        // Geneve with 4 bytes of options, carrying IPv4 directly
+        let mut udp = vec![0xc0, 0x00, 0x17, 0xc1, 0, 0, 0, 0];
+        udp.extend_from_slice(&[0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x2a, 0]);
+        udp.extend_from_slice(&[0xaa; 4]);
+        udp.extend_from_slice(&ipv4_header(IP_PROTO_UDP, 0));
+        let mut frame = ethernet(EtherTypes::Ipv4.0);
+        frame.extend_from_slice(&ipv4_header(IP_PROTO_UDP, udp.len()));
+        frame.extend_from_slice(&udp);
+
+        let decoded = decapsulate(&frame).unwrap();
+        assert_eq!(decoded.offset, 14 + 20 + 8 + 8 + 4);
+        assert_eq!(decoded.tunnel_ids(), vec!["Geneve VNI 42".to_string()]);
+        assert_eq!(fields(&decoded.layers[2])[5].value, "42");
+    }
+}
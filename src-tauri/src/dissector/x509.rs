@@ -0,0 +1,347 @@
+//! Just enough DER to describe an X.509 certificate: subject, issuer,
+//! serial number, validity and subject alternative names.
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+/// `[0] EXPLICIT` version and `[3] EXPLICIT` extensions in TBSCertificate
+const TAG_VERSION: u8 = 0xa0;
+const TAG_EXTENSIONS: u8 = 0xa3;
+
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Short names for the attribute types that appear in practice.
+const ATTRIBUTE_NAMES: &[(&[u8], &str)] = &[
+    (&[0x55, 0x04, 0x03], "CN"),
+    (&[0x55, 0x04, 0x05], "serialNumber"),
+    (&[0x55, 0x04, 0x06], "C"),
+    (&[0x55, 0x04, 0x07], "L"),
+    (&[0x55, 0x04, 0x08], "ST"),
+    (&[0x55, 0x04, 0x09], "street"),
+    (&[0x55, 0x04, 0x0a], "O"),
+    (&[0x55, 0x04, 0x0b], "OU"),
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01],
+        "emailAddress",
+    ),
+    (
+        &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19],
+        "DC",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub subject_alt_names: Vec<String>,
+}
+
+impl Certificate {
+    pub fn is_self_signed(&self) -> bool {
+        self.subject == self.issuer
+    }
+}
+
+/// Reads one TLV at `pos`, returning (tag, contents, offset past the value).
+fn read_tlv(data: &[u8], pos: usize) -> Result<(u8, &[u8], usize), String> {
+    let tag = *data.get(pos).ok_or("DER value truncated")?;
+    let first = *data.get(pos + 1).ok_or("DER length truncated")?;
+    let (len, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 {
+            return Err(format!("Unsupported DER length form 0x{:02x}", first));
+        }
+        let bytes = data
+            .get(pos + 2..pos + 2 + count)
+            .ok_or("DER length truncated")?;
+        let len = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, 2 + count)
+    };
+    let start = pos + header;
+    let contents = data
+        .get(start..start + len)
+        .ok_or_else(|| format!("DER value with tag 0x{:02x} truncated", tag))?;
+    Ok((tag, contents, start + len))
+}
+
+/// Reads a TLV that must carry `expected`.
+fn expect_tlv(data: &[u8], pos: usize, expected: u8) -> Result<(&[u8], usize), String> {
+    let (tag, contents, next) = read_tlv(data, pos)?;
+    if tag != expected {
+        return Err(format!(
+            "Expected DER tag 0x{:02x}, found 0x{:02x}",
+            expected, tag
+        ));
+    }
+    Ok((contents, next))
+}
+
+/// Iterates over the TLVs packed in a constructed value.
+fn children(data: &[u8]) -> Result<Vec<(u8, &[u8])>, String> {
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (tag, contents, next) = read_tlv(data, pos)?;
+        items.push((tag, contents));
+        pos = next;
+    }
+    Ok(items)
+}
+
+fn format_oid(oid: &[u8]) -> String {
+    let Some((&first, rest)) = oid.split_first() else {
+        return String::new();
+    };
+    let top = (first / 40).min(2);
+    let mut arcs = vec![top as u64, (first - 40 * top) as u64];
+    let mut value = 0u64;
+    for &byte in rest {
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            arcs.push(value);
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(|arc| arc.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Formats a Name as "CN=example.com, O=Example, C=US" in encoded order.
+fn format_name(name: &[u8]) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for (tag, rdn) in children(name)? {
+        if tag != TAG_SET {
+            return Err("Malformed distinguished name".to_string());
+        }
+        for (_, attribute) in children(rdn)? {
+            let (oid, next) = expect_tlv(attribute, 0, TAG_OID)?;
+            let (_, value, _) = read_tlv(attribute, next)?;
+            let key = ATTRIBUTE_NAMES
+                .iter()
+                .find(|(known, _)| *known == oid)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format_oid(oid));
+            parts.push(format!("{}={}", key, String::from_utf8_lossy(value)));
+        }
+    }
+    Ok(parts.join(", "))
+}
+
+/// Formats UTCTime or GeneralizedTime as "2024-01-31 12:00:00 UTC".
+fn format_time(tag: u8, value: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(value).map_err(|_| "Invalid certificate time".to_string())?;
+    if !text.is_ascii() {
+        return Err("Invalid certificate time".to_string());
+    }
+    let digits = text.trim_end_matches('Z');
+    let (year, rest) = match tag {
+        TAG_UTC_TIME if digits.len() >= 10 => {
+            let yy: u32 = digits[..2].parse().map_err(|_| "Invalid UTCTime")?;
+            // RFC 5280: two-digit years 50-99 are 19xx
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &digits[2..])
+        }
+        TAG_GENERALIZED_TIME if digits.len() >= 12 => (
+            digits[..4].parse().map_err(|_| "Invalid GeneralizedTime")?,
+            &digits[4..],
+        ),
+        _ => return Err(format!("Unsupported certificate time '{}'", text)),
+    };
+    let part = |i: usize| rest.get(i..i + 2).unwrap_or("00");
+    Ok(format!(
+        "{}-{}-{} {}:{}:{} UTC",
+        year,
+        part(0),
+        part(2),
+        part(4),
+        part(6),
+        part(8)
+    ))
+}
+
+fn format_general_name(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        // rfc822Name, dNSName, uniformResourceIdentifier
+        0x81 | 0x82 | 0x86 => Some(String::from_utf8_lossy(value).into_owned()),
+        // iPAddress
+        0x87 => match value.len() {
+            4 => Some(std::net::Ipv4Addr::new(value[0], value[1], value[2], value[3]).to_string()),
+            16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(value);
+                Some(std::net::Ipv6Addr::from(octets).to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn subject_alt_names(extensions: &[u8]) -> Result<Vec<String>, String> {
+    let (list, _) = expect_tlv(extensions, 0, TAG_SEQUENCE)?;
+    for (_, extension) in children(list)? {
+        let (oid, mut pos) = expect_tlv(extension, 0, TAG_OID)?;
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        // Skip the optional `critical` BOOLEAN
+        let (tag, _, next) = read_tlv(extension, pos)?;
+        if tag != TAG_OCTET_STRING {
+            pos = next;
+        }
+        let (value, _) = expect_tlv(extension, pos, TAG_OCTET_STRING)?;
+        let (names, _) = expect_tlv(value, 0, TAG_SEQUENCE)?;
+        return Ok(children(names)?
+            .into_iter()
+            .filter_map(|(tag, value)| format_general_name(tag, value))
+            .collect());
+    }
+    Ok(Vec::new())
+}
+
+/// Parses a DER-encoded certificate.
+pub fn parse_certificate(der: &[u8]) -> Result<Certificate, String> {
+    let (certificate, _) = expect_tlv(der, 0, TAG_SEQUENCE)?;
+    let (tbs, _) = expect_tlv(certificate, 0, TAG_SEQUENCE)?;
+
+    let mut pos = 0;
+    let (tag, _, next) = read_tlv(tbs, pos)?;
+    if tag == TAG_VERSION {
+        pos = next;
+    }
+    let (serial, next) = expect_tlv(tbs, pos, TAG_INTEGER)?;
+    let (_signature, next) = expect_tlv(tbs, next, TAG_SEQUENCE)?;
+    let (issuer, next) = expect_tlv(tbs, next, TAG_SEQUENCE)?;
+    let (validity, next) = expect_tlv(tbs, next, TAG_SEQUENCE)?;
+    let (subject, next) = expect_tlv(tbs, next, TAG_SEQUENCE)?;
+    let (_spki, mut pos) = expect_tlv(tbs, next, TAG_SEQUENCE)?;
+
+    let (before_tag, before, next) = read_tlv(validity, 0)?;
+    let (after_tag, after, _) = read_tlv(validity, next)?;
+
+    // Optional issuerUniqueID [1], subjectUniqueID [2], then extensions [3]
+    let mut subject_alt_names_list = Vec::new();
+    while pos < tbs.len() {
+        let (tag, contents, next) = read_tlv(tbs, pos)?;
+        if tag == TAG_EXTENSIONS {
+            subject_alt_names_list = subject_alt_names(contents)?;
+        }
+        pos = next;
+    }
+
+    let serial = serial.strip_prefix(&[0]).unwrap_or(serial);
+    Ok(Certificate {
+        subject: format_name(subject)?,
+        issuer: format_name(issuer)?,
+        serial: serial
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":"),
+        not_before: format_time(before_tag, before)?,
+        not_after: format_time(after_tag, after)?,
+        subject_alt_names: subject_alt_names_list,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if contents.len() < 0x80 {
+            out.push(contents.len() as u8);
+        } else {
+            out.extend_from_slice(&[0x82, (contents.len() >> 8) as u8, contents.len() as u8]);
+        }
+        out.extend_from_slice(contents);
+        out
+    }
+
+    fn name(attributes: &[(&[u8], &str)]) -> Vec<u8> {
+        let rdns: Vec<u8> = attributes
+            .iter()
+            .flat_map(|(oid, value)| {
+                let attribute = [tlv(TAG_OID, oid), tlv(0x0c, value.as_bytes())].concat();
+                tlv(TAG_SET, &tlv(TAG_SEQUENCE, &attribute))
+            })
+            .collect();
+        tlv(TAG_SEQUENCE, &rdns)
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let san = tlv(
+            TAG_SEQUENCE,
+            &[tlv(0x82, b"example.com"), tlv(0x87, &[10, 0, 0, 1])].concat(),
+        );
+        let extension = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_OID, OID_SUBJECT_ALT_NAME),
+                tlv(0x01, &[0xff]),
+                tlv(TAG_OCTET_STRING, &san),
+            ]
+            .concat(),
+        );
+        let tbs = [
+            tlv(TAG_VERSION, &tlv(TAG_INTEGER, &[2])),
+            tlv(TAG_INTEGER, &[0x00, 0x9a, 0x01]),
+            tlv(
+                TAG_SEQUENCE,
+                &tlv(TAG_OID, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]),
+            ),
+            name(&[
+                (&[0x55, 0x04, 0x06], "US"),
+                (&[0x55, 0x04, 0x0a], "Example CA"),
+            ]),
+            tlv(
+                TAG_SEQUENCE,
+                &[
+                    tlv(TAG_UTC_TIME, b"240131120000Z"),
+                    tlv(TAG_GENERALIZED_TIME, b"20250131120000Z"),
+                ]
+                .concat(),
+            ),
+            name(&[
+                (&[0x55, 0x04, 0x03], "example.com"),
+                (&[0x55, 0x04, 0x63], "x"),
+            ]),
+            tlv(TAG_SEQUENCE, &[]),
+            tlv(TAG_EXTENSIONS, &tlv(TAG_SEQUENCE, &extension)),
+        ]
+        .concat();
+        let der = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_SEQUENCE, &tbs),
+                tlv(TAG_SEQUENCE, &[]),
+                tlv(0x03, &[0]),
+            ]
+            .concat(),
+        );
+
+        let cert = parse_certificate(&der).unwrap();
+        assert_eq!(cert.subject, "CN=example.com, 2.5.4.99=x");
+        assert_eq!(cert.issuer, "C=US, O=Example CA");
+        assert_eq!(cert.serial, "9a:01");
+        assert_eq!(cert.not_before, "2024-01-31 12:00:00 UTC");
+        assert_eq!(cert.not_after, "2025-01-31 12:00:00 UTC");
+        assert_eq!(cert.subject_alt_names, vec!["example.com", "10.0.0.1"]);
+        assert!(!cert.is_self_signed());
+
+        assert!(parse_certificate(&der[..der.len() - 5]).is_err());
+    }
+}
@@ -0,0 +1,1980 @@
+//! Display filter language.
+//!
+//! Filters are parsed into an [`Expr`] tree supporting `and`/`or`/`not`,
+//! parentheses, comparisons (`==`, `!=`, `<`, `>`, `<=`, `>=`), `contains`,
+//! `matches`, bit tests (`tcp.flags & 0x04`), CIDR membership and port ranges, e.g.
+//! `tcp and port 443 and not ip 10.0.0.5` or `ip.src == 10.0.0.0/8 and frame.len > 1000`.
+//! The legacy `prefix:value` shorthand (`protocol:dns`, `src:10.0.0.1`) is still accepted.
+//! A bare address or network such as `10.0.0.0/8` stands for `ip.addr == 10.0.0.0/8`.
+//! Macros (`$internal`, or `$peer(10.0.0.5)` with `$1` placeholders in the body)
+//! are expanded while tokenizing, see [`parse_with_macros`].
+//! Besides the summary fields below, any field published in the dissector's
+//! [`FIELD_REGISTRY`](crate::dissector::FIELD_REGISTRY) (e.g. `http.method`,
+//! `ip.ttl`) can be compared or tested for presence by name.
+//!
+//! A parsed filter is split by [`plan`] into a parameterized SQL clause for the
+//! predicates SQLite can evaluate, plus a residual expression that is evaluated
+//! in Rust over each remaining packet.
+
+use crate::dissector::{self, FieldDef, FieldType};
+use crate::model::{PacketDetail, PacketSummary};
+use regex::Regex;
+use rusqlite::types::Value as SqlValue;
+use serde::Serialize;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+/// A filter syntax or semantic error, with the byte offset where it was detected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl FilterError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        FilterError {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Comparison operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+    /// Bit test: true when the field shares any set bit with the operand
+    BitAnd,
+}
+
+impl CmpOp {
+    fn is_ordering(self) -> bool {
+        matches!(self, CmpOp::Lt | CmpOp::Le | CmpOp::Gt | CmpOp::Ge)
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+            CmpOp::BitAnd => "&",
+            CmpOp::Contains | CmpOp::Matches => "",
+        }
+    }
+}
+
+/// Filterable packet fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// `frame.len`: captured length in bytes
+    FrameLen,
+    /// `protocol`: protocol column shown in the packet list
+    Protocol,
+    /// `info`: info column shown in the packet list
+    Info,
+    /// `vlan.id`: ID of the outermost VLAN tag
+    VlanId,
+    /// `ip.src`
+    IpSrc,
+    /// `ip.dst`
+    IpDst,
+    /// `ip.addr`: either source or destination
+    IpAddr,
+    /// `ip.proto`: IP protocol number (next header for IPv6)
+    IpProto,
+    /// `srcport`, or `tcp.srcport`/`udp.srcport` when restricted to a transport
+    SrcPort(Option<u8>),
+    /// `dstport`, or `tcp.dstport`/`udp.dstport`
+    DstPort(Option<u8>),
+    /// `port`, or `tcp.port`/`udp.port`: either source or destination
+    Port(Option<u8>),
+    /// `tag`: tags assigned by the colouring rules
+    Tag,
+    /// `payload`: transport payload, as text
+    Payload,
+    /// A field from the dissector's registry, evaluated on the dissected packet
+    Dissected(&'static FieldDef),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Number,
+    Port,
+    Text,
+    Address,
+}
+
+const IP_PROTO_ICMP: u8 = 1;
+pub(crate) const IP_PROTO_TCP: u8 = 6;
+pub(crate) const IP_PROTO_UDP: u8 = 17;
+pub(crate) const IP_PROTO_ICMPV6: u8 = 58;
+
+impl Field {
+    /// Resolves a field name (including aliases) to a field.
+    pub fn lookup(name: &str) -> Option<Field> {
+        let field = match name.to_ascii_lowercase().as_str() {
+            "frame.len" | "len" | "length" => Field::FrameLen,
+            "protocol" => Field::Protocol,
+            "info" => Field::Info,
+            "vlan.id" | "vlan" => Field::VlanId,
+            "ip.src" | "src" => Field::IpSrc,
+            "ip.dst" | "dst" => Field::IpDst,
+            "ip.addr" | "addr" | "host" => Field::IpAddr,
+            "ip.proto" => Field::IpProto,
+            "srcport" => Field::SrcPort(None),
+            "dstport" => Field::DstPort(None),
+            "port" => Field::Port(None),
+            "tcp.srcport" => Field::SrcPort(Some(IP_PROTO_TCP)),
+            "tcp.dstport" => Field::DstPort(Some(IP_PROTO_TCP)),
+            "tcp.port" => Field::Port(Some(IP_PROTO_TCP)),
+            "udp.srcport" => Field::SrcPort(Some(IP_PROTO_UDP)),
+            "udp.dstport" => Field::DstPort(Some(IP_PROTO_UDP)),
+            "udp.port" => Field::Port(Some(IP_PROTO_UDP)),
+            "tag" | "tags" => Field::Tag,
+            "payload" => Field::Payload,
+            other => return dissector::lookup_field(other).map(Field::Dissected),
+        };
+        Some(field)
+    }
+
+    /// Canonical name of the field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::FrameLen => "frame.len",
+            Field::Protocol => "protocol",
+            Field::Info => "info",
+            Field::VlanId => "vlan.id",
+            Field::IpSrc => "ip.src",
+            Field::IpDst => "ip.dst",
+            Field::IpAddr => "ip.addr",
+            Field::IpProto => "ip.proto",
+            Field::SrcPort(None) => "srcport",
+            Field::DstPort(None) => "dstport",
+            Field::Port(None) => "port",
+            Field::SrcPort(Some(IP_PROTO_TCP)) => "tcp.srcport",
+            Field::DstPort(Some(IP_PROTO_TCP)) => "tcp.dstport",
+            Field::Port(Some(IP_PROTO_TCP)) => "tcp.port",
+            Field::SrcPort(Some(_)) => "udp.srcport",
+            Field::DstPort(Some(_)) => "udp.dstport",
+            Field::Port(Some(_)) => "udp.port",
+            Field::Tag => "tag",
+            Field::Payload => "payload",
+            Field::Dissected(def) => def.name,
+        }
+    }
+
+    fn kind(&self) -> FieldKind {
+        match self {
+            Field::FrameLen | Field::VlanId | Field::IpProto => FieldKind::Number,
+            Field::SrcPort(_) | Field::DstPort(_) | Field::Port(_) => FieldKind::Port,
+            Field::Protocol | Field::Info | Field::Tag | Field::Payload => FieldKind::Text,
+            Field::IpSrc | Field::IpDst | Field::IpAddr => FieldKind::Address,
+            Field::Dissected(def) => match def.field_type {
+                FieldType::Number => FieldKind::Number,
+                FieldType::Text => FieldKind::Text,
+                FieldType::Address => FieldKind::Address,
+            },
+        }
+    }
+
+    /// Database columns holding the field's value(s).
+    fn columns(&self) -> &'static [&'static str] {
+        match self {
+            Field::FrameLen => &["length"],
+            Field::Protocol => &["protocol"],
+            Field::Info => &["info"],
+            Field::VlanId => &["vlan_id"],
+            Field::IpSrc => &["source_addr"],
+            Field::IpDst => &["dest_addr"],
+            Field::IpAddr => &["source_addr", "dest_addr"],
+            Field::IpProto => &["ip_proto"],
+            Field::SrcPort(_) => &["src_port"],
+            Field::DstPort(_) => &["dst_port"],
+            Field::Port(_) => &["src_port", "dst_port"],
+            Field::Tag => &["tags"],
+            Field::Payload | Field::Dissected(_) => &[],
+        }
+    }
+
+    /// Transport protocol a port field is restricted to, if any.
+    pub(crate) fn transport(&self) -> Option<u8> {
+        match self {
+            Field::SrcPort(t) | Field::DstPort(t) | Field::Port(t) => *t,
+            _ => None,
+        }
+    }
+}
+
+/// A literal operand of a comparison.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(i64),
+    /// Inclusive numeric range, e.g. a port range `1000-2000`
+    Range(i64, i64),
+    Ip(IpAddr),
+    /// Network address and prefix length, e.g. `10.0.0.0/8`
+    Cidr(IpAddr, u8),
+    Text(String),
+    Regex(Regex),
+}
+
+/// Protocol names accepted as bare filter terms.
+const PROTOCOL_NAMES: &[&str] = &[
+    "tcp",
+    "udp",
+    "icmp",
+    "icmpv6",
+    "ip",
+    "ipv4",
+    "ipv6",
+    "arp",
+    "http",
+    "https",
+    "tls",
+    "quic",
+    "ssh",
+    "dns",
+    "mdns",
+    "dhcp",
+    "dhcpv6",
+    "tftp",
+    "ntp",
+    "netbios",
+    "snmp",
+    "ldap",
+    "smb",
+    "smb2",
+    "smb3",
+    "syslog",
+    "ipp",
+    "ssdp",
+    "sip",
+    "json",
+    "elasticsearch",
+    "mongodb",
+];
+
+/// Prefixes of the legacy `prefix:value` filter shorthand.
+const LEGACY_PREFIXES: &[&str] = &["protocol", "ip", "src", "dst", "port", "tag"];
+
+/// A parsed display filter.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        field: Field,
+        op: CmpOp,
+        value: Value,
+    },
+    /// Bare field name: true when the packet carries the field
+    Exists(Field),
+    /// Bare protocol name such as `tcp` or `dns`
+    Protocol(String),
+    /// Free-text search across all summary columns
+    Search(String),
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            CmpOp::Contains => "contains",
+            CmpOp::Matches => "matches",
+            CmpOp::Eq => "==",
+            other => other.sql(),
+        };
+        f.write_str(text)
+    }
+}
+
+/// Writes `text` as a quoted filter string.
+fn write_quoted(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Range(lo, hi) => write!(f, "{}-{}", lo, hi),
+            Value::Ip(ip) => write!(f, "{}", ip),
+            Value::Cidr(ip, prefix) => write!(f, "{}/{}", ip, prefix),
+            Value::Text(text) => write_quoted(f, text),
+            Value::Regex(re) => {
+                let pattern = re.as_str();
+                write_quoted(f, pattern.strip_prefix("(?i)").unwrap_or(pattern))
+            }
+        }
+    }
+}
+
+/// Renders the expression back into filter syntax that parses to the same tree.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::And(lhs, rhs) => {
+                let wrap = |e: &Expr| matches!(e, Expr::Or(..));
+                write_operand(f, lhs, wrap(lhs))?;
+                f.write_str(" and ")?;
+                write_operand(f, rhs, wrap(rhs) || matches!(**rhs, Expr::And(..)))
+            }
+            Expr::Or(lhs, rhs) => {
+                write!(f, "{} or ", lhs)?;
+                write_operand(f, rhs, matches!(**rhs, Expr::Or(..)))
+            }
+            Expr::Not(inner) => {
+                f.write_str("not ")?;
+                write_operand(f, inner, matches!(**inner, Expr::And(..) | Expr::Or(..)))
+            }
+            Expr::Compare { field, op, value } => write!(f, "{} {} {}", field.name(), op, value),
+            Expr::Exists(field) => f.write_str(field.name()),
+            Expr::Protocol(name) => f.write_str(name),
+            Expr::Search(text) => write_quoted(f, text),
+        }
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter<'_>, expr: &Expr, parenthesize: bool) -> fmt::Result {
+    if parenthesize {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Lexer
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(CmpOp),
+    Word(String),
+    Str(String),
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    pos: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | ':' | '/' | '-' | '_')
+}
+
+/// Filter macros by name (without the leading `$`), mapped to their body.
+pub type Macros = HashMap<String, String>;
+
+/// Nesting limit for macros referencing other macros, which also stops cycles.
+const MAX_MACRO_DEPTH: usize = 8;
+
+fn is_macro_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Checks a macro name: letters, digits and underscores, not starting with a digit.
+pub fn is_valid_macro_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(is_macro_name_char)
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Number of `$N` placeholders a macro body expects (the highest N used).
+pub fn macro_arity(body: &str) -> usize {
+    let mut arity = 0;
+    let mut rest = body;
+    while let Some(i) = rest.find('$') {
+        rest = &rest[i + 1..];
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(n) = digits.parse::<usize>() {
+            arity = arity.max(n);
+        }
+    }
+    arity
+}
+
+/// Expands a `$name` or `$name(arg, ...)` reference starting at `pos`.
+///
+/// Returns the expansion wrapped in parentheses, with every token positioned at
+/// the reference so errors point at the macro use, and the byte length consumed.
+fn expand_macro(
+    input: &str,
+    pos: usize,
+    macros: &Macros,
+    depth: usize,
+) -> Result<(Vec<Token>, usize), FilterError> {
+    let rest = &input[pos + 1..];
+    let name_len = rest
+        .find(|c: char| !is_macro_name_char(c))
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if name.is_empty() {
+        return Err(FilterError::new("Expected a macro name after '$'", pos));
+    }
+    let mut len = 1 + name_len;
+
+    // Arguments follow the name without whitespace: `$name(a, b)`
+    let mut args = Vec::new();
+    if rest[name_len..].starts_with('(') {
+        let mut nesting = 0;
+        let mut current = String::new();
+        let mut closed = false;
+        for (i, c) in rest[name_len..].char_indices() {
+            match c {
+                '(' if nesting == 0 => {
+                    nesting += 1;
+                    continue;
+                }
+                '(' => nesting += 1,
+                ')' if nesting == 1 => {
+                    args.push(current.trim().to_string());
+                    len += i + 1;
+                    closed = true;
+                    break;
+                }
+                ')' => nesting -= 1,
+                ',' if nesting == 1 => {
+                    args.push(current.trim().to_string());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        if !closed {
+            return Err(FilterError::new("Unterminated macro arguments", pos));
+        }
+        if args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+    }
+
+    let body = macros
+        .get(name)
+        .ok_or_else(|| FilterError::new(format!("Unknown macro '${}'", name), pos))?;
+    let arity = macro_arity(body);
+    if args.len() != arity {
+        return Err(FilterError::new(
+            format!(
+                "Macro '${}' expects {} argument(s), found {}",
+                name,
+                arity,
+                args.len()
+            ),
+            pos,
+        ));
+    }
+    if depth >= MAX_MACRO_DEPTH {
+        return Err(FilterError::new(
+            format!("Macro '${}' is nested too deeply (is it recursive?)", name),
+            pos,
+        ));
+    }
+
+    // Substitute from the highest placeholder down so `$1` never eats `$10`
+    let mut expanded = body.clone();
+    for (i, arg) in args.iter().enumerate().rev() {
+        expanded = expanded.replace(&format!("${}", i + 1), arg);
+    }
+
+    let inner = tokenize_with_macros(&expanded, macros, depth + 1)
+        .map_err(|e| FilterError::new(format!("In macro '${}': {}", name, e.message), pos))?;
+    let mut tokens = Vec::with_capacity(inner.len() + 1);
+    tokens.push(Token {
+        tok: Tok::LParen,
+        pos,
+    });
+    tokens.extend(
+        inner
+            .into_iter()
+            .filter(|t| t.tok != Tok::Eof)
+            .map(|t| Token { tok: t.tok, pos }),
+    );
+    tokens.push(Token {
+        tok: Tok::RParen,
+        pos,
+    });
+    Ok((tokens, len))
+}
+
+fn tokenize_with_macros(
+    input: &str,
+    macros: &Macros,
+    depth: usize,
+) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == '$' {
+            let (expansion, len) = expand_macro(input, pos, macros, depth)?;
+            tokens.extend(expansion);
+            while chars.peek().is_some_and(|&(i, _)| i < pos + len) {
+                chars.next();
+            }
+            continue;
+        }
+
+        let two: String = input[pos..].chars().take(2).collect();
+        let (tok, len) = match two.as_str() {
+            "==" => (Tok::Op(CmpOp::Eq), 2),
+            "!=" => (Tok::Op(CmpOp::Ne), 2),
+            "<=" => (Tok::Op(CmpOp::Le), 2),
+            ">=" => (Tok::Op(CmpOp::Ge), 2),
+            "&&" => (Tok::And, 2),
+            "||" => (Tok::Or, 2),
+            _ => match c {
+                '(' => (Tok::LParen, 1),
+                ')' => (Tok::RParen, 1),
+                '=' => (Tok::Op(CmpOp::Eq), 1),
+                '<' => (Tok::Op(CmpOp::Lt), 1),
+                '>' => (Tok::Op(CmpOp::Gt), 1),
+                '!' => (Tok::Not, 1),
+                '"' => {
+                    chars.next();
+                    let mut text = String::new();
+                    let mut closed = false;
+                    while let Some((_, ch)) = chars.next() {
+                        match ch {
+                            '"' => {
+                                closed = true;
+                                break;
+                            }
+                            '\\' => match chars.next() {
+                                Some((_, escaped)) => text.push(escaped),
+                                None => break,
+                            },
+                            _ => text.push(ch),
+                        }
+                    }
+                    if !closed {
+                        return Err(FilterError::new("Unterminated string", pos));
+                    }
+                    tokens.push(Token {
+                        tok: Tok::Str(text),
+                        pos,
+                    });
+                    continue;
+                }
+                c if is_word_char(c) => {
+                    let mut end = pos;
+                    while let Some(&(i, ch)) = chars.peek() {
+                        if !is_word_char(ch) {
+                            break;
+                        }
+                        end = i + ch.len_utf8();
+                        chars.next();
+                    }
+                    let word = &input[pos..end];
+                    let tok = match word.to_ascii_lowercase().as_str() {
+                        "and" => Tok::And,
+                        "or" => Tok::Or,
+                        "not" => Tok::Not,
+                        "eq" => Tok::Op(CmpOp::Eq),
+                        "ne" => Tok::Op(CmpOp::Ne),
+                        "lt" => Tok::Op(CmpOp::Lt),
+                        "le" => Tok::Op(CmpOp::Le),
+                        "gt" => Tok::Op(CmpOp::Gt),
+                        "ge" => Tok::Op(CmpOp::Ge),
+                        "contains" => Tok::Op(CmpOp::Contains),
+                        "matches" => Tok::Op(CmpOp::Matches),
+                        _ => Tok::Word(word.to_string()),
+                    };
+                    tokens.push(Token { tok, pos });
+                    continue;
+                }
+                '~' => (Tok::Op(CmpOp::Matches), 1),
+                '&' => (Tok::Op(CmpOp::BitAnd), 1),
+                _ => {
+                    return Err(FilterError::new(
+                        format!("Unexpected character '{}'", c),
+                        pos,
+                    ))
+                }
+            },
+        };
+
+        for _ in 0..len {
+            chars.next();
+        }
+        tokens.push(Token { tok, pos });
+    }
+
+    tokens.push(Token {
+        tok: Tok::Eof,
+        pos: input.len(),
+    });
+    Ok(tokens)
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+// ---------------------------------------------------------------------------
+
+/// Parses a display filter string.
+pub fn parse(input: &str) -> Result<Expr, FilterError> {
+    parse_with_macros(input, &Macros::new())
+}
+
+/// Parses a display filter string, expanding `$name` macro references.
+pub fn parse_with_macros(input: &str, macros: &Macros) -> Result<Expr, FilterError> {
+    let tokens = tokenize_with_macros(input, macros, 0)?;
+    let mut parser = Parser { tokens, index: 0 };
+    let expr = parser.parse_or()?;
+    let trailing = parser.peek();
+    if trailing.tok != Tok::Eof {
+        return Err(FilterError::new(
+            "Expected 'and', 'or' or end of filter",
+            trailing.pos,
+        ));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.index].clone();
+        if self.index < self.tokens.len() - 1 {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FilterError> {
+        let mut lhs = self.parse_and()?;
+        while self.peek().tok == Tok::Or {
+            self.advance();
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FilterError> {
+        let mut lhs = self.parse_not()?;
+        while self.peek().tok == Tok::And {
+            self.advance();
+            let rhs = self.parse_not()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, FilterError> {
+        if self.peek().tok == Tok::Not {
+            self.advance();
+            let inner = self.parse_not()?;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, FilterError> {
+        let token = self.advance();
+        match token.tok {
+            Tok::LParen => {
+                let expr = self.parse_or()?;
+                let close = self.advance();
+                if close.tok != Tok::RParen {
+                    return Err(FilterError::new("Expected ')'", close.pos));
+                }
+                Ok(expr)
+            }
+            Tok::Str(text) => Ok(Expr::Search(text)),
+            Tok::Word(word) => self.parse_term(word, token.pos),
+            Tok::Eof => Err(FilterError::new("Unexpected end of filter", token.pos)),
+            Tok::RParen => Err(FilterError::new("Unexpected ')'", token.pos)),
+            Tok::And | Tok::Or => Err(FilterError::new(
+                "Expected a filter term before 'and'/'or'",
+                token.pos,
+            )),
+            Tok::Not => unreachable!("'not' is handled by parse_not"),
+            Tok::Op(_) => Err(FilterError::new(
+                "Expected a field name before the operator",
+                token.pos,
+            )),
+        }
+    }
+
+    /// True when the next token can serve as a value operand.
+    fn value_follows(&self) -> bool {
+        matches!(self.peek().tok, Tok::Word(_) | Tok::Str(_))
+    }
+
+    fn parse_term(&mut self, word: String, pos: usize) -> Result<Expr, FilterError> {
+        let lower = word.to_ascii_lowercase();
+
+        // Legacy `prefix:value` shorthand
+        if let Some((prefix, rest)) = lower.split_once(':') {
+            if LEGACY_PREFIXES.contains(&prefix) {
+                let value_pos = pos + prefix.len() + 1;
+                let (value, value_pos) = if rest.is_empty() {
+                    let next = self.advance();
+                    match next.tok {
+                        Tok::Word(w) | Tok::Str(w) => (w, next.pos),
+                        _ => return Err(FilterError::new("Expected a value", next.pos)),
+                    }
+                } else {
+                    (word[prefix.len() + 1..].to_string(), value_pos)
+                };
+                return legacy_term(prefix, &value, value_pos);
+            }
+        }
+
+        // Field comparison
+        if let Tok::Op(op) = self.peek().tok {
+            let field = Field::lookup(&lower)
+                .ok_or_else(|| FilterError::new(format!("Unknown field '{}'", word), pos))?;
+            self.advance();
+            let operand = self.advance();
+            let text = match operand.tok {
+                Tok::Word(w) | Tok::Str(w) => w,
+                _ => return Err(FilterError::new("Expected a value", operand.pos)),
+            };
+            let value = parse_value(&field, op, &text, operand.pos)?;
+            return Ok(Expr::Compare { field, op, value });
+        }
+
+        // BPF-style primitives: host/ip/net/src/dst/port/portrange
+        match lower.as_str() {
+            "src" | "dst" if self.value_follows() => {
+                let (addr_field, port_field) = if lower == "src" {
+                    (Field::IpSrc, Field::SrcPort(None))
+                } else {
+                    (Field::IpDst, Field::DstPort(None))
+                };
+                let qualifier = match &self.peek().tok {
+                    Tok::Word(w) => w.to_ascii_lowercase(),
+                    _ => String::new(),
+                };
+                let field = match qualifier.as_str() {
+                    "host" | "net" => {
+                        self.advance();
+                        addr_field
+                    }
+                    "port" | "portrange" => {
+                        self.advance();
+                        port_field
+                    }
+                    _ => addr_field,
+                };
+                return self.primitive_operand(field);
+            }
+            "host" | "ip" | "net" if self.value_follows() => {
+                return self.primitive_operand(Field::IpAddr);
+            }
+            "port" | "portrange" if self.value_follows() => {
+                return self.primitive_operand(Field::Port(None));
+            }
+            "tcp" | "udp" if matches!(&self.peek().tok, Tok::Word(w) if w.eq_ignore_ascii_case("port")) =>
+            {
+                self.advance();
+                let transport = if lower == "tcp" {
+                    IP_PROTO_TCP
+                } else {
+                    IP_PROTO_UDP
+                };
+                return self.primitive_operand(Field::Port(Some(transport)));
+            }
+            _ => {}
+        }
+
+        if PROTOCOL_NAMES.contains(&lower.as_str()) {
+            return Ok(Expr::Protocol(lower));
+        }
+
+        if let Some(field) = Field::lookup(&lower) {
+            return Ok(Expr::Exists(field));
+        }
+
+        // A bare address or network matches either endpoint, as `ip.addr == ...`
+        if word.parse::<IpAddr>().is_ok() || parse_cidr(&word).is_some() {
+            return self.address_term(word, pos);
+        }
+
+        // A bare number is a free-text search, anything else is a typo
+        if word.parse::<i64>().is_ok() {
+            return Ok(Expr::Search(word));
+        }
+
+        Err(FilterError::new(
+            format!(
+                "Unknown field or protocol '{}' (quote free-text searches, e.g. \"{}\")",
+                word, word
+            ),
+            pos,
+        ))
+    }
+
+    fn address_term(&self, word: String, pos: usize) -> Result<Expr, FilterError> {
+        let value = parse_value(&Field::IpAddr, CmpOp::Eq, &word, pos)?;
+        Ok(Expr::Compare {
+            field: Field::IpAddr,
+            op: CmpOp::Eq,
+            value,
+        })
+    }
+
+    fn primitive_operand(&mut self, field: Field) -> Result<Expr, FilterError> {
+        let operand = self.advance();
+        let text = match operand.tok {
+            Tok::Word(w) | Tok::Str(w) => w,
+            _ => return Err(FilterError::new("Expected a value", operand.pos)),
+        };
+        let value = parse_value(&field, CmpOp::Eq, &text, operand.pos)?;
+        Ok(Expr::Compare {
+            field,
+            op: CmpOp::Eq,
+            value,
+        })
+    }
+}
+
+fn legacy_term(prefix: &str, value: &str, pos: usize) -> Result<Expr, FilterError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(FilterError::new("Expected a value", pos));
+    }
+
+    let field = match prefix {
+        "protocol" => Field::Protocol,
+        "ip" => Field::IpAddr,
+        "src" => Field::IpSrc,
+        "dst" => Field::IpDst,
+        "tag" => Field::Tag,
+        _ => Field::Port(None),
+    };
+
+    // Addresses match exactly when complete, and by substring when partial (e.g. `ip:192.168`)
+    let op = match field.kind() {
+        FieldKind::Address if value.parse::<IpAddr>().is_err() && parse_cidr(value).is_none() => {
+            CmpOp::Contains
+        }
+        _ => CmpOp::Eq,
+    };
+    let value = parse_value(&field, op, value, pos)?;
+    Ok(Expr::Compare { field, op, value })
+}
+
+fn parse_cidr(text: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = text.split_once('/')?;
+    let addr: IpAddr = addr.parse().ok()?;
+    let prefix: u8 = prefix.parse().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    (prefix <= max).then_some((addr, prefix))
+}
+
+fn is_mac_address(text: &str) -> bool {
+    let octets: Vec<&str> = text.split(':').collect();
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+fn parse_number(text: &str) -> Option<i64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn parse_range(text: &str) -> Option<(i64, i64)> {
+    let (lo, hi) = text.split_once('-')?;
+    let lo: i64 = lo.trim().parse().ok()?;
+    let hi: i64 = hi.trim().parse().ok()?;
+    (lo <= hi).then_some((lo, hi))
+}
+
+fn parse_value(field: &Field, op: CmpOp, text: &str, pos: usize) -> Result<Value, FilterError> {
+    let name = field.name();
+
+    // Regular expressions are matched case-insensitively
+    if op == CmpOp::Matches {
+        return Regex::new(&format!("(?i){}", text))
+            .map(Value::Regex)
+            .map_err(|e| FilterError::new(format!("Invalid regular expression: {}", e), pos));
+    }
+
+    if op == CmpOp::Contains {
+        if matches!(field.kind(), FieldKind::Number | FieldKind::Port) {
+            return Err(FilterError::new(
+                format!("'contains' is not supported for numeric field '{}'", name),
+                pos,
+            ));
+        }
+        return Ok(Value::Text(text.to_lowercase()));
+    }
+
+    if op == CmpOp::BitAnd && field.kind() != FieldKind::Number {
+        return Err(FilterError::new(
+            format!("'&' is only supported for numeric fields, not '{}'", name),
+            pos,
+        ));
+    }
+
+    match field.kind() {
+        FieldKind::Number | FieldKind::Port => {
+            let max = if field.kind() == FieldKind::Port {
+                u16::MAX as i64
+            } else {
+                i64::MAX
+            };
+            if let Some(n) = parse_number(text) {
+                if n < 0 || n > max {
+                    return Err(FilterError::new(
+                        format!("Value {} out of range for '{}'", n, name),
+                        pos,
+                    ));
+                }
+                return Ok(Value::Number(n));
+            }
+            if let Some((lo, hi)) = parse_range(text) {
+                if op.is_ordering() || op == CmpOp::BitAnd {
+                    return Err(FilterError::new(
+                        "Ranges can only be compared with '==' or '!='",
+                        pos,
+                    ));
+                }
+                if hi > max {
+                    return Err(FilterError::new(
+                        format!("Range {} out of bounds for '{}'", text, name),
+                        pos,
+                    ));
+                }
+                return Ok(Value::Range(lo, hi));
+            }
+            Err(FilterError::new(
+                format!(
+                    "Expected a number or range for '{}', found '{}'",
+                    name, text
+                ),
+                pos,
+            ))
+        }
+        FieldKind::Address => {
+            if op.is_ordering() {
+                return Err(FilterError::new(
+                    format!("Ordering comparisons are not supported for '{}'", name),
+                    pos,
+                ));
+            }
+            if let Ok(ip) = text.parse::<IpAddr>() {
+                return Ok(Value::Ip(ip));
+            }
+            if let Some((addr, prefix)) = parse_cidr(text) {
+                return Ok(Value::Cidr(addr, prefix));
+            }
+            if is_mac_address(text) {
+                return Ok(Value::Text(text.to_string()));
+            }
+            Err(FilterError::new(
+                format!(
+                    "Expected an IP address, CIDR or MAC address for '{}', found '{}'",
+                    name, text
+                ),
+                pos,
+            ))
+        }
+        FieldKind::Text => {
+            if op.is_ordering() {
+                return Err(FilterError::new(
+                    format!("Ordering comparisons are not supported for '{}'", name),
+                    pos,
+                ));
+            }
+            Ok(Value::Text(text.to_string()))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Field autocomplete
+// ---------------------------------------------------------------------------
+
+/// A field name offered to the filter bar's autocomplete.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldSuggestion {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub field_type: FieldType,
+}
+
+/// Summary-level fields, which take precedence over same-named registry fields.
+const SUMMARY_FIELDS: &[(&str, &str, FieldType)] = &[
+    ("frame.len", "Captured length in bytes", FieldType::Number),
+    ("protocol", "Protocol column", FieldType::Text),
+    ("info", "Info column", FieldType::Text),
+    ("vlan.id", "Outermost VLAN ID", FieldType::Number),
+    ("ip.src", "Source IP address", FieldType::Address),
+    ("ip.dst", "Destination IP address", FieldType::Address),
+    (
+        "ip.addr",
+        "Source or destination IP address",
+        FieldType::Address,
+    ),
+    ("ip.proto", "IP protocol number", FieldType::Number),
+    ("port", "Source or destination port", FieldType::Number),
+    ("srcport", "Source port", FieldType::Number),
+    ("dstport", "Destination port", FieldType::Number),
+    (
+        "tcp.port",
+        "TCP source or destination port",
+        FieldType::Number,
+    ),
+    ("tcp.srcport", "TCP source port", FieldType::Number),
+    ("tcp.dstport", "TCP destination port", FieldType::Number),
+    (
+        "udp.port",
+        "UDP source or destination port",
+        FieldType::Number,
+    ),
+    ("udp.srcport", "UDP source port", FieldType::Number),
+    ("udp.dstport", "UDP destination port", FieldType::Number),
+    ("tag", "Tag assigned by a colouring rule", FieldType::Text),
+    ("payload", "Transport payload as text", FieldType::Text),
+];
+
+/// Lists filterable fields whose name starts with `prefix` (case-insensitive), sorted by name.
+pub fn complete_field(prefix: &str) -> Vec<FieldSuggestion> {
+    let prefix = prefix.trim().to_ascii_lowercase();
+    let summary = SUMMARY_FIELDS
+        .iter()
+        .map(|&(name, description, field_type)| FieldSuggestion {
+            name,
+            description,
+            field_type,
+        });
+    let registry = dissector::FIELD_REGISTRY
+        .iter()
+        .filter(|def| !SUMMARY_FIELDS.iter().any(|(name, ..)| *name == def.name))
+        .map(|def| FieldSuggestion {
+            name: def.name,
+            description: def.description,
+            field_type: def.field_type,
+        });
+
+    let mut suggestions: Vec<FieldSuggestion> = summary
+        .chain(registry)
+        .filter(|s| s.name.starts_with(&prefix))
+        .collect();
+    suggestions.sort_by_key(|s| s.name);
+    suggestions
+}
+
+// ---------------------------------------------------------------------------
+// SQL compilation
+// ---------------------------------------------------------------------------
+
+/// A parameterized SQL boolean expression over the `packets` table.
+#[derive(Debug, Clone)]
+pub struct SqlFilter {
+    pub clause: String,
+    pub params: Vec<SqlValue>,
+}
+
+/// A filter split into a SQL-evaluable part and a residual evaluated in Rust.
+#[derive(Debug, Clone)]
+pub struct FilterPlan {
+    pub sql: Option<SqlFilter>,
+    pub residual: Option<Expr>,
+}
+
+impl FilterPlan {
+    /// Renders the SQL part as a `WHERE` clause (empty when everything is residual).
+    pub fn where_clause(&self) -> String {
+        match &self.sql {
+            Some(sql) => format!("WHERE {}", sql.clause),
+            None => String::new(),
+        }
+    }
+
+    /// Parameters bound by [`FilterPlan::where_clause`].
+    pub fn params(&self) -> &[SqlValue] {
+        match &self.sql {
+            Some(sql) => &sql.params,
+            None => &[],
+        }
+    }
+}
+
+/// Splits the top-level conjunction of `expr` into SQL-evaluable and residual parts.
+pub fn plan(expr: Expr) -> FilterPlan {
+    let mut conjuncts = Vec::new();
+    flatten_and(expr, &mut conjuncts);
+
+    let mut clauses = Vec::new();
+    let mut params = Vec::new();
+    let mut residual: Option<Expr> = None;
+
+    for conjunct in conjuncts {
+        match to_sql(&conjunct) {
+            Some(sql) => {
+                clauses.push(sql.clause);
+                params.extend(sql.params);
+            }
+            None => {
+                residual = Some(match residual {
+                    Some(prev) => Expr::And(Box::new(prev), Box::new(conjunct)),
+                    None => conjunct,
+                });
+            }
+        }
+    }
+
+    let sql = (!clauses.is_empty()).then(|| SqlFilter {
+        clause: clauses.join(" AND "),
+        params,
+    });
+    FilterPlan { sql, residual }
+}
+
+pub(crate) fn flatten_and(expr: Expr, out: &mut Vec<Expr>) {
+    match expr {
+        Expr::And(lhs, rhs) => {
+            flatten_and(*lhs, out);
+            flatten_and(*rhs, out);
+        }
+        other => out.push(other),
+    }
+}
+
+/// Compiles an expression to SQL, or `None` if any part needs the Rust evaluator.
+pub fn to_sql(expr: &Expr) -> Option<SqlFilter> {
+    match expr {
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            let joiner = if matches!(expr, Expr::And(..)) {
+                "AND"
+            } else {
+                "OR"
+            };
+            let lhs = to_sql(lhs)?;
+            let rhs = to_sql(rhs)?;
+            let mut params = lhs.params;
+            params.extend(rhs.params);
+            Some(SqlFilter {
+                clause: format!("({} {} {})", lhs.clause, joiner, rhs.clause),
+                params,
+            })
+        }
+        Expr::Not(inner) => {
+            let inner = to_sql(inner)?;
+            Some(SqlFilter {
+                clause: format!("(NOT {})", inner.clause),
+                params: inner.params,
+            })
+        }
+        Expr::Protocol(name) => Some(protocol_sql(name)),
+        Expr::Search(text) => {
+            let needle = SqlValue::Text(text.to_lowercase());
+            let columns = ["protocol", "source_addr", "dest_addr", "info"];
+            let mut parts: Vec<String> = columns
+                .iter()
+                .map(|c| format!("instr(lower({}), ?) > 0", c))
+                .collect();
+            parts.push("instr(CAST(length AS TEXT), ?) > 0".to_string());
+            Some(SqlFilter {
+                clause: format!("COALESCE(({}), 0)", parts.join(" OR ")),
+                params: vec![needle; columns.len() + 1],
+            })
+        }
+        Expr::Compare { field, op, value } => compare_sql(field, *op, value),
+        Expr::Exists(field) => exists_sql(field),
+    }
+}
+
+fn exists_sql(field: &Field) -> Option<SqlFilter> {
+    let columns = field.columns();
+    if columns.is_empty() {
+        return None;
+    }
+
+    let mut clause = columns
+        .iter()
+        .map(|c| format!("{} IS NOT NULL", c))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let mut params = Vec::new();
+    if let Some(transport) = field.transport() {
+        params.push(SqlValue::Integer(transport as i64));
+        clause = format!("ip_proto = ? AND ({})", clause);
+    } else if field.kind() == FieldKind::Address {
+        clause = format!("ip_proto IS NOT NULL AND ({})", clause);
+    }
+
+    Some(SqlFilter {
+        clause: format!("COALESCE(({}), 0)", clause),
+        params,
+    })
+}
+
+fn protocol_sql(name: &str) -> SqlFilter {
+    let (clause, params) = match name {
+        "tcp" => ("ip_proto = ?", vec![SqlValue::Integer(IP_PROTO_TCP as i64)]),
+        "udp" => ("ip_proto = ?", vec![SqlValue::Integer(IP_PROTO_UDP as i64)]),
+        "icmp" => (
+            "ip_proto = ?",
+            vec![SqlValue::Integer(IP_PROTO_ICMP as i64)],
+        ),
+        "icmpv6" => (
+            "ip_proto = ?",
+            vec![SqlValue::Integer(IP_PROTO_ICMPV6 as i64)],
+        ),
+        "ip" => ("ip_proto IS NOT NULL", vec![]),
+        "ipv4" => (
+            "ip_proto IS NOT NULL AND instr(source_addr, ':') = 0",
+            vec![],
+        ),
+        "ipv6" => (
+            "ip_proto IS NOT NULL AND instr(source_addr, ':') > 0",
+            vec![],
+        ),
+        _ => (
+            "protocol = ? COLLATE NOCASE",
+            vec![SqlValue::Text(name.to_string())],
+        ),
+    };
+    SqlFilter {
+        clause: format!("COALESCE(({}), 0)", clause),
+        params,
+    }
+}
+
+fn compare_sql(field: &Field, op: CmpOp, value: &Value) -> Option<SqlFilter> {
+    let columns = field.columns();
+    if columns.is_empty() {
+        return None;
+    }
+    let mut params = Vec::new();
+
+    // Any-of semantics over the field's columns; `!=` is the negation of `==`
+    let cmp_op = if op == CmpOp::Ne { CmpOp::Eq } else { op };
+    let mut parts = Vec::new();
+    for column in columns {
+        let part = match (cmp_op, value) {
+            // Tags are stored as `,tag1,tag2,` so a whole tag can be matched
+            (CmpOp::Eq, Value::Text(text)) if *field == Field::Tag => {
+                params.push(SqlValue::Text(format!(",{},", text.to_lowercase())));
+                format!("instr({}, ?) > 0", column)
+            }
+            (CmpOp::Contains, Value::Text(text)) => {
+                params.push(SqlValue::Text(text.clone()));
+                format!("instr(lower({}), ?) > 0", column)
+            }
+            (CmpOp::BitAnd, Value::Number(n)) => {
+                params.push(SqlValue::Integer(*n));
+                format!("({} & ?) != 0", column)
+            }
+            (CmpOp::Eq, Value::Range(lo, hi)) => {
+                params.push(SqlValue::Integer(*lo));
+                params.push(SqlValue::Integer(*hi));
+                format!("{} BETWEEN ? AND ?", column)
+            }
+            (_, Value::Number(n)) => {
+                params.push(SqlValue::Integer(*n));
+                format!("{} {} ?", column, cmp_op.sql())
+            }
+            (CmpOp::Eq, Value::Ip(ip)) => {
+                params.push(SqlValue::Text(ip.to_string()));
+                format!("{} = ?", column)
+            }
+            (CmpOp::Eq, Value::Text(text)) => {
+                params.push(SqlValue::Text(text.clone()));
+                format!("{} = ? COLLATE NOCASE", column)
+            }
+            // CIDR membership and regular expressions are evaluated in Rust
+            _ => return None,
+        };
+        parts.push(part);
+    }
+
+    let mut clause = format!("({})", parts.join(" OR "));
+    if op == CmpOp::Ne {
+        clause = format!("NOT {}", clause);
+    }
+    if let Some(transport) = field.transport() {
+        params.insert(0, SqlValue::Integer(transport as i64));
+        clause = format!("ip_proto = ? AND {}", clause);
+    } else if field.kind() == FieldKind::Address {
+        // Non-IP frames carry MAC addresses in the address columns
+        clause = format!("ip_proto IS NOT NULL AND {}", clause);
+    }
+
+    // Comparisons against a missing (NULL) value are false, never NULL
+    Some(SqlFilter {
+        clause: format!("COALESCE(({}), 0)", clause),
+        params,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Rust evaluator
+// ---------------------------------------------------------------------------
+
+/// A stored packet as seen by the filter evaluator.
+///
+/// Full dissection is only performed, once, when a registry field is evaluated.
+pub struct PacketRecord<'a> {
+    summary: &'a PacketSummary,
+    ip_proto: Option<u8>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+    data: &'a [u8],
+    detail: OnceCell<Option<PacketDetail>>,
+    payload: OnceCell<Option<String>>,
+}
+
+enum FieldValue<'a> {
+    Number(i64),
+    Text(&'a str),
+}
+
+impl<'a> PacketRecord<'a> {
+    pub fn new(
+        summary: &'a PacketSummary,
+        ip_proto: Option<u8>,
+        src_port: Option<u16>,
+        dst_port: Option<u16>,
+        data: &'a [u8],
+    ) -> Self {
+        PacketRecord {
+            summary,
+            ip_proto,
+            src_port,
+            dst_port,
+            data,
+            detail: OnceCell::new(),
+            payload: OnceCell::new(),
+        }
+    }
+
+    fn detail(&self) -> Option<&PacketDetail> {
+        self.detail
+            .get_or_init(|| {
+                dissector::dissect_packet(self.data, self.summary.id, self.summary.timestamp)
+            })
+            .as_ref()
+    }
+
+    fn payload(&self) -> Option<&str> {
+        self.payload
+            .get_or_init(|| {
+                dissector::get_transport_payload(self.data)
+                    .filter(|payload| !payload.is_empty())
+                    .map(|payload| String::from_utf8_lossy(&payload).into_owned())
+            })
+            .as_deref()
+    }
+
+    fn values(&self, field: &Field) -> Vec<FieldValue<'_>> {
+        if let Some(transport) = field.transport() {
+            if self.ip_proto != Some(transport) {
+                return Vec::new();
+            }
+        }
+
+        let port = |p: Option<u16>| p.map(|p| FieldValue::Number(p as i64));
+        let is_ip = self.ip_proto.is_some();
+        match field {
+            Field::FrameLen => vec![FieldValue::Number(self.summary.length as i64)],
+            Field::Protocol => vec![FieldValue::Text(&self.summary.protocol)],
+            Field::Info => vec![FieldValue::Text(&self.summary.info)],
+            Field::VlanId => self
+                .summary
+                .vlan_id
+                .map(|id| FieldValue::Number(id as i64))
+                .into_iter()
+                .collect(),
+            Field::IpSrc if is_ip => vec![FieldValue::Text(&self.summary.source_addr)],
+            Field::IpDst if is_ip => vec![FieldValue::Text(&self.summary.dest_addr)],
+            Field::IpAddr if is_ip => vec![
+                FieldValue::Text(&self.summary.source_addr),
+                FieldValue::Text(&self.summary.dest_addr),
+            ],
+            Field::IpSrc | Field::IpDst | Field::IpAddr => Vec::new(),
+            Field::IpProto => self
+                .ip_proto
+                .map(|p| FieldValue::Number(p as i64))
+                .into_iter()
+                .collect(),
+            Field::SrcPort(_) => port(self.src_port).into_iter().collect(),
+            Field::DstPort(_) => port(self.dst_port).into_iter().collect(),
+            Field::Port(_) => port(self.src_port)
+                .into_iter()
+                .chain(port(self.dst_port))
+                .collect(),
+            Field::Tag => self
+                .summary
+                .tags
+                .iter()
+                .map(|tag| FieldValue::Text(tag))
+                .collect(),
+            Field::Payload => self.payload().map(FieldValue::Text).into_iter().collect(),
+            Field::Dissected(def) => {
+                let Some(detail) = self.detail() else {
+                    return Vec::new();
+                };
+                dissector::field_values(detail, def)
+                    .into_iter()
+                    .filter_map(|v| match def.field_type {
+                        FieldType::Number => parse_leading_number(v).map(FieldValue::Number),
+                        FieldType::Text | FieldType::Address => Some(FieldValue::Text(v)),
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Parses the leading number of a display value such as "60 bytes", "0x0012" or "6 (Tcp)".
+fn parse_leading_number(value: &str) -> Option<i64> {
+    let token = value.split_whitespace().next()?;
+    match token.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
+}
+
+fn cidr_contains(network: IpAddr, prefix: u8, addr: IpAddr) -> bool {
+    match (network, addr) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => {
+            let mask = if prefix == 0 {
+                0
+            } else {
+                u32::MAX << (32 - prefix as u32)
+            };
+            (u32::from(net) & mask) == (u32::from(ip) & mask)
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) => {
+            let mask = if prefix == 0 {
+                0
+            } else {
+                u128::MAX << (128 - prefix as u32)
+            };
+            (u128::from(net) & mask) == (u128::from(ip) & mask)
+        }
+        _ => false,
+    }
+}
+
+fn value_matches(actual: &FieldValue, op: CmpOp, expected: &Value) -> bool {
+    match (actual, expected) {
+        (FieldValue::Number(n), Value::Number(v)) => match op {
+            CmpOp::Eq => n == v,
+            CmpOp::Lt => n < v,
+            CmpOp::Le => n <= v,
+            CmpOp::Gt => n > v,
+            CmpOp::Ge => n >= v,
+            CmpOp::BitAnd => n & v != 0,
+            _ => false,
+        },
+        (FieldValue::Number(n), Value::Range(lo, hi)) => (lo..=hi).contains(&n),
+        (FieldValue::Text(text), Value::Ip(ip)) => text.parse::<IpAddr>().ok() == Some(*ip),
+        (FieldValue::Text(text), Value::Cidr(net, prefix)) => text
+            .parse::<IpAddr>()
+            .map(|ip| cidr_contains(*net, *prefix, ip))
+            .unwrap_or(false),
+        (FieldValue::Text(text), Value::Text(v)) => match op {
+            CmpOp::Contains => text.to_lowercase().contains(v.as_str()),
+            _ => text.eq_ignore_ascii_case(v),
+        },
+        (FieldValue::Text(text), Value::Regex(re)) => re.is_match(text),
+        (FieldValue::Number(n), Value::Regex(re)) => re.is_match(&n.to_string()),
+        _ => false,
+    }
+}
+
+fn protocol_matches(name: &str, record: &PacketRecord) -> bool {
+    let is_ipv6 = record.summary.source_addr.contains(':');
+    match name {
+        "tcp" => record.ip_proto == Some(IP_PROTO_TCP),
+        "udp" => record.ip_proto == Some(IP_PROTO_UDP),
+        "icmp" => record.ip_proto == Some(IP_PROTO_ICMP),
+        "icmpv6" => record.ip_proto == Some(IP_PROTO_ICMPV6),
+        "ip" => record.ip_proto.is_some(),
+        "ipv4" => record.ip_proto.is_some() && !is_ipv6,
+        "ipv6" => record.ip_proto.is_some() && is_ipv6,
+        _ => record.summary.protocol.eq_ignore_ascii_case(name),
+    }
+}
+
+/// Evaluates a filter expression against a packet.
+pub fn evaluate(expr: &Expr, record: &PacketRecord) -> bool {
+    match expr {
+        Expr::And(lhs, rhs) => evaluate(lhs, record) && evaluate(rhs, record),
+        Expr::Or(lhs, rhs) => evaluate(lhs, record) || evaluate(rhs, record),
+        Expr::Not(inner) => !evaluate(inner, record),
+        Expr::Protocol(name) => protocol_matches(name, record),
+        Expr::Search(text) => {
+            let needle = text.to_lowercase();
+            let summary = record.summary;
+            [
+                &summary.protocol,
+                &summary.source_addr,
+                &summary.dest_addr,
+                &summary.info,
+            ]
+            .iter()
+            .any(|s| s.to_lowercase().contains(&needle))
+                || summary.length.to_string().contains(&needle)
+        }
+        Expr::Exists(field) => !record.values(field).is_empty(),
+        Expr::Compare { field, op, value } => {
+            let values = record.values(field);
+            if values.is_empty() {
+                return false;
+            }
+            match op {
+                CmpOp::Ne => !values.iter().any(|v| value_matches(v, CmpOp::Eq, value)),
+                _ => values.iter().any(|v| value_matches(v, *op, value)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(src: &str, dst: &str, protocol: &str, length: u32) -> PacketSummary {
+        PacketSummary {
+            id: 1,
+            timestamp: 0,
+            source_addr: src.to_string(),
+            dest_addr: dst.to_string(),
+            protocol: protocol.to_string(),
+            length,
+            info: String::new(),
+            vlan_id: None,
+            tags: Vec::new(),
+            color: None,
+        }
+    }
+
+    fn matches(filter: &str, record: &PacketRecord) -> bool {
+        evaluate(&parse(filter).expect("filter should parse"), record)
+    }
+
+    #[test]
+    fn test_boolean_logic_and_primitives() {
+        let s = summary("10.0.0.1", "10.0.0.5", "HTTPS", 60);
+        let record = PacketRecord::new(&s, Some(6), Some(51000), Some(443), &[]);
+
+        assert!(matches("tcp and port 443", &record));
+        assert!(!matches("tcp and port 443 and not ip 10.0.0.5", &record));
+        assert!(matches("udp or (tcp && dstport == 443)", &record));
+        assert!(matches("!udp", &record));
+        assert!(matches("tcp.port == 400-500", &record));
+        assert!(!matches("udp.port == 443", &record));
+    }
+
+    #[test]
+    fn test_macro_expansion() {
+        let s = summary("10.0.0.15", "192.168.1.1", "TCP", 60);
+        let record = PacketRecord::new(&s, Some(6), Some(51000), Some(22), &[]);
+        let mut macros = Macros::new();
+        macros.insert(
+            "internal".to_string(),
+            "ip.src == 10.0.0.0/8 or ip.src == 192.168.0.0/16".to_string(),
+        );
+        macros.insert(
+            "svc".to_string(),
+            "tcp.port == $1 and $internal".to_string(),
+        );
+        macros.insert("loop".to_string(), "$loop".to_string());
+
+        let eval = |f: &str| evaluate(&parse_with_macros(f, &macros).unwrap(), &record);
+        // The expansion is parenthesized, so `or` in the body cannot leak out
+        assert!(eval("$internal and tcp"));
+        assert!(!eval("udp and $internal"));
+        assert!(eval("$svc(22)"));
+        assert!(!eval("$svc(443)"));
+
+        let err = parse_with_macros("tcp and $nope", &macros).unwrap_err();
+        assert_eq!(err.position, 8);
+        assert!(parse_with_macros("$svc", &macros).is_err());
+        assert!(parse_with_macros("$loop", &macros).is_err());
+        assert!(parse("$internal").is_err());
+        assert_eq!(macro_arity("tcp.port == $1 or udp.port == $2"), 2);
+    }
+
+    #[test]
+    fn test_bare_addresses_in_macros() {
+        let mut macros = Macros::new();
+        macros.insert(
+            "internal".to_string(),
+            "10.0.0.0/8 or 192.168.0.0/16".to_string(),
+        );
+        let expr = parse_with_macros("$internal", &macros).unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "ip.addr == 10.0.0.0/8 or ip.addr == 192.168.0.0/16"
+        );
+
+        let inside = summary("203.0.113.7", "192.168.1.1", "TCP", 60);
+        let record = PacketRecord::new(&inside, Some(6), Some(1), Some(2), &[]);
+        assert!(evaluate(&expr, &record));
+        let outside = summary("203.0.113.7", "172.16.0.1", "TCP", 60);
+        let record = PacketRecord::new(&outside, Some(6), Some(1), Some(2), &[]);
+        assert!(!evaluate(&expr, &record));
+        assert!(matches("172.16.0.1", &record));
+    }
+
+    #[test]
+    fn test_address_matching_is_exact() {
+        let s = summary("10.0.0.15", "192.168.1.1", "TCP", 60);
+        let record = PacketRecord::new(&s, Some(6), Some(1), Some(2), &[]);
+
+        // Previously `src:10.0.0.1` matched 10.0.0.15 through a LIKE substring
+        assert!(!matches("src:10.0.0.1", &record));
+        assert!(matches("src:10.0.0.15", &record));
+        assert!(matches("ip.src == 10.0.0.0/8", &record));
+        assert!(matches("ip.addr == 192.168.0.0/16", &record));
+        assert!(!matches("ip.dst == 10.0.0.0/8", &record));
+        assert!(matches("ip:192.168", &record));
+    }
+
+    /// An Ethernet/IPv4 fragment of a UDP datagram from 10.0.0.53 to 10.0.0.1.
+    fn udp_fragment(flags_offset: u16, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x00; 12];
+        data.extend_from_slice(&[0x08, 0x00, 0x45, 0x00]);
+        data.extend_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0x12, 0x34]);
+        data.extend_from_slice(&flags_offset.to_be_bytes());
+        data.extend_from_slice(&[0x40, 0x11, 0x00, 0x00, 10, 0, 0, 53, 10, 0, 0, 1]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_reassembled_datagram_fields() {
+        // A DNS response for example.com, 53 -> 50000, split after 16 bytes
+        let mut dns = vec![0xAB, 0xCD, 0x81, 0x80, 0, 1, 0, 0, 0, 0, 0, 0];
+        dns.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+        let mut udp = vec![0x00, 0x35, 0xC3, 0x50];
+        udp.extend_from_slice(&(8 + dns.len() as u16).to_be_bytes());
+        udp.extend_from_slice(&[0x00, 0x00]);
+        udp.extend_from_slice(&dns);
+        let first = udp_fragment(0x2000, &udp[..16]);
+        let last = udp_fragment(2, &udp[16..]);
+
+        let reassembly = crate::dissector::reassemble_frames([
+            (1, 0, first.as_slice()),
+            (2, 1, last.as_slice()),
+        ])
+        .unwrap();
+        let frame = crate::dissector::reassembled_frame(&reassembly);
+        // The last fragment's row holds the reassembled datagram's summary and ports
+        let s = crate::dissector::parse_summary(&frame, 2, 1).unwrap();
+        let filter = "udp.port == 53 and dns.qry.name contains \"example\"";
+
+        let record = PacketRecord::new(&s, Some(17), Some(53), Some(50000), &frame);
+        assert!(matches(filter, &record));
+        let record = PacketRecord::new(&s, Some(17), Some(53), Some(50000), &last);
+        assert!(!matches(filter, &record));
+    }
+
+    #[test]
+    fn test_comparisons_and_string_operators() {
+        let mut s = summary("10.0.0.1", "10.0.0.2", "DNS", 1200);
+        s.info = "Standard query A example.com".to_string();
+        let record = PacketRecord::new(&s, Some(17), Some(5353), Some(53), &[]);
+
+        assert!(matches("frame.len > 1000", &record));
+        assert!(!matches("len <= 1000", &record));
+        assert!(matches("protocol == dns", &record));
+        assert!(matches("dns", &record));
+        assert!(matches("info contains \"EXAMPLE\"", &record));
+        assert!(matches("info matches \"query [A-Z]+ example\"", &record));
+        assert!(matches("port != 80", &record));
+        assert!(matches("\"example.com\"", &record));
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let err = parse("tcp and prot == 1").unwrap_err();
+        assert_eq!(err.position, 8);
+        assert!(err.message.contains("prot"));
+
+        let err = parse("(tcp or udp").unwrap_err();
+        assert_eq!(err.position, 11);
+
+        let err = parse("port == 70000").unwrap_err();
+        assert_eq!(err.position, 8);
+
+        let err = parse("ip.src > 10.0.0.1").unwrap_err();
+        assert!(err.message.contains("Ordering"));
+
+        assert!(parse("tcp and").is_err());
+        assert!(parse("info matches \"(\"").is_err());
+        assert!(parse("tpc").is_err());
+    }
+
+    #[test]
+    fn test_plan_pushes_sql_and_keeps_residual() {
+        let plan = super::plan(parse("tcp and ip.src == 10.0.0.0/8 and port 443").unwrap());
+        let sql = plan.sql.expect("tcp and port should compile to SQL");
+        assert!(sql.clause.contains("ip_proto = ?"));
+        assert!(sql.clause.contains("src_port = ?"));
+        assert_eq!(sql.params.len(), 3);
+        assert!(matches!(
+            plan.residual,
+            Some(Expr::Compare {
+                value: Value::Cidr(..),
+                ..
+            })
+        ));
+
+        let plan = super::plan(parse("ip.src == 10.0.0.1 or info matches \"x\"").unwrap());
+        assert!(plan.sql.is_none());
+        assert!(plan.residual.is_some());
+    }
+
+    #[test]
+    fn test_sql_agrees_with_evaluator() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE packets (id INTEGER PRIMARY KEY, timestamp_ns INTEGER, source_addr TEXT,
+                dest_addr TEXT, protocol TEXT, length INTEGER, info TEXT, ip_proto INTEGER,
+                src_port INTEGER, dst_port INTEGER, data BLOB)",
+        )
+        .unwrap();
+
+        type Row = (
+            &'static str,
+            &'static str,
+            &'static str,
+            u32,
+            Option<u8>,
+            Option<u16>,
+            Option<u16>,
+        );
+        let rows: Vec<Row> = vec![
+            (
+                "10.0.0.1",
+                "10.0.0.5",
+                "HTTPS",
+                60,
+                Some(6),
+                Some(51000),
+                Some(443),
+            ),
+            (
+                "10.0.0.15",
+                "8.8.8.8",
+                "DNS",
+                80,
+                Some(17),
+                Some(5353),
+                Some(53),
+            ),
+            (
+                "aa:bb:cc:dd:ee:ff",
+                "ff:ff:ff:ff:ff:ff",
+                "ARP",
+                42,
+                None,
+                None,
+                None,
+            ),
+            ("fe80::1", "fe80::2", "ICMPv6", 1200, Some(58), None, None),
+        ];
+        let summaries: Vec<PacketSummary> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let mut s = summary(r.0, r.1, r.2, r.3);
+                s.id = i as u64 + 1;
+                db.execute(
+                    "INSERT INTO packets VALUES (?1, 0, ?2, ?3, ?4, ?5, '', ?6, ?7, ?8, x'')",
+                    rusqlite::params![s.id as i64, r.0, r.1, r.2, r.3, r.4, r.5, r.6],
+                )
+                .unwrap();
+                s
+            })
+            .collect();
+
+        let filters = [
+            "tcp or udp",
+            "not port 443",
+            "port != 53",
+            "ip.addr == 10.0.0.1",
+            "src:10.0.0.1",
+            "ip:aa:bb",
+            "ipv6 or arp",
+            "frame.len >= 80 and not dns",
+            "\"8.8\"",
+            "udp.port == 50-60",
+        ];
+        for f in filters {
+            let expr = parse(f).unwrap();
+            let sql = to_sql(&expr).unwrap_or_else(|| panic!("{} should compile to SQL", f));
+            let query = format!("SELECT id FROM packets WHERE {} ORDER BY id", sql.clause);
+            let mut stmt = db.prepare(&query).unwrap();
+            let sql_ids: Vec<u64> = stmt
+                .query_map(rusqlite::params_from_iter(&sql.params), |r| {
+                    r.get::<_, i64>(0)
+                })
+                .unwrap()
+                .map(|id| id.unwrap() as u64)
+                .collect();
+
+            let rust_ids: Vec<u64> = summaries
+                .iter()
+                .zip(&rows)
+                .filter(|(s, r)| {
+                    let record = PacketRecord::new(s, r.4, r.5, r.6, &[]);
+                    evaluate(&expr, &record)
+                })
+                .map(|(s, _)| s.id)
+                .collect();
+
+            assert_eq!(sql_ids, rust_ids, "SQL and evaluator disagree on '{}'", f);
+        }
+    }
+
+    fn http_get_packet() -> Vec<u8> {
+        let payload = b"GET /login HTTP/1.1\r\n\r\n";
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        data.extend_from_slice(&[0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB]);
+        data.extend_from_slice(&[0x08, 0x00]);
+        let total_len = (20 + 20 + payload.len()) as u16;
+        data.extend_from_slice(&[0x45, 0x00]);
+        data.extend_from_slice(&total_len.to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        data.extend_from_slice(&[0x05, 0x06, 0x00, 0x00]); // TTL 5, TCP
+        data.extend_from_slice(&[0xC0, 0xA8, 0x01, 0x01]);
+        data.extend_from_slice(&[0xC0, 0xA8, 0x01, 0x02]);
+        data.extend_from_slice(&[0xD4, 0x31, 0x00, 0x50]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0x50, 0x18, 0x20, 0x00]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_dissected_fields() {
+        let data = http_get_packet();
+        let s = crate::dissector::parse_summary(&data, 1, 0).unwrap();
+        let record = PacketRecord::new(&s, Some(6), Some(54321), Some(80), &data);
+
+        assert!(matches("http.method == GET", &record));
+        assert!(matches("http.method", &record));
+        assert!(matches("ip.ttl < 10 and tcp.window == 8192", &record));
+        assert!(matches("eth.src == 66:77:88:99:aa:bb", &record));
+        assert!(!matches("http.method == \"POST\"", &record));
+        assert!(!matches("udp.length", &record));
+
+        // Registry fields can never be pushed down to SQL
+        let plan = super::plan(parse("tcp and http.method == GET").unwrap());
+        assert!(plan.sql.is_some());
+        assert!(plan.residual.is_some());
+    }
+
+    #[test]
+    fn test_tls_protocol() {
+        let s = summary("10.0.0.1", "10.0.0.5", "TLS", 571);
+        let record = PacketRecord::new(&s, Some(6), Some(51000), Some(8443), &[]);
+        assert!(matches("tls", &record));
+        assert!(matches("TLS and tcp", &record));
+        assert!(!matches("https", &record));
+
+        let plan = super::plan(parse("tls").unwrap());
+        assert!(plan.sql.is_some());
+        assert!(plan.residual.is_none());
+    }
+
+    #[test]
+    fn test_ssh_protocol() {
+        let s = summary("10.0.0.1", "10.0.0.5", "SSH", 98);
+        let record = PacketRecord::new(&s, Some(6), Some(51000), Some(2222), &[]);
+        assert!(matches("ssh", &record));
+        assert!(matches("ssh and not tls", &record));
+        assert!(!matches("ssh and udp", &record));
+
+        let plan = super::plan(parse("ssh").unwrap());
+        assert!(plan.sql.is_some());
+        assert!(plan.residual.is_none());
+    }
+
+    #[test]
+    fn test_smb2_protocol() {
+        let s = summary("10.0.0.1", "10.0.0.5", "SMB2", 170);
+        let record = PacketRecord::new(&s, Some(6), Some(51000), Some(445), &[]);
+        assert!(matches("smb2", &record));
+        assert!(matches("smb2 or smb3", &record));
+        assert!(!matches("smb3", &record));
+
+        let plan = super::plan(parse("smb2").unwrap());
+        assert!(plan.sql.is_some());
+        assert!(plan.residual.is_none());
+    }
+
+    #[test]
+    fn test_complete_field() {
+        let names: Vec<&str> = complete_field("tcp.").iter().map(|s| s.name).collect();
+        assert!(names.contains(&"tcp.window"));
+        assert!(names.contains(&"tcp.port"));
+        assert!(names.iter().all(|n| n.starts_with("tcp.")));
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
+
+        assert_eq!(complete_field("HTTP.M").len(), 1);
+        assert!(complete_field("nope.").is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+//! Saved display filters, filter macros and per-session filter history.
+//!
+//! Everything is stored in the capture database, in tables that survive the
+//! packet table being reset, and can be exported to (or imported from) a JSON
+//! file for sharing within a team.
+
+use crate::filter::{self, Macros};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Version written to exported library files.
+pub const LIBRARY_FORMAT_VERSION: u32 = 1;
+
+/// History entries kept per session; older ones are pruned.
+const HISTORY_LIMIT_PER_SESSION: i64 = 200;
+
+/// A named display filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub name: String,
+    pub expression: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A filter macro, referenced as `$name` or `$name(arg, ...)` inside filters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterMacro {
+    pub name: String,
+    /// Filter text substituted for the reference; `$1`, `$2`, ... are arguments
+    pub body: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A filter applied during a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub session_id: i64,
+    pub expression: String,
+    /// Last time the filter was applied, in nanoseconds since Unix epoch
+    pub last_used_ns: i64,
+    pub use_count: i64,
+}
+
+/// Contents of a shared filter library file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterLibrary {
+    pub version: u32,
+    #[serde(default)]
+    pub saved_filters: Vec<SavedFilter>,
+    #[serde(default)]
+    pub macros: Vec<FilterMacro>,
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+}
+
+/// Creates the library tables if they do not exist yet.
+pub fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS saved_filters (
+            name TEXT PRIMARY KEY,
+            expression TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT ''
+        );
+        CREATE TABLE IF NOT EXISTS filter_macros (
+            name TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT ''
+        );
+        CREATE TABLE IF NOT EXISTS filter_history (
+            session_id INTEGER NOT NULL,
+            expression TEXT NOT NULL,
+            last_used_ns INTEGER NOT NULL,
+            use_count INTEGER NOT NULL DEFAULT 1,
+            PRIMARY KEY (session_id, expression)
+        );
+    ",
+    )
+}
+
+fn validate_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.len() > 100 {
+        return Err(format!(
+            "{} name must be between 1 and 100 characters",
+            kind
+        ));
+    }
+    if name.contains('\0') {
+        return Err(format!("{} name contains invalid characters", kind));
+    }
+    Ok(())
+}
+
+/// Checks that a saved filter has a usable name and parses with `macros`.
+pub fn validate_saved_filter(saved: &SavedFilter, macros: &Macros) -> Result<(), String> {
+    validate_name("Filter", &saved.name)?;
+    filter::parse_with_macros(&saved.expression, macros)
+        .map_err(|e| format!("Invalid filter '{}': {}", saved.name, e))?;
+    Ok(())
+}
+
+/// Checks a macro's name, and that its body parses when it takes no arguments.
+///
+/// Bodies with `$N` placeholders can only be checked once arguments are supplied.
+pub fn validate_macro(filter_macro: &FilterMacro, macros: &Macros) -> Result<(), String> {
+    if !filter::is_valid_macro_name(&filter_macro.name) {
+        return Err(format!(
+            "Invalid macro name '{}': use letters, digits and '_', not starting with a digit",
+            filter_macro.name
+        ));
+    }
+    if filter::macro_arity(&filter_macro.body) == 0 {
+        filter::parse_with_macros(&filter_macro.body, macros)
+            .map_err(|e| format!("Invalid macro '${}': {}", filter_macro.name, e))?;
+    }
+    Ok(())
+}
+
+pub fn list_saved_filters(conn: &Connection) -> rusqlite::Result<Vec<SavedFilter>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name, expression, description FROM saved_filters ORDER BY name COLLATE NOCASE",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SavedFilter {
+            name: row.get(0)?,
+            expression: row.get(1)?,
+            description: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Inserts or replaces a saved filter by name.
+pub fn save_filter(conn: &Connection, saved: &SavedFilter) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO saved_filters (name, expression, description) VALUES (?1, ?2, ?3)",
+        params![saved.name, saved.expression, saved.description],
+    )?;
+    Ok(())
+}
+
+/// Deletes a saved filter, returning whether it existed.
+pub fn delete_saved_filter(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM saved_filters WHERE name = ?1", [name])? > 0)
+}
+
+pub fn list_macros(conn: &Connection) -> rusqlite::Result<Vec<FilterMacro>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT name, body, description FROM filter_macros ORDER BY name")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(FilterMacro {
+            name: row.get(0)?,
+            body: row.get(1)?,
+            description: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Loads all macros in the form the filter parser expands.
+pub fn load_macros(conn: &Connection) -> rusqlite::Result<Macros> {
+    Ok(list_macros(conn)?
+        .into_iter()
+        .map(|m| (m.name, m.body))
+        .collect())
+}
+
+/// Inserts or replaces a macro by name.
+pub fn save_macro(conn: &Connection, filter_macro: &FilterMacro) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO filter_macros (name, body, description) VALUES (?1, ?2, ?3)",
+        params![
+            filter_macro.name,
+            filter_macro.body,
+            filter_macro.description
+        ],
+    )?;
+    Ok(())
+}
+
+/// Deletes a macro, returning whether it existed.
+pub fn delete_macro(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM filter_macros WHERE name = ?1", [name])? > 0)
+}
+
+/// Records that `expression` was applied during `session_id`.
+///
+/// Re-applying a filter bumps its count and timestamp instead of adding a row.
+pub fn record_history(
+    conn: &Connection,
+    session_id: i64,
+    expression: &str,
+    now_ns: i64,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO filter_history (session_id, expression, last_used_ns, use_count)
+         VALUES (?1, ?2, ?3, 1)
+         ON CONFLICT (session_id, expression)
+         DO UPDATE SET last_used_ns = excluded.last_used_ns, use_count = use_count + 1",
+        params![session_id, expression, now_ns],
+    )?;
+    conn.execute(
+        "DELETE FROM filter_history WHERE session_id = ?1 AND expression NOT IN (
+            SELECT expression FROM filter_history WHERE session_id = ?1
+            ORDER BY last_used_ns DESC LIMIT ?2
+        )",
+        params![session_id, HISTORY_LIMIT_PER_SESSION],
+    )?;
+    Ok(())
+}
+
+/// Returns the most recently used filters, for one session or all of them.
+pub fn history(
+    conn: &Connection,
+    session_id: Option<i64>,
+    limit: usize,
+) -> rusqlite::Result<Vec<HistoryEntry>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT session_id, expression, last_used_ns, use_count FROM filter_history
+         WHERE ?1 IS NULL OR session_id = ?1
+         ORDER BY last_used_ns DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![session_id, limit as i64], |row| {
+        Ok(HistoryEntry {
+            session_id: row.get(0)?,
+            expression: row.get(1)?,
+            last_used_ns: row.get(2)?,
+            use_count: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Collects saved filters, macros and all history into a shareable library.
+pub fn export_library(conn: &Connection) -> rusqlite::Result<FilterLibrary> {
+    Ok(FilterLibrary {
+        version: LIBRARY_FORMAT_VERSION,
+        saved_filters: list_saved_filters(conn)?,
+        macros: list_macros(conn)?,
+        history: history(conn, None, i64::MAX as usize)?,
+    })
+}
+
+/// Merges a library into the database, replacing same-named filters and macros.
+///
+/// Every entry is validated first (against the union of existing and imported
+/// macros), so a bad file leaves the database untouched. Returns the number of
+/// entries imported.
+pub fn import_library(conn: &mut Connection, library: &FilterLibrary) -> Result<usize, String> {
+    if library.version > LIBRARY_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported filter library version {} (max {})",
+            library.version, LIBRARY_FORMAT_VERSION
+        ));
+    }
+
+    let mut macros = load_macros(conn).map_err(|e| format!("Failed to load macros: {}", e))?;
+    for filter_macro in &library.macros {
+        macros.insert(filter_macro.name.clone(), filter_macro.body.clone());
+    }
+    for filter_macro in &library.macros {
+        validate_macro(filter_macro, &macros)?;
+    }
+    for saved in &library.saved_filters {
+        validate_saved_filter(saved, &macros)?;
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for filter_macro in &library.macros {
+        save_macro(&tx, filter_macro).map_err(|e| format!("Failed to save macro: {}", e))?;
+    }
+    for saved in &library.saved_filters {
+        save_filter(&tx, saved).map_err(|e| format!("Failed to save filter: {}", e))?;
+    }
+    for entry in &library.history {
+        tx.execute(
+            "INSERT INTO filter_history (session_id, expression, last_used_ns, use_count)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (session_id, expression) DO UPDATE SET
+                last_used_ns = max(last_used_ns, excluded.last_used_ns),
+                use_count = max(use_count, excluded.use_count)",
+            params![
+                entry.session_id,
+                entry.expression,
+                entry.last_used_ns,
+                entry.use_count
+            ],
+        )
+        .map_err(|e| format!("Failed to import history: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit filter library: {}", e))?;
+
+    Ok(library.saved_filters.len() + library.macros.len() + library.history.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn internal_macro() -> FilterMacro {
+        FilterMacro {
+            name: "internal".to_string(),
+            body: "ip.addr == 10.0.0.0/8 or ip.addr == 192.168.0.0/16".to_string(),
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_history_is_per_session_and_deduplicated() {
+        let conn = open();
+        record_history(&conn, 1, "tcp", 10).unwrap();
+        record_history(&conn, 1, "udp", 20).unwrap();
+        record_history(&conn, 1, "tcp", 30).unwrap();
+        record_history(&conn, 2, "dns", 40).unwrap();
+
+        let session = history(&conn, Some(1), 10).unwrap();
+        let expressions: Vec<_> = session.iter().map(|h| h.expression.as_str()).collect();
+        assert_eq!(expressions, ["tcp", "udp"]);
+        assert_eq!(session[0].use_count, 2);
+
+        assert_eq!(history(&conn, None, 10).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_library_round_trip() {
+        let source = open();
+        save_macro(&source, &internal_macro()).unwrap();
+        save_filter(
+            &source,
+            &SavedFilter {
+                name: "Internal web".to_string(),
+                expression: "$internal and tcp.port == 80".to_string(),
+                description: "Plain HTTP inside the LAN".to_string(),
+            },
+        )
+        .unwrap();
+        record_history(&source, 7, "$internal", 1).unwrap();
+
+        let json = serde_json::to_string(&export_library(&source).unwrap()).unwrap();
+        let library: FilterLibrary = serde_json::from_str(&json).unwrap();
+
+        let mut target = open();
+        assert_eq!(import_library(&mut target, &library).unwrap(), 3);
+        assert_eq!(load_macros(&target).unwrap().len(), 1);
+        let saved = list_saved_filters(&target).unwrap();
+        assert_eq!(saved[0].expression, "$internal and tcp.port == 80");
+        assert_eq!(history(&target, Some(7), 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_entries_atomically() {
+        let mut conn = open();
+        let library = FilterLibrary {
+            version: LIBRARY_FORMAT_VERSION,
+            saved_filters: vec![SavedFilter {
+                name: "broken".to_string(),
+                expression: "$undefined and tcp".to_string(),
+                description: String::new(),
+            }],
+            macros: vec![internal_macro()],
+            history: Vec::new(),
+        };
+
+        assert!(import_library(&mut conn, &library).is_err());
+        assert!(list_macros(&conn).unwrap().is_empty());
+    }
+}
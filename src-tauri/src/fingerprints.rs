@@ -0,0 +1,221 @@
+//! Local database of known TLS and SSH fingerprints.
+//!
+//! Entries map a JA3/JA3S or HASSH/HASSHServer (32 hex digits) or JA4/JA4S
+//! fingerprint to a label such as a malware family. The database is imported
+//! from a JSON array of `{"fingerprint", "label"}` objects or from a CSV file
+//! whose first column is the fingerprint and last column the label (as
+//! published by abuse.ch and similar feeds), and replaces any previously
+//! imported entries.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper bound on imported entries, to keep the lookup table in memory.
+pub const MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownFingerprint {
+    pub fingerprint: String,
+    pub label: String,
+}
+
+/// Whether `value` looks like a JA3/JA3S or HASSH hash or a JA4/JA4S fingerprint.
+fn is_fingerprint(value: &str) -> bool {
+    let is_hex = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if value.len() == 32 && is_hex(value) {
+        return true;
+    }
+    let parts: Vec<&str> = value.split('_').collect();
+    parts.len() == 3
+        && parts[0].len() >= 6
+        && parts[0].bytes().all(|b| b.is_ascii_alphanumeric())
+        && is_hex(parts[1])
+        && is_hex(parts[2])
+}
+
+/// Parses a fingerprint file. JSON is recognized by a leading `[`; anything
+/// else is read as CSV, skipping blank lines, `#` comments and header rows.
+pub fn parse_file(content: &str) -> Result<Vec<KnownFingerprint>, String> {
+    let entries: Vec<KnownFingerprint> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).map_err(|e| format!("Invalid fingerprint file: {}", e))?
+    } else {
+        let mut entries = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = line
+                .split(',')
+                .map(|c| c.trim().trim_matches('"'))
+                .collect();
+            let fingerprint = columns[0];
+            if !is_fingerprint(fingerprint) && entries.is_empty() {
+                continue; // header row
+            }
+            if columns.len() < 2 {
+                return Err(format!(
+                    "Line {}: expected fingerprint and label",
+                    number + 1
+                ));
+            }
+            entries.push(KnownFingerprint {
+                fingerprint: fingerprint.to_string(),
+                label: columns[columns.len() - 1].to_string(),
+            });
+        }
+        entries
+    };
+
+    if entries.len() > MAX_ENTRIES {
+        return Err(format!(
+            "Too many fingerprints ({}, max {})",
+            entries.len(),
+            MAX_ENTRIES
+        ));
+    }
+    for entry in &entries {
+        if !is_fingerprint(&entry.fingerprint) {
+            return Err(format!("Invalid fingerprint '{}'", entry.fingerprint));
+        }
+        if entry.label.is_empty() {
+            return Err(format!("Fingerprint '{}' has no label", entry.fingerprint));
+        }
+    }
+    Ok(entries)
+}
+
+pub fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS known_fingerprints (
+            fingerprint TEXT NOT NULL,
+            label TEXT NOT NULL,
+            PRIMARY KEY (fingerprint, label)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Replaces the stored fingerprints with `entries`.
+pub fn replace_all(conn: &mut Connection, entries: &[KnownFingerprint]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM known_fingerprints", [])?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO known_fingerprints (fingerprint, label) VALUES (?1, ?2)",
+        )?;
+        for entry in entries {
+            stmt.execute(params![entry.fingerprint.to_ascii_lowercase(), entry.label])?;
+        }
+    }
+    tx.commit()
+}
+
+/// In-memory lookup table of the stored fingerprints.
+#[derive(Debug, Default)]
+pub struct FingerprintDb {
+    labels: HashMap<String, Vec<String>>,
+}
+
+impl FingerprintDb {
+    pub fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT fingerprint, label FROM known_fingerprints ORDER BY fingerprint, label",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut db = FingerprintDb::default();
+        for row in rows {
+            let (fingerprint, label): (String, String) = row?;
+            db.labels.entry(fingerprint).or_default().push(label);
+        }
+        Ok(db)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Labels of all entries matching any of `fingerprints`, without duplicates.
+    pub fn lookup<'a>(&self, fingerprints: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut labels: Vec<String> = Vec::new();
+        for value in fingerprints {
+            for label in self
+                .labels
+                .get(&value.to_ascii_lowercase())
+                .into_iter()
+                .flatten()
+            {
+                if !labels.contains(label) {
+                    labels.push(label.clone());
+                }
+            }
+        }
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TlsFingerprints;
+
+    const JA3: &str = "e7d705a3286e19ea42f587b344ee6865";
+    const JA4: &str = "t13d1516h2_8daaf6152771_02713d6af862";
+
+    #[test]
+    fn test_parse_csv_and_json() {
+        let csv = format!(
+            "# abuse.ch SSLBL\nja3_md5,Firstseen,Lastseen,Listingreason\n{},2019-01-01,2019-02-01,Dridex\n",
+            JA3.to_uppercase()
+        );
+        let entries = parse_file(&csv).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].label, "Dridex");
+
+        let json = format!(
+            r#"[{{"fingerprint": "{}", "label": "Cobalt Strike"}}]"#,
+            JA4
+        );
+        assert_eq!(parse_file(&json).unwrap()[0].fingerprint, JA4);
+
+        assert!(parse_file(&format!("{},x\nnot-a-hash,y\n", JA3)).is_err());
+    }
+
+    #[test]
+    fn test_replace_and_lookup() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let entry = |fingerprint: &str, label: &str| KnownFingerprint {
+            fingerprint: fingerprint.to_string(),
+            label: label.to_string(),
+        };
+        replace_all(
+            &mut conn,
+            &[entry("stale00000000000000000000000000a", "Old")],
+        )
+        .unwrap();
+        replace_all(
+            &mut conn,
+            &[
+                entry(&JA3.to_uppercase(), "Dridex"),
+                entry(JA4, "Cobalt Strike"),
+            ],
+        )
+        .unwrap();
+
+        let db = FingerprintDb::load(&conn).unwrap();
+        let fingerprints = TlsFingerprints {
+            ja3: Some(JA3.to_string()),
+            ja4: Some(JA4.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.lookup(fingerprints.values()),
+            vec!["Dridex", "Cobalt Strike"]
+        );
+        assert!(db.lookup(TlsFingerprints::default().values()).is_empty());
+        assert_eq!(db.labels.len(), 2);
+    }
+}
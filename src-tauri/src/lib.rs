@@ -1,20 +1,47 @@
+pub mod bpf;
 pub mod capture;
+pub mod db;
 pub mod dissector;
 pub mod export;
+pub mod filter;
+pub mod filter_library;
+pub mod fingerprints;
 pub mod model;
+pub mod rules;
 pub mod state;
 
 use rusqlite::Connection;
 use state::FlowTable;
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tauri::Manager;
 use tokio::sync::mpsc;
 
-type PacketBatchEntry = (i64, i64, String, String, String, i32, String, Vec<u8>);
+type PacketBatchEntry = (
+    i64,
+    i64,
+    String,
+    String,
+    String,
+    i32,
+    String,
+    Option<u16>,
+    Option<String>,
+    Option<String>,
+    Option<dissector::Endpoints>,
+    Vec<u8>,
+);
+type FilterRow = (
+    model::PacketSummary,
+    Option<u8>,
+    Option<u16>,
+    Option<u16>,
+    Vec<u8>,
+);
 
 // Initialize logging
 #[cfg(not(debug_assertions))]
@@ -142,21 +169,19 @@ fn validate_interface_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Validates BPF filter string to prevent command injection.
+/// Rejects BPF filter strings libpcap could never accept.
+///
+/// The filter goes straight to `pcap_compile`, never through a shell, so the
+/// syntax itself is checked by compiling it (see `check_bpf_filter`).
 fn validate_bpf_filter(filter: &str) -> Result<(), String> {
     // BPF filters should be reasonable length
     if filter.len() > 1024 {
         return Err("BPF filter too long (max 1024 characters)".to_string());
     }
 
-    // Reject filters with shell metacharacters
-    let dangerous_chars = [
-        ';', '&', '|', '`', '$', '(', ')', '{', '}', '<', '>', '!', '\n', '\r',
-    ];
-    for ch in dangerous_chars {
-        if filter.contains(ch) {
-            return Err(format!("BPF filter contains invalid character: {}", ch));
-        }
+    // pcap_compile takes a C string, and a filter is a single expression
+    if filter.contains(['\0', '\n', '\r']) {
+        return Err("BPF filter contains invalid characters".to_string());
     }
 
     Ok(())
@@ -205,12 +230,60 @@ fn validate_filter(filter: &str) -> Result<(), String> {
 pub struct AppState {
     // Sender to signal the capture task to stop
     pub stop_tx: Mutex<Option<mpsc::Sender<()>>>,
-    // SQLite connection for packet storage
-    pub db_conn: Arc<Mutex<Connection>>,
-    // Global flow table for connection tracking
+    // SQLite writer connection and read-only pool for packet storage
+    pub db: db::DbPool,
+    // Global flow table for connection tracking, with DHCP lease and ICMP echo history
     pub flow_table: Arc<Mutex<FlowTable>>,
     // Rate limiter for capture operations
     pub rate_limiter: CaptureRateLimiter,
+    // Identifies this application run in the filter history
+    pub session_id: i64,
+    // Compiled tagging rules, shared with the capture task
+    pub rules: Arc<Mutex<rules::RuleEngine>>,
+    // Local interface addresses, looked up once per capture session
+    pub local_addrs: Mutex<Option<Vec<std::net::IpAddr>>>,
+    // Filter last added to the history, so re-applying it is not counted again
+    pub last_filter: Mutex<Option<String>>,
+    // Progress of the last display filter that needs packets dissected
+    pub filter_scan: Mutex<FilterScan>,
+}
+
+/// Current time in nanoseconds since Unix epoch.
+fn now_ns() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+/// Loads the filter macros used to expand `$name` references.
+fn load_filter_macros(state: &AppState) -> Result<filter::Macros, String> {
+    let db = state.db.read()?;
+    filter_library::load_macros(&db).map_err(|e| format!("Failed to load filter macros: {}", e))
+}
+
+/// Compiles the stored tagging rules, expanding filter macros, together with
+/// the known TLS fingerprint database.
+fn load_rule_engine(db: &db::DbPool) -> Result<rules::RuleEngine, String> {
+    let conn = db.read()?;
+    let stored = rules::list_rules(&conn).map_err(|e| format!("Failed to load rules: {}", e))?;
+    let macros = filter_library::load_macros(&conn)
+        .map_err(|e| format!("Failed to load filter macros: {}", e))?;
+    let known = fingerprints::FingerprintDb::load(&conn)
+        .map_err(|e| format!("Failed to load fingerprint database: {}", e))?;
+    Ok(rules::RuleEngine::new(stored, &macros).with_fingerprints(known))
+}
+
+/// Recompiles the tagging rules after rules or macros changed, and forgets
+/// filter scans that may have expanded a changed macro.
+fn reload_rules(state: &AppState) -> Result<(), String> {
+    reset_filter_scan(state)?;
+    let engine = load_rule_engine(&state.db)?;
+    *state
+        .rules
+        .lock()
+        .map_err(|e| format!("Failed to lock rules: {}", e))? = engine;
+    Ok(())
 }
 
 /// Retrieves a paginated list of packets, optionally filtered.
@@ -228,47 +301,56 @@ async fn get_packets(
     if let Some(ref f) = filter {
         validate_filter(f)?;
     }
+    let plan = plan_filter(filter.as_deref(), &load_filter_macros(&state)?)?;
+
+    let db = state.db.read()?;
+
+    // Predicates SQLite cannot evaluate are applied while scanning, from the
+    // nearest checkpoint of earlier scans with the same filter
+    if let Some(plan) = plan.as_ref().filter(|p| p.residual.is_some()) {
+        let mut scan = lock_filter_scan(&state)?;
+        let filter = filter.as_deref().unwrap_or_default().trim();
+        scan.extend(filter, plan, &db, &state, Some(offset + limit))?;
+        let Some(&start) = scan.checkpoints.get(offset / FILTER_CHECKPOINT_INTERVAL) else {
+            return Ok(Vec::new());
+        };
+        let mut packets = Vec::new();
+        let mut skip = offset % FILTER_CHECKPOINT_INTERVAL;
+        scan_filtered(&db, &state, plan, (start - 1, scan.scanned_to), |summary| {
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                packets.push(summary);
+            }
+            packets.len() < limit
+        })?;
+        return Ok(packets);
+    }
 
-    let db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
-
-    let (where_clause, params) = if let Some(f) = filter {
-        build_filter_clause(&f)
-    } else {
-        ("".to_string(), vec![])
+    let (where_clause, params) = match plan {
+        Some(ref plan) => (plan.where_clause(), plan.params()),
+        None => ("".to_string(), &[][..]),
     };
 
     let query = format!(
-        "SELECT id, timestamp_ns, source_addr, dest_addr, protocol, length, info FROM packets {} ORDER BY id ASC LIMIT ? OFFSET ?",
-        where_clause
+        "SELECT {} FROM packets {} ORDER BY id ASC LIMIT ? OFFSET ?",
+        SUMMARY_COLUMNS, where_clause
     );
 
     let mut stmt = db
         .prepare(&query)
         .map_err(|e| format!("Prepare failed: {}", e))?;
 
-    // Convert Vec<String> to Vec<&dyn ToSql>
+    // Convert filter values to Vec<&dyn ToSql>
     let mut sql_params: Vec<&dyn rusqlite::ToSql> =
-        params.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
     let limit_i64 = limit as i64;
     let offset_i64 = offset as i64;
     sql_params.push(&limit_i64);
     sql_params.push(&offset_i64);
 
     let packet_rows = stmt
-        .query_map(&*sql_params, |row| {
-            Ok(model::PacketSummary {
-                id: row.get::<_, i64>(0)? as u64,
-                timestamp: row.get(1)?,
-                source_addr: row.get(2)?,
-                dest_addr: row.get(3)?,
-                protocol: row.get(4)?,
-                length: row.get(5)?,
-                info: row.get(6)?,
-            })
-        })
+        .query_map(&*sql_params, summary_from_row)
         .map_err(|e| format!("Query failed: {}", e))?;
 
     let mut packets = Vec::new();
@@ -289,16 +371,21 @@ async fn get_packet_count(
     if let Some(ref f) = filter {
         validate_filter(f)?;
     }
+    let plan = plan_filter(filter.as_deref(), &load_filter_macros(&state)?)?;
 
-    let db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
+    let db = state.db.read()?;
 
-    let (where_clause, params) = if let Some(f) = filter {
-        build_filter_clause(&f)
-    } else {
-        ("".to_string(), vec![])
+    // Only packets added since the last count with this filter are scanned
+    if let Some(plan) = plan.as_ref().filter(|p| p.residual.is_some()) {
+        let mut scan = lock_filter_scan(&state)?;
+        let filter = filter.as_deref().unwrap_or_default().trim();
+        scan.extend(filter, plan, &db, &state, None)?;
+        return Ok(scan.count);
+    }
+
+    let (where_clause, params) = match plan {
+        Some(ref plan) => (plan.where_clause(), plan.params()),
+        None => ("".to_string(), &[][..]),
     };
 
     let query = format!("SELECT COUNT(*) FROM packets {}", where_clause);
@@ -307,7 +394,7 @@ async fn get_packet_count(
         .map_err(|e| format!("Prepare failed: {}", e))?;
 
     let sql_params: Vec<&dyn rusqlite::ToSql> =
-        params.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        params.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
 
     let count: i64 = stmt
         .query_row(&*sql_params, |row| row.get(0))
@@ -316,57 +403,468 @@ async fn get_packet_count(
     Ok(count as usize)
 }
 
-fn build_filter_clause(filter: &str) -> (String, Vec<String>) {
-    let filter = filter.to_lowercase();
-    let filter = filter.trim();
-    if filter.is_empty() {
-        return ("".to_string(), vec![]);
+/// Parses a display filter into a SQL/residual plan. Blank filters match everything.
+fn plan_filter(
+    filter: Option<&str>,
+    macros: &filter::Macros,
+) -> Result<Option<filter::FilterPlan>, String> {
+    match filter.map(str::trim) {
+        Some(f) if !f.is_empty() => {
+            let expr = filter::parse_with_macros(f, macros)
+                .map_err(|e| format!("Invalid filter: {}", e))?;
+            Ok(Some(filter::plan(expr)))
+        }
+        _ => Ok(None),
     }
+}
 
-    if filter.starts_with("protocol:") {
-        let val = filter.replace("protocol:", "").trim().to_string();
-        return (
-            "WHERE protocol LIKE ?".to_string(),
-            vec![format!("%{}%", val)],
-        );
+/// Columns read by [`summary_from_row`], in order.
+const SUMMARY_COLUMNS: &str =
+    "id, timestamp_ns, source_addr, dest_addr, protocol, length, info, vlan_id, tags, color";
+
+/// Maps the leading [`SUMMARY_COLUMNS`] of a row to a summary.
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<model::PacketSummary> {
+    Ok(model::PacketSummary {
+        id: row.get::<_, i64>(0)? as u64,
+        timestamp: row.get(1)?,
+        source_addr: row.get(2)?,
+        dest_addr: row.get(3)?,
+        protocol: row.get(4)?,
+        length: row.get(5)?,
+        info: row.get(6)?,
+        vlan_id: row.get(7)?,
+        tags: rules::decode_tags(row.get(8)?),
+        color: row.get(9)?,
+    })
+}
+
+/// Reads the summary, transport and raw data columns used by the filter evaluator.
+fn filter_row(row: &rusqlite::Row) -> rusqlite::Result<FilterRow> {
+    Ok((
+        summary_from_row(row)?,
+        row.get(10)?,
+        row.get(11)?,
+        row.get(12)?,
+        row.get(13)?,
+    ))
+}
+
+/// Walks packets with IDs in `(after, up_to]` matching the SQL part of `plan` in id
+/// order, evaluating its residual predicate in Rust, on the reassembled datagram for
+/// a datagram's last fragment. `visit` receives each match and returns false to stop
+/// the scan.
+fn scan_filtered(
+    db: &Connection,
+    state: &AppState,
+    plan: &filter::FilterPlan,
+    (after, up_to): (i64, i64),
+    mut visit: impl FnMut(model::PacketSummary) -> bool,
+) -> Result<(), String> {
+    let sql = plan
+        .sql
+        .as_ref()
+        .map(|sql| format!(" AND ({})", sql.clause))
+        .unwrap_or_default();
+    let query = format!(
+        "SELECT {}, ip_proto, src_port, dst_port, data FROM packets
+         WHERE id > ? AND id <= ?{} ORDER BY id ASC",
+        SUMMARY_COLUMNS, sql
+    );
+    let mut stmt = db
+        .prepare(&query)
+        .map_err(|e| format!("Prepare failed: {}", e))?;
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&after, &up_to];
+    params.extend(plan.params().iter().map(|v| v as &dyn rusqlite::ToSql));
+    let mut rows = stmt
+        .query(&*params)
+        .map_err(|e| format!("Query failed: {}", e))?;
+
+    while let Some(row) = rows.next().map_err(|e| format!("Query failed: {}", e))? {
+        let (summary, ip_proto, src_port, dst_port, mut data) =
+            filter_row(row).map_err(|e| format!("Row mapping failed: {}", e))?;
+        if let Some(reassembly) = reassemble_fragments(db, summary.id, state)? {
+            data = dissector::reassembled_frame(&reassembly);
+        }
+        let record = filter::PacketRecord::new(&summary, ip_proto, src_port, dst_port, &data);
+
+        let matched = plan
+            .residual
+            .as_ref()
+            .is_none_or(|expr| filter::evaluate(expr, &record));
+        if matched && !visit(summary) {
+            break;
+        }
     }
-    if filter.starts_with("ip:") {
-        let val = filter.replace("ip:", "").trim().to_string();
-        return (
-            "WHERE source_addr LIKE ? OR dest_addr LIKE ?".to_string(),
-            vec![format!("%{}%", val), format!("%{}%", val)],
-        );
+
+    Ok(())
+}
+
+/// Matches between the checkpoints kept for a filter that needs dissection.
+const FILTER_CHECKPOINT_INTERVAL: usize = 256;
+
+/// How far the packet table has been scanned with a filter that needs packets
+/// dissected, so paging and counting only evaluate packets not seen before.
+#[derive(Debug, Default)]
+pub struct FilterScan {
+    filter: String,
+    /// Highest packet ID evaluated
+    scanned_to: i64,
+    /// Matching packets up to `scanned_to`
+    count: usize,
+    /// IDs of the first match and of every [`FILTER_CHECKPOINT_INTERVAL`] matches after it
+    checkpoints: Vec<i64>,
+}
+
+impl FilterScan {
+    /// Evaluates `plan` on the packets not yet scanned with `filter`, starting over
+    /// for a different filter, until `wanted` packets have matched.
+    fn extend(
+        &mut self,
+        filter: &str,
+        plan: &filter::FilterPlan,
+        db: &Connection,
+        state: &AppState,
+        wanted: Option<usize>,
+    ) -> Result<(), String> {
+        if self.filter != filter {
+            *self = FilterScan {
+                filter: filter.to_string(),
+                ..FilterScan::default()
+            };
+        }
+        let wanted = wanted.unwrap_or(usize::MAX);
+        if self.count >= wanted {
+            return Ok(());
+        }
+        let max_id: i64 = db
+            .query_row("SELECT COALESCE(MAX(id), 0) FROM packets", [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| format!("Query failed: {}", e))?;
+        if max_id <= self.scanned_to {
+            return Ok(());
+        }
+        let mut last_match = None;
+        scan_filtered(db, state, plan, (self.scanned_to, max_id), |summary| {
+            if self.count % FILTER_CHECKPOINT_INTERVAL == 0 {
+                self.checkpoints.push(summary.id as i64);
+            }
+            self.count += 1;
+            last_match = Some(summary.id as i64);
+            self.count < wanted
+        })?;
+        self.scanned_to = match last_match {
+            Some(id) if self.count >= wanted => id,
+            _ => max_id,
+        };
+        Ok(())
     }
-    if filter.starts_with("src:") {
-        let val = filter.replace("src:", "").trim().to_string();
-        return (
-            "WHERE source_addr LIKE ?".to_string(),
-            vec![format!("%{}%", val)],
-        );
+}
+
+/// Locks the filter scan progress shared by paging and counting.
+fn lock_filter_scan(state: &AppState) -> Result<std::sync::MutexGuard<'_, FilterScan>, String> {
+    state
+        .filter_scan
+        .lock()
+        .map_err(|e| format!("Failed to lock filter scan: {}", e))
+}
+
+/// Forgets filter scan progress, after the packet table or filter macros changed.
+fn reset_filter_scan(state: &AppState) -> Result<(), String> {
+    *lock_filter_scan(state)? = FilterScan::default();
+    Ok(())
+}
+
+/// Suggests filterable field names starting with `prefix`, for filter autocomplete.
+#[tauri::command]
+fn complete_filter_field(prefix: String) -> Vec<filter::FieldSuggestion> {
+    filter::complete_field(&prefix)
+}
+
+/// Compiles a BPF capture filter with libpcap, without needing capture privileges.
+///
+/// `link_type` is a DLT value (Ethernet when omitted) and `snaplen` defaults to
+/// the snaplen used for live captures.
+#[tauri::command]
+fn check_bpf_filter(
+    filter: String,
+    link_type: Option<i32>,
+    snaplen: Option<i32>,
+) -> Result<model::BpfCheckResult, String> {
+    validate_bpf_filter(&filter)?;
+
+    let link_type = link_type.unwrap_or(pcap::Linktype::ETHERNET.0);
+    let snaplen = snaplen.unwrap_or(capture::CAPTURE_SNAPLEN);
+    if snaplen <= 0 || snaplen > 262_144 {
+        return Err("Snaplen must be between 1 and 262,144".to_string());
     }
-    if filter.starts_with("dst:") {
-        let val = filter.replace("dst:", "").trim().to_string();
-        return (
-            "WHERE dest_addr LIKE ?".to_string(),
-            vec![format!("%{}%", val)],
-        );
+
+    Ok(match capture::compile_bpf(&filter, link_type, snaplen) {
+        Ok(instructions) => model::BpfCheckResult {
+            valid: true,
+            error: None,
+            instructions,
+        },
+        Err(e) => model::BpfCheckResult {
+            valid: false,
+            error: Some(e),
+            instructions: Vec::new(),
+        },
+    })
+}
+
+/// Translates a display filter into a capture (BPF) filter for `start_capture`.
+///
+/// The returned residual is the part of the display filter that BPF cannot
+/// express and which should still be applied to the captured packets.
+#[tauri::command]
+fn translate_filter_to_bpf(
+    filter: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bpf::BpfTranslation, String> {
+    validate_filter(&filter)?;
+    let macros = load_filter_macros(&state)?;
+    let expr = filter::parse_with_macros(filter.trim(), &macros)
+        .map_err(|e| format!("Invalid filter: {}", e))?;
+    let translation = bpf::translate(expr);
+
+    // Make sure the generated filter is accepted by the same check start_capture applies
+    if let Some(ref bpf_filter) = translation.filter {
+        validate_bpf_filter(bpf_filter)?;
+        capture::compile_bpf(
+            bpf_filter,
+            pcap::Linktype::ETHERNET.0,
+            capture::CAPTURE_SNAPLEN,
+        )
+        .map_err(|e| {
+            format!(
+                "Generated BPF filter '{}' failed to compile: {}",
+                bpf_filter, e
+            )
+        })?;
+    }
+
+    Ok(translation)
+}
+
+/// Lists the saved display filters by name.
+#[tauri::command]
+fn list_saved_filters(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<filter_library::SavedFilter>, String> {
+    let db = state.db.read()?;
+    filter_library::list_saved_filters(&db).map_err(|e| format!("Query failed: {}", e))
+}
+
+/// Saves (or replaces) a named display filter after checking that it parses.
+#[tauri::command]
+fn save_filter(
+    name: String,
+    expression: String,
+    description: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    validate_filter(&expression)?;
+    let saved = filter_library::SavedFilter {
+        name: name.trim().to_string(),
+        expression: expression.trim().to_string(),
+        description: description.unwrap_or_default(),
+    };
+    filter_library::validate_saved_filter(&saved, &load_filter_macros(&state)?)?;
+
+    let db = state.db.write()?;
+    filter_library::save_filter(&db, &saved).map_err(|e| format!("Failed to save filter: {}", e))
+}
+
+/// Deletes a saved filter, returning whether it existed.
+#[tauri::command]
+fn delete_saved_filter(name: String, state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let db = state.db.write()?;
+    filter_library::delete_saved_filter(&db, &name)
+        .map_err(|e| format!("Failed to delete filter: {}", e))
+}
+
+/// Lists the filter macros available as `$name` in display filters.
+#[tauri::command]
+fn list_filter_macros(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<filter_library::FilterMacro>, String> {
+    let db = state.db.read()?;
+    filter_library::list_macros(&db).map_err(|e| format!("Query failed: {}", e))
+}
+
+/// Saves (or replaces) a filter macro. `$1`, `$2`, ... in the body are arguments.
+#[tauri::command]
+fn save_filter_macro(
+    name: String,
+    body: String,
+    description: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    validate_filter(&body)?;
+    let filter_macro = filter_library::FilterMacro {
+        name: name.trim().trim_start_matches('$').to_string(),
+        body: body.trim().to_string(),
+        description: description.unwrap_or_default(),
+    };
+    let mut macros = load_filter_macros(&state)?;
+    macros.insert(filter_macro.name.clone(), filter_macro.body.clone());
+    filter_library::validate_macro(&filter_macro, &macros)?;
+
+    {
+        let db = state.db.write()?;
+        filter_library::save_macro(&db, &filter_macro)
+            .map_err(|e| format!("Failed to save macro: {}", e))?;
+    }
+    reload_rules(&state)
+}
+
+/// Deletes a filter macro, returning whether it existed.
+#[tauri::command]
+fn delete_filter_macro(name: String, state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let existed = {
+        let db = state.db.write()?;
+        filter_library::delete_macro(&db, name.trim_start_matches('$'))
+            .map_err(|e| format!("Failed to delete macro: {}", e))?
+    };
+    reload_rules(&state)?;
+    Ok(existed)
+}
+
+/// Adds an applied filter to this session's history, unless it is the filter
+/// added last.
+#[tauri::command]
+async fn record_filter_history(
+    filter: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Ok(());
+    }
+    validate_filter(filter)?;
+    plan_filter(Some(filter), &load_filter_macros(&state)?)?;
+    {
+        let mut last = state
+            .last_filter
+            .lock()
+            .map_err(|e| format!("Failed to lock filter history: {}", e))?;
+        if last.as_deref() == Some(filter) {
+            return Ok(());
+        }
+        *last = Some(filter.to_string());
+    }
+    let db = state.db.write()?;
+    filter_library::record_history(&db, state.session_id, filter, now_ns())
+        .map_err(|e| format!("Failed to record filter history: {}", e))
+}
+
+/// Returns recently applied filters, for this session unless `all_sessions` is set.
+#[tauri::command]
+fn get_filter_history(
+    all_sessions: Option<bool>,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<filter_library::HistoryEntry>, String> {
+    let session_id = (!all_sessions.unwrap_or(false)).then_some(state.session_id);
+    let limit = limit.unwrap_or(50).clamp(1, 1000);
+    let db = state.db.read()?;
+    filter_library::history(&db, session_id, limit).map_err(|e| format!("Query failed: {}", e))
+}
+
+/// Writes saved filters, macros and filter history to a JSON file.
+#[tauri::command]
+fn export_filter_library(
+    file_path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let path = validate_file_path(&file_path, LIBRARY_FILE_EXTENSIONS, false)?;
+    let library = {
+        let db = state.db.read()?;
+        filter_library::export_library(&db).map_err(|e| format!("Query failed: {}", e))?
+    };
+    let json = serde_json::to_string_pretty(&library)
+        .map_err(|e| format!("Failed to serialize filter library: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(library.saved_filters.len() + library.macros.len() + library.history.len())
+}
+
+/// Merges a filter library JSON file into the saved filters, macros and history.
+#[tauri::command]
+fn import_filter_library(
+    file_path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let path = validate_file_path(&file_path, LIBRARY_FILE_EXTENSIONS, true)?;
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if metadata.len() > 10 * 1024 * 1024 {
+        return Err("Filter library too large (max 10 MB)".to_string());
     }
-    if filter.starts_with("port:") {
-        let val = filter.replace("port:", "").trim().to_string();
-        return ("WHERE info LIKE ?".to_string(), vec![format!("%{}%", val)]);
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let library: filter_library::FilterLibrary =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid filter library: {}", e))?;
+
+    let imported = {
+        let mut db = state.db.write()?;
+        filter_library::import_library(&mut db, &library)?
+    };
+    reload_rules(&state)?;
+    Ok(imported)
+}
+
+/// Lists the application-layer dissectors and whether each is enabled.
+#[tauri::command]
+fn list_dissectors() -> Vec<dissector::registry::DissectorInfo> {
+    dissector::list_dissectors()
+}
+
+/// Enables or disables an application-layer dissector. Applies to packets
+/// captured, imported or opened afterwards; stored summaries keep their protocol.
+#[tauri::command]
+fn set_dissector_enabled(name: String, enabled: bool) -> Result<(), String> {
+    dissector::set_dissector_enabled(&name, enabled)
+}
+
+/// Lists the tagging rules, highest priority first.
+#[tauri::command]
+fn list_tag_rules(state: tauri::State<'_, AppState>) -> Result<Vec<rules::TagRule>, String> {
+    let db = state.db.read()?;
+    rules::list_rules(&db).map_err(|e| format!("Query failed: {}", e))
+}
+
+/// Saves (or replaces) a tagging rule. Applies to packets captured or imported afterwards.
+#[tauri::command]
+fn save_tag_rule(rule: rules::TagRule, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    validate_filter(&rule.filter)?;
+    rules::validate_rule(&rule, &load_filter_macros(&state)?)?;
+    {
+        let db = state.db.write()?;
+        rules::save_rule(&db, &rule).map_err(|e| format!("Failed to save rule: {}", e))?;
     }
+    reload_rules(&state)
+}
 
-    // General search
-    (
-        "WHERE protocol LIKE ? OR source_addr LIKE ? OR dest_addr LIKE ? OR info LIKE ? OR CAST(length AS TEXT) LIKE ?".to_string(),
-        vec![
-            format!("%{}%", filter),
-            format!("%{}%", filter),
-            format!("%{}%", filter),
-            format!("%{}%", filter),
-            format!("%{}%", filter),
-        ]
-    )
+/// Deletes a tagging rule, returning whether it existed.
+#[tauri::command]
+fn delete_tag_rule(name: String, state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let existed = {
+        let db = state.db.write()?;
+        rules::delete_rule(&db, &name).map_err(|e| format!("Failed to delete rule: {}", e))?
+    };
+    reload_rules(&state)?;
+    Ok(existed)
+}
+
+/// Replaces all tagging rules with the default set.
+#[tauri::command]
+fn reset_tag_rules(state: tauri::State<'_, AppState>) -> Result<Vec<rules::TagRule>, String> {
+    {
+        let mut db = state.db.write()?;
+        rules::reset_rules(&mut db).map_err(|e| format!("Failed to reset rules: {}", e))?;
+    }
+    reload_rules(&state)?;
+    Ok(rules::default_rules())
 }
 
 /// Lists all available network interfaces for packet capture.
@@ -389,9 +887,12 @@ async fn start_capture(
     // Validate interface name
     validate_interface_name(&interface_name)?;
 
-    // Validate BPF filter if provided
+    // Validate BPF filter if provided, compiling it up front so syntax errors
+    // are reported before the capture task starts
     if let Some(ref f) = filter {
         validate_bpf_filter(f)?;
+        capture::compile_bpf(f, pcap::Linktype::ETHERNET.0, capture::CAPTURE_SNAPLEN)
+            .map_err(|e| format!("Invalid BPF filter: {}", e))?;
     }
 
     // Check rate limit
@@ -416,10 +917,7 @@ async fn start_capture(
 
     // Clear packet table and flow table
     {
-        let db = state
-            .db_conn
-            .lock()
-            .map_err(|e| format!("Failed to lock db: {}", e))?;
+        let db = state.db.write()?;
         db.execute("DELETE FROM packets", [])
             .map_err(|e| format!("Failed to clear packets: {}", e))?;
 
@@ -429,10 +927,18 @@ async fn start_capture(
             .map_err(|e| format!("Failed to lock flow table: {}", e))?;
         flows.clear();
     }
+    state
+        .rules
+        .lock()
+        .map_err(|e| format!("Failed to lock rules: {}", e))?
+        .reset();
+    reset_local_addresses(&state)?;
+    reset_filter_scan(&state)?;
 
-    // Clone the DB Arc for the task
-    let db_conn = Arc::clone(&state.db_conn);
+    // Clone the writer handle for the task
+    let db_conn = state.db.writer_handle();
     let flow_table = Arc::clone(&state.flow_table);
+    let rules = Arc::clone(&state.rules);
 
     // Spawn the capture task
     let app_handle_clone = app_handle.clone();
@@ -444,6 +950,7 @@ async fn start_capture(
             stop_rx,
             db_conn,
             flow_table,
+            rules,
         )
         .await
         {
@@ -460,10 +967,7 @@ fn export_pcap_all(file_path: String, state: tauri::State<'_, AppState>) -> Resu
     // Validate file path to prevent path traversal
     let path = validate_export_path_new(&file_path)?;
 
-    let db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
+    let db = state.db.read()?;
 
     let mut stmt = db
         .prepare("SELECT id, timestamp_ns, data FROM packets ORDER BY id ASC")
@@ -497,6 +1001,134 @@ fn export_pcap_all(file_path: String, state: tauri::State<'_, AppState>) -> Resu
     Ok(exported_count)
 }
 
+/// Replaces the known TLS fingerprint database with a JSON or CSV file.
+#[tauri::command]
+fn import_fingerprint_db(
+    file_path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let path = validate_file_path(&file_path, FINGERPRINT_FILE_EXTENSIONS, true)?;
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if metadata.len() > 10 * 1024 * 1024 {
+        return Err("Fingerprint database too large (max 10 MB)".to_string());
+    }
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let entries = fingerprints::parse_file(&content)?;
+
+    {
+        let mut db = state.db.write()?;
+        fingerprints::replace_all(&mut db, &entries)
+            .map_err(|e| format!("Failed to save fingerprints: {}", e))?;
+    }
+    reload_rules(&state)?;
+    Ok(entries.len())
+}
+
+#[tauri::command]
+fn clear_fingerprint_db(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    {
+        let mut db = state.db.write()?;
+        fingerprints::replace_all(&mut db, &[])
+            .map_err(|e| format!("Failed to clear fingerprints: {}", e))?;
+    }
+    reload_rules(&state)
+}
+
+/// Lists the flows with TLS fingerprints, with any known-fingerprint matches.
+#[tauri::command]
+fn list_tls_flows(state: tauri::State<'_, AppState>) -> Result<Vec<model::TlsFlow>, String> {
+    let rules = state
+        .rules
+        .lock()
+        .map_err(|e| format!("Failed to lock rules: {}", e))?;
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+
+    let mut tls_flows: Vec<model::TlsFlow> = flows
+        .flows
+        .values()
+        .filter(|flow| !flow.tls_fingerprints.is_empty())
+        .map(|flow| model::TlsFlow {
+            key: flow.key.clone(),
+            start_time_ns: flow.start_time_ns,
+            packet_count: flow.packet_count,
+            known_fingerprints: rules.known_fingerprints(flow.tls_fingerprints.values()),
+            fingerprints: flow.tls_fingerprints.clone(),
+        })
+        .collect();
+    tls_flows.sort_by_key(|flow| flow.start_time_ns);
+    Ok(tls_flows)
+}
+
+/// Lists the flows with SSH fingerprints, with any known-fingerprint matches.
+#[tauri::command]
+fn list_ssh_flows(state: tauri::State<'_, AppState>) -> Result<Vec<model::SshFlow>, String> {
+    let rules = state
+        .rules
+        .lock()
+        .map_err(|e| format!("Failed to lock rules: {}", e))?;
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+
+    let mut ssh_flows: Vec<model::SshFlow> = flows
+        .flows
+        .values()
+        .filter(|flow| !flow.ssh_fingerprints.is_empty())
+        .map(|flow| model::SshFlow {
+            key: flow.key.clone(),
+            start_time_ns: flow.start_time_ns,
+            packet_count: flow.packet_count,
+            known_fingerprints: rules.known_fingerprints(flow.ssh_fingerprints.values()),
+            fingerprints: flow.ssh_fingerprints.clone(),
+        })
+        .collect();
+    ssh_flows.sort_by_key(|flow| flow.start_time_ns);
+    Ok(ssh_flows)
+}
+
+/// Lists the DHCP and DHCPv6 leases seen in the current capture.
+#[tauri::command]
+fn get_dhcp_leases(state: tauri::State<'_, AppState>) -> Result<Vec<state::Lease>, String> {
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    Ok(flows.leases.leases.clone())
+}
+
+/// Lists the SMB2 sessions seen in the current capture with their file activity.
+#[tauri::command]
+fn get_smb_sessions(state: tauri::State<'_, AppState>) -> Result<Vec<state::SmbSession>, String> {
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    Ok(flows.smb.sessions.clone())
+}
+
+/// Finds the device that held `ip` at `timestamp_ns`, according to DHCP.
+#[tauri::command]
+fn get_lease_holder(
+    ip: String,
+    timestamp_ns: i64,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<state::Lease>, String> {
+    let ip: std::net::IpAddr = ip
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid IP address: {}", ip))?;
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    Ok(flows.leases.holder_at(ip, timestamp_ns).cloned())
+}
+
 /// Retrieves all packet summaries belonging to the same flow as the given packet.
 #[tauri::command]
 async fn get_flow_packets(
@@ -528,18 +1160,15 @@ async fn get_flow_packets(
         let packet_ids = flow.packet_ids.clone();
         drop(flows); // Release lock
 
-        let db = state
-            .db_conn
-            .lock()
-            .map_err(|e| format!("Failed to lock db: {}", e))?;
+        let db = state.db.read()?;
 
         let mut packet_list = Vec::new();
         // Fetch summaries for all IDs in this flow
         for chunk in packet_ids.chunks(999) {
             let placeholders = vec!["?"; chunk.len()].join(",");
             let query = format!(
-                "SELECT id, timestamp_ns, source_addr, dest_addr, protocol, length, info FROM packets WHERE id IN ({}) ORDER BY id ASC", 
-                placeholders
+                "SELECT {} FROM packets WHERE id IN ({}) ORDER BY id ASC",
+                SUMMARY_COLUMNS, placeholders
             );
             let mut stmt = db
                 .prepare(&query)
@@ -552,17 +1181,7 @@ async fn get_flow_packets(
                 .collect();
 
             let rows = stmt
-                .query_map(&*params, |row| {
-                    Ok(model::PacketSummary {
-                        id: row.get::<_, i64>(0)? as u64,
-                        timestamp: row.get(1)?,
-                        source_addr: row.get(2)?,
-                        dest_addr: row.get(3)?,
-                        protocol: row.get(4)?,
-                        length: row.get(5)?,
-                        info: row.get(6)?,
-                    })
-                })
+                .query_map(&*params, summary_from_row)
                 .map_err(|e| format!("Query failed: {}", e))?;
 
             for row in rows.flatten() {
@@ -601,10 +1220,7 @@ async fn get_stream_content(
         let flow_key = key.clone();
         drop(flows);
 
-        let db = state
-            .db_conn
-            .lock()
-            .map_err(|e| format!("Failed to lock db: {}", e))?;
+        let db = state.db.read()?;
         let mut messages: Vec<model::StreamMessage> = Vec::new();
 
         // Fetch raw data for all packets in the flow
@@ -681,10 +1297,42 @@ fn stop_capture(state: tauri::State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// Addresses of the local interfaces, for telling apart packets this host sent.
+fn local_addresses() -> Vec<std::net::IpAddr> {
+    pcap::Device::list()
+        .map(|devices| {
+            devices
+                .iter()
+                .flat_map(|d| d.addresses.iter().map(|a| a.addr))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Local interface addresses of the current capture session, looked up on first use.
+fn session_local_addresses(state: &AppState) -> Vec<std::net::IpAddr> {
+    match state.local_addrs.lock() {
+        Ok(mut cached) => cached.get_or_insert_with(local_addresses).clone(),
+        Err(_) => local_addresses(),
+    }
+}
+
+/// Forgets the local interface addresses, so a new capture session looks them up again.
+fn reset_local_addresses(state: &AppState) -> Result<(), String> {
+    *state
+        .local_addrs
+        .lock()
+        .map_err(|e| format!("Failed to lock local addresses: {}", e))? = None;
+    Ok(())
+}
+
 /// Retrieves detailed protocol dissection for a specific packet.
+///
+/// Checksums are verified unless `verify_checksums` is false.
 #[tauri::command]
 async fn get_packet_detail(
     id: u64,
+    verify_checksums: Option<bool>,
     state: tauri::State<'_, AppState>,
 ) -> Result<model::PacketDetail, String> {
     // Validate packet ID
@@ -692,10 +1340,7 @@ async fn get_packet_detail(
         return Err("Invalid packet ID".to_string());
     }
 
-    let db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
+    let db = state.db.read()?;
 
     let id_i64 = id as i64;
     let mut stmt = db
@@ -706,7 +1351,89 @@ async fn get_packet_detail(
         .ok();
 
     if let Some((data, timestamp_ns)) = packet {
-        if let Some(detail) = dissector::dissect_packet(&data, id, timestamp_ns) {
+        let app_protocol = dissector::get_flow_key(&data).and_then(|key| {
+            let flows = state.flow_table.lock().ok()?;
+            flows.app_protocol(&key).map(str::to_string)
+        });
+        let sequence_bases = dissector::tcp_segment(&data)
+            .and_then(|segment| Some(state.flow_table.lock().ok()?.sequences.bases(&segment)))
+            .unwrap_or_default();
+        let smb_files = smb_transfer_files(id, &state)?;
+        let options = if verify_checksums.unwrap_or(true) {
+            dissector::DissectOptions {
+                verify_checksums: true,
+                local_addrs: session_local_addresses(&state),
+                app_protocol,
+                sequence_bases,
+                smb_files,
+            }
+        } else {
+            dissector::DissectOptions {
+                app_protocol,
+                sequence_bases,
+                smb_files,
+                ..dissector::DissectOptions::default()
+            }
+        };
+        let reassembly = reassemble_fragments(&db, id, &state)?;
+        let dissected = match &reassembly {
+            Some(reassembly) => {
+                dissector::dissect_reassembled(reassembly, id, timestamp_ns, &options).map(
+                    |mut detail| {
+                        detail.summary.length = data.len() as u32;
+                        detail
+                    },
+                )
+            }
+            None => dissector::dissect_packet_with(&data, id, timestamp_ns, &options),
+        };
+        if let Some(mut detail) = dissected {
+            let matches = {
+                let rules = state
+                    .rules
+                    .lock()
+                    .map_err(|e| format!("Failed to lock rules: {}", e))?;
+                let intelligence = &detail.intelligence;
+                [
+                    (
+                        "TLS",
+                        rules.known_fingerprints(
+                            intelligence
+                                .tls_fingerprints
+                                .iter()
+                                .flat_map(|f| f.values()),
+                        ),
+                    ),
+                    (
+                        "SSH",
+                        rules.known_fingerprints(
+                            intelligence
+                                .ssh_fingerprints
+                                .iter()
+                                .flat_map(|f| f.values()),
+                        ),
+                    ),
+                ]
+            };
+            for (kind, known) in matches {
+                if known.is_empty() {
+                    continue;
+                }
+                detail.expert_summary.push(format!(
+                    "{} fingerprint matches known stack: {}",
+                    kind,
+                    known.join(", ")
+                ));
+                detail.intelligence.risk_score = detail.intelligence.risk_score.max(90);
+                for label in known {
+                    if !detail.intelligence.known_fingerprints.contains(&label) {
+                        detail.intelligence.known_fingerprints.push(label);
+                    }
+                }
+            }
+            add_echo_response_fields(&mut detail, &state)?;
+            add_smb_activity_fields(&mut detail, &state)?;
+            add_fragment_notes(&mut detail, &state)?;
             Ok(detail)
         } else {
             Err("Failed to dissect packet.".to_string())
@@ -716,6 +1443,179 @@ async fn get_packet_detail(
     }
 }
 
+/// Rebuilds the datagram reassembled at packet `id`, if its last fragment is `id`.
+fn reassemble_fragments(
+    db: &Connection,
+    id: u64,
+    state: &AppState,
+) -> Result<Option<state::Reassembly>, String> {
+    let fragment_ids = match state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?
+        .fragments
+        .fragment_ids(id)
+    {
+        Some(ids) => ids.to_vec(),
+        None => return Ok(None),
+    };
+    let mut stmt = db
+        .prepare_cached("SELECT data, timestamp_ns FROM packets WHERE id = ?1")
+        .map_err(|e| format!("Prepare failed: {}", e))?;
+    let mut frames = Vec::with_capacity(fragment_ids.len());
+    for fragment_id in fragment_ids {
+        let (data, timestamp_ns): (Vec<u8>, i64) = stmt
+            .query_row([fragment_id as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Fragment {} not found: {}", fragment_id, e))?;
+        frames.push((fragment_id, timestamp_ns, data));
+    }
+    Ok(dissector::reassemble_frames(
+        frames
+            .iter()
+            .map(|(id, ts, data)| (*id, *ts, data.as_slice())),
+    ))
+}
+
+/// Adds fragment expert notes, and where an earlier fragment was reassembled.
+fn add_fragment_notes(detail: &mut model::PacketDetail, state: &AppState) -> Result<(), String> {
+    let id = detail.summary.id;
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    detail
+        .expert_summary
+        .extend(flows.fragments.notes(id).iter().cloned());
+    if let Some(last) = flows
+        .fragments
+        .reassembled_in(id)
+        .filter(|&last| last != id)
+    {
+        detail
+            .expert_summary
+            .push(format!("Reassembled in frame {}", last));
+    }
+    Ok(())
+}
+
+/// Adds the matching request or reply and the round-trip time to an ICMP echo.
+fn add_echo_response_fields(
+    detail: &mut model::PacketDetail,
+    state: &AppState,
+) -> Result<(), String> {
+    let id = detail.summary.id;
+    let matched = match state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?
+        .echoes
+        .get(id)
+    {
+        Some(matched) => *matched,
+        None => return Ok(()),
+    };
+    let Some(layer) = detail
+        .layers
+        .iter_mut()
+        .find(|l| l.name.starts_with("Internet Control Message Protocol"))
+    else {
+        return Ok(());
+    };
+    let (name, other) = if matched.request_id == id {
+        ("Response In", matched.reply_id)
+    } else {
+        ("Request In", matched.request_id)
+    };
+    for (name, value) in [
+        (name, format!("Frame {}", other)),
+        ("Response Time", format!("{:.3} ms", matched.rtt_ms())),
+    ] {
+        layer.fields.push(model::PacketField {
+            name: name.to_string(),
+            value,
+            range: (0, 0),
+            expert: None,
+        });
+    }
+    Ok(())
+}
+
+/// Adds the account, share and file of SMB2 operations to the SMB2 layer,
+/// with the frame of the matching request or response.
+fn add_smb_activity_fields(
+    detail: &mut model::PacketDetail,
+    state: &AppState,
+) -> Result<(), String> {
+    let id = detail.summary.id;
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    let activity = flows.smb.activity(id);
+    if activity.is_empty() {
+        return Ok(());
+    }
+    let Some(layer) = detail
+        .layers
+        .iter_mut()
+        .find(|l| l.name.starts_with("SMB2"))
+    else {
+        return Ok(());
+    };
+    for (session, activity) in &activity {
+        let mut values = vec![
+            ("Account", session.account()),
+            ("Share", activity.share.clone()),
+            ("File", activity.file.clone()),
+        ];
+        if activity.packet_id == id {
+            let response = activity.response_id.map(|r| format!("Frame {}", r));
+            values.push(("Response In", response));
+        } else {
+            values.push(("Request In", Some(format!("Frame {}", activity.packet_id))));
+        }
+        for (name, value) in values {
+            if let Some(value) = value {
+                layer.fields.push(model::PacketField {
+                    name: name.to_string(),
+                    value,
+                    range: (0, 0),
+                    expert: None,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The file and offset of each SMB2 read or write in the packet, by message
+/// ID, for naming the transferred data.
+fn smb_transfer_files(id: u64, state: &AppState) -> Result<HashMap<u64, String>, String> {
+    let flows = state
+        .flow_table
+        .lock()
+        .map_err(|e| format!("Failed to lock flow table: {}", e))?;
+    Ok(flows
+        .smb
+        .activity(id)
+        .into_iter()
+        .filter(|(_, activity)| {
+            matches!(
+                activity.action,
+                state::FileAction::Read | state::FileAction::Write
+            )
+        })
+        .filter_map(|(_, activity)| {
+            let file = activity.file.as_ref()?;
+            let offset = activity.offset?;
+            Some((
+                activity.message_id,
+                format!("{} at offset {}", file, offset),
+            ))
+        })
+        .collect())
+}
+
 /// Exports selected packets to a PCAP file.
 #[tauri::command]
 fn export_pcap(
@@ -729,10 +1629,7 @@ fn export_pcap(
     // Validate file path to prevent path traversal
     let path = validate_export_path_new(&file_path)?;
 
-    let db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
+    let db = state.db.read()?;
 
     // Fetch packets from DB in chronological order
     let mut packet_list = Vec::new();
@@ -783,7 +1680,7 @@ fn export_pcap(
     Ok(exported_count)
 }
 
-fn init_db(_app_handle: &tauri::AppHandle) -> Result<Connection, Box<dyn std::error::Error>> {
+fn init_db(_app_handle: &tauri::AppHandle) -> Result<db::DbPool, Box<dyn std::error::Error>> {
     // Use platform-specific app data directory for security
     let db_path = if let Some(data_dir) = dirs::data_local_dir() {
         let app_dir = data_dir.join("auracap");
@@ -829,11 +1726,22 @@ fn init_db(_app_handle: &tauri::AppHandle) -> Result<Connection, Box<dyn std::er
             protocol TEXT,
             length INTEGER,
             info TEXT,
+            vlan_id INTEGER,
+            ip_proto INTEGER,
+            src_port INTEGER,
+            dst_port INTEGER,
+            tags TEXT,
+            color TEXT,
             data BLOB NOT NULL
         )",
         [],
     )?;
 
+    // Saved filters, macros, history, tagging rules and known fingerprints outlive the packet table
+    filter_library::init_schema(&conn)?;
+    rules::init_schema(&conn)?;
+    fingerprints::init_schema(&conn)?;
+
     // Create an index on id for fast pagination
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_packets_id ON packets(id)",
@@ -849,10 +1757,27 @@ fn init_db(_app_handle: &tauri::AppHandle) -> Result<Connection, Box<dyn std::er
     ",
     )?;
 
-    Ok(conn)
+    // Readers are opened only once the schema exists and WAL is enabled
+    let pool = db::DbPool::new(conn, &db_path, db::READER_POOL_SIZE)?;
+
+    Ok(pool)
 }
 
-fn validate_import_path(file_path: &str) -> Result<std::path::PathBuf, String> {
+/// Extensions accepted by `import_pcap`.
+const CAPTURE_FILE_EXTENSIONS: &[&str] = &["pcap", "pcapng", "cap"];
+/// Extensions accepted for fingerprint databases.
+const FINGERPRINT_FILE_EXTENSIONS: &[&str] = &["json", "csv"];
+/// Extensions accepted for filter libraries.
+const LIBRARY_FILE_EXTENSIONS: &[&str] = &["json"];
+
+/// Validates the path of a file to import or export: no null bytes or path
+/// traversal, one of `extensions` (case-insensitive), and when `must_exist`
+/// an existing regular file.
+fn validate_file_path(
+    file_path: &str,
+    extensions: &[&str],
+    must_exist: bool,
+) -> Result<std::path::PathBuf, String> {
     let path = Path::new(file_path);
 
     if file_path.contains('\0') {
@@ -866,21 +1791,28 @@ fn validate_import_path(file_path: &str) -> Result<std::path::PathBuf, String> {
         }
     }
 
-    if let Some(ext) = path.extension() {
-        let ext_lower = ext.to_string_lossy().to_lowercase();
-        if ext_lower != "pcap" && ext_lower != "pcapng" && ext_lower != "cap" {
-            return Err("File must have .pcap, .pcapng, or .cap extension".to_string());
-        }
-    } else {
-        return Err("File must have .pcap, .pcapng, or .cap extension".to_string());
-    }
-
-    if !path.exists() {
-        return Err("File does not exist".to_string());
+    let allowed = path.extension().is_some_and(|ext| {
+        let ext = ext.to_string_lossy();
+        extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))
+    });
+    if !allowed {
+        let names: Vec<String> = extensions.iter().map(|e| format!(".{}", e)).collect();
+        let listed = match names.as_slice() {
+            [only] => only.clone(),
+            [first, second] => format!("{} or {}", first, second),
+            [rest @ .., last] => format!("{}, or {}", rest.join(", "), last),
+            [] => String::new(),
+        };
+        return Err(format!("File must have {} extension", listed));
     }
 
-    if !path.is_file() {
-        return Err("Path is not a file".to_string());
+    if must_exist {
+        if !path.exists() {
+            return Err("File does not exist".to_string());
+        }
+        if !path.is_file() {
+            return Err("Path is not a file".to_string());
+        }
     }
 
     Ok(path.to_path_buf())
@@ -888,7 +1820,7 @@ fn validate_import_path(file_path: &str) -> Result<std::path::PathBuf, String> {
 
 #[tauri::command]
 fn import_pcap(file_path: String, state: tauri::State<'_, AppState>) -> Result<usize, String> {
-    let path = validate_import_path(&file_path)?;
+    let path = validate_file_path(&file_path, CAPTURE_FILE_EXTENSIONS, true)?;
 
     let mut capture = pcap::Capture::from_file(path.as_path())
         .map_err(|e| format!("Failed to read PCAP file: {}", e))?;
@@ -901,10 +1833,7 @@ fn import_pcap(file_path: String, state: tauri::State<'_, AppState>) -> Result<u
         ));
     }
 
-    let mut db = state
-        .db_conn
-        .lock()
-        .map_err(|e| format!("Failed to lock db: {}", e))?;
+    let mut db = state.db.write()?;
 
     db.execute("DELETE FROM packets", [])
         .map_err(|e| format!("Failed to clear packets: {}", e))?;
@@ -916,6 +1845,14 @@ fn import_pcap(file_path: String, state: tauri::State<'_, AppState>) -> Result<u
             .map_err(|e| format!("Failed to lock flow table: {}", e))?;
         flows.clear();
     }
+    reset_local_addresses(&state)?;
+    reset_filter_scan(&state)?;
+
+    let mut rules = state
+        .rules
+        .lock()
+        .map_err(|e| format!("Failed to lock rules: {}", e))?;
+    rules.reset();
 
     let mut packet_count = 0u64;
     let mut packet_id = 0u64;
@@ -930,8 +1867,59 @@ fn import_pcap(file_path: String, state: tauri::State<'_, AppState>) -> Result<u
                 let timestamp_ns = packet.header.ts.tv_sec * 1_000_000_000
                     + (packet.header.ts.tv_usec as i64) * 1_000;
 
-                if let Some(summary) = dissector::parse_summary(&data, packet_id, timestamp_ns) {
-                    let data_clone = data.clone();
+                // A fragment that completes its datagram is analysed as the reassembled datagram
+                let reassembly = dissector::ip_fragment(&data).and_then(|fragment| {
+                    state.flow_table.lock().ok()?.fragments.record(
+                        packet_id,
+                        timestamp_ns,
+                        data.len() as u32,
+                        fragment,
+                    )
+                });
+                let frame = reassembly
+                    .as_ref()
+                    .map_or_else(|| data.clone(), dissector::reassembled_frame);
+
+                // Later packets of a flow keep the application protocol learned from its first ones
+                let flow_key = dissector::get_flow_key(&frame);
+                let learned = flow_key.as_ref().and_then(|key| {
+                    state
+                        .flow_table
+                        .lock()
+                        .ok()?
+                        .app_protocol(key)
+                        .map(str::to_string)
+                });
+
+                // Seq/Ack numbers are shown relative to each direction's ISN
+                let sequence_bases = dissector::tcp_segment(&frame)
+                    .and_then(|segment| {
+                        Some(state.flow_table.lock().ok()?.sequences.record(&segment))
+                    })
+                    .unwrap_or_default();
+
+                if let Some(mut summary) = dissector::parse_flow_summary(
+                    &frame,
+                    packet_id,
+                    timestamp_ns,
+                    learned.as_deref(),
+                    sequence_bases,
+                ) {
+                    summary.length = data.len() as u32;
+                    rules.apply(&mut summary, &frame);
+                    if let Some(echo) = dissector::icmp_echo(&frame) {
+                        if let Ok(mut flows) = state.flow_table.lock() {
+                            if let Some(matched) =
+                                flows.echoes.record(packet_id, timestamp_ns, echo)
+                            {
+                                summary.info.push_str(&format!(
+                                    " (request in {}, rtt {:.3} ms)",
+                                    matched.request_id,
+                                    matched.rtt_ms()
+                                ));
+                            }
+                        }
+                    }
                     batch.push((
                         packet_id as i64,
                         timestamp_ns,
@@ -940,12 +1928,64 @@ fn import_pcap(file_path: String, state: tauri::State<'_, AppState>) -> Result<u
                         summary.protocol,
                         summary.length as i32,
                         summary.info,
+                        summary.vlan_id,
+                        rules::encode_tags(&summary.tags),
+                        summary.color,
+                        // Ports of a reassembled datagram come from its first fragment
+                        dissector::get_transport_endpoints(&frame),
                         data,
                     ));
 
-                    if let Some(key) = dissector::get_flow_key(&data_clone) {
+                    if let Some(key) = flow_key {
+                        if let Ok(mut flows) = state.flow_table.lock() {
+                            match &reassembly {
+                                Some(reassembly) => {
+                                    for fragment in &reassembly.fragments {
+                                        flows.update(
+                                            fragment.packet_id,
+                                            fragment.timestamp_ns,
+                                            fragment.length,
+                                            key.clone(),
+                                        );
+                                    }
+                                }
+                                None => flows.update(
+                                    packet_id,
+                                    timestamp_ns,
+                                    summary.length,
+                                    key.clone(),
+                                ),
+                            }
+                            if flows.needs_app_probe(&key) {
+                                if let Some(detected) = dissector::detect_application(&frame) {
+                                    flows.record_app_probe(&key, detected);
+                                }
+                            }
+                            if let Some(fingerprints) =
+                                dissector::flow_tls_fingerprints(&frame, &mut flows.quic)
+                            {
+                                flows.record_fingerprints(&key, &fingerprints);
+                            }
+                            let ssh = dissector::ssh_fingerprints(&frame, flows.app_protocol(&key));
+                            if let Some(fingerprints) = ssh {
+                                flows.record_ssh_fingerprints(&key, &fingerprints);
+                            }
+                            flows.record_tunnels(&key, &dissector::tunnel_ids(&frame));
+                        }
+                    }
+
+                    if let Some(event) = dissector::dhcp_lease_event(&frame) {
                         if let Ok(mut flows) = state.flow_table.lock() {
-                            flows.update(packet_id, timestamp_ns, summary.length, key);
+                            flows.leases.record(packet_id, timestamp_ns, event);
+                        }
+                    }
+
+                    let commands = dissector::smb_commands(&frame);
+                    if !commands.is_empty() {
+                        if let Ok(mut flows) = state.flow_table.lock() {
+                            for command in commands {
+                                flows.smb.record(packet_id, timestamp_ns, command);
+                            }
                         }
                     }
 
@@ -974,12 +2014,19 @@ fn insert_batch(db: &mut Connection, batch: &[PacketBatchEntry]) -> Result<(), S
     match db.transaction() {
         Ok(tx) => {
             let mut stmt = tx.prepare_cached(
-                "INSERT INTO packets (id, timestamp_ns, source_addr, dest_addr, protocol, length, info, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+                "INSERT INTO packets (id, timestamp_ns, source_addr, dest_addr, protocol, length, info, vlan_id, ip_proto, src_port, dst_port, tags, color, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"
             ).map_err(|e| format!("Prepare failed: {}", e))?;
 
-            for (id, ts, src, dst, proto, len, info, data) in batch {
-                stmt.execute(rusqlite::params![id, ts, src, dst, proto, len, info, data])
-                    .map_err(|e| format!("Insert failed: {}", e))?;
+            for (id, ts, src, dst, proto, len, info, vlan_id, tags, color, endpoints, data) in batch
+            {
+                let ip_proto = endpoints.map(|e| e.0);
+                let src_port = endpoints.and_then(|e| e.1);
+                let dst_port = endpoints.and_then(|e| e.2);
+                stmt.execute(rusqlite::params![
+                    id, ts, src, dst, proto, len, info, vlan_id, ip_proto, src_port, dst_port,
+                    tags, color, data
+                ])
+                .map_err(|e| format!("Insert failed: {}", e))?;
             }
 
             drop(stmt);
@@ -1012,13 +2059,22 @@ pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
             let handle = app.handle();
-            let db_conn = init_db(&handle).expect("Failed to initialize SQLite database");
+            let db = init_db(&handle).expect("Failed to initialize SQLite database");
+            let rules = load_rule_engine(&db).unwrap_or_else(|e| {
+                log::error!("Failed to load tagging rules: {}", e);
+                rules::RuleEngine::default()
+            });
 
             app.manage(AppState {
                 stop_tx: Mutex::new(None),
-                db_conn: Arc::new(Mutex::new(db_conn)),
+                db,
                 flow_table: Arc::new(Mutex::new(FlowTable::new())),
                 rate_limiter: CaptureRateLimiter::new(),
+                session_id: now_ns(),
+                rules: Arc::new(Mutex::new(rules)),
+                local_addrs: Mutex::new(None),
+                last_filter: Mutex::new(None),
+                filter_scan: Mutex::new(FilterScan::default()),
             });
             Ok(())
         })
@@ -1031,6 +2087,32 @@ pub fn run() {
             export_pcap_all,
             get_packets,
             get_packet_count,
+            complete_filter_field,
+            check_bpf_filter,
+            translate_filter_to_bpf,
+            list_saved_filters,
+            save_filter,
+            delete_saved_filter,
+            list_filter_macros,
+            save_filter_macro,
+            delete_filter_macro,
+            record_filter_history,
+            get_filter_history,
+            export_filter_library,
+            import_filter_library,
+            list_dissectors,
+            set_dissector_enabled,
+            list_tag_rules,
+            save_tag_rule,
+            delete_tag_rule,
+            reset_tag_rules,
+            import_fingerprint_db,
+            clear_fingerprint_db,
+            list_tls_flows,
+            list_ssh_flows,
+            get_dhcp_leases,
+            get_smb_sessions,
+            get_lease_holder,
             get_flow_packets,
             get_stream_content,
             import_pcap
@@ -1,3 +1,4 @@
+use crate::state::FlowKey;
 use serde::{Deserialize, Serialize};
 
 /// Cached packet data with timestamp for efficient storage and retrieval.
@@ -33,6 +34,15 @@ pub struct PacketSummary {
     pub length: u32,
     /// Human-readable packet description/information
     pub info: String,
+    /// ID of the outermost 802.1Q/802.1ad VLAN tag
+    #[serde(default)]
+    pub vlan_id: Option<u16>,
+    /// Tags assigned by matching colouring rules, highest priority first
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Row colour from the highest-priority matching rule that sets one
+    #[serde(default)]
+    pub color: Option<String>,
 }
 
 /// Detailed packet analysis with full protocol dissection.
@@ -70,6 +80,95 @@ pub struct ForensicIntelligence {
     pub ja3_hash: Option<String>,
     pub manufacturer: Option<String>,
     pub risk_score: u8, // 0-100
+    /// JA3/JA3S/JA4/JA4S of a ClientHello or ServerHello in this packet
+    #[serde(default)]
+    pub tls_fingerprints: Option<TlsFingerprints>,
+    /// HASSH/HASSHServer and identification string of an SSH packet
+    #[serde(default)]
+    pub ssh_fingerprints: Option<SshFingerprints>,
+    /// Labels of fingerprint database entries matching `tls_fingerprints` or
+    /// `ssh_fingerprints`
+    #[serde(default)]
+    pub known_fingerprints: Vec<String>,
+}
+
+/// TLS client (JA3, JA4) and server (JA3S, JA4S) fingerprints.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsFingerprints {
+    pub ja3: Option<String>,
+    pub ja3s: Option<String>,
+    pub ja4: Option<String>,
+    pub ja4s: Option<String>,
+}
+
+impl TlsFingerprints {
+    pub fn is_empty(&self) -> bool {
+        self.values().next().is_none()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        [&self.ja3, &self.ja3s, &self.ja4, &self.ja4s]
+            .into_iter()
+            .filter_map(|v| v.as_deref())
+    }
+
+    /// Fills in the fingerprints `other` has, keeping the rest.
+    pub fn merge(&mut self, other: &TlsFingerprints) {
+        for (slot, value) in [
+            (&mut self.ja3, &other.ja3),
+            (&mut self.ja3s, &other.ja3s),
+            (&mut self.ja4, &other.ja4),
+            (&mut self.ja4s, &other.ja4s),
+        ] {
+            if value.is_some() {
+                slot.clone_from(value);
+            }
+        }
+    }
+}
+
+/// SSH client (HASSH) and server (HASSHServer) fingerprints, with the
+/// identification strings both sides sent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SshFingerprints {
+    pub hassh: Option<String>,
+    pub hassh_server: Option<String>,
+    pub client_banner: Option<String>,
+    pub server_banner: Option<String>,
+    /// Whether a NEWKEYS message ended the cleartext phase
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+impl SshFingerprints {
+    pub fn is_empty(&self) -> bool {
+        self.client_banner.is_none()
+            && self.server_banner.is_none()
+            && self.values().next().is_none()
+            && !self.encrypted
+    }
+
+    /// The HASSH and HASSHServer present.
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        [&self.hassh, &self.hassh_server]
+            .into_iter()
+            .filter_map(|v| v.as_deref())
+    }
+
+    /// Fills in what `other` has, keeping the rest.
+    pub fn merge(&mut self, other: &SshFingerprints) {
+        for (slot, value) in [
+            (&mut self.hassh, &other.hassh),
+            (&mut self.hassh_server, &other.hassh_server),
+            (&mut self.client_banner, &other.client_banner),
+            (&mut self.server_banner, &other.server_banner),
+        ] {
+            if value.is_some() {
+                slot.clone_from(value);
+            }
+        }
+        self.encrypted |= other.encrypted;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,6 +192,28 @@ pub struct ProtocolLayer {
 }
 
 /// A message in a reassembled stream (e.g., TCP or UDP).
+/// A flow whose TLS hellos were fingerprinted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsFlow {
+    pub key: FlowKey,
+    pub start_time_ns: i64,
+    pub packet_count: u64,
+    pub fingerprints: TlsFingerprints,
+    /// Labels of fingerprint database entries matching `fingerprints`
+    pub known_fingerprints: Vec<String>,
+}
+
+/// A flow whose SSH key exchange was fingerprinted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshFlow {
+    pub key: FlowKey,
+    pub start_time_ns: i64,
+    pub packet_count: u64,
+    pub fingerprints: SshFingerprints,
+    /// Labels of fingerprint database entries matching `fingerprints`
+    pub known_fingerprints: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamMessage {
     /// The side that sent the message (true for client -> server, false for server -> client)
@@ -115,3 +236,14 @@ pub struct PacketField {
     /// Optional expert info for this specific field
     pub expert: Option<String>,
 }
+
+/// Outcome of compiling a capture (BPF) filter without opening a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BpfCheckResult {
+    /// Whether libpcap accepted the expression
+    pub valid: bool,
+    /// The libpcap compiler error, when the expression was rejected
+    pub error: Option<String>,
+    /// Compiled program as `code jt jf k` lines (empty when invalid)
+    pub instructions: Vec<String>,
+}
@@ -0,0 +1,441 @@
+//! Colouring and tagging rules.
+//!
+//! A rule maps a display filter to a tag, an optional row colour and a
+//! priority. Rules are evaluated as packets are summarized during capture and
+//! import; matching tags are stored with the packet (and can be filtered on
+//! with `tag:name`), and the colour of the highest-priority match is used for
+//! the row.
+//!
+//! Packets whose TLS fingerprints appear in the known-fingerprint database
+//! are also tagged, with [`KNOWN_FINGERPRINT_TAG`].
+
+use crate::dissector;
+use crate::filter::{self, Expr, Macros, PacketRecord};
+use crate::fingerprints::FingerprintDb;
+use crate::model::PacketSummary;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Window over which `rate_per_sec` thresholds are counted.
+const RATE_WINDOW_NS: i64 = 1_000_000_000;
+
+/// Tag for packets whose TLS fingerprint is in the fingerprint database.
+pub const KNOWN_FINGERPRINT_TAG: &str = "known-tls-fingerprint";
+
+/// A user-defined tagging rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagRule {
+    pub name: String,
+    /// Display filter selecting the packets to tag
+    pub filter: String,
+    pub tag: String,
+    /// Row colour as `#rrggbb`
+    pub color: Option<String>,
+    /// Higher priorities win the row colour and sort first among tags
+    pub priority: i32,
+    pub enabled: bool,
+    /// Only tag once more than this many packets matched within one second
+    #[serde(default)]
+    pub rate_per_sec: Option<u32>,
+}
+
+fn rule(
+    name: &str,
+    filter: &str,
+    tag: &str,
+    color: &str,
+    priority: i32,
+    rate_per_sec: Option<u32>,
+) -> TagRule {
+    TagRule {
+        name: name.to_string(),
+        filter: filter.to_string(),
+        tag: tag.to_string(),
+        color: Some(color.to_string()),
+        priority,
+        enabled: true,
+        rate_per_sec,
+    }
+}
+
+/// Rules installed on first start and by [`reset_rules`].
+pub fn default_rules() -> Vec<TagRule> {
+    vec![
+        rule(
+            "Cleartext credentials",
+            "tcp and payload matches \"(?m)^(user|pass) [^ ]|^auth (plain|login)|^authorization: basic |^[a-z0-9]+ login [^ ]\"",
+            "cleartext-credentials",
+            "#facc15",
+            90,
+            None,
+        ),
+        rule(
+            "TCP reset",
+            "tcp and tcp.flags & 0x04",
+            "tcp-error",
+            "#ef4444",
+            80,
+            None,
+        ),
+        rule(
+            "TCP zero window",
+            "tcp and tcp.window == 0 and not tcp.flags & 0x07",
+            "tcp-error",
+            "#ef4444",
+            75,
+            None,
+        ),
+        rule(
+            "Broadcast storm",
+            "eth.dst == ff:ff:ff:ff:ff:ff",
+            "broadcast-storm",
+            "#f97316",
+            60,
+            Some(100),
+        ),
+        rule("ARP", "arp", "arp", "#faf0d7", 10, None),
+    ]
+}
+
+/// Stores tags as `,tag1,tag2,` so SQL can match a whole tag with `instr`.
+pub fn encode_tags(tags: &[String]) -> Option<String> {
+    (!tags.is_empty()).then(|| format!(",{},", tags.join(",")))
+}
+
+pub fn decode_tags(stored: Option<String>) -> Vec<String> {
+    stored
+        .unwrap_or_default()
+        .split(',')
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Checks a rule's fields and that its filter parses with `macros`.
+pub fn validate_rule(rule: &TagRule, macros: &Macros) -> Result<(), String> {
+    if rule.name.trim().is_empty() || rule.name.len() > 100 {
+        return Err("Rule name must be between 1 and 100 characters".to_string());
+    }
+    if rule.tag.is_empty()
+        || rule.tag.len() > 32
+        || !rule
+            .tag
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid tag '{}': use 1-32 lowercase letters, digits, '-', '_' or '.'",
+            rule.tag
+        ));
+    }
+    if let Some(color) = &rule.color {
+        let hex = color.strip_prefix('#').unwrap_or("");
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid colour '{}': expected #rrggbb", color));
+        }
+    }
+    if rule.rate_per_sec == Some(0) {
+        return Err("Rate threshold must be at least 1 packet per second".to_string());
+    }
+    filter::parse_with_macros(&rule.filter, macros)
+        .map_err(|e| format!("Invalid filter for rule '{}': {}", rule.name, e))?;
+    Ok(())
+}
+
+/// Creates the rules table, seeding it with [`default_rules`] when first created.
+pub fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tag_rules')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tag_rules (
+            name TEXT PRIMARY KEY,
+            filter TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            color TEXT,
+            priority INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            rate_per_sec INTEGER
+        )",
+        [],
+    )?;
+    if !exists {
+        for rule in default_rules() {
+            save_rule(conn, &rule)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn list_rules(conn: &Connection) -> rusqlite::Result<Vec<TagRule>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name, filter, tag, color, priority, enabled, rate_per_sec FROM tag_rules
+         ORDER BY priority DESC, name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TagRule {
+            name: row.get(0)?,
+            filter: row.get(1)?,
+            tag: row.get(2)?,
+            color: row.get(3)?,
+            priority: row.get(4)?,
+            enabled: row.get(5)?,
+            rate_per_sec: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Inserts or replaces a rule by name.
+pub fn save_rule(conn: &Connection, rule: &TagRule) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO tag_rules (name, filter, tag, color, priority, enabled, rate_per_sec)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            rule.name,
+            rule.filter,
+            rule.tag,
+            rule.color,
+            rule.priority,
+            rule.enabled,
+            rule.rate_per_sec
+        ],
+    )?;
+    Ok(())
+}
+
+/// Deletes a rule, returning whether it existed.
+pub fn delete_rule(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM tag_rules WHERE name = ?1", [name])? > 0)
+}
+
+/// Replaces all rules with [`default_rules`].
+pub fn reset_rules(conn: &mut Connection) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM tag_rules", [])?;
+    for rule in default_rules() {
+        save_rule(&tx, &rule)?;
+    }
+    tx.commit()
+}
+
+struct CompiledRule {
+    rule: TagRule,
+    expr: Expr,
+    /// Timestamps of recent matches, for rate thresholds
+    recent: VecDeque<i64>,
+}
+
+/// Enabled rules compiled for evaluation, highest priority first.
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+    fingerprints: FingerprintDb,
+}
+
+impl RuleEngine {
+    /// Compiles the enabled rules. Rules whose filter no longer parses (e.g. a
+    /// macro they use was deleted) are skipped with a warning.
+    pub fn new(rules: Vec<TagRule>, macros: &Macros) -> Self {
+        let mut compiled: Vec<CompiledRule> = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(
+                |rule| match filter::parse_with_macros(&rule.filter, macros) {
+                    Ok(expr) => Some(CompiledRule {
+                        rule,
+                        expr,
+                        recent: VecDeque::new(),
+                    }),
+                    Err(e) => {
+                        log::warn!("Skipping rule '{}': {}", rule.name, e);
+                        None
+                    }
+                },
+            )
+            .collect();
+        compiled.sort_by_key(|c| std::cmp::Reverse(c.rule.priority));
+        RuleEngine {
+            rules: compiled,
+            fingerprints: FingerprintDb::default(),
+        }
+    }
+
+    /// Uses `db` to tag packets carrying known TLS or SSH fingerprints.
+    pub fn with_fingerprints(mut self, db: FingerprintDb) -> Self {
+        self.fingerprints = db;
+        self
+    }
+
+    /// Labels of the fingerprint database entries matching `fingerprints`.
+    pub fn known_fingerprints<'a>(
+        &self,
+        fingerprints: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        self.fingerprints.lookup(fingerprints)
+    }
+
+    /// Forgets rate-threshold state, at the start of a capture or import.
+    pub fn reset(&mut self) {
+        for rule in &mut self.rules {
+            rule.recent.clear();
+        }
+    }
+
+    /// Evaluates all rules against a packet, setting its tags and colour.
+    pub fn apply(&mut self, summary: &mut PacketSummary, data: &[u8]) {
+        if self.rules.is_empty() && self.fingerprints.is_empty() {
+            return;
+        }
+
+        let endpoints = dissector::get_transport_endpoints(data);
+        let mut tags: Vec<String> = Vec::new();
+        let mut color = None;
+        {
+            let record = PacketRecord::new(
+                summary,
+                endpoints.map(|e| e.0),
+                endpoints.and_then(|e| e.1),
+                endpoints.and_then(|e| e.2),
+                data,
+            );
+            for compiled in &mut self.rules {
+                if !filter::evaluate(&compiled.expr, &record) {
+                    continue;
+                }
+                if let Some(rate) = compiled.rule.rate_per_sec {
+                    let now = summary.timestamp;
+                    compiled.recent.push_back(now);
+                    while compiled
+                        .recent
+                        .front()
+                        .is_some_and(|&t| now - t >= RATE_WINDOW_NS)
+                    {
+                        compiled.recent.pop_front();
+                    }
+                    if compiled.recent.len() <= rate as usize {
+                        continue;
+                    }
+                }
+                if !tags.contains(&compiled.rule.tag) {
+                    tags.push(compiled.rule.tag.clone());
+                }
+                if color.is_none() {
+                    color = compiled.rule.color.clone();
+                }
+            }
+        }
+        if !self.fingerprints.is_empty()
+            && (dissector::tls_fingerprints(data)
+                .is_some_and(|f| !self.fingerprints.lookup(f.values()).is_empty())
+                || dissector::ssh_fingerprints(data, None)
+                    .is_some_and(|f| !self.fingerprints.lookup(f.values()).is_empty()))
+        {
+            tags.push(KNOWN_FINGERPRINT_TAG.to_string());
+        }
+        summary.tags = tags;
+        summary.color = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arp_packet() -> Vec<u8> {
+        let mut data = vec![0xff; 6];
+        data.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        data.extend_from_slice(&[0x08, 0x06]);
+        data.extend_from_slice(&[0u8; 28]);
+        data
+    }
+
+    #[test]
+    fn test_default_rules_are_valid() {
+        for rule in default_rules() {
+            validate_rule(&rule, &Macros::new()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_priority_and_rate_threshold() {
+        let data = arp_packet();
+        let mut engine = RuleEngine::new(
+            vec![
+                rule("ARP", "arp", "arp", "#faf0d7", 10, None),
+                rule(
+                    "Storm",
+                    "eth.dst == ff:ff:ff:ff:ff:ff",
+                    "broadcast-storm",
+                    "#f97316",
+                    60,
+                    Some(2),
+                ),
+            ],
+            &Macros::new(),
+        );
+
+        let mut tagged = Vec::new();
+        for i in 0..4 {
+            let mut summary = dissector::parse_summary(&data, i + 1, i as i64 * 1000).unwrap();
+            engine.apply(&mut summary, &data);
+            tagged.push(summary);
+        }
+
+        // The storm tag only appears once more than 2 matches fall within a second
+        assert_eq!(tagged[0].tags, ["arp"]);
+        assert_eq!(tagged[0].color.as_deref(), Some("#faf0d7"));
+        assert_eq!(tagged[2].tags, ["broadcast-storm", "arp"]);
+        assert_eq!(tagged[2].color.as_deref(), Some("#f97316"));
+
+        engine.reset();
+        let mut summary = dissector::parse_summary(&data, 9, 0).unwrap();
+        engine.apply(&mut summary, &data);
+        assert_eq!(summary.tags, ["arp"]);
+    }
+
+    #[test]
+    fn test_tags_round_trip_and_filter() {
+        let tags = vec!["tcp-error".to_string(), "arp".to_string()];
+        let stored = encode_tags(&tags);
+        assert_eq!(stored.as_deref(), Some(",tcp-error,arp,"));
+        assert_eq!(decode_tags(stored.clone()), tags);
+        assert!(decode_tags(None).is_empty());
+
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE packets (id INTEGER PRIMARY KEY, tags TEXT)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO packets VALUES (1, ?1), (2, ',tcp-error-x,'), (3, NULL)",
+            [stored],
+        )
+        .unwrap();
+        let sql = filter::to_sql(&filter::parse("tag:tcp-error").unwrap()).unwrap();
+        let ids: Vec<i64> = db
+            .prepare(&format!("SELECT id FROM packets WHERE {}", sql.clause))
+            .unwrap()
+            .query_map(rusqlite::params_from_iter(&sql.params), |r| r.get(0))
+            .unwrap()
+            .map(|id| id.unwrap())
+            .collect();
+        assert_eq!(ids, [1]);
+    }
+
+    #[test]
+    fn test_reset_restores_defaults() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert_eq!(list_rules(&conn).unwrap().len(), default_rules().len());
+
+        delete_rule(&conn, "ARP").unwrap();
+        // Re-running the schema setup must not resurrect deleted defaults
+        init_schema(&conn).unwrap();
+        assert_eq!(list_rules(&conn).unwrap().len(), default_rules().len() - 1);
+
+        reset_rules(&mut conn).unwrap();
+        assert_eq!(list_rules(&conn).unwrap().len(), default_rules().len());
+    }
+}
@@ -1,5 +1,6 @@
+use crate::model::{SshFingerprints, TlsFingerprints};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::IpAddr;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -43,10 +44,41 @@ pub struct Flow {
     pub end_time_ns: i64,
     pub total_bytes: u64,
     pub packet_count: u64,
+    /// TLS fingerprints seen in this flow's hellos
+    #[serde(default)]
+    pub tls_fingerprints: TlsFingerprints,
+    /// SSH fingerprints and identification strings seen in this flow
+    #[serde(default)]
+    pub ssh_fingerprints: SshFingerprints,
+    /// VXLAN/Geneve VNIs, GRE keys and ERSPAN sessions of the tunnels carrying this flow
+    #[serde(default)]
+    pub tunnel_ids: Vec<String>,
+    /// Application dissector recognized in the flow's first payload packets
+    #[serde(default)]
+    pub app_protocol: Option<String>,
+    /// Payload packets inspected for the application protocol so far
+    #[serde(default)]
+    pub app_probes: u32,
 }
 
+/// Payload packets per flow inspected for an application protocol signature
+/// before the flow is left to port-based detection.
+pub const MAX_APP_PROBES: u32 = 4;
+
 pub struct FlowTable {
     pub flows: HashMap<FlowKey, Flow>,
+    /// DHCP and DHCPv6 address assignments seen in the same capture
+    pub leases: LeaseTable,
+    /// ICMP echo requests matched to their replies
+    pub echoes: EchoTable,
+    /// IP fragments awaiting or used in reassembly
+    pub fragments: FragmentTable,
+    /// SMB2 sessions and the file activity in them
+    pub smb: SmbTable,
+    /// Initial sequence numbers of TCP connections
+    pub sequences: SequenceTable,
+    /// CRYPTO data of QUIC client Initials awaiting the rest of a ClientHello
+    pub quic: QuicCryptoTable,
 }
 
 impl Default for FlowTable {
@@ -59,6 +91,12 @@ impl FlowTable {
     pub fn new() -> Self {
         FlowTable {
             flows: HashMap::new(),
+            leases: LeaseTable::default(),
+            echoes: EchoTable::default(),
+            fragments: FragmentTable::default(),
+            smb: SmbTable::default(),
+            sequences: SequenceTable::default(),
+            quic: QuicCryptoTable::default(),
         }
     }
 
@@ -70,6 +108,11 @@ impl FlowTable {
             end_time_ns: timestamp_ns,
             total_bytes: 0,
             packet_count: 0,
+            tls_fingerprints: TlsFingerprints::default(),
+            ssh_fingerprints: SshFingerprints::default(),
+            tunnel_ids: Vec::new(),
+            app_protocol: None,
+            app_probes: 0,
         });
 
         flow.packet_ids.push(packet_id);
@@ -78,8 +121,1005 @@ impl FlowTable {
         flow.packet_count += 1;
     }
 
+    /// Records TLS fingerprints on an existing flow.
+    pub fn record_fingerprints(&mut self, key: &FlowKey, fingerprints: &TlsFingerprints) {
+        if let Some(flow) = self.flows.get_mut(key) {
+            flow.tls_fingerprints.merge(fingerprints);
+        }
+    }
+
+    /// Records SSH fingerprints on an existing flow.
+    pub fn record_ssh_fingerprints(&mut self, key: &FlowKey, fingerprints: &SshFingerprints) {
+        if let Some(flow) = self.flows.get_mut(key) {
+            flow.ssh_fingerprints.merge(fingerprints);
+        }
+    }
+
+    /// Records the tunnel IDs an existing flow was seen in.
+    pub fn record_tunnels(&mut self, key: &FlowKey, tunnel_ids: &[String]) {
+        if let Some(flow) = self.flows.get_mut(key) {
+            for id in tunnel_ids {
+                if !flow.tunnel_ids.contains(id) {
+                    flow.tunnel_ids.push(id.clone());
+                }
+            }
+        }
+    }
+
+    /// Application dissector learned for a flow.
+    pub fn app_protocol(&self, key: &FlowKey) -> Option<&str> {
+        self.flows.get(key)?.app_protocol.as_deref()
+    }
+
+    /// Whether the next payload packet of an existing flow should be probed
+    /// for its application protocol.
+    pub fn needs_app_probe(&self, key: &FlowKey) -> bool {
+        self.flows
+            .get(key)
+            .is_some_and(|flow| flow.app_protocol.is_none() && flow.app_probes < MAX_APP_PROBES)
+    }
+
+    /// Records the outcome of probing one payload packet of an existing flow.
+    /// The first protocol recognized is kept for the rest of the flow.
+    pub fn record_app_probe(&mut self, key: &FlowKey, detected: Option<&str>) {
+        if let Some(flow) = self.flows.get_mut(key) {
+            if flow.app_protocol.is_none() && flow.app_probes < MAX_APP_PROBES {
+                flow.app_probes += 1;
+                flow.app_protocol = detected.map(str::to_string);
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.flows.clear();
+        self.leases = LeaseTable::default();
+        self.echoes = EchoTable::default();
+        self.fragments = FragmentTable::default();
+        self.smb = SmbTable::default();
+        self.sequences = SequenceTable::default();
+        self.quic = QuicCryptoTable::default();
+    }
+}
+
+/// Upper bound on echo requests awaiting a reply.
+const MAX_PENDING_ECHOES: usize = 65_536;
+/// Requests older than this are dropped when the pending table is full.
+const ECHO_TIMEOUT_NS: i64 = 10_000_000_000;
+
+/// An ICMP or ICMPv6 echo request or reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcmpEcho {
+    pub key: FlowKey,
+    pub is_request: bool,
+    pub identifier: u16,
+    pub sequence: u16,
+}
+
+/// An echo request and the reply that answered it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EchoMatch {
+    pub request_id: u64,
+    pub reply_id: u64,
+    pub rtt_ns: i64,
+}
+
+impl EchoMatch {
+    pub fn rtt_ms(&self) -> f64 {
+        self.rtt_ns as f64 / 1_000_000.0
+    }
+}
+
+/// Matches echo replies to requests by flow, identifier and sequence number.
+#[derive(Debug, Default)]
+pub struct EchoTable {
+    pending: HashMap<(FlowKey, u16, u16), (u64, i64)>,
+    matches: HashMap<u64, EchoMatch>,
+}
+
+impl EchoTable {
+    /// Records an echo, returning the match when it is a reply to a known request.
+    pub fn record(
+        &mut self,
+        packet_id: u64,
+        timestamp_ns: i64,
+        echo: IcmpEcho,
+    ) -> Option<EchoMatch> {
+        let key = (echo.key, echo.identifier, echo.sequence);
+        if echo.is_request {
+            if self.pending.len() >= MAX_PENDING_ECHOES {
+                self.pending
+                    .retain(|_, &mut (_, ts)| timestamp_ns - ts < ECHO_TIMEOUT_NS);
+                if self.pending.len() >= MAX_PENDING_ECHOES {
+                    self.pending.clear();
+                }
+            }
+            self.pending.insert(key, (packet_id, timestamp_ns));
+            return None;
+        }
+        let (request_id, request_ns) = self.pending.remove(&key)?;
+        let matched = EchoMatch {
+            request_id,
+            reply_id: packet_id,
+            rtt_ns: timestamp_ns - request_ns,
+        };
+        self.matches.insert(request_id, matched);
+        self.matches.insert(packet_id, matched);
+        Some(matched)
+    }
+
+    /// The request/reply pair `packet_id` belongs to, if it was answered.
+    pub fn get(&self, packet_id: u64) -> Option<&EchoMatch> {
+        self.matches.get(&packet_id)
+    }
+}
+
+/// Upper bound on TCP connections tracked for relative sequence numbers.
+const MAX_SEQUENCE_FLOWS: usize = 65_536;
+
+/// A TCP segment's sequence number and direction within its connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpSegment {
+    pub key: FlowKey,
+    /// Whether the segment travels from `key.src_ip` to `key.dst_ip`
+    pub forward: bool,
+    pub sequence: u32,
+    pub syn: bool,
+}
+
+/// Bases subtracted from a segment's Seq and Ack numbers; None leaves a
+/// number absolute.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SequenceBases {
+    pub seq: Option<u32>,
+    pub ack: Option<u32>,
+}
+
+/// Initial sequence numbers of both directions of each TCP connection.
+#[derive(Debug, Default)]
+pub struct SequenceTable {
+    isns: HashMap<FlowKey, [Option<u32>; 2]>,
+}
+
+impl SequenceTable {
+    /// Takes the first sequence number seen in each direction, or that of a
+    /// later SYN, as its ISN and returns the bases for `segment`.
+    pub fn record(&mut self, segment: &TcpSegment) -> SequenceBases {
+        if self.isns.len() >= MAX_SEQUENCE_FLOWS && !self.isns.contains_key(&segment.key) {
+            return SequenceBases::default();
+        }
+        let isns = self.isns.entry(segment.key.clone()).or_default();
+        let own = usize::from(!segment.forward);
+        if segment.syn || isns[own].is_none() {
+            isns[own] = Some(segment.sequence);
+        }
+        self.bases(segment)
+    }
+
+    /// The bases for `segment`: the ISN of its own direction for Seq and that
+    /// of the other direction for Ack.
+    pub fn bases(&self, segment: &TcpSegment) -> SequenceBases {
+        let Some(isns) = self.isns.get(&segment.key) else {
+            return SequenceBases::default();
+        };
+        let own = usize::from(!segment.forward);
+        SequenceBases {
+            seq: isns[own],
+            ack: isns[1 - own],
+        }
+    }
+}
+
+/// Upper bound on QUIC connections with a ClientHello in progress.
+const MAX_PENDING_HELLOS: usize = 4096;
+/// CRYPTO stream bytes buffered per connection; ClientHellos with large key
+/// shares span a few datagrams, nowhere near this.
+const MAX_HELLO_LEN: u64 = 65_536;
+
+/// Stream offset and data of a QUIC CRYPTO frame.
+pub type CryptoChunk = (u64, Vec<u8>);
+
+/// Identifies the client Initials of one QUIC connection attempt: the flow
+/// and the Destination Connection ID the client chose.
+pub type QuicInitialKey = (FlowKey, Vec<u8>);
+
+/// Buffers the CRYPTO frames of QUIC client Initials, as a ClientHello may
+/// span several Initial datagrams.
+#[derive(Debug, Default)]
+pub struct QuicCryptoTable {
+    pending: HashMap<QuicInitialKey, Vec<CryptoChunk>>,
+}
+
+impl QuicCryptoTable {
+    /// Adds CRYPTO frames from a client Initial, returning all buffered so far
+    /// for the same connection attempt.
+    pub fn record(&mut self, key: QuicInitialKey, chunks: Vec<CryptoChunk>) -> &[CryptoChunk] {
+        if self.pending.len() >= MAX_PENDING_HELLOS && !self.pending.contains_key(&key) {
+            self.pending.clear();
+        }
+        let buffered = self.pending.entry(key).or_default();
+        for (offset, data) in chunks {
+            if offset + data.len() as u64 <= MAX_HELLO_LEN {
+                buffered.push((offset, data));
+            }
+        }
+        buffered
+    }
+
+    /// Forgets a connection attempt once its ClientHello is complete.
+    pub fn finish(&mut self, key: &QuicInitialKey) {
+        self.pending.remove(key);
+    }
+}
+
+/// Upper bound on datagrams awaiting their remaining fragments.
+const MAX_PENDING_DATAGRAMS: usize = 4096;
+/// Incomplete datagrams are dropped after this long, as with Linux's default `ipfrag_time`.
+const FRAGMENT_TIMEOUT_NS: i64 = 30_000_000_000;
+/// Fragments and payload bytes a pending datagram may collect before
+/// reassembly is abandoned. Room is left for duplicate and overlapping copies.
+const MAX_FRAGMENTS_PER_DATAGRAM: usize = 1024;
+const MAX_PENDING_BYTES: usize = 2 * MAX_DATAGRAM_LEN;
+/// Reassembled datagrams and noted fragments remembered for the detail view;
+/// the oldest are forgotten when timed-out datagrams are expired.
+const MAX_FRAGMENT_HISTORY: usize = 65_536;
+const MAX_DATAGRAM_LEN: usize = 65_535;
+const IPV6_HEADER_LEN: usize = 40;
+/// Every link must carry 68-byte datagrams (RFC 791), so a non-final fragment
+/// with less payload than that allows was fragmented deliberately small.
+const MIN_FRAGMENT_LEN: usize = 48;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+
+/// Identifies the fragments of one datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FragmentKey {
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub protocol: u8,
+    pub id: u32,
+}
+
+/// A fragment of an IP datagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    pub key: FragmentKey,
+    /// Offset of `payload` in the datagram, in bytes
+    pub offset: usize,
+    pub more_fragments: bool,
+    pub payload: Vec<u8>,
+    /// Frame bytes in front of the fragment payload, up to the end of the IP header
+    pub headers: Vec<u8>,
+    /// Offset of the IP header in `headers`
+    pub ip_offset: usize,
+    /// IP version of the header at `ip_offset`, 4 or 6
+    pub version: u8,
+}
+
+/// A frame that contributed to a reassembled datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FragmentInfo {
+    pub packet_id: u64,
+    pub timestamp_ns: i64,
+    /// Captured length of the frame
+    pub length: u32,
+    pub offset: usize,
+    pub payload_len: usize,
+}
+
+/// A datagram rebuilt from its fragments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reassembly {
+    /// Headers of the first fragment, see [`Fragment::headers`]
+    pub headers: Vec<u8>,
+    pub ip_offset: usize,
+    /// IP version of the first fragment's header, 4 or 6
+    pub version: u8,
+    pub payload: Vec<u8>,
+    /// Contributing frames in arrival order
+    pub fragments: Vec<FragmentInfo>,
+}
+
+#[derive(Debug)]
+struct PendingDatagram {
+    first_seen_ns: i64,
+    /// Headers and IP header offset from the first fragment, once seen
+    headers: Option<(Vec<u8>, usize)>,
+    version: u8,
+    /// Datagram length, known from the final fragment
+    total_len: Option<usize>,
+    pieces: Vec<(FragmentInfo, Vec<u8>)>,
+    /// Payload bytes in `pieces`
+    bytes: usize,
+}
+
+impl PendingDatagram {
+    fn is_complete(&self) -> bool {
+        let (Some(total), Some(_)) = (self.total_len, &self.headers) else {
+            return false;
+        };
+        let mut ranges: Vec<(usize, usize)> = self
+            .pieces
+            .iter()
+            .map(|(info, _)| (info.offset, info.offset + info.payload_len))
+            .collect();
+        ranges.sort_unstable();
+        let mut covered = 0;
+        for (start, end) in ranges {
+            if start > covered {
+                return false;
+            }
+            covered = covered.max(end);
+        }
+        covered >= total
+    }
+
+    /// Joins the pieces; where fragments overlap, the first to arrive wins.
+    fn assemble(self) -> Option<Reassembly> {
+        let total = self.total_len?;
+        let (headers, ip_offset) = self.headers?;
+        let mut payload = vec![0u8; total];
+        let mut written = vec![false; total];
+        for (info, data) in &self.pieces {
+            for (i, &byte) in data.iter().enumerate() {
+                let pos = info.offset + i;
+                if pos < total && !written[pos] {
+                    payload[pos] = byte;
+                    written[pos] = true;
+                }
+            }
+        }
+        Some(Reassembly {
+            headers,
+            ip_offset,
+            version: self.version,
+            payload,
+            fragments: self.pieces.into_iter().map(|(info, _)| info).collect(),
+        })
+    }
+}
+
+/// Reassembles fragmented datagrams keyed on (src, dst, protocol, ID), and
+/// remembers which frames were reassembled where.
+#[derive(Debug, Default)]
+pub struct FragmentTable {
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    /// Fragment packet IDs of each reassembled datagram, by the ID of its last fragment
+    datagrams: HashMap<u64, Vec<u64>>,
+    /// Last fragment of the datagram each fragment was reassembled in
+    reassembled_in: HashMap<u64, u64>,
+    /// Expert notes on individual fragments
+    notes: HashMap<u64, Vec<String>>,
+    /// Keys of `datagrams` and `notes` in insertion order, for pruning
+    completed: VecDeque<u64>,
+    noted: VecDeque<u64>,
+}
+
+impl FragmentTable {
+    /// Records a fragment, returning the datagram when this fragment completes it.
+    pub fn record(
+        &mut self,
+        packet_id: u64,
+        timestamp_ns: i64,
+        length: u32,
+        fragment: Fragment,
+    ) -> Option<Reassembly> {
+        let Fragment {
+            key,
+            offset,
+            more_fragments,
+            payload,
+            headers,
+            ip_offset,
+            version,
+        } = fragment;
+        let end = offset + payload.len();
+        // IPv4's length limit covers its header, IPv6's only what follows the fixed header
+        let fixed_len = if version == 6 { IPV6_HEADER_LEN } else { 0 };
+        let header_len = headers
+            .len()
+            .checked_sub(ip_offset)?
+            .checked_sub(fixed_len)?;
+        let mut notes = Vec::new();
+
+        if header_len + end > MAX_DATAGRAM_LEN {
+            self.note(
+                packet_id,
+                format!(
+                    "Fragment ends at byte {}, past the {}-byte datagram limit (Ping of Death)",
+                    header_len + end,
+                    MAX_DATAGRAM_LEN
+                ),
+            );
+            return None;
+        }
+        if more_fragments && payload.len() < MIN_FRAGMENT_LEN {
+            notes.push(format!(
+                "Tiny fragment: {} bytes of payload in a non-final fragment",
+                payload.len()
+            ));
+        }
+        if more_fragments && payload.len() % 8 != 0 {
+            notes.push("Non-final fragment length is not a multiple of 8 bytes".to_string());
+        }
+        let transport_header_len = match key.protocol {
+            IP_PROTO_TCP => 20,
+            IP_PROTO_UDP => 8,
+            _ => 0,
+        };
+        if offset == 0 && payload.len() < transport_header_len {
+            notes.push(format!(
+                "First fragment too short for the transport header ({} of {} bytes)",
+                payload.len(),
+                transport_header_len
+            ));
+        }
+
+        if !self.pending.contains_key(&key) {
+            self.expire(timestamp_ns);
+        }
+        let datagram = self.pending.entry(key).or_insert_with(|| PendingDatagram {
+            first_seen_ns: timestamp_ns,
+            headers: None,
+            version,
+            total_len: None,
+            pieces: Vec::new(),
+            bytes: 0,
+        });
+        if datagram.pieces.len() >= MAX_FRAGMENTS_PER_DATAGRAM
+            || datagram.bytes + payload.len() > MAX_PENDING_BYTES
+        {
+            let abandoned = self.pending.remove(&key).into_iter().flat_map(|d| d.pieces);
+            let ids: Vec<u64> = abandoned.map(|(info, _)| info.packet_id).collect();
+            let note = format!(
+                "Reassembly abandoned: datagram exceeded {} fragments or {} bytes",
+                MAX_FRAGMENTS_PER_DATAGRAM, MAX_PENDING_BYTES
+            );
+            for id in ids.into_iter().chain([packet_id]) {
+                self.note(id, note.clone());
+            }
+            return None;
+        }
+
+        for (other, data) in &datagram.pieces {
+            let start = offset.max(other.offset);
+            let stop = end.min(other.offset + other.payload_len);
+            if start >= stop {
+                continue;
+            }
+            let ours = &payload[start - offset..stop - offset];
+            let theirs = &data[start - other.offset..stop - other.offset];
+            notes.push(if ours == theirs {
+                format!(
+                    "Overlapping fragment: bytes {}-{} repeat frame {}",
+                    start,
+                    stop - 1,
+                    other.packet_id
+                )
+            } else {
+                format!(
+                    "Overlapping fragment: bytes {}-{} conflict with frame {} (possible evasion attempt)",
+                    start,
+                    stop - 1,
+                    other.packet_id
+                )
+            });
+        }
+        match datagram.total_len {
+            Some(total) if !more_fragments && total != end => {
+                notes.push(format!(
+                    "Final fragment ends at byte {}, but an earlier one ended at {}",
+                    end, total
+                ));
+            }
+            Some(total) if end > total => {
+                notes.push(format!(
+                    "Fragment extends past the datagram end at byte {}",
+                    total
+                ));
+            }
+            None if !more_fragments => datagram.total_len = Some(end),
+            _ => {}
+        }
+        if offset == 0 && datagram.headers.is_none() {
+            datagram.headers = Some((headers, ip_offset));
+        }
+        datagram.pieces.push((
+            FragmentInfo {
+                packet_id,
+                timestamp_ns,
+                length,
+                offset,
+                payload_len: payload.len(),
+            },
+            payload,
+        ));
+        datagram.bytes += end - offset;
+
+        let complete = datagram.is_complete();
+        for note in notes {
+            self.note(packet_id, note);
+        }
+        if !complete {
+            return None;
+        }
+        let reassembly = self.pending.remove(&key)?.assemble()?;
+        let ids: Vec<u64> = reassembly.fragments.iter().map(|f| f.packet_id).collect();
+        for &id in &ids {
+            self.reassembled_in.insert(id, packet_id);
+        }
+        self.datagrams.insert(packet_id, ids);
+        self.completed.push_back(packet_id);
+        Some(reassembly)
+    }
+
+    /// Drops datagrams that timed out, and the oldest one if the table is still full.
+    fn expire(&mut self, timestamp_ns: i64) {
+        let expired: Vec<FragmentKey> = self
+            .pending
+            .iter()
+            .filter(|(_, d)| timestamp_ns - d.first_seen_ns >= FRAGMENT_TIMEOUT_NS)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired {
+            if let Some(datagram) = self.pending.remove(&key) {
+                for (info, _) in datagram.pieces {
+                    self.note(
+                        info.packet_id,
+                        "Reassembly timed out before all fragments arrived".to_string(),
+                    );
+                }
+            }
+        }
+        if self.pending.len() >= MAX_PENDING_DATAGRAMS {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, d)| d.first_seen_ns)
+                .map(|(key, _)| *key);
+            if let Some(key) = oldest {
+                self.pending.remove(&key);
+            }
+        }
+        while self.completed.len() > MAX_FRAGMENT_HISTORY {
+            let Some(last) = self.completed.pop_front() else {
+                break;
+            };
+            for id in self.datagrams.remove(&last).unwrap_or_default() {
+                self.reassembled_in.remove(&id);
+            }
+        }
+        while self.noted.len() > MAX_FRAGMENT_HISTORY {
+            let Some(id) = self.noted.pop_front() else {
+                break;
+            };
+            self.notes.remove(&id);
+        }
+    }
+
+    fn note(&mut self, packet_id: u64, note: String) {
+        let notes = self.notes.entry(packet_id).or_default();
+        if notes.is_empty() {
+            self.noted.push_back(packet_id);
+        }
+        notes.push(note);
+    }
+
+    /// The last fragment of the datagram `packet_id` was reassembled in.
+    pub fn reassembled_in(&self, packet_id: u64) -> Option<u64> {
+        self.reassembled_in.get(&packet_id).copied()
+    }
+
+    /// Fragments of the datagram reassembled at `packet_id`, in arrival order.
+    pub fn fragment_ids(&self, packet_id: u64) -> Option<&[u64]> {
+        self.datagrams.get(&packet_id).map(Vec::as_slice)
+    }
+
+    /// Expert notes on a fragment, e.g. overlaps.
+    pub fn notes(&self, packet_id: u64) -> &[String] {
+        self.notes.get(&packet_id).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Address assignment information carried by a DHCP or DHCPv6 message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaseEvent {
+    /// DHCPACK, or a DHCPv6 Reply with an IA address
+    Assigned {
+        mac: String,
+        ip: IpAddr,
+        hostname: Option<String>,
+        lease_secs: Option<u32>,
+    },
+    /// DHCPRELEASE/DHCPDECLINE, or the DHCPv6 equivalents
+    Released { mac: String, ip: IpAddr },
+    /// A client announcing its hostname, e.g. in a request
+    Hostname { mac: String, hostname: String },
+}
+
+/// One period during which a device held an address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub mac: String,
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    /// Time of the first assignment
+    pub start_time_ns: i64,
+    /// Time of the last assignment or renewal
+    pub last_seen_ns: i64,
+    /// Lease duration granted by the last assignment
+    pub lease_secs: Option<u32>,
+    /// Time the client released or declined the address
+    pub released_ns: Option<i64>,
+    pub packet_ids: Vec<u64>,
+}
+
+impl Lease {
+    /// Whether the device held the address at `timestamp_ns`. Without a
+    /// release, the lease is assumed to run for its granted duration.
+    pub fn is_held_at(&self, timestamp_ns: i64) -> bool {
+        let end = self.released_ns.unwrap_or_else(|| {
+            self.lease_secs.map_or(i64::MAX, |secs| {
+                self.last_seen_ns
+                    .saturating_add(i64::from(secs).saturating_mul(1_000_000_000))
+            })
+        });
+        self.start_time_ns <= timestamp_ns && timestamp_ns <= end
+    }
+}
+
+/// Capture-wide history of MAC → IP → hostname assignments.
+#[derive(Debug, Default)]
+pub struct LeaseTable {
+    /// Leases in order of first assignment
+    pub leases: Vec<Lease>,
+    /// Last hostname each MAC announced, for assignments that omit it
+    hostnames: HashMap<String, String>,
+}
+
+impl LeaseTable {
+    pub fn record(&mut self, packet_id: u64, timestamp_ns: i64, event: LeaseEvent) {
+        match event {
+            LeaseEvent::Assigned {
+                mac,
+                ip,
+                hostname,
+                lease_secs,
+            } => {
+                if let Some(name) = &hostname {
+                    self.hostnames.insert(mac.clone(), name.clone());
+                }
+                let hostname = hostname.or_else(|| self.hostnames.get(&mac).cloned());
+                if let Some(lease) = self.active_mut(&mac, ip) {
+                    lease.last_seen_ns = timestamp_ns;
+                    lease.lease_secs = lease_secs.or(lease.lease_secs);
+                    if hostname.is_some() {
+                        lease.hostname = hostname;
+                    }
+                    lease.packet_ids.push(packet_id);
+                    return;
+                }
+                self.leases.push(Lease {
+                    mac,
+                    ip,
+                    hostname,
+                    start_time_ns: timestamp_ns,
+                    last_seen_ns: timestamp_ns,
+                    lease_secs,
+                    released_ns: None,
+                    packet_ids: vec![packet_id],
+                });
+            }
+            LeaseEvent::Released { mac, ip } => {
+                if let Some(lease) = self.active_mut(&mac, ip) {
+                    lease.released_ns = Some(timestamp_ns);
+                    lease.packet_ids.push(packet_id);
+                }
+            }
+            LeaseEvent::Hostname { mac, hostname } => {
+                for lease in self.leases.iter_mut().filter(|l| l.mac == mac) {
+                    if lease.released_ns.is_none() {
+                        lease.hostname = Some(hostname.clone());
+                    }
+                }
+                self.hostnames.insert(mac, hostname);
+            }
+        }
+    }
+
+    fn active_mut(&mut self, mac: &str, ip: IpAddr) -> Option<&mut Lease> {
+        self.leases
+            .iter_mut()
+            .rev()
+            .find(|l| l.mac == mac && l.ip == ip && l.released_ns.is_none())
+    }
+
+    /// The lease that covered `ip` at `timestamp_ns`, most recent first.
+    pub fn holder_at(&self, ip: IpAddr, timestamp_ns: i64) -> Option<&Lease> {
+        self.leases
+            .iter()
+            .rev()
+            .find(|l| l.ip == ip && l.is_held_at(timestamp_ns))
+    }
+}
+
+/// Upper bound on SMB2 requests awaiting a response.
+const MAX_PENDING_SMB_REQUESTS: usize = 65_536;
+/// Upper bound on open SMB2 files remembered by file ID.
+const MAX_OPEN_SMB_FILES: usize = 65_536;
+const SMB_STATUS_SUCCESS: u32 = 0;
+/// Interim response to a request that completes later (MS-SMB2 section 3.3.4.2)
+const SMB_STATUS_PENDING: u32 = 0x0000_0103;
+
+/// What an SMB2 request or response did, as far as file activity goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmbOp {
+    /// Session setup request authenticating an account with NTLMSSP
+    SessionSetup {
+        user: String,
+        domain: String,
+        host: String,
+    },
+    TreeConnect {
+        path: String,
+    },
+    Create {
+        name: String,
+    },
+    /// Create response
+    Opened {
+        file_id: u128,
+    },
+    Read {
+        file_id: u128,
+        offset: u64,
+        length: u64,
+    },
+    /// Read response
+    ReadData {
+        length: u64,
+    },
+    Write {
+        file_id: u128,
+        offset: u64,
+        length: u64,
+    },
+    Close {
+        file_id: u128,
+    },
+    /// Any other request or response
+    Other,
+}
+
+/// An SMB2 request or response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmbCommand {
+    pub key: FlowKey,
+    pub message_id: u64,
+    pub session_id: u64,
+    pub tree_id: u32,
+    pub is_response: bool,
+    pub status: u32,
+    pub op: SmbOp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileAction {
+    SessionSetup,
+    TreeConnect,
+    Open,
+    Read,
+    Write,
+    Close,
+}
+
+/// One operation in an SMB2 session's timeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileActivity {
+    pub action: FileAction,
+    /// SMB2 message ID shared by the request and its response
+    pub message_id: u64,
+    pub packet_id: u64,
+    pub timestamp_ns: i64,
+    pub response_id: Option<u64>,
+    /// NT status of the response
+    pub status: Option<u32>,
+    /// Share path, e.g. `\\fs01\finance`
+    pub share: Option<String>,
+    /// File name relative to the share
+    pub file: Option<String>,
+    pub offset: Option<u64>,
+    /// Bytes requested or written, then bytes returned by a read
+    pub length: Option<u64>,
+}
+
+/// An SMB2 session and the operations performed in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmbSession {
+    pub key: FlowKey,
+    pub session_id: u64,
+    pub user: Option<String>,
+    pub domain: Option<String>,
+    pub host: Option<String>,
+    pub start_time_ns: i64,
+    /// Operations in the order they were requested
+    pub activity: Vec<FileActivity>,
+}
+
+impl SmbSession {
+    /// `DOMAIN\user`, or the user alone without a domain.
+    pub fn account(&self) -> Option<String> {
+        let user = self.user.as_deref()?;
+        Some(match self.domain.as_deref() {
+            Some(domain) if !domain.is_empty() => format!("{}\\{}", domain, user),
+            _ => user.to_string(),
+        })
+    }
+}
+
+/// Where an activity is, by session index and position in its timeline.
+type ActivityIndex = (usize, usize);
+
+/// Pairs SMB2 requests with their responses per connection, following tree
+/// IDs to shares and file IDs to names, to build each session's timeline.
+#[derive(Debug, Default)]
+pub struct SmbTable {
+    /// Sessions in order of first appearance
+    pub sessions: Vec<SmbSession>,
+    session_index: HashMap<(FlowKey, u64), usize>,
+    trees: HashMap<(FlowKey, u64, u32), String>,
+    files: HashMap<(FlowKey, u128), String>,
+    pending: HashMap<(FlowKey, u64), (ActivityIndex, Option<u128>)>,
+    by_packet: HashMap<u64, Vec<ActivityIndex>>,
+}
+
+impl SmbTable {
+    pub fn record(&mut self, packet_id: u64, timestamp_ns: i64, command: SmbCommand) {
+        if command.is_response {
+            self.respond(packet_id, command);
+            return;
+        }
+        let SmbCommand {
+            key,
+            message_id,
+            session_id,
+            tree_id,
+            op,
+            ..
+        } = command;
+        let action = match &op {
+            SmbOp::SessionSetup { .. } => FileAction::SessionSetup,
+            SmbOp::TreeConnect { .. } => FileAction::TreeConnect,
+            SmbOp::Create { .. } => FileAction::Open,
+            SmbOp::Read { .. } => FileAction::Read,
+            SmbOp::Write { .. } => FileAction::Write,
+            SmbOp::Close { .. } => FileAction::Close,
+            _ => return,
+        };
+        let mut activity = FileActivity {
+            action,
+            message_id,
+            packet_id,
+            timestamp_ns,
+            response_id: None,
+            status: None,
+            share: self.trees.get(&(key.clone(), session_id, tree_id)).cloned(),
+            file: None,
+            offset: None,
+            length: None,
+        };
+        let mut file_id = None;
+        match &op {
+            SmbOp::TreeConnect { path } => activity.share = Some(path.clone()),
+            SmbOp::Create { name } => activity.file = Some(name.clone()),
+            SmbOp::Read {
+                file_id: id,
+                offset,
+                length,
+            }
+            | SmbOp::Write {
+                file_id: id,
+                offset,
+                length,
+            } => {
+                activity.offset = Some(*offset);
+                activity.length = Some(*length);
+                file_id = Some(*id);
+            }
+            SmbOp::Close { file_id: id } => file_id = Some(*id),
+            _ => {}
+        }
+        if let Some(id) = file_id {
+            activity.file = self.files.get(&(key.clone(), id)).cloned();
+        }
+
+        let index = match self.session_index.get(&(key.clone(), session_id)) {
+            Some(&index) => index,
+            None => {
+                self.sessions.push(SmbSession {
+                    key: key.clone(),
+                    session_id,
+                    user: None,
+                    domain: None,
+                    host: None,
+                    start_time_ns: timestamp_ns,
+                    activity: Vec::new(),
+                });
+                self.session_index
+                    .insert((key.clone(), session_id), self.sessions.len() - 1);
+                self.sessions.len() - 1
+            }
+        };
+        let session = &mut self.sessions[index];
+        if let SmbOp::SessionSetup { user, domain, host } = op {
+            session.user = Some(user);
+            session.domain = Some(domain);
+            session.host = Some(host);
+        }
+        session.activity.push(activity);
+        let position = (index, session.activity.len() - 1);
+
+        if self.pending.len() >= MAX_PENDING_SMB_REQUESTS {
+            self.pending.clear();
+        }
+        self.pending.insert((key, message_id), (position, file_id));
+        self.by_packet.entry(packet_id).or_default().push(position);
+    }
+
+    fn respond(&mut self, packet_id: u64, command: SmbCommand) {
+        let pending_key = (command.key.clone(), command.message_id);
+        if command.status == SMB_STATUS_PENDING {
+            return;
+        }
+        let Some(((index, position), file_id)) = self.pending.remove(&pending_key) else {
+            return;
+        };
+        let activity = &mut self.sessions[index].activity[position];
+        activity.response_id = Some(packet_id);
+        activity.status = Some(command.status);
+        let by_packet = self.by_packet.entry(packet_id).or_default();
+        if !by_packet.contains(&(index, position)) {
+            by_packet.push((index, position));
+        }
+        if command.status != SMB_STATUS_SUCCESS {
+            return;
+        }
+        let key = command.key;
+        match (activity.action, command.op) {
+            (FileAction::TreeConnect, _) => {
+                if let Some(share) = &activity.share {
+                    self.trees
+                        .insert((key, command.session_id, command.tree_id), share.clone());
+                }
+            }
+            (FileAction::Open, SmbOp::Opened { file_id }) => {
+                if let Some(file) = &activity.file {
+                    if self.files.len() >= MAX_OPEN_SMB_FILES {
+                        self.files.clear();
+                    }
+                    self.files.insert((key, file_id), file.clone());
+                }
+            }
+            (FileAction::Read, SmbOp::ReadData { length }) => activity.length = Some(length),
+            (FileAction::Close, _) => {
+                if let Some(file_id) = file_id {
+                    self.files.remove(&(key, file_id));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The sessions and operations a request or response packet belongs to.
+    pub fn activity(&self, packet_id: u64) -> Vec<(&SmbSession, &FileActivity)> {
+        self.by_packet
+            .get(&packet_id)
+            .into_iter()
+            .flatten()
+            .map(|&(index, position)| {
+                let session = &self.sessions[index];
+                (session, &session.activity[position])
+            })
+            .collect()
     }
 }
 
@@ -125,4 +1165,322 @@ mod tests {
         assert_eq!(flow.end_time_ns, 200);
         assert_eq!(flow.packet_ids, vec![1, 2]);
     }
+
+    #[test]
+    fn test_app_protocol_learning() {
+        let mut table = FlowTable::new();
+        let key = FlowKey::new(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            6,
+            50000,
+            8000,
+        );
+        assert!(!table.needs_app_probe(&key));
+        table.update(1, 0, 60, key.clone());
+        assert!(table.needs_app_probe(&key));
+        table.record_app_probe(&key, None);
+        table.record_app_probe(&key, Some("http"));
+        assert_eq!(table.app_protocol(&key), Some("http"));
+        // Learned once, kept for the rest of the flow
+        assert!(!table.needs_app_probe(&key));
+        table.record_app_probe(&key, Some("tls"));
+        assert_eq!(table.app_protocol(&key), Some("http"));
+
+        let other = FlowKey {
+            dst_port: 8001,
+            ..key
+        };
+        table.update(2, 0, 60, other.clone());
+        for _ in 0..MAX_APP_PROBES {
+            table.record_app_probe(&other, None);
+        }
+        assert!(!table.needs_app_probe(&other));
+        table.record_app_probe(&other, Some("http"));
+        assert_eq!(table.app_protocol(&other), None);
+    }
+
+    #[test]
+    fn test_lease_history() {
+        let mut table = LeaseTable::default();
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 50));
+        let assigned = |mac: &str| LeaseEvent::Assigned {
+            mac: mac.to_string(),
+            ip,
+            hostname: None,
+            lease_secs: Some(3600),
+        };
+        let sec = 1_000_000_000;
+
+        table.record(
+            1,
+            0,
+            LeaseEvent::Hostname {
+                mac: "aa:aa:aa:aa:aa:aa".to_string(),
+                hostname: "laptop".to_string(),
+            },
+        );
+        table.record(2, sec, assigned("aa:aa:aa:aa:aa:aa"));
+        table.record(3, 1800 * sec, assigned("aa:aa:aa:aa:aa:aa"));
+        table.record(
+            4,
+            2000 * sec,
+            LeaseEvent::Released {
+                mac: "aa:aa:aa:aa:aa:aa".to_string(),
+                ip,
+            },
+        );
+        table.record(5, 3000 * sec, assigned("bb:bb:bb:bb:bb:bb"));
+
+        assert_eq!(table.leases.len(), 2);
+        assert_eq!(table.leases[0].hostname.as_deref(), Some("laptop"));
+        assert_eq!(table.leases[0].packet_ids, vec![2, 3, 4]);
+        assert_eq!(
+            table.holder_at(ip, 1900 * sec).unwrap().mac,
+            "aa:aa:aa:aa:aa:aa"
+        );
+        assert!(table.holder_at(ip, 2500 * sec).is_none());
+        assert_eq!(
+            table.holder_at(ip, 4000 * sec).unwrap().mac,
+            "bb:bb:bb:bb:bb:bb"
+        );
+        assert!(table.holder_at(ip, 9000 * sec).is_none());
+    }
+
+    #[test]
+    fn test_echo_matching() {
+        let mut table = EchoTable::default();
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let echo = |is_request: bool, src, dst, sequence| IcmpEcho {
+            key: FlowKey::new(src, dst, 1, 0, 0),
+            is_request,
+            identifier: 7,
+            sequence,
+        };
+
+        assert_eq!(table.record(1, 1_000, echo(true, a, b, 1)), None);
+        assert_eq!(table.record(2, 2_000, echo(true, a, b, 2)), None);
+        let matched = table.record(3, 2_500, echo(false, b, a, 1)).unwrap();
+        assert_eq!(matched.request_id, 1);
+        assert_eq!(matched.rtt_ns, 1_500);
+        assert_eq!(table.get(1), Some(&matched));
+        // Duplicate replies and unknown sequence numbers are not matched
+        assert_eq!(table.record(4, 3_000, echo(false, b, a, 1)), None);
+        assert_eq!(table.record(5, 3_000, echo(false, b, a, 9)), None);
+        assert!(table.get(2).is_none());
+    }
+
+    #[test]
+    fn test_sequence_bases() {
+        let mut table = SequenceTable::default();
+        let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let server = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let key = FlowKey::new(client, server, 6, 54321, 80);
+        let forward = (key.src_ip, key.src_port) == (client, 54321);
+        let segment = |from_client: bool, sequence, syn| TcpSegment {
+            key: key.clone(),
+            forward: from_client == forward,
+            sequence,
+            syn,
+        };
+
+        let syn = table.record(&segment(true, 1000, true));
+        assert_eq!((syn.seq, syn.ack), (Some(1000), None));
+        let syn_ack = table.record(&segment(false, 5000, true));
+        assert_eq!((syn_ack.seq, syn_ack.ack), (Some(5000), Some(1000)));
+        let ack = table.record(&segment(true, 1001, false));
+        assert_eq!((ack.seq, ack.ack), (Some(1000), Some(5000)));
+
+        // A new SYN on the same ports starts a new connection
+        let reused = table.record(&segment(true, 90_000, true));
+        assert_eq!(reused.seq, Some(90_000));
+    }
+
+    #[test]
+    fn test_fragment_reassembly() {
+        let key = FragmentKey {
+            src: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            dst: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            protocol: 1,
+            id: 7,
+        };
+        let fragment = |offset: usize, more_fragments: bool, payload: &[u8]| Fragment {
+            key,
+            offset,
+            more_fragments,
+            payload: payload.to_vec(),
+            headers: vec![0; 34],
+            ip_offset: 14,
+            version: 4,
+        };
+        let mut table = FragmentTable::default();
+
+        // Final fragment first, then tiny fragments that overlap at bytes 8-15
+        assert!(table
+            .record(1, 0, 50, fragment(64, false, &[3; 16]))
+            .is_none());
+        assert!(table
+            .record(2, 1, 50, fragment(0, true, &[1; 16]))
+            .is_none());
+        assert!(table.record(3, 2, 42, fragment(8, true, &[2; 8])).is_none());
+        let mut last = vec![1; 8];
+        last.extend_from_slice(&[4; 48]);
+        let reassembly = table.record(4, 3, 90, fragment(8, true, &last)).unwrap();
+
+        assert_eq!(reassembly.payload.len(), 80);
+        // Bytes 8-15 arrived first in frame 2, so later copies are ignored
+        assert_eq!(&reassembly.payload[..16], &[1; 16]);
+        assert_eq!(&reassembly.payload[16..64], &[4; 48]);
+        assert_eq!(&reassembly.payload[64..], &[3; 16]);
+        assert!(table.notes(2)[0].starts_with("Tiny fragment"));
+        assert!(table.notes(3)[1].contains("conflict with frame 2"));
+        assert!(table.notes(4)[0].contains("repeat frame 2"));
+        assert!(table.notes(4)[1].contains("conflict with frame 3"));
+        assert_eq!(table.reassembled_in(1), Some(4));
+        assert_eq!(table.fragment_ids(4), Some(&[1, 2, 3, 4][..]));
+
+        // Datagrams missing a fragment time out
+        assert!(table
+            .record(5, 0, 90, fragment(0, true, &[0; 48]))
+            .is_none());
+        let other = Fragment {
+            key: FragmentKey { id: 8, ..key },
+            ..fragment(0, true, &[0; 48])
+        };
+        assert!(table.record(6, FRAGMENT_TIMEOUT_NS, 90, other).is_none());
+        assert_eq!(
+            table.notes(5),
+            &["Reassembly timed out before all fragments arrived".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_fragment_limits() {
+        let key = FragmentKey {
+            src: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            dst: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            protocol: 17,
+            id: 7,
+        };
+        let fragment = |id: u32, more_fragments: bool, len: usize| Fragment {
+            key: FragmentKey { id, ..key },
+            offset: 0,
+            more_fragments,
+            payload: vec![0; len],
+            headers: vec![0; 34],
+            ip_offset: 14,
+            version: 4,
+        };
+        let mut table = FragmentTable::default();
+
+        // Endless copies of the same fragment are given up on
+        let copies = MAX_PENDING_BYTES / 1400 + 1;
+        for id in 1..=copies as u64 {
+            assert!(table.record(id, 0, 1434, fragment(7, true, 1400)).is_none());
+        }
+        assert!(table.pending.is_empty());
+        assert!(table
+            .notes(1)
+            .last()
+            .unwrap()
+            .starts_with("Reassembly abandoned"));
+        assert!(table.notes(copies as u64)[0].starts_with("Reassembly abandoned"));
+
+        // Only the most recent reassemblies and notes (here on the too short
+        // first fragments) are remembered
+        let first = copies as u64 + 1;
+        let last = first + MAX_FRAGMENT_HISTORY as u64 + 1;
+        for id in first..=last {
+            assert!(table
+                .record(id, 0, 58, fragment(id as u32, false, 4))
+                .is_some());
+        }
+        assert_eq!(table.reassembled_in(first), None);
+        assert_eq!(table.fragment_ids(first), None);
+        assert_eq!(table.reassembled_in(last), Some(last));
+        assert!(table.notes(1).is_empty());
+        assert!(table.datagrams.len() <= MAX_FRAGMENT_HISTORY + 1);
+    }
+
+    #[test]
+    fn test_smb_file_activity() {
+        let key = FlowKey::new(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9)),
+            6,
+            50123,
+            445,
+        );
+        let mut table = SmbTable::default();
+        let mut message_id = 0;
+        let mut exchange = |table: &mut SmbTable, tree_id, request: SmbOp, status, response| {
+            message_id += 1;
+            let command = |is_response, status, op| SmbCommand {
+                key: key.clone(),
+                message_id,
+                session_id: 0x41,
+                tree_id,
+                is_response,
+                status,
+                op,
+            };
+            let id = message_id * 2;
+            table.record(id, id as i64, command(false, 0, request));
+            table.record(id + 1, id as i64 + 1, command(true, status, response));
+        };
+
+        let auth = SmbOp::SessionSetup {
+            user: "alice".to_string(),
+            domain: "CORP".to_string(),
+            host: "WS01".to_string(),
+        };
+        exchange(&mut table, 0, auth, 0, SmbOp::Other);
+        let share = SmbOp::TreeConnect {
+            path: "\\\\fs01\\finance".to_string(),
+        };
+        exchange(&mut table, 5, share, 0, SmbOp::Other);
+        let create = SmbOp::Create {
+            name: "q3.xlsx".to_string(),
+        };
+        exchange(&mut table, 5, create, 0, SmbOp::Opened { file_id: 7 });
+        let read = SmbOp::Read {
+            file_id: 7,
+            offset: 0,
+            length: 65536,
+        };
+        exchange(&mut table, 5, read, 0, SmbOp::ReadData { length: 1200 });
+        exchange(&mut table, 5, SmbOp::Close { file_id: 7 }, 0, SmbOp::Other);
+        let denied = SmbOp::Create {
+            name: "payroll.xlsx".to_string(),
+        };
+        exchange(&mut table, 5, denied, 0xc000_0022, SmbOp::Other);
+
+        assert_eq!(table.sessions.len(), 1);
+        let session = &table.sessions[0];
+        assert_eq!(session.account().as_deref(), Some("CORP\\alice"));
+        let actions: Vec<FileAction> = session.activity.iter().map(|a| a.action).collect();
+        assert_eq!(
+            actions,
+            [
+                FileAction::SessionSetup,
+                FileAction::TreeConnect,
+                FileAction::Open,
+                FileAction::Read,
+                FileAction::Close,
+                FileAction::Open,
+            ]
+        );
+        let read = &session.activity[3];
+        assert_eq!(read.share.as_deref(), Some("\\\\fs01\\finance"));
+        assert_eq!(read.file.as_deref(), Some("q3.xlsx"));
+        assert_eq!(read.length, Some(1200));
+        assert_eq!(read.response_id, Some(9));
+        assert_eq!(session.activity[5].status, Some(0xc000_0022));
+
+        // Both the request and the response lead to the read
+        assert_eq!(table.activity(8)[0].1, read);
+        assert_eq!(table.activity(9)[0].1, read);
+        assert!(table.files.is_empty());
+    }
 }
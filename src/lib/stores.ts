@@ -15,6 +15,8 @@ export interface PacketSummary {
   protocol: string;
   length: number;
   info: string;
+  tags?: string[];
+  color?: string | null;
 }
 
 export interface PacketField {
@@ -39,6 +41,24 @@ export interface ForensicIntelligence {
   ja3_hash: string | null;
   manufacturer: string | null;
   risk_score: number;
+  tls_fingerprints?: TlsFingerprints | null;
+  ssh_fingerprints?: SshFingerprints | null;
+  known_fingerprints?: string[];
+}
+
+export interface TlsFingerprints {
+  ja3: string | null;
+  ja3s: string | null;
+  ja4: string | null;
+  ja4s: string | null;
+}
+
+export interface SshFingerprints {
+  hassh: string | null;
+  hassh_server: string | null;
+  client_banner: string | null;
+  server_banner: string | null;
+  encrypted: boolean;
 }
 
 export interface Artifact {